@@ -0,0 +1,112 @@
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+usage: yeesh [options] [script [arg ...]]
+       yeesh [options] -c command [name [arg ...]]
+       yeesh [options] -s [arg ...]
+
+options:
+  -c command    run command, then exit
+  -s, -         read commands from standard input
+  -l, --login   run as a login shell, reading the login profiles
+  --norc        don't read the rc files of an interactive shell
+  -h, --help    show this help, then exit
+  --version     show the version, then exit";
+
+/// Where the shell reads its commands from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// from the terminal, or from standard input if it is not a terminal
+    Interactive,
+    /// from standard input, even if it is a terminal
+    Stdin,
+    /// from the argument to `-c`
+    Command(String),
+    /// from a script file
+    Script(PathBuf),
+}
+
+/// The command line arguments of the shell
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Args {
+    pub mode:       Mode,
+    /// the name given by `$0`, if not the shell's own
+    pub name:       Option<String>,
+    /// the positional parameters, `$1` onwards
+    pub positional: Vec<String>,
+    /// whether to run as a login shell, reading the login profiles
+    pub login:      bool,
+    /// whether to skip the rc files of an interactive shell
+    pub norc:       bool,
+    pub help:       bool,
+    pub version:    bool,
+}
+
+impl Args {
+    /// Parse the arguments of the shell, not including the name it was run as
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Args, String> {
+        let mut parsed = Args {
+            mode:       Mode::Interactive,
+            name:       None,
+            positional: vec![],
+            login:      false,
+            norc:       false,
+            help:       false,
+            version:    false,
+        };
+        let mut command = None;
+        let mut read_stdin = false;
+
+        let mut args = args.into_iter();
+        let mut operands = vec![];
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-c" => match args.next() {
+                    Some(cmd) => command = Some(cmd),
+                    None => return Err("-c: option requires an argument".to_string()),
+                },
+                "-s" => read_stdin = true,
+                "-l" | "--login" => parsed.login = true,
+                "--norc" => parsed.norc = true,
+                "-h" | "--help" => parsed.help = true,
+                "--version" => parsed.version = true,
+                "--" => {
+                    operands.extend(args.by_ref());
+                    break;
+                }
+                "-" => {
+                    read_stdin = true;
+                    operands.extend(args.by_ref());
+                    break;
+                }
+                _ if arg.starts_with('-') => return Err(format!("{}: invalid option", arg)),
+                // options end at the first operand, so that arguments for a script are left alone
+                _ => {
+                    operands.push(arg);
+                    operands.extend(args.by_ref());
+                    break;
+                }
+            }
+        }
+
+        let mut operands = operands.into_iter();
+        parsed.mode = match (command, read_stdin) {
+            (Some(_), true) => return Err("-c and -s cannot be used together".to_string()),
+            (Some(command), false) => {
+                parsed.name = operands.next();
+                Mode::Command(command)
+            }
+            (None, true) => Mode::Stdin,
+            (None, false) => match operands.next() {
+                Some(script) => {
+                    parsed.name = Some(script.clone());
+                    Mode::Script(PathBuf::from(script))
+                }
+                None => Mode::Interactive,
+            },
+        };
+        parsed.positional = operands.collect();
+
+        Ok(parsed)
+    }
+}
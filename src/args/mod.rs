@@ -0,0 +1,6 @@
+mod args;
+
+#[cfg(test)]
+mod test_args;
+
+pub use args::*;
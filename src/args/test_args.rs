@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod args_tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::args::{Args, Mode};
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_interactive() {
+        let args = parse(&[]).unwrap();
+        assert_eq!(args.mode, Mode::Interactive);
+        assert_eq!(args.name, None);
+        assert!(args.positional.is_empty());
+        assert!(!args.help && !args.version);
+    }
+
+    #[test]
+    fn test_script() {
+        let args = parse(&["script.ysh", "-c", "--", "x"]).unwrap();
+        assert_eq!(args.mode, Mode::Script(PathBuf::from("script.ysh")));
+        assert_eq!(args.name.as_deref(), Some("script.ysh"));
+        assert_eq!(args.positional, strings(&["-c", "--", "x"]));
+
+        let args = parse(&["--", "-script"]).unwrap();
+        assert_eq!(args.mode, Mode::Script(PathBuf::from("-script")));
+    }
+
+    #[test]
+    fn test_command() {
+        let args = parse(&["-c", "echo $1", "name", "a", "b"]).unwrap();
+        assert_eq!(args.mode, Mode::Command("echo $1".to_string()));
+        assert_eq!(args.name.as_deref(), Some("name"));
+        assert_eq!(args.positional, strings(&["a", "b"]));
+
+        let args = parse(&["-c", "true"]).unwrap();
+        assert_eq!(args.name, None);
+        assert!(parse(&["-c"]).is_err());
+        assert_eq!(parse(&["-c", "true", "name", "-s"]).unwrap().positional, strings(&["-s"]));
+        assert!(parse(&["-s", "-c", "true"]).is_err());
+    }
+
+    #[test]
+    fn test_stdin() {
+        let args = parse(&["-s", "a", "b"]).unwrap();
+        assert_eq!(args.mode, Mode::Stdin);
+        assert_eq!(args.positional, strings(&["a", "b"]));
+
+        let args = parse(&["-", "a"]).unwrap();
+        assert_eq!(args.mode, Mode::Stdin);
+        assert_eq!(args.positional, strings(&["a"]));
+    }
+
+    #[test]
+    fn test_flags() {
+        assert!(parse(&["--help"]).unwrap().help);
+        assert!(parse(&["-h"]).unwrap().help);
+        assert!(parse(&["--version"]).unwrap().version);
+        assert!(parse(&["-l"]).unwrap().login);
+        assert!(parse(&["--login", "--norc"]).unwrap().norc);
+        assert!(!parse(&["script.ysh", "--norc"]).unwrap().norc);
+        assert_eq!(parse(&["-x"]), Err("-x: invalid option".to_string()));
+    }
+}
@@ -5,17 +5,30 @@ use filesystem::FileSystem;
 use log::{debug, info, trace, warn};
 use termion::cursor::DetectCursorPos;
 use termion::event::Key;
-use termion::{clear, cursor};
+use termion::{clear, cursor, style};
 
+use crate::cmd_input::completion_context::CompletionContext;
 use crate::cmd_input::token::Token;
-use crate::cmd_input::TabHandler;
+use crate::cmd_input::{SuggesterRegistry, TabHandler};
+use crate::intrinsics::{AliasTable, DirectoryDb, IntrinsicRegistry};
+use crate::prompt::{char_width, Prompt};
+use crate::shell::VariableStore;
 
+// expands to the expression alone, not a block, so that the formatted string lives until the end of
+// the statement using its bytes
 macro_rules! format_u8 {
-    ($($arg:tt)*) => {{
+    ($($arg:tt)*) => {
         format!($($arg)*).as_bytes()
-    }};
+    };
 }
 
+/// Columns taken by the spinner shown after the input while suggestions are still arriving
+const SPINNER_WIDTH: usize = 2;
+/// Spaces between the input and the description of the current suggestion
+const DESCRIPTION_GAP: usize = 4;
+/// Columns kept empty between the rest of the line and the right prompt
+const RIGHT_PROMPT_GAP: usize = 1;
+
 pub trait DetectCursorPosAlias {
     fn get_cursor_pos(&mut self) -> (usize, usize);
 }
@@ -51,7 +64,18 @@ pub struct CmdInput {
 
     tab_handler: TabHandler,
 
-    last_key_was_tab: bool,
+    last_key_was_tab:    bool,
+    /// set when tab was pressed but no suggestions had arrived before the completion timeout
+    awaiting_completion: bool,
+
+    /// the description of the suggestion last completed, shown after the input until another key
+    /// is pressed
+    description:         Option<String>,
+
+    /// shown at the right edge of the line while the input doesn't reach it
+    right_prompt: Option<Prompt>,
+    /// the width of the terminal, which the right prompt is aligned to
+    columns:      usize,
 }
 
 #[inline]
@@ -64,7 +88,14 @@ where
 }
 
 impl CmdInput {
-    pub fn new<T: FileSystem + 'static>(fs: T) -> CmdInput {
+    pub fn new<T: FileSystem + Clone + Send + 'static>(
+        fs: T,
+        variables: VariableStore,
+        aliases: AliasTable,
+        intrinsics: IntrinsicRegistry,
+        directories: DirectoryDb,
+        suggesters: SuggesterRegistry,
+    ) -> CmdInput {
         CmdInput {
             input: vec![],
             index: 0,
@@ -72,11 +103,22 @@ impl CmdInput {
             prev_cursor_pos_x: 0,
             last_key_was_motion: false,
 
-            tab_handler:      TabHandler::new(fs),
-            last_key_was_tab: false,
+            tab_handler:         TabHandler::new(fs, variables, aliases, intrinsics, directories, suggesters),
+            last_key_was_tab:    false,
+            awaiting_completion: false,
+            description:         None,
+
+            right_prompt: None,
+            columns:      0,
         }
     }
 
+    /// Show `prompt` at the right edge of a terminal `columns` wide, or nothing if it's `None`
+    pub fn set_right_prompt(&mut self, prompt: Option<Prompt>, columns: usize) {
+        self.right_prompt = prompt;
+        self.columns = columns;
+    }
+
     pub fn get_input(&self) -> &Vec<char> {
         &self.input
     }
@@ -103,6 +145,43 @@ impl CmdInput {
             clear::AfterCursor,
         ));
         buf.extend_from_slice(self.input.iter().map(|x| *x as u8).collect::<Vec<u8>>().as_slice());
+        if self.tab_handler.is_pending() {
+            buf.push(b' ');
+            buf.push(self.tab_handler.next_spinner_frame() as u8);
+        }
+        // room is kept for the spinner whether it's showing or not, so that what follows the input
+        // doesn't come and go with it
+        let mut line_len = prompt_len + self.input.len() + SPINNER_WIDTH;
+        // the description is cut short before the last column of the terminal, if its width is known
+        if let Some(description) = &self.description {
+            let room = match self.columns {
+                0 => usize::MAX,
+                columns => columns.saturating_sub(line_len + DESCRIPTION_GAP + 1),
+            };
+            let (mut text, mut width) = (String::new(), 0);
+            for c in description.chars().map(|c| if c.is_control() { ' ' } else { c }) {
+                if width + char_width(c) > room {
+                    break;
+                }
+                width += char_width(c);
+                text.push(c);
+            }
+            if !text.is_empty() {
+                let gap = " ".repeat(DESCRIPTION_GAP);
+                buf.extend_from_slice(format_u8!("{}{}{}{}", gap, style::Faint, text, style::NoFaint));
+                line_len += DESCRIPTION_GAP + width;
+            }
+        }
+        // the line was cleared after the prompt, so the right prompt is redrawn whenever it still fits
+        if let Some(right_prompt) = &self.right_prompt
+            && line_len + RIGHT_PROMPT_GAP + right_prompt.width <= self.columns
+        {
+            buf.extend_from_slice(format_u8!(
+                "{}{}",
+                cursor::Goto((self.columns - right_prompt.width + 1) as u16, cursor_pos.1 as u16),
+                right_prompt.text,
+            ));
+        }
         buf.extend_from_slice(format_u8!(
             "{}{}{}",
             cursor::Goto((prompt_len + self.index + 1) as u16, cursor_pos.1 as u16),
@@ -114,46 +193,83 @@ impl CmdInput {
         Ok(())
     }
 
+    /// Replace the token under the cursor with the next suggestion from the tab handler
+    fn complete_active_token(&mut self) {
+        // self.index reflects the space that's added at the end of the input sequence
+        let idx_corrected = if self.index == self.input.len() && self.index > 0 {
+            self.index - 1
+        }
+        else {
+            self.index
+        };
+
+        let mut tokens = Token::parse_input(&self.input);
+        if tokens.is_empty() && self.input.is_empty() {
+            tokens.push(Token::new("".to_string(), false, '"', 0, 0));
+        }
+        let active_idx = tokens
+            .iter()
+            .position(|t| t.get_end_pos() <= idx_corrected && t.get_end_pos() >= idx_corrected);
+
+        trace!(
+            "Found active token: {:?}, idx_corrected: {}",
+            active_idx.map(|idx| &tokens[idx]),
+            idx_corrected
+        );
+
+        if let Some(idx) = active_idx {
+            let context = CompletionContext::from_tokens(&tokens, idx);
+            if let Some(suggestion) = self.tab_handler.get_suggestion(&context) {
+                info!("Found suggestion: '{}'", suggestion);
+                self.description = self.tab_handler.get_description().map(String::from);
+                let token = &mut tokens[idx];
+                token.set_contents(suggestion);
+                self.index = token.get_end_pos();
+                self.input = Token::assemble_tokens(&tokens);
+                self.awaiting_completion = false;
+            }
+            else if self.tab_handler.is_pending() {
+                debug!("No suggestions yet, waiting for suggesters");
+                self.awaiting_completion = true;
+            }
+            else {
+                warn!("Unable to find suggestion");
+            }
+        }
+    }
+
+    /// Collect suggestions which have arrived in the background. If tab was pressed before any
+    /// suggestions were available, the first one is applied. Returns true if the line should be
+    /// re-rendered.
+    pub fn poll_completion(&mut self) -> bool {
+        let changed = self.tab_handler.poll();
+        if changed && self.awaiting_completion && self.tab_handler.get_suggestion_cnt() > 0 {
+            self.complete_active_token();
+        }
+        changed
+    }
+
+    /// Returns true while suggesters are still running in the background
+    pub fn is_completion_pending(&self) -> bool {
+        self.tab_handler.is_pending()
+    }
+
     pub fn insert(&mut self, key: Key) {
         debug!("Inserting key '{:?}'", key);
+        if key != Key::Char('\t') {
+            // any other key makes in-flight suggestions stale
+            self.tab_handler.cancel();
+            self.awaiting_completion = false;
+            self.description = None;
+        }
         match key {
             Key::Char('\t') => {
-                // self.index reflects the space that's added at the end of the input sequence
-                let idx_corrected = if self.index == self.input.len() && self.index > 0 {
-                    self.index - 1
-                }
-                else {
-                    self.index
-                };
-
-                let mut tokens = Token::parse_input(&self.input);
-                if tokens.len() == 0 && self.input.len() == 0 {
-                    tokens.push(Token::new("".to_string(), false, '"', 0, 0));
-                }
-                let active_token = tokens
-                    .iter_mut()
-                    .find(|t| t.get_end_pos() <= idx_corrected && t.get_end_pos() >= idx_corrected);
-
-                trace!(
-                    "Found active token: {:?}, idx_corrected: {}",
-                    active_token,
-                    idx_corrected
-                );
-
-                if let Some(token) = active_token {
-                    if !self.last_key_was_tab || self.tab_handler.get_suggestion_cnt() <= 1 {
-                        self.tab_handler.refresh();
-                    }
-                    if let Some(suggestion) = self.tab_handler.get_suggestion(&token.get_contents().to_string()) {
-                        info!("Found suggestion: '{}'", suggestion);
-                        token.set_contents(suggestion);
-                        self.index = token.get_end_pos();
-                        self.input = Token::assemble_tokens(&tokens);
-                    }
-                    else {
-                        warn!("Unable to find suggestion");
-                    }
+                if !self.last_key_was_tab
+                    || (!self.tab_handler.is_pending() && self.tab_handler.get_suggestion_cnt() <= 1)
+                {
+                    self.tab_handler.refresh();
                 }
+                self.complete_active_token();
                 self.last_key_was_tab = true;
             }
 
@@ -195,6 +311,7 @@ impl CmdInput {
         debug!("Clearing input");
         self.input.clear();
         self.index = 0;
+        self.description = None;
     }
 
     pub fn get_cmd(&self) -> Vec<String> {
@@ -0,0 +1,124 @@
+use std::env;
+
+use filesystem::{DirEntry, FileSystem};
+use itertools::Itertools;
+use log::{debug, trace};
+
+use crate::cmd_input::completion_context::{Applicability, CompletionContext};
+use crate::cmd_input::suggester::{Suggester, Suggestion, SuggestionType};
+use crate::intrinsics::{AliasTable, IntrinsicRegistry};
+
+/// Suggests command names (aliases, intrinsics and executables in `$PATH`) for the first token
+/// of a command line, alongside any matching paths
+#[derive(Clone)]
+pub struct CommandSuggester<T>
+where
+    T: FileSystem,
+{
+    filesystem:  T,
+    aliases:     AliasTable,
+    intrinsics:  IntrinsicRegistry,
+    /// a `:` separated list of directories to search, or `None` to use `$PATH`
+    search_path: Option<String>,
+}
+
+impl<T: FileSystem> CommandSuggester<T> {
+    pub fn new(
+        filesystem: T,
+        aliases: AliasTable,
+        intrinsics: IntrinsicRegistry,
+        search_path: Option<String>,
+    ) -> Self {
+        CommandSuggester {
+            filesystem,
+            aliases,
+            intrinsics,
+            search_path,
+        }
+    }
+
+    fn get_executables(&self, search_str: &str) -> Vec<Suggestion> {
+        let search_path = match &self.search_path {
+            Some(path) => path.clone(),
+            None => env::var("PATH").unwrap_or_default(),
+        };
+
+        search_path
+            .split(':')
+            .filter(|dir| !dir.is_empty())
+            .filter_map(|dir| self.filesystem.read_dir(dir).ok())
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| !self.filesystem.is_dir(entry.path()))
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| name.contains(search_str))
+            .map(|name| Suggestion {
+                is_prefix:   name.starts_with(search_str),
+                replacement: name,
+                s_type:      SuggestionType::PathExecutable,
+                description: None,
+                rank:        0,
+            })
+            .collect()
+    }
+}
+
+impl<T: FileSystem + Send> Suggester for CommandSuggester<T> {
+    fn get_suggestions(&mut self, context: &CompletionContext) -> Vec<Suggestion> {
+        let search_str = context.prefix.as_str();
+        debug!("CommandSuggester - Getting suggestions for prefix '{}'", search_str);
+
+        let aliases = self
+            .aliases
+            .get_all()
+            .into_iter()
+            .filter(|(name, _)| name.contains(search_str))
+            .map(|(name, value)| Suggestion {
+                is_prefix:   name.starts_with(search_str),
+                replacement: name,
+                s_type:      SuggestionType::Alias,
+                description: Some(value),
+                rank:        0,
+            });
+        let intrinsics = self
+            .intrinsics
+            .get_all()
+            .into_iter()
+            .map(|i| (i.get_command(), i.get_description()))
+            .filter(|(name, _)| name.contains(search_str))
+            .map(|(name, description)| Suggestion {
+                replacement: name.to_string(),
+                is_prefix:   name.starts_with(search_str),
+                s_type:      SuggestionType::Intrinsic,
+                description: Some(description.to_string()),
+                rank:        0,
+            });
+
+        // an alias shadows an intrinsic, which shadows an executable of the same name
+        let suggestions: Vec<Suggestion> = aliases
+            .chain(intrinsics)
+            .chain(self.get_executables(search_str))
+            .unique_by(|s| s.replacement.clone())
+            .sorted()
+            .collect();
+        trace!("Found command suggestions: {:?}", suggestions);
+
+        suggestions
+    }
+
+    fn get_applicability(&self, context: &CompletionContext) -> Applicability {
+        // an empty prefix would match every command, which is more noise than help
+        let prefix = &context.prefix;
+        if context.token_idx == 0 && !prefix.is_empty() && !prefix.contains('/') && !prefix.starts_with('$') {
+            Applicability::Applicable
+        }
+        else {
+            Applicability::NotApplicable
+        }
+    }
+
+    #[cfg(test)]
+    fn get_get_suggestion_count(&self) -> usize {
+        0
+    }
+}
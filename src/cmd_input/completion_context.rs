@@ -0,0 +1,65 @@
+use crate::cmd_input::token::Token;
+
+/// Describes the token which is being completed, along with the parts of the command line around it
+/// which suggesters may use to decide whether they are relevant.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompletionContext {
+    /// the current contents of the token being completed
+    pub prefix:    String,
+    /// the first token of the command line, if it is not the token being completed
+    pub command:   Option<String>,
+    /// the token immediately before the token being completed
+    pub previous:  Option<String>,
+    /// the index of the token being completed within the command line
+    pub token_idx: usize,
+}
+
+/// How relevant a [Suggester](crate::cmd_input::suggester::Suggester) is in a given [CompletionContext]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Applicability {
+    NotApplicable,
+    /// suggestions are mixed in with those from other applicable suggesters
+    Applicable,
+    /// only suggesters which are exclusive for this context are consulted
+    Exclusive,
+}
+
+impl CompletionContext {
+    pub fn from_tokens(tokens: &[Token], active_idx: usize) -> Self {
+        CompletionContext {
+            prefix:    tokens[active_idx].get_contents().to_string(),
+            command:   if active_idx > 0 {
+                Some(tokens[0].get_contents().to_string())
+            }
+            else {
+                None
+            },
+            previous:  if active_idx > 0 {
+                Some(tokens[active_idx - 1].get_contents().to_string())
+            }
+            else {
+                None
+            },
+            token_idx: active_idx,
+        }
+    }
+
+    /// Returns true if the token being completed is an argument to one of `commands`
+    pub fn is_argument_of(&self, commands: &[&str]) -> bool {
+        self.command.as_ref().is_some_and(|cmd| commands.contains(&cmd.as_str()))
+    }
+
+    /// Returns true if the token being completed is an option, e.g. `-9`
+    pub fn is_option(&self) -> bool {
+        self.prefix.starts_with('-')
+    }
+}
+
+impl From<&str> for CompletionContext {
+    fn from(prefix: &str) -> Self {
+        CompletionContext {
+            prefix: prefix.to_string(),
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{debug, error, trace};
+
+use crate::cmd_input::completion_context::{Applicability, CompletionContext};
+use crate::cmd_input::suggester::{Suggester, Suggestion};
+
+pub type SharedSuggester = Arc<Mutex<Box<dyn Suggester>>>;
+
+enum CompletionMessage {
+    Applicability(usize, Applicability),
+    Suggestion(usize, Suggestion),
+    Finished(usize),
+}
+
+/// The sending half of a [CompletionJob], handed to [Suggester::stream_suggestions]
+pub struct SuggestionSink {
+    suggester_idx: usize,
+    sender:        Sender<CompletionMessage>,
+    cancelled:     Arc<AtomicBool>,
+}
+
+impl SuggestionSink {
+    /// Send `suggestion` to the job. Returns false if the job has been cancelled, in which case
+    /// the suggester should stop looking for more suggestions.
+    pub fn send(&self, suggestion: Suggestion) -> bool {
+        !self.is_cancelled()
+            && self
+                .sender
+                .send(CompletionMessage::Suggestion(self.suggester_idx, suggestion))
+                .is_ok()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// What a [SuggesterWorker] is asked to do: find suggestions for `context`, and send them to
+/// `sink`
+struct CompletionRequest {
+    context: CompletionContext,
+    sink:    SuggestionSink,
+}
+
+/// A suggester with a thread of its own, which handles the requests of one [CompletionJob] after
+/// another. Clones share the suggester and its thread, which ends once every clone is dropped.
+#[derive(Clone)]
+pub struct SuggesterWorker {
+    suggester: SharedSuggester,
+    requests:  Sender<CompletionRequest>,
+    /// set while a request is waiting for the worker or being handled
+    busy:      Arc<AtomicBool>,
+}
+
+impl SuggesterWorker {
+    pub fn new(suggester: Box<dyn Suggester>) -> Self {
+        let (requests, receiver) = channel();
+        let worker = SuggesterWorker {
+            suggester: Arc::new(Mutex::new(suggester)),
+            requests,
+            busy: Arc::new(AtomicBool::new(false)),
+        };
+        let (suggester, busy) = (worker.suggester.clone(), worker.busy.clone());
+        let spawn_res = thread::Builder::new()
+            .name("suggester".to_string())
+            .spawn(move || Self::run(suggester, receiver, busy));
+        // without its thread, requests can't be sent, so jobs go without the suggester
+        if let Err(e) = spawn_res {
+            error!("Unable to spawn suggester thread: {}", e);
+        }
+        worker
+    }
+
+    pub fn get_suggester(&self) -> &SharedSuggester {
+        &self.suggester
+    }
+
+    /// Ask the worker to find suggestions for `context`. Returns false if it is still busy with an
+    /// earlier request, in which case the request isn't made.
+    fn request(&self, context: &CompletionContext, sink: SuggestionSink) -> bool {
+        // a previous job may still be using the suggester, possibly stuck reading a hung mount,
+        // so rather than wait for it, which could be forever, the job goes without it
+        if self.busy.swap(true, Ordering::AcqRel) {
+            debug!("Suggester {} is still busy, skipping it", sink.suggester_idx);
+            return false;
+        }
+        let request = CompletionRequest {
+            context: context.clone(),
+            sink,
+        };
+        if self.requests.send(request).is_err() {
+            self.busy.store(false, Ordering::Release);
+            return false;
+        }
+        true
+    }
+
+    fn run(suggester: SharedSuggester, requests: Receiver<CompletionRequest>, busy: Arc<AtomicBool>) {
+        for CompletionRequest { context, sink } in requests {
+            let mut suggester = suggester.lock().unwrap();
+            // a job cancelled before its request was reached no longer needs it
+            if !sink.is_cancelled() {
+                let applicability = suggester.get_applicability(&context);
+                let _ = sink
+                    .sender
+                    .send(CompletionMessage::Applicability(sink.suggester_idx, applicability));
+                if applicability != Applicability::NotApplicable && !sink.is_cancelled() {
+                    suggester.stream_suggestions(&context, &sink);
+                }
+            }
+            // the next job may use the suggester as soon as this one is done with it
+            busy.store(false, Ordering::Release);
+            drop(suggester);
+            let _ = sink.sender.send(CompletionMessage::Finished(sink.suggester_idx));
+        }
+    }
+}
+
+/// Runs a set of suggesters on their workers' threads, collecting their suggestions as they
+/// arrive.
+///
+/// A suggester which is blocked (e.g. reading a hung network mount) cannot be interrupted, but
+/// once the job is cancelled its results are discarded and it stops as soon as it next checks
+/// its [SuggestionSink], and requests it hasn't started are dropped. Until then, later jobs skip
+/// it.
+pub struct CompletionJob {
+    receiver:      Receiver<CompletionMessage>,
+    cancelled:     Arc<AtomicBool>,
+    applicability: Vec<Option<Applicability>>,
+    suggestions:   Vec<Vec<Suggestion>>,
+    finished_cnt:  usize,
+}
+
+impl CompletionJob {
+    pub fn start(workers: &[SuggesterWorker], context: &CompletionContext) -> Self {
+        let (sender, receiver) = channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        for (idx, worker) in workers.iter().enumerate() {
+            let sink = SuggestionSink {
+                suggester_idx: idx,
+                sender:        sender.clone(),
+                cancelled:     cancelled.clone(),
+            };
+            if !worker.request(context, sink) {
+                let _ = sender.send(CompletionMessage::Finished(idx));
+            }
+        }
+
+        CompletionJob {
+            receiver,
+            cancelled,
+            applicability: vec![None; workers.len()],
+            suggestions: vec![vec![]; workers.len()],
+            finished_cnt: 0,
+        }
+    }
+
+    /// Collect any suggestions which have arrived, waiting at most `timeout` for every suggester
+    /// to finish. Returns true if anything changed.
+    pub fn poll(&mut self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut changed = false;
+        while !self.is_finished() {
+            let message = match self.receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    // every sender is gone, so no suggester can still be running
+                    self.finished_cnt = self.suggestions.len();
+                    changed = true;
+                    break;
+                }
+            };
+
+            changed = true;
+            match message {
+                CompletionMessage::Applicability(idx, applicability) => self.applicability[idx] = Some(applicability),
+                CompletionMessage::Suggestion(idx, suggestion) => self.suggestions[idx].push(suggestion),
+                CompletionMessage::Finished(idx) => {
+                    trace!("Suggester {} finished", idx);
+                    self.finished_cnt += 1;
+                }
+            }
+        }
+
+        changed
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished_cnt >= self.suggestions.len()
+    }
+
+    pub fn cancel(&mut self) {
+        if !self.is_finished() {
+            debug!("Cancelling completion job");
+        }
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns the sorted suggestions received so far. If any suggester has declared itself
+    /// exclusive to the context, only suggestions from exclusive suggesters are included.
+    pub fn get_suggestions(&self) -> Vec<Suggestion> {
+        let has_exclusive = self.applicability.contains(&Some(Applicability::Exclusive));
+        let mut suggestions: Vec<Suggestion> = self
+            .applicability
+            .iter()
+            .zip(self.suggestions.iter())
+            .filter(|(applicability, _)| match applicability {
+                Some(Applicability::Exclusive) => true,
+                Some(Applicability::Applicable) => !has_exclusive,
+                _ => false,
+            })
+            .flat_map(|(_, suggestions)| suggestions.iter().cloned())
+            .collect();
+        suggestions.sort();
+
+        suggestions
+    }
+}
+
+impl Drop for CompletionJob {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
@@ -0,0 +1,79 @@
+use filesystem::FileSystem;
+use log::{debug, trace};
+
+use crate::cmd_input::completion_context::{Applicability, CompletionContext};
+use crate::cmd_input::suggester::{Suggester, Suggestion, SuggestionType};
+use crate::intrinsics::DirectoryDb;
+
+/// Commands whose arguments may be frecent directories, mixed in with the files around them
+const DIRECTORY_COMMANDS: [&str; 2] = ["cd", "pushd"];
+/// Commands whose arguments are only ever fragments of frecent directories
+const JUMP_COMMANDS: [&str; 1] = ["z"];
+/// How many frecent directories are suggested at most
+const MAX_SUGGESTIONS: usize = 10;
+
+/// Suggests the directories `z` would jump to when completing the argument of `cd`, `pushd` or
+/// `z`, the most frecent first
+#[derive(Clone)]
+pub struct FrecentDirectorySuggester<T>
+where
+    T: FileSystem,
+{
+    filesystem:  T,
+    directories: DirectoryDb,
+}
+
+impl<T: FileSystem> FrecentDirectorySuggester<T> {
+    pub fn new(filesystem: T, directories: DirectoryDb) -> Self {
+        FrecentDirectorySuggester {
+            filesystem,
+            directories,
+        }
+    }
+}
+
+impl<T: FileSystem + Send> Suggester for FrecentDirectorySuggester<T> {
+    fn get_suggestions(&mut self, context: &CompletionContext) -> Vec<Suggestion> {
+        debug!("FrecentDirectorySuggester - Getting suggestions for prefix '{}'", context.prefix);
+        let suggestions: Vec<Suggestion> = self
+            .directories
+            .find(std::slice::from_ref(&context.prefix))
+            .into_iter()
+            .filter(|(dir, _)| self.filesystem.is_dir(dir))
+            .take(MAX_SUGGESTIONS)
+            .map(|(dir, frecency)| {
+                let replacement = format!("{}/", dir.trim_end_matches('/'));
+                Suggestion {
+                    is_prefix:   replacement.starts_with(&context.prefix),
+                    replacement,
+                    s_type:      SuggestionType::Directory,
+                    description: None,
+                    rank:        (frecency * 10.0) as u32,
+                }
+            })
+            .collect();
+        trace!("Found frecent directory suggestions: {:?}", suggestions);
+
+        suggestions
+    }
+
+    fn get_applicability(&self, context: &CompletionContext) -> Applicability {
+        if context.is_option() {
+            Applicability::NotApplicable
+        }
+        else if context.is_argument_of(&JUMP_COMMANDS) {
+            Applicability::Exclusive
+        }
+        else if context.is_argument_of(&DIRECTORY_COMMANDS) {
+            Applicability::Applicable
+        }
+        else {
+            Applicability::NotApplicable
+        }
+    }
+
+    #[cfg(test)]
+    fn get_get_suggestion_count(&self) -> usize {
+        0
+    }
+}
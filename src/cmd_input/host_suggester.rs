@@ -0,0 +1,166 @@
+use std::env;
+use std::path::PathBuf;
+
+use filesystem::FileSystem;
+use itertools::Itertools;
+use log::{debug, trace};
+
+use crate::cmd_input::completion_context::{Applicability, CompletionContext};
+use crate::cmd_input::suggester::{Suggester, Suggestion, SuggestionType};
+
+/// Commands whose arguments are only ever hosts
+const REMOTE_SHELL_COMMANDS: [&str; 3] = ["ssh", "sftp", "mosh"];
+/// Commands whose arguments may be either local paths or `host:path`
+const REMOTE_COPY_COMMANDS: [&str; 2] = ["scp", "rsync"];
+/// Options of the above commands which take a (non-host) value
+const OPTIONS_WITH_VALUES: [&str; 10] = ["-i", "-p", "-P", "-l", "-F", "-o", "-J", "-L", "-R", "-D"];
+
+/// Suggests host names from an ssh client config and a hosts file when completing arguments to
+/// `ssh`, `scp` and friends
+#[derive(Clone)]
+pub struct HostSuggester<T>
+where
+    T: FileSystem,
+{
+    filesystem:      T,
+    ssh_config_path: Option<PathBuf>,
+    hosts_path:      PathBuf,
+}
+
+impl<T: FileSystem> HostSuggester<T> {
+    pub fn new(filesystem: T, ssh_config_path: Option<PathBuf>, hosts_path: PathBuf) -> Self {
+        HostSuggester {
+            filesystem,
+            ssh_config_path,
+            hosts_path,
+        }
+    }
+
+    /// Create a [HostSuggester] which reads `~/.ssh/config` and `/etc/hosts`
+    pub fn with_default_paths(filesystem: T) -> Self {
+        let ssh_config_path = env::var("HOME").ok().map(|home| PathBuf::from(home).join(".ssh/config"));
+        Self::new(filesystem, ssh_config_path, PathBuf::from("/etc/hosts"))
+    }
+
+    /// Return the concrete hosts declared by `Host` lines in an ssh config, along with their
+    /// `HostName` if one is given. Patterns such as `*.example.com` are skipped.
+    pub(super) fn parse_ssh_config(contents: &str) -> Vec<(String, Option<String>)> {
+        let mut hosts: Vec<(String, Option<String>)> = vec![];
+        let mut block_start = 0_usize;
+        for line in contents.lines().map(str::trim) {
+            let mut words = line.split_whitespace();
+            let keyword = match words.next() {
+                Some(word) if !word.starts_with('#') => word.to_lowercase(),
+                _ => continue,
+            };
+
+            match keyword.as_str() {
+                "host" => {
+                    block_start = hosts.len();
+                    hosts.extend(
+                        words
+                            .filter(|h| !h.contains(['*', '?', '!']))
+                            .map(|h| (h.to_string(), None)),
+                    );
+                }
+                "match" => block_start = hosts.len(),
+                "hostname" => {
+                    if let Some(host_name) = words.next() {
+                        for (_, description) in hosts[block_start..].iter_mut() {
+                            *description = Some(host_name.to_string());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        hosts
+    }
+
+    /// Return every host name listed in a hosts file
+    pub(super) fn parse_hosts_file(contents: &str) -> Vec<String> {
+        contents
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default())
+            .flat_map(|line| line.split_whitespace().skip(1).map(String::from))
+            .collect()
+    }
+
+    fn read_file(&self, path: &PathBuf) -> String {
+        self.filesystem.read_file_to_string(path).unwrap_or_else(|e| {
+            debug!("Unable to read host file '{}': {}", path.display(), e);
+            String::new()
+        })
+    }
+
+    fn get_hosts(&self) -> Vec<(String, Option<String>)> {
+        let mut hosts = match &self.ssh_config_path {
+            Some(path) => Self::parse_ssh_config(&self.read_file(path)),
+            None => vec![],
+        };
+        hosts.extend(
+            Self::parse_hosts_file(&self.read_file(&self.hosts_path))
+                .into_iter()
+                .map(|h| (h, None)),
+        );
+
+        hosts.into_iter().unique_by(|(host, _)| host.clone()).collect()
+    }
+}
+
+impl<T: FileSystem + Send> Suggester for HostSuggester<T> {
+    fn get_suggestions(&mut self, context: &CompletionContext) -> Vec<Suggestion> {
+        debug!("HostSuggester - Getting suggestions for prefix '{}'", context.prefix);
+        // keep any `user@` part of the token as-is and only complete the host
+        let (user, partial_host) = match context.prefix.rfind('@') {
+            Some(idx) => context.prefix.split_at(idx + 1),
+            None => ("", context.prefix.as_str()),
+        };
+        if partial_host.contains(':') {
+            return vec![];
+        }
+        let suffix = if context.is_argument_of(&REMOTE_COPY_COMMANDS) { ":" } else { "" };
+
+        let suggestions: Vec<Suggestion> = self
+            .get_hosts()
+            .into_iter()
+            .filter(|(host, _)| host.contains(partial_host))
+            .map(|(host, description)| Suggestion {
+                replacement: format!("{}{}{}", user, host, suffix),
+                is_prefix: host.starts_with(partial_host),
+                s_type: SuggestionType::Host,
+                description,
+                rank: 0,
+            })
+            .sorted()
+            .collect();
+        trace!("Found host suggestions: {:?}", suggestions);
+
+        suggestions
+    }
+
+    fn get_applicability(&self, context: &CompletionContext) -> Applicability {
+        let follows_option = context
+            .previous
+            .as_ref()
+            .is_some_and(|prev| OPTIONS_WITH_VALUES.contains(&prev.as_str()));
+        if context.is_option() || follows_option {
+            Applicability::NotApplicable
+        }
+        else if context.is_argument_of(&REMOTE_SHELL_COMMANDS) {
+            Applicability::Exclusive
+        }
+        else if context.is_argument_of(&REMOTE_COPY_COMMANDS) {
+            Applicability::Applicable
+        }
+        else {
+            Applicability::NotApplicable
+        }
+    }
+
+    #[cfg(test)]
+    fn get_get_suggestion_count(&self) -> usize {
+        0
+    }
+}
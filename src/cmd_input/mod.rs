@@ -3,9 +3,18 @@ mod cmd_input;
 #[cfg(test)]
 mod tests;
 
-mod suggester;
+mod command_suggester;
+pub(crate) mod completion_context;
+mod completion_job;
+mod directory_suggester;
+mod host_suggester;
+mod process_suggester;
+pub(crate) mod suggester;
 mod tab_handler;
 mod token;
+mod user_suggester;
+mod variable_suggester;
 
 pub use cmd_input::*;
 pub use tab_handler::*;
+pub use variable_suggester::VariableSource;
@@ -0,0 +1,101 @@
+use std::path::PathBuf;
+
+use filesystem::{DirEntry, FileSystem};
+use itertools::Itertools;
+use log::{debug, trace};
+
+use crate::cmd_input::completion_context::{Applicability, CompletionContext};
+use crate::cmd_input::suggester::{Suggester, Suggestion, SuggestionType};
+
+/// Commands whose non-option arguments are process IDs
+const PROCESS_COMMANDS: [&str; 1] = ["kill"];
+/// Options of the above commands which take a (non-PID) value
+const OPTIONS_WITH_VALUES: [&str; 2] = ["-s", "-n"];
+
+/// Suggests the IDs of running processes, read from a procfs mount, when completing arguments
+/// to `kill`. A token matches a process if it is part of either its ID or its name.
+#[derive(Clone)]
+pub struct ProcessSuggester<T>
+where
+    T: FileSystem,
+{
+    filesystem: T,
+    proc_path:  PathBuf,
+}
+
+impl<T: FileSystem> ProcessSuggester<T> {
+    pub fn new(filesystem: T, proc_path: PathBuf) -> Self {
+        ProcessSuggester { filesystem, proc_path }
+    }
+
+    /// Create a [ProcessSuggester] which reads `/proc`
+    pub fn with_default_paths(filesystem: T) -> Self {
+        Self::new(filesystem, PathBuf::from("/proc"))
+    }
+
+    /// Return the ID and name of every process listed in `self.proc_path`
+    pub(super) fn get_processes(&self) -> Vec<(String, String)> {
+        let entries = match self.filesystem.read_dir(&self.proc_path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                debug!("Unable to read '{}': {}", self.proc_path.display(), e);
+                return vec![];
+            }
+        };
+
+        entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let pid = entry.file_name().to_string_lossy().to_string();
+                if pid.is_empty() || !pid.chars().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+                // processes may exit between listing the directory and reading their name
+                let name = self.filesystem.read_file_to_string(entry.path().join("comm")).ok()?;
+                Some((pid, name.trim().to_string()))
+            })
+            .collect()
+    }
+}
+
+impl<T: FileSystem + Send> Suggester for ProcessSuggester<T> {
+    fn get_suggestions(&mut self, context: &CompletionContext) -> Vec<Suggestion> {
+        debug!("ProcessSuggester - Getting suggestions for prefix '{}'", context.prefix);
+        let prefix = context.prefix.as_str();
+
+        let suggestions: Vec<Suggestion> = self
+            .get_processes()
+            .into_iter()
+            .filter(|(pid, name)| pid.contains(prefix) || name.contains(prefix))
+            .map(|(pid, name)| Suggestion {
+                is_prefix:   pid.starts_with(prefix),
+                replacement: pid,
+                s_type:      SuggestionType::Process,
+                description: Some(name),
+                rank:        0,
+            })
+            .sorted()
+            .collect();
+        trace!("Found process suggestions: {:?}", suggestions);
+
+        suggestions
+    }
+
+    fn get_applicability(&self, context: &CompletionContext) -> Applicability {
+        let follows_option = context
+            .previous
+            .as_ref()
+            .is_some_and(|prev| OPTIONS_WITH_VALUES.contains(&prev.as_str()));
+        if context.is_argument_of(&PROCESS_COMMANDS) && !context.is_option() && !follows_option {
+            Applicability::Exclusive
+        }
+        else {
+            Applicability::NotApplicable
+        }
+    }
+
+    #[cfg(test)]
+    fn get_get_suggestion_count(&self) -> usize {
+        0
+    }
+}
@@ -5,8 +5,10 @@ use std::path::{Path, PathBuf};
 
 use filesystem::{DirEntry, FileSystem};
 use itertools::Itertools;
-use log::{debug, error, info, trace};
+use log::{debug, error, trace};
 
+use crate::cmd_input::completion_context::{Applicability, CompletionContext};
+use crate::cmd_input::completion_job::SuggestionSink;
 use crate::cmd_input::suggester::SuggestionType::{Directory, File};
 
 #[derive(PartialEq, Eq, Clone, Debug)]
@@ -15,6 +17,20 @@ pub enum SuggestionType {
     File,
     /// an executable in $PATH
     PathExecutable,
+    /// an alias defined with the `alias` intrinsic
+    Alias,
+    /// a command built into the shell
+    Intrinsic,
+    /// a shell or environment variable
+    Variable,
+    /// a host name, e.g. from `~/.ssh/config`
+    Host,
+    /// a user name from the passwd database
+    User,
+    /// the ID of a running process
+    Process,
+    /// from a suggester registered while the shell runs, e.g. from Python
+    Registered,
 }
 
 impl SuggestionType {
@@ -33,11 +49,29 @@ pub struct Suggestion {
     pub replacement:      String,
     pub(super) is_prefix: bool,
     pub s_type:           SuggestionType,
+    /// extra information shown alongside the suggestion, such as a variable's value
+    pub description:      Option<String>,
+    /// how strongly the suggestion is preferred over others which match as well, such as a
+    /// frecent directory. Higher ranks sort first.
+    pub(super) rank:      u32,
+}
+
+impl Suggestion {
+    /// Create a suggestion to replace `prefix` with `replacement`
+    pub fn new(replacement: String, prefix: &str, s_type: SuggestionType, description: Option<String>) -> Self {
+        Suggestion {
+            is_prefix: replacement.starts_with(prefix),
+            replacement,
+            s_type,
+            description,
+            rank: 0,
+        }
+    }
 }
 
 impl Ord for Suggestion {
     fn cmp(&self, other: &Self) -> Ordering {
-        let res = self.is_prefix.cmp(&other.is_prefix).reverse();
+        let res = self.is_prefix.cmp(&other.is_prefix).reverse().then(self.rank.cmp(&other.rank).reverse());
         if res == Equal {
             self.replacement.cmp(&other.replacement)
         }
@@ -53,8 +87,25 @@ impl PartialOrd for Suggestion {
     }
 }
 
-pub trait Suggester {
-    fn get_suggestions(&mut self, prefix: &str) -> Vec<Suggestion>;
+/// A source of completions. Suggesters are run on background threads by
+/// [CompletionJob](crate::cmd_input::completion_job::CompletionJob), so they must be [Send].
+pub trait Suggester: Send {
+    fn get_suggestions(&mut self, context: &CompletionContext) -> Vec<Suggestion>;
+
+    /// Send suggestions for `context` to `sink` as they are found, stopping early if the sink is
+    /// cancelled. Suggesters which may be slow should override this to report partial results.
+    fn stream_suggestions(&mut self, context: &CompletionContext, sink: &SuggestionSink) {
+        for suggestion in self.get_suggestions(context) {
+            if !sink.send(suggestion) {
+                break;
+            }
+        }
+    }
+
+    /// Returns whether this suggester should be consulted for `context`
+    fn get_applicability(&self, _context: &CompletionContext) -> Applicability {
+        Applicability::Applicable
+    }
 
     #[cfg(test)]
     fn get_get_suggestion_count(&self) -> usize;
@@ -95,6 +146,8 @@ impl<T: FileSystem> FileSystemSuggester<T> {
                 replacement: path.to_string() + &replacement_suffix,
                 is_prefix: replacement_suffix.starts_with(search_str),
                 s_type,
+                description: None,
+                rank: 0,
             })
         }
         else {
@@ -106,21 +159,23 @@ impl<T: FileSystem> FileSystemSuggester<T> {
     /// Return a list of files in `path` whose name `search_str` is a substring of
     /// `search_str` should describe a path in the [FileSystem] `self.filesystem`
     fn _get_suggestions(&self, path: &str, search_str: &str) -> io::Result<Vec<Suggestion>> {
-        let search_path = if path.is_empty() || path == "./" {
-            self.filesystem.current_dir().unwrap()
-        }
-        else {
-            path.into()
-        };
-
         Ok(self
             .filesystem
-            .read_dir(search_path)?
+            .read_dir(self.get_search_dir(path)?)?
             .filter_map(|x| self.get_suggestion_from_file(&x.unwrap(), path, search_str))
             .sorted()
             .collect())
     }
 
+    fn get_search_dir(&self, path: &str) -> io::Result<PathBuf> {
+        if path.is_empty() || path == "./" {
+            self.filesystem.current_dir()
+        }
+        else {
+            Ok(path.into())
+        }
+    }
+
     pub(super) fn get_search_params(&self, prefix: &str) -> (String, String) {
         let path = Path::new(prefix);
         if self.filesystem.is_dir(path) && prefix.ends_with('/') {
@@ -139,8 +194,9 @@ impl<T: FileSystem> FileSystemSuggester<T> {
     }
 }
 
-impl<T: FileSystem> Suggester for FileSystemSuggester<T> {
-    fn get_suggestions(&mut self, prefix: &str) -> Vec<Suggestion> {
+impl<T: FileSystem + Send> Suggester for FileSystemSuggester<T> {
+    fn get_suggestions(&mut self, context: &CompletionContext) -> Vec<Suggestion> {
+        let prefix = context.prefix.as_str();
         debug!("FileSystemSuggester - Getting suggestions for prefix '{}'", prefix);
         let (search_path, search_str) = self.get_search_params(prefix);
         trace!(
@@ -159,6 +215,35 @@ impl<T: FileSystem> Suggester for FileSystemSuggester<T> {
         suggestions_res.unwrap_or_default()
     }
 
+    /// Like [Suggester::get_suggestions], but sends each matching entry as soon as it is read so
+    /// that large or slow directories produce partial results
+    fn stream_suggestions(&mut self, context: &CompletionContext, sink: &SuggestionSink) {
+        let (search_path, search_str) = self.get_search_params(&context.prefix);
+        let entries = match self.get_search_dir(&search_path).and_then(|dir| self.filesystem.read_dir(dir)) {
+            Ok(entries) => entries,
+            Err(e) => {
+                error!(
+                    "Unable to get suggestions from path '{}', search_str '{}', reason: '{}'",
+                    search_path, search_str, e
+                );
+                return;
+            }
+        };
+
+        for entry in entries {
+            if sink.is_cancelled() {
+                debug!("FileSystemSuggester - cancelled while reading '{}'", search_path);
+                return;
+            }
+            if let Ok(entry) = entry
+                && let Some(suggestion) = self.get_suggestion_from_file(&entry, &search_path, &search_str)
+                && !sink.send(suggestion)
+            {
+                return;
+            }
+        }
+    }
+
     #[cfg(test)]
     fn get_get_suggestion_count(&self) -> usize {
         0
@@ -1,54 +1,191 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
 use filesystem::FileSystem;
 use log::{debug, trace};
 
+use crate::cmd_input::command_suggester::CommandSuggester;
+use crate::cmd_input::completion_context::CompletionContext;
+use crate::cmd_input::completion_job::{CompletionJob, SuggesterWorker};
+use crate::cmd_input::directory_suggester::FrecentDirectorySuggester;
+use crate::cmd_input::host_suggester::HostSuggester;
+use crate::cmd_input::process_suggester::ProcessSuggester;
 use crate::cmd_input::suggester::{FileSystemSuggester, Suggester, Suggestion};
+use crate::cmd_input::user_suggester::UserSuggester;
+use crate::cmd_input::variable_suggester::VariableSuggester;
+use crate::intrinsics::{AliasTable, DirectoryDb, IntrinsicRegistry};
+use crate::shell::VariableStore;
+
+/// How long a tab press waits for suggesters before returning whatever has been found so far
+const DEFAULT_COMPLETION_TIMEOUT: Duration = Duration::from_millis(100);
+const SPINNER_FRAMES: [char; 4] = ['|', '/', '-', '\\'];
+
+/// Suggesters added while the shell runs, e.g. from Python in an rc file. Clones refer to the same
+/// set, so a [TabHandler] consults suggesters registered after it was created.
+#[derive(Clone, Default)]
+pub struct SuggesterRegistry {
+    suggesters: Arc<RwLock<Vec<SuggesterWorker>>>,
+}
+
+impl SuggesterRegistry {
+    /// Create a registry with no suggesters
+    pub fn new() -> Self {
+        SuggesterRegistry::default()
+    }
+
+    /// Add a suggester which is consulted alongside those every [TabHandler] has of its own
+    pub fn register(&self, suggester: Box<dyn Suggester>) {
+        self.suggesters.write().unwrap().push(SuggesterWorker::new(suggester));
+    }
+
+    /// Returns every suggester, in the order they were registered
+    pub fn get_all(&self) -> Vec<SuggesterWorker> {
+        self.suggesters.read().unwrap().clone()
+    }
+}
 
 pub struct TabHandler {
-    suggesters:     Vec<Box<dyn Suggester>>,
+    suggesters:     Vec<SuggesterWorker>,
+    /// suggesters registered with the shell, which are consulted as well as `suggesters`
+    registered:     SuggesterRegistry,
     should_refresh: bool,
-    cached_iter:    Box<dyn Iterator<Item = Suggestion>>,
-    suggestion_cnt: usize,
+    job:            Option<CompletionJob>,
+    suggestions:    Vec<Suggestion>,
+    next_idx:       usize,
+    timeout:        Duration,
+    spinner_frame:  usize,
 }
 
 impl TabHandler {
-    pub fn new<T: FileSystem + 'static>(fs: T) -> Self {
-        let tmp = vec![];
+    pub fn new<T: FileSystem + Clone + Send + 'static>(
+        fs: T,
+        variables: VariableStore,
+        aliases: AliasTable,
+        intrinsics: IntrinsicRegistry,
+        directories: DirectoryDb,
+        registered: SuggesterRegistry,
+    ) -> Self {
+        let suggesters: Vec<Box<dyn Suggester>> = vec![
+            Box::new(FileSystemSuggester::new(fs.clone())),
+            Box::new(FrecentDirectorySuggester::new(fs.clone(), directories)),
+            Box::new(CommandSuggester::new(fs.clone(), aliases, intrinsics, None)),
+            Box::new(VariableSuggester::new(variables)),
+            Box::new(HostSuggester::with_default_paths(fs.clone())),
+            Box::new(UserSuggester::with_default_paths(fs.clone())),
+            Box::new(ProcessSuggester::with_default_paths(fs)),
+        ];
         TabHandler {
-            suggesters:     vec![Box::new(FileSystemSuggester::new(fs))],
+            suggesters:     suggesters.into_iter().map(SuggesterWorker::new).collect(),
+            registered,
             should_refresh: true,
-            cached_iter:    Box::new(tmp.into_iter().cycle()),
-            suggestion_cnt: 0,
+            job:            None,
+            suggestions:    vec![],
+            next_idx:       0,
+            timeout:        DEFAULT_COMPLETION_TIMEOUT,
+            spinner_frame:  0,
         }
     }
 
+    /// Consult `suggesters` instead of the ones every handler has, as well as the registered ones
     #[cfg(test)]
     pub fn set_suggesters(&mut self, suggesters: Vec<Box<dyn Suggester>>) {
-        self.suggesters = suggesters;
+        self.suggesters = suggesters.into_iter().map(SuggesterWorker::new).collect();
+    }
+
+    #[cfg(test)]
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
     }
 
-    pub fn get_suggesters(&self) -> &Vec<Box<dyn Suggester>> {
-        &self.suggesters
+    #[cfg(test)]
+    pub fn get_suggesters(&self) -> Vec<crate::cmd_input::completion_job::SharedSuggester> {
+        self.suggesters.iter().map(|worker| worker.get_suggester().clone()).collect()
     }
 
     /// Returns an `Option<String>` representing the value that the current token should be
     /// replaced with, or `None` if there are no suggestions.
     ///
-    pub fn get_suggestion(&mut self, prefix: &String) -> Option<String> {
-        debug!("Getting suggestion for prefix '{}'", prefix);
+    /// On refresh, the suggesters are started in the background and this waits for at most the
+    /// completion timeout for them to finish. Suggestions which arrive later are picked up by
+    /// subsequent calls, or by [TabHandler::poll].
+    pub fn get_suggestion(&mut self, context: &CompletionContext) -> Option<String> {
+        debug!("Getting suggestion for context '{:?}'", context);
         if self.should_refresh {
             trace!("Refreshing suggestions...");
-            let mut suggestions = self.suggesters.iter_mut().fold(vec![], |mut acc, s| {
-                acc.append(&mut s.get_suggestions(prefix));
-                acc
-            });
-            suggestions.sort();
-            trace!("Found suggestions: '{:?}'", suggestions);
-            self.suggestion_cnt = suggestions.len();
-            self.cached_iter = Box::new(suggestions.into_iter().cycle());
+            self.cancel();
+            let mut suggesters = self.suggesters.clone();
+            suggesters.extend(self.registered.get_all());
+            let mut job = CompletionJob::start(&suggesters, context);
+            job.poll(self.timeout);
+            self.suggestions = job.get_suggestions();
+            self.next_idx = 0;
+            self.job = Some(job);
             self.should_refresh = false;
+            trace!("Found suggestions: '{:?}'", self.suggestions);
+        }
+        else {
+            self.poll();
+        }
+
+        if self.suggestions.is_empty() {
+            return None;
         }
+        let suggestion = &self.suggestions[self.next_idx % self.suggestions.len()];
+        self.next_idx = (self.next_idx + 1) % self.suggestions.len();
+        Some(suggestion.replacement.clone())
+    }
+
+    /// Collect any suggestions which have arrived since the last call without blocking. Returns
+    /// true if the suggestions or pending state changed.
+    pub fn poll(&mut self) -> bool {
+        // keep cycling from the suggestion that was returned last, wherever it now sorts
+        let last_returned = self.get_last_returned().cloned();
+        let job = match &mut self.job {
+            Some(job) if !job.is_finished() => job,
+            _ => return false,
+        };
+        if !job.poll(Duration::ZERO) {
+            return false;
+        }
+
+        self.suggestions = job.get_suggestions();
+        self.next_idx = last_returned
+            .and_then(|last| self.suggestions.iter().position(|s| *s == last))
+            .map_or(0, |pos| (pos + 1) % self.suggestions.len());
+        trace!("Suggestions after poll: '{:?}'", self.suggestions);
 
-        self.cached_iter.next().map(|suggestion| suggestion.replacement)
+        true
+    }
+
+    fn get_last_returned(&self) -> Option<&Suggestion> {
+        match self.next_idx {
+            0 => self.suggestions.last(),
+            idx => self.suggestions.get(idx - 1),
+        }
+    }
+
+    /// Returns the description of the suggestion [TabHandler::get_suggestion] returned last, if
+    /// it has one
+    pub fn get_description(&self) -> Option<&str> {
+        self.get_last_returned()?.description.as_deref()
+    }
+
+    /// Returns true while suggesters are still running in the background
+    pub fn is_pending(&self) -> bool {
+        self.job.as_ref().is_some_and(|job| !job.is_finished())
+    }
+
+    /// Returns the next frame of the indicator shown while suggestions are pending
+    pub fn next_spinner_frame(&mut self) -> char {
+        self.spinner_frame = (self.spinner_frame + 1) % SPINNER_FRAMES.len();
+        SPINNER_FRAMES[self.spinner_frame]
+    }
+
+    /// Stop any running suggesters and discard their results
+    pub fn cancel(&mut self) {
+        if let Some(mut job) = self.job.take() {
+            job.cancel();
+        }
     }
 
     pub fn refresh(&mut self) {
@@ -56,6 +193,6 @@ impl TabHandler {
     }
 
     pub fn get_suggestion_cnt(&self) -> usize {
-        self.suggestion_cnt
+        self.suggestions.len()
     }
 }
@@ -1,5 +1,23 @@
 #[cfg(test)]
 mod test_cmd_input;
+mod test_command_suggester;
+mod test_directory_suggester;
+mod test_host_suggester;
+mod test_process_suggester;
 mod test_suggester;
 mod test_tab_handler;
 mod test_token;
+mod test_user_suggester;
+mod test_variable_suggester;
+
+use crate::cmd_input::completion_context::CompletionContext;
+
+/// Returns the context for completing the last of the words of `line`, which has at least two
+pub fn context(line: &[&str]) -> CompletionContext {
+    CompletionContext {
+        prefix:    line.last().unwrap().to_string(),
+        command:   Some(line[0].to_string()),
+        previous:  Some(line[line.len() - 2].to_string()),
+        token_idx: line.len() - 1,
+    }
+}
@@ -6,8 +6,11 @@ mod cmd_input_tests {
     use filesystem::{FakeFileSystem, FileSystem};
     use termion::event::Key;
 
-    use crate::cmd_input::DetectCursorPosAlias;
     use crate::fixture::raw_tty_emulator::RawTTYEmulator;
+    use crate::cmd_input::SuggesterRegistry;
+    use crate::intrinsics::{AliasTable, DirectoryDb, IntrinsicRegistry};
+    use crate::prompt::Prompt;
+    use crate::shell::VariableStore;
     use crate::CmdInput;
 
     #[derive(Display, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -37,15 +40,21 @@ mod cmd_input_tests {
         }
     }
 
+    #[cfg(test)]
+    fn new_cmd_input(fs: FakeFileSystem, variables: VariableStore) -> CmdInput {
+        let (aliases, intrinsics, directories) = (AliasTable::new(), IntrinsicRegistry::new(), DirectoryDb::new());
+        CmdInput::new(fs, variables, aliases, intrinsics, directories, SuggesterRegistry::new())
+    }
+
     #[cfg(test)]
     fn setup() -> (CmdInput, RawTTYEmulator) {
-        (CmdInput::new(FakeFileSystem::new()), RawTTYEmulator::new())
+        (new_cmd_input(FakeFileSystem::new(), VariableStore::new()), RawTTYEmulator::new())
     }
 
     #[cfg(test)]
     fn setup_with_fs() -> (CmdInput, RawTTYEmulator, FakeFileSystem) {
         let fs = FakeFileSystem::new();
-        (CmdInput::new(fs.clone()), RawTTYEmulator::new(), fs)
+        (new_cmd_input(fs.clone(), VariableStore::new()), RawTTYEmulator::new(), fs)
     }
 
     #[cfg(test)]
@@ -89,6 +98,60 @@ mod cmd_input_tests {
         assert_eq!(out.get_cursor_pos(), (input.len(), 1));
     }
 
+    #[test]
+    fn test_render_right_prompt() {
+        let (mut cmd, mut out) = setup();
+        let prompt = Prompt {
+            text:  "[12:00]".to_string(),
+            width: 7,
+        };
+        cmd.set_right_prompt(Some(prompt), 20);
+
+        insert_word(&mut cmd, &mut out, "hello");
+        assert_eq!(out.get_line_str(), "hello        [12:00]");
+        assert_eq!(out.get_cursor_pos(), (6, 1));
+
+        // once the input comes too close, the right prompt is no longer shown
+        insert_word(&mut cmd, &mut out, " world");
+        assert_eq!(out.get_line_str(), "hello world ");
+    }
+
+    #[test]
+    fn test_render_description() {
+        let variables = VariableStore::new();
+        variables.set("YEESH_DESCRIBED", "a\tvalue");
+        let (mut cmd, mut out) = (new_cmd_input(FakeFileSystem::new(), variables), RawTTYEmulator::new());
+
+        insert_word(&mut cmd, &mut out, "echo $YEESH_DESCR");
+        cmd.insert(Key::Char('\t'));
+        cmd.render_line(&mut out, 0).expect("Unable to render line");
+        assert_eq!(out.get_line_str(), "echo $YEESH_DESCRIBED    a value");
+        assert!(out.get_cell(26, 1).attrs.dim && !out.get_cell(21, 1).attrs.dim);
+        assert_eq!(out.get_cursor_pos().0, 22);
+
+        // it's cut short at the edge of the terminal, and gone once another key is pressed
+        cmd.set_right_prompt(None, 31);
+        cmd.render_line(&mut out, 0).expect("Unable to render line");
+        assert_eq!(out.get_line_str(), "echo $YEESH_DESCRIBED    a v");
+        cmd.insert(Key::Char('x'));
+        cmd.render_line(&mut out, 0).expect("Unable to render line");
+        assert_eq!(out.get_line_str(), "echo $YEESH_DESCRIBEDx ");
+    }
+
+    #[test]
+    fn test_render_wide_description() {
+        let variables = VariableStore::new();
+        variables.set("YEESH_DESCRIBED", "日本語");
+        let (mut cmd, mut out) = (new_cmd_input(FakeFileSystem::new(), variables), RawTTYEmulator::new());
+
+        // the description is cut short by the columns its characters take up, not their number
+        insert_word(&mut cmd, &mut out, "echo $YEESH_DESCR");
+        cmd.insert(Key::Char('\t'));
+        cmd.set_right_prompt(None, 33);
+        cmd.render_line(&mut out, 0).expect("Unable to render line");
+        assert_eq!(out.get_line_str(), "echo $YEESH_DESCRIBED    日本");
+    }
+
     #[test]
     fn test_cmd_input_empty_after_no_input() {
         let (cmd, _out) = setup();
@@ -0,0 +1,91 @@
+#[cfg(test)]
+mod command_suggester_tests {
+    use filesystem::{FakeFileSystem, FileSystem};
+
+    use crate::cmd_input::command_suggester::CommandSuggester;
+    use crate::cmd_input::completion_context::{Applicability, CompletionContext};
+    use crate::cmd_input::suggester::{Suggester, SuggestionType};
+    use crate::intrinsics::{Alias, AliasTable, ChangeDirectory, IntrinsicRegistry, OutputAdapter};
+
+    fn setup() -> (CommandSuggester<FakeFileSystem>, AliasTable) {
+        let fs = FakeFileSystem::new();
+        fs.create_dir_all("/usr/bin").unwrap();
+        fs.create_dir_all("/bin/subdir").unwrap();
+        fs.create_file("/usr/bin/git", "").unwrap();
+        fs.create_file("/usr/bin/grep", "").unwrap();
+        fs.create_file("/bin/ls", "").unwrap();
+        fs.create_file("/bin/gl", "").unwrap();
+
+        let aliases = AliasTable::new();
+        let intrinsics = IntrinsicRegistry::new();
+        intrinsics.register(Box::new(OutputAdapter(ChangeDirectory::new(fs.clone()))));
+        intrinsics.register(Box::new(Alias));
+        let suggester = CommandSuggester::new(
+            fs,
+            aliases.clone(),
+            intrinsics,
+            Some("/usr/bin:/bin:/does/not/exist".to_string()),
+        );
+        (suggester, aliases)
+    }
+
+    fn replacements(suggester: &mut CommandSuggester<FakeFileSystem>, prefix: &str) -> Vec<String> {
+        suggester
+            .get_suggestions(&prefix.into())
+            .into_iter()
+            .map(|s| s.replacement)
+            .collect()
+    }
+
+    #[test]
+    fn test_path_executables() {
+        let (mut suggester, _) = setup();
+
+        assert_eq!(replacements(&mut suggester, "g"), vec!["git", "gl", "grep"]);
+        assert_eq!(replacements(&mut suggester, "l"), vec!["ls", "alias", "gl"]);
+        assert!(replacements(&mut suggester, "subdir").is_empty());
+    }
+
+    #[test]
+    fn test_aliases_and_intrinsics() {
+        let (mut suggester, aliases) = setup();
+        aliases.set("gst", "git status");
+
+        let suggestions = suggester.get_suggestions(&"gs".into());
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].replacement, "gst");
+        assert_eq!(suggestions[0].s_type, SuggestionType::Alias);
+        assert_eq!(suggestions[0].description.as_deref(), Some("git status"));
+
+        let suggestions = suggester.get_suggestions(&"c".into());
+        assert_eq!(suggestions[0].replacement, "cd");
+        assert_eq!(suggestions[0].s_type, SuggestionType::Intrinsic);
+    }
+
+    #[test]
+    fn test_alias_shadows_executable() {
+        let (mut suggester, aliases) = setup();
+        aliases.set("ls", "ls --color");
+
+        let suggestions = suggester.get_suggestions(&"ls".into());
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].s_type, SuggestionType::Alias);
+    }
+
+    #[test]
+    fn test_applicability() {
+        let (suggester, _) = setup();
+        let argument = CompletionContext {
+            prefix:    "g".to_string(),
+            command:   Some("sudo".to_string()),
+            previous:  Some("sudo".to_string()),
+            token_idx: 1,
+        };
+
+        assert_eq!(suggester.get_applicability(&"g".into()), Applicability::Applicable);
+        assert_eq!(suggester.get_applicability(&"".into()), Applicability::NotApplicable);
+        assert_eq!(suggester.get_applicability(&"./g".into()), Applicability::NotApplicable);
+        assert_eq!(suggester.get_applicability(&"$g".into()), Applicability::NotApplicable);
+        assert_eq!(suggester.get_applicability(&argument), Applicability::NotApplicable);
+    }
+}
@@ -0,0 +1,51 @@
+#[cfg(test)]
+mod directory_suggester_tests {
+    use filesystem::{FakeFileSystem, FileSystem};
+
+    use crate::cmd_input::completion_context::{Applicability, CompletionContext};
+    use crate::cmd_input::directory_suggester::FrecentDirectorySuggester;
+    use crate::cmd_input::suggester::Suggester;
+    use crate::cmd_input::tests::context;
+    use crate::intrinsics::DirectoryDb;
+
+    fn setup() -> FrecentDirectorySuggester<FakeFileSystem> {
+        let fs = FakeFileSystem::new();
+        let directories = DirectoryDb::new();
+        let visits = [("/src/yeesh", 3), ("/src/yeesh/docs", 1), ("/work/yeesh-old", 5), ("/gone/yeesh", 9)];
+        for (dir, visits) in visits {
+            if !dir.starts_with("/gone") {
+                fs.create_dir_all(dir).unwrap();
+            }
+            for _ in 0..visits {
+                directories.visit(&fs, dir.as_ref());
+            }
+        }
+        FrecentDirectorySuggester::new(fs, directories)
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let mut suggester = setup();
+
+        // the most frecent first, leaving out directories which no longer exist
+        let mut suggestions = suggester.get_suggestions(&context(&["cd", "yeesh"]));
+        suggestions.sort();
+        let dirs: Vec<&str> = suggestions.iter().map(|s| s.replacement.as_str()).collect();
+        assert_eq!(dirs, vec!["/work/yeesh-old/", "/src/yeesh/", "/src/yeesh/docs/"]);
+
+        let suggestions = suggester.get_suggestions(&context(&["z", "DOC"]));
+        let dirs: Vec<&str> = suggestions.iter().map(|s| s.replacement.as_str()).collect();
+        assert_eq!(dirs, vec!["/src/yeesh/docs/"]);
+    }
+
+    #[test]
+    fn test_applicability() {
+        let suggester = setup();
+        assert_eq!(suggester.get_applicability(&context(&["z", "ye"])), Applicability::Exclusive);
+        assert_eq!(suggester.get_applicability(&context(&["cd", "ye"])), Applicability::Applicable);
+        assert_eq!(suggester.get_applicability(&context(&["pushd", "ye"])), Applicability::Applicable);
+        assert_eq!(suggester.get_applicability(&context(&["cd", "-"])), Applicability::NotApplicable);
+        assert_eq!(suggester.get_applicability(&context(&["ls", "ye"])), Applicability::NotApplicable);
+        assert_eq!(suggester.get_applicability(&CompletionContext::from("ye")), Applicability::NotApplicable);
+    }
+}
@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod host_suggester_tests {
+    use std::path::PathBuf;
+
+    use filesystem::{FakeFileSystem, FileSystem};
+
+    use crate::cmd_input::completion_context::Applicability;
+    use crate::cmd_input::host_suggester::HostSuggester;
+    use crate::cmd_input::tests::context;
+    use crate::cmd_input::suggester::Suggester;
+
+    const SSH_CONFIG: &str = "\
+# personal machines
+Host desktop laptop
+    HostName 192.168.1.20
+    User person
+
+Host *.internal !bastion
+    ProxyJump bastion
+
+host build-server
+  hostname build.example.com
+";
+
+    const HOSTS: &str = "\
+127.0.0.1   localhost
+::1         localhost ip6-localhost  # loopback
+10.0.0.5    nas nas.lan
+";
+
+    fn setup() -> HostSuggester<FakeFileSystem> {
+        let fs = FakeFileSystem::new();
+        fs.create_dir_all("/home/person/.ssh").unwrap();
+        fs.create_dir_all("/etc").unwrap();
+        fs.create_file("/home/person/.ssh/config", SSH_CONFIG).unwrap();
+        fs.create_file("/etc/hosts", HOSTS).unwrap();
+
+        HostSuggester::new(fs, Some(PathBuf::from("/home/person/.ssh/config")), PathBuf::from("/etc/hosts"))
+    }
+
+    fn replacements(suggester: &mut HostSuggester<FakeFileSystem>, line: &[&str]) -> Vec<String> {
+        suggester
+            .get_suggestions(&context(line))
+            .into_iter()
+            .map(|s| s.replacement)
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_ssh_config() {
+        let hosts = HostSuggester::<FakeFileSystem>::parse_ssh_config(SSH_CONFIG);
+        assert_eq!(
+            hosts,
+            vec![
+                ("desktop".to_string(), Some("192.168.1.20".to_string())),
+                ("laptop".to_string(), Some("192.168.1.20".to_string())),
+                ("build-server".to_string(), Some("build.example.com".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_hosts_file() {
+        let hosts = HostSuggester::<FakeFileSystem>::parse_hosts_file(HOSTS);
+        assert_eq!(hosts, vec!["localhost", "localhost", "ip6-localhost", "nas", "nas.lan"]);
+    }
+
+    #[test]
+    fn test_ssh_suggestions() {
+        let mut suggester = setup();
+
+        assert_eq!(replacements(&mut suggester, &["ssh", "lap"]), vec!["laptop"]);
+        assert_eq!(replacements(&mut suggester, &["ssh", "la"]), vec!["laptop", "nas.lan"]);
+        assert_eq!(replacements(&mut suggester, &["ssh", "na"]), vec!["nas", "nas.lan"]);
+        assert_eq!(
+            replacements(&mut suggester, &["ssh", "localhost"]),
+            vec!["localhost", "ip6-localhost"]
+        );
+    }
+
+    #[test]
+    fn test_user_prefix_preserved() {
+        let mut suggester = setup();
+
+        assert_eq!(replacements(&mut suggester, &["ssh", "root@desk"]), vec!["root@desktop"]);
+    }
+
+    #[test]
+    fn test_scp_appends_colon() {
+        let mut suggester = setup();
+
+        assert_eq!(replacements(&mut suggester, &["scp", "file.txt", "buil"]), vec!["build-server:"]);
+        assert!(replacements(&mut suggester, &["scp", "file.txt", "nas:/tm"]).is_empty());
+    }
+
+    #[test]
+    fn test_description_is_host_name() {
+        let mut suggester = setup();
+
+        let suggestions = suggester.get_suggestions(&context(&["ssh", "desk"]));
+        assert_eq!(suggestions[0].description.as_deref(), Some("192.168.1.20"));
+    }
+
+    #[test]
+    fn test_missing_files() {
+        let mut suggester = HostSuggester::new(
+            FakeFileSystem::new(),
+            Some(PathBuf::from("/nope/config")),
+            PathBuf::from("/nope/hosts"),
+        );
+
+        assert!(replacements(&mut suggester, &["ssh", ""]).is_empty());
+    }
+
+    #[test]
+    fn test_applicability() {
+        let suggester = setup();
+
+        assert_eq!(suggester.get_applicability(&context(&["ssh", "de"])), Applicability::Exclusive);
+        assert_eq!(suggester.get_applicability(&context(&["scp", "de"])), Applicability::Applicable);
+        assert_eq!(
+            suggester.get_applicability(&context(&["ssh", "-i", "ke"])),
+            Applicability::NotApplicable
+        );
+        assert_eq!(suggester.get_applicability(&context(&["ssh", "-"])), Applicability::NotApplicable);
+        assert_eq!(suggester.get_applicability(&context(&["ls", "de"])), Applicability::NotApplicable);
+        assert_eq!(suggester.get_applicability(&"ssh".into()), Applicability::NotApplicable);
+    }
+}
@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod process_suggester_tests {
+    use std::path::PathBuf;
+
+    use filesystem::{FakeFileSystem, FileSystem};
+
+    use crate::cmd_input::completion_context::Applicability;
+    use crate::cmd_input::process_suggester::ProcessSuggester;
+    use crate::cmd_input::suggester::Suggester;
+    use crate::cmd_input::tests::context;
+
+    fn setup(processes: Vec<(&'static str, &'static str)>) -> ProcessSuggester<FakeFileSystem> {
+        let fs = FakeFileSystem::new();
+        fs.create_dir_all("/proc/self").unwrap();
+        fs.create_file("/proc/uptime", "12.5 40.2").unwrap();
+        for (pid, name) in processes {
+            let dir = format!("/proc/{}", pid);
+            fs.create_dir_all(&dir).unwrap();
+            fs.create_file(format!("{}/comm", dir), format!("{}\n", name)).unwrap();
+        }
+
+        ProcessSuggester::new(fs, PathBuf::from("/proc"))
+    }
+
+    #[test]
+    fn test_get_processes() {
+        let suggester = setup(vec![("1", "init"), ("4242", "firefox")]);
+
+        let mut processes = suggester.get_processes();
+        processes.sort();
+        assert_eq!(
+            processes,
+            vec![("1".to_string(), "init".to_string()), ("4242".to_string(), "firefox".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_match_by_pid_and_name() {
+        let mut suggester = setup(vec![("1", "init"), ("42", "bash"), ("4242", "firefox")]);
+
+        let suggestions = suggester.get_suggestions(&context(&["kill", "42"]));
+        let pids: Vec<&str> = suggestions.iter().map(|s| s.replacement.as_str()).collect();
+        assert_eq!(pids, vec!["42", "4242"]);
+        assert_eq!(suggestions[0].description.as_deref(), Some("bash"));
+
+        let suggestions = suggester.get_suggestions(&context(&["kill", "-9", "fire"]));
+        let pids: Vec<&str> = suggestions.iter().map(|s| s.replacement.as_str()).collect();
+        assert_eq!(pids, vec!["4242"]);
+    }
+
+    #[test]
+    fn test_missing_proc() {
+        let mut suggester = ProcessSuggester::new(FakeFileSystem::new(), PathBuf::from("/proc"));
+
+        assert!(suggester.get_suggestions(&context(&["kill", ""])).is_empty());
+    }
+
+    #[test]
+    fn test_applicability() {
+        let suggester = setup(vec![]);
+
+        assert_eq!(suggester.get_applicability(&context(&["kill", ""])), Applicability::Exclusive);
+        assert_eq!(
+            suggester.get_applicability(&context(&["kill", "-9", ""])),
+            Applicability::Exclusive
+        );
+        assert_eq!(
+            suggester.get_applicability(&context(&["kill", "-s", ""])),
+            Applicability::NotApplicable
+        );
+        assert_eq!(suggester.get_applicability(&context(&["kill", "-"])), Applicability::NotApplicable);
+        assert_eq!(suggester.get_applicability(&context(&["ls", ""])), Applicability::NotApplicable);
+    }
+}
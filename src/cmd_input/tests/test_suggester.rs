@@ -36,11 +36,15 @@ mod suggester_tests {
             replacement: "a".to_string(),
             is_prefix:   true,
             s_type:      File,
+            description: None,
+            rank:        0,
         };
         let mut s2 = Suggestion {
             replacement: "b".to_string(),
             is_prefix:   true,
             s_type:      File,
+            description: None,
+            rank:        0,
         };
 
         assert_eq!(s1.cmp(&s2), Less);
@@ -76,10 +80,10 @@ mod suggester_tests {
         let (mut suggester, fs) = setup_filesystem();
         create_directories(&fs, vec!["a", "aa", "b"]).unwrap();
 
-        let mut suggestions = suggester.get_suggestions("b");
+        let mut suggestions = suggester.get_suggestions(&"b".into());
         assert_eq!(suggestions, vec![("b/", true, Directory).into()]);
 
-        suggestions = suggester.get_suggestions("a");
+        suggestions = suggester.get_suggestions(&"a".into());
         assert_eq!(
             suggestions,
             vec![("a/", true, Directory).into(), ("aa/", true, Directory).into()]
@@ -92,10 +96,10 @@ mod suggester_tests {
         create_directories(&fs, vec!["ello"]).unwrap();
         create_files(&fs, vec!["hello", "ello/there"]).unwrap();
 
-        let mut suggestions = suggester.get_suggestions("a");
+        let mut suggestions = suggester.get_suggestions(&"a".into());
         assert_eq!(suggestions, vec![]);
 
-        suggestions = suggester.get_suggestions("a/");
+        suggestions = suggester.get_suggestions(&"a/".into());
         assert_eq!(suggestions, vec![]);
     }
 
@@ -105,7 +109,7 @@ mod suggester_tests {
         create_files(&fs, vec!["hello", "there", "world"]).unwrap();
         create_directories(&fs, vec!["ello", "here", "orld"]).unwrap();
 
-        let mut suggestions = suggester.get_suggestions("");
+        let mut suggestions = suggester.get_suggestions(&"".into());
         assert_eq!(
             suggestions,
             vec![
@@ -118,7 +122,7 @@ mod suggester_tests {
             ]
         );
 
-        suggestions = suggester.get_suggestions("./");
+        suggestions = suggester.get_suggestions(&"./".into());
         assert_eq!(
             suggestions,
             vec![
@@ -131,7 +135,7 @@ mod suggester_tests {
             ]
         );
 
-        suggestions = suggester.get_suggestions("he");
+        suggestions = suggester.get_suggestions(&"he".into());
         assert_eq!(
             suggestions,
             vec![
@@ -141,7 +145,7 @@ mod suggester_tests {
             ]
         );
 
-        suggestions = suggester.get_suggestions("./he");
+        suggestions = suggester.get_suggestions(&"./he".into());
         assert_eq!(
             suggestions,
             vec![
@@ -158,7 +162,7 @@ mod suggester_tests {
         create_directories(&fs, vec!["test", "test/ello"]).unwrap();
         create_files(&fs, vec!["test/hello", "test/there", "test/world"]).unwrap();
 
-        let mut suggestions = suggester.get_suggestions("test/");
+        let mut suggestions = suggester.get_suggestions(&"test/".into());
         assert_eq!(
             suggestions,
             vec![
@@ -169,7 +173,7 @@ mod suggester_tests {
             ]
         );
 
-        suggestions = suggester.get_suggestions("./test/");
+        suggestions = suggester.get_suggestions(&"./test/".into());
         assert_eq!(
             suggestions,
             vec![
@@ -180,13 +184,13 @@ mod suggester_tests {
             ]
         );
 
-        suggestions = suggester.get_suggestions("test/he");
+        suggestions = suggester.get_suggestions(&"test/he".into());
         assert_eq!(
             suggestions,
             vec![("test/hello", true, File).into(), ("test/there", false, File).into(),]
         );
 
-        suggestions = suggester.get_suggestions("./test/he");
+        suggestions = suggester.get_suggestions(&"./test/he".into());
         assert_eq!(
             suggestions,
             vec![
@@ -203,7 +207,7 @@ mod suggester_tests {
         create_files(&fs, vec!["hello", "there", "world"]).unwrap();
         fs.set_current_dir(Path::new("ello")).unwrap();
 
-        let mut suggestions = suggester.get_suggestions("/");
+        let mut suggestions = suggester.get_suggestions(&"/".into());
         assert_eq!(
             suggestions,
             vec![
@@ -216,7 +220,7 @@ mod suggester_tests {
             ]
         );
 
-        suggestions = suggester.get_suggestions("/he");
+        suggestions = suggester.get_suggestions(&"/he".into());
         assert_eq!(
             suggestions,
             vec![
@@ -1,15 +1,25 @@
 #[cfg(test)]
 mod tab_handler_tests {
+    use std::sync::mpsc::{channel, Receiver};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
     use filesystem::FakeFileSystem;
 
+    use crate::cmd_input::completion_context::{Applicability, CompletionContext};
+    use crate::cmd_input::completion_job::SuggestionSink;
     use crate::cmd_input::suggester::SuggestionType::File;
     use crate::cmd_input::suggester::{Suggester, Suggestion, SuggestionType};
-    use crate::cmd_input::TabHandler;
+    use crate::cmd_input::{SuggesterRegistry, TabHandler};
+    use crate::intrinsics::{AliasTable, DirectoryDb, IntrinsicRegistry};
+    use crate::shell::VariableStore;
 
     #[derive(Clone)]
     struct TestSuggester {
         suggestions:        Vec<Suggestion>,
         get_suggestion_cnt: usize,
+        applicability:      Applicability,
     }
 
     impl From<(&'static str, bool, SuggestionType)> for Suggestion {
@@ -18,6 +28,8 @@ mod tab_handler_tests {
                 replacement: replacement.to_string(),
                 is_prefix,
                 s_type,
+                description: None,
+                rank: 0,
             }
         }
     }
@@ -27,23 +39,69 @@ mod tab_handler_tests {
             TestSuggester {
                 suggestions:        suggestions.into_iter().map(|x| x.into()).collect(),
                 get_suggestion_cnt: 0,
+                applicability:      Applicability::Applicable,
             }
         }
+
+        pub fn with_applicability(mut self, applicability: Applicability) -> Self {
+            self.applicability = applicability;
+            self
+        }
     }
 
     impl Suggester for TestSuggester {
-        fn get_suggestions(&mut self, _: &str) -> Vec<Suggestion> {
+        fn get_suggestions(&mut self, _: &CompletionContext) -> Vec<Suggestion> {
             self.get_suggestion_cnt += 1;
             self.suggestions.to_vec()
         }
 
+        fn get_applicability(&self, _: &CompletionContext) -> Applicability {
+            self.applicability
+        }
+
         fn get_get_suggestion_count(&self) -> usize {
             self.get_suggestion_cnt
         }
     }
 
+    /// A suggester which sends one suggestion, then blocks until `release` is signalled before
+    /// sending the rest
+    struct SlowSuggester {
+        release:   Arc<Mutex<Receiver<()>>>,
+        cancelled: Arc<Mutex<bool>>,
+    }
+
+    impl Suggester for SlowSuggester {
+        fn get_suggestions(&mut self, _: &CompletionContext) -> Vec<Suggestion> {
+            vec![]
+        }
+
+        fn stream_suggestions(&mut self, _: &CompletionContext, sink: &SuggestionSink) {
+            sink.send(("fast", true, File).into());
+            let _ = self.release.lock().unwrap().recv();
+            if !sink.send(("slow", true, File).into()) {
+                *self.cancelled.lock().unwrap() = true;
+            }
+        }
+
+        fn get_get_suggestion_count(&self) -> usize {
+            0
+        }
+    }
+
     fn setup(suggesters: Vec<Box<dyn Suggester>>) -> TabHandler {
-        let mut handler = TabHandler::new(FakeFileSystem::new());
+        setup_with_registered(suggesters, SuggesterRegistry::new())
+    }
+
+    fn setup_with_registered(suggesters: Vec<Box<dyn Suggester>>, registered: SuggesterRegistry) -> TabHandler {
+        let mut handler = TabHandler::new(
+            FakeFileSystem::new(),
+            VariableStore::new(),
+            AliasTable::new(),
+            IntrinsicRegistry::new(),
+            DirectoryDb::new(),
+            registered,
+        );
         handler.set_suggesters(suggesters);
         handler
     }
@@ -54,23 +112,21 @@ mod tab_handler_tests {
         let suggester = TestSuggester::new(suggestions.clone());
         let mut handler = setup(vec![Box::new(suggester)]);
 
-        let a = &"a".to_string();
-        let b = &"b".to_string();
+        let a = &CompletionContext::from("a");
+        let b = &CompletionContext::from("b");
 
         handler.get_suggestion(a);
-        let suggest_count_save = handler.get_suggesters()[0].get_get_suggestion_count();
+        let suggest_count_save = handler.get_suggesters()[0].lock().unwrap().get_get_suggestion_count();
         handler.get_suggestion(a);
         assert_eq!(
             suggest_count_save,
-            handler.get_suggesters()[0].get_get_suggestion_count()
+            handler.get_suggesters()[0].lock().unwrap().get_get_suggestion_count()
         );
 
         handler.refresh();
         handler.get_suggestion(b);
-        assert_eq!(
-            suggest_count_save + 1,
-            handler.get_suggesters()[0].get_get_suggestion_count()
-        )
+        let suggest_count = handler.get_suggesters()[0].lock().unwrap().get_get_suggestion_count();
+        assert_eq!(suggest_count_save + 1, suggest_count);
     }
 
     #[test]
@@ -79,7 +135,7 @@ mod tab_handler_tests {
         let suggester = TestSuggester::new(suggestions.clone());
         let mut handler = setup(vec![Box::new(suggester)]);
 
-        let a = &"".to_string();
+        let a = &CompletionContext::from("");
 
         let s = handler.get_suggestion(a);
         assert!(s.is_some());
@@ -93,4 +149,142 @@ mod tab_handler_tests {
         assert!(s.is_some());
         assert_eq!(s.unwrap(), suggestions[0].0);
     }
+
+    #[test]
+    fn test_applicability() {
+        let applicable = TestSuggester::new(vec![("file", true, File)]);
+        let exclusive = TestSuggester::new(vec![("host", true, File)]).with_applicability(Applicability::Exclusive);
+        let not_applicable =
+            TestSuggester::new(vec![("nope", true, File)]).with_applicability(Applicability::NotApplicable);
+        let a = &CompletionContext::from("");
+
+        // exclusive suggesters hide applicable ones
+        let mut handler = setup(vec![
+            Box::new(applicable.clone()),
+            Box::new(exclusive),
+            Box::new(not_applicable.clone()),
+        ]);
+        assert_eq!(handler.get_suggestion(a).unwrap(), "host");
+        assert_eq!(handler.get_suggestion(a).unwrap(), "host");
+        assert_eq!(handler.get_suggestion_cnt(), 1);
+
+        // without an exclusive suggester, every applicable one is used
+        let mut handler = setup(vec![Box::new(applicable), Box::new(not_applicable)]);
+        assert_eq!(handler.get_suggestion(a).unwrap(), "file");
+        assert_eq!(handler.get_suggestion_cnt(), 1);
+    }
+
+    #[test]
+    fn test_registered_suggesters() {
+        let registered = SuggesterRegistry::new();
+        let suggester = TestSuggester::new(vec![("file", true, File)]);
+        let mut handler = setup_with_registered(vec![Box::new(suggester)], registered.clone());
+        let a = &CompletionContext::from("");
+        assert_eq!(handler.get_suggestion(a).unwrap(), "file");
+        assert_eq!(handler.get_suggestion(a).unwrap(), "file");
+
+        // a suggester registered after the handler was created is consulted from the next refresh
+        registered.register(Box::new(TestSuggester::new(vec![("registered", true, File)])));
+        handler.refresh();
+        let mut suggestions = vec![handler.get_suggestion(a).unwrap(), handler.get_suggestion(a).unwrap()];
+        suggestions.sort();
+        assert_eq!(suggestions, vec!["file", "registered"]);
+    }
+
+    #[test]
+    fn test_partial_results_after_timeout() {
+        let (release, release_rx) = channel();
+        let suggester = SlowSuggester {
+            release:   Arc::new(Mutex::new(release_rx)),
+            cancelled: Arc::new(Mutex::new(false)),
+        };
+        let mut handler = setup(vec![Box::new(suggester)]);
+        handler.set_timeout(Duration::from_millis(20));
+        let a = &CompletionContext::from("");
+
+        // only the first suggestion arrives before the timeout
+        assert_eq!(handler.get_suggestion(a).unwrap(), "fast");
+        assert!(handler.is_pending());
+        assert_eq!(handler.get_suggestion_cnt(), 1);
+
+        release.send(()).unwrap();
+        while handler.is_pending() {
+            handler.poll();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(handler.get_suggestion_cnt(), 2);
+        assert_eq!(handler.get_suggestion(a).unwrap(), "slow");
+        assert_eq!(handler.get_suggestion(a).unwrap(), "fast");
+    }
+
+    #[test]
+    fn test_cancel() {
+        let (release, release_rx) = channel();
+        let cancelled = Arc::new(Mutex::new(false));
+        let suggester = SlowSuggester {
+            release:   Arc::new(Mutex::new(release_rx)),
+            cancelled: cancelled.clone(),
+        };
+        let mut handler = setup(vec![Box::new(suggester)]);
+        handler.set_timeout(Duration::from_millis(20));
+
+        handler.get_suggestion(&CompletionContext::from(""));
+        assert!(handler.is_pending());
+        handler.cancel();
+        assert!(!handler.is_pending());
+
+        // the suggester notices it has been cancelled the next time it sends a suggestion
+        release.send(()).unwrap();
+        let suggester = handler.get_suggesters()[0].clone();
+        drop(suggester.lock().unwrap());
+        assert!(*cancelled.lock().unwrap());
+    }
+
+    #[test]
+    fn test_cancelled_before_start() {
+        let mut handler = setup(vec![Box::new(TestSuggester::new(vec![("file", true, File)]))]);
+        handler.set_timeout(Duration::from_millis(20));
+        let a = &CompletionContext::from("");
+
+        // the job is cancelled while its request is still waiting for the suggester
+        let suggester = handler.get_suggesters()[0].clone();
+        let guard = suggester.lock().unwrap();
+        assert_eq!(handler.get_suggestion(a), None);
+        assert!(handler.is_pending());
+        handler.cancel();
+        drop(guard);
+
+        // once the worker is free, the next job runs the suggester, which the cancelled one never did
+        while handler.get_suggestion(a).is_none() {
+            thread::sleep(Duration::from_millis(1));
+            handler.refresh();
+        }
+        assert_eq!(suggester.lock().unwrap().get_get_suggestion_count(), 1);
+    }
+
+    #[test]
+    fn test_busy_suggester_skipped() {
+        let (release, release_rx) = channel();
+        let suggester = SlowSuggester {
+            release:   Arc::new(Mutex::new(release_rx)),
+            cancelled: Arc::new(Mutex::new(false)),
+        };
+        let mut handler = setup(vec![Box::new(suggester)]);
+        handler.set_timeout(Duration::from_millis(20));
+
+        let a = &CompletionContext::from("");
+        assert_eq!(handler.get_suggestion(a).unwrap(), "fast");
+        assert!(handler.is_pending());
+
+        // while the first job's suggester is stuck, the next job finishes without it
+        handler.refresh();
+        assert_eq!(handler.get_suggestion(a), None);
+        assert!(!handler.is_pending());
+
+        release.send(()).unwrap();
+        let suggester = handler.get_suggesters()[0].clone();
+        drop(suggester.lock().unwrap());
+        handler.refresh();
+        assert_eq!(handler.get_suggestion(a).unwrap(), "fast");
+    }
 }
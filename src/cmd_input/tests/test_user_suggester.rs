@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod user_suggester_tests {
+    use std::path::PathBuf;
+
+    use filesystem::{FakeFileSystem, FileSystem};
+
+    use crate::cmd_input::completion_context::Applicability;
+    use crate::cmd_input::suggester::Suggester;
+    use crate::cmd_input::tests::context;
+    use crate::cmd_input::user_suggester::UserSuggester;
+
+    const PASSWD: &str = "\
+root:x:0:0:root:/root:/bin/bash
+daemon:x:1:1:daemon:/usr/sbin:/usr/sbin/nologin
+# a comment
+person:x:1000:1000:Some Person,,,:/home/person:/bin/zsh
+postgres:x:112:120::/var/lib/postgresql:/bin/bash
+broken line
+";
+
+    fn setup() -> UserSuggester<FakeFileSystem> {
+        let fs = FakeFileSystem::new();
+        fs.create_dir_all("/etc").unwrap();
+        fs.create_file("/etc/passwd", PASSWD).unwrap();
+
+        UserSuggester::new(fs, PathBuf::from("/etc/passwd"))
+    }
+
+    #[test]
+    fn test_parse_passwd() {
+        let users = UserSuggester::<FakeFileSystem>::parse_passwd(PASSWD);
+        assert_eq!(
+            users,
+            vec![
+                ("root".to_string(), "root".to_string()),
+                ("daemon".to_string(), "daemon".to_string()),
+                ("person".to_string(), "Some Person".to_string()),
+                ("postgres".to_string(), "/var/lib/postgresql".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let mut suggester = setup();
+
+        let suggestions = suggester.get_suggestions(&context(&["sudo", "-u", "p"]));
+        let names: Vec<&str> = suggestions.iter().map(|s| s.replacement.as_str()).collect();
+        assert_eq!(names, vec!["person", "postgres"]);
+        assert_eq!(suggestions[0].description.as_deref(), Some("Some Person"));
+
+        let suggestions = suggester.get_suggestions(&context(&["su", "oo"]));
+        let names: Vec<&str> = suggestions.iter().map(|s| s.replacement.as_str()).collect();
+        assert_eq!(names, vec!["root"]);
+    }
+
+    #[test]
+    fn test_missing_passwd() {
+        let mut suggester = UserSuggester::new(FakeFileSystem::new(), PathBuf::from("/etc/passwd"));
+
+        assert!(suggester.get_suggestions(&context(&["su", ""])).is_empty());
+    }
+
+    #[test]
+    fn test_applicability() {
+        let suggester = setup();
+
+        assert_eq!(
+            suggester.get_applicability(&context(&["sudo", "-u", "r"])),
+            Applicability::Exclusive
+        );
+        assert_eq!(suggester.get_applicability(&context(&["su", "r"])), Applicability::Exclusive);
+        assert_eq!(suggester.get_applicability(&context(&["su", "-"])), Applicability::NotApplicable);
+        assert_eq!(
+            suggester.get_applicability(&context(&["sudo", "ls"])),
+            Applicability::NotApplicable
+        );
+        assert_eq!(
+            suggester.get_applicability(&context(&["ls", "-u", "r"])),
+            Applicability::NotApplicable
+        );
+    }
+}
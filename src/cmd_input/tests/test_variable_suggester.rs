@@ -0,0 +1,94 @@
+#[cfg(test)]
+mod variable_suggester_tests {
+    use crate::cmd_input::completion_context::Applicability;
+    use crate::cmd_input::suggester::{Suggester, SuggestionType};
+    use crate::cmd_input::variable_suggester::{VariableSource, VariableSuggester};
+
+    struct FakeVariableSource {
+        variables: Vec<(String, String)>,
+    }
+
+    impl VariableSource for FakeVariableSource {
+        fn get_variables(&self) -> Vec<(String, String)> {
+            self.variables.clone()
+        }
+    }
+
+    fn setup(variables: Vec<(&'static str, &'static str)>) -> VariableSuggester<FakeVariableSource> {
+        VariableSuggester::new(FakeVariableSource {
+            variables: variables
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        })
+    }
+
+    fn replacements(suggester: &mut impl Suggester, prefix: &str) -> Vec<String> {
+        suggester
+            .get_suggestions(&prefix.into())
+            .into_iter()
+            .map(|s| s.replacement)
+            .collect()
+    }
+
+    #[test]
+    fn test_split_prefix() {
+        type VarSuggester = VariableSuggester<FakeVariableSource>;
+        assert_eq!(VarSuggester::split_prefix("$HO"), Some(("$", "HO")));
+        assert_eq!(VarSuggester::split_prefix("${HO"), Some(("${", "HO")));
+        assert_eq!(VarSuggester::split_prefix("$"), Some(("$", "")));
+        assert_eq!(VarSuggester::split_prefix("HOME"), None);
+        assert_eq!(VarSuggester::split_prefix(""), None);
+    }
+
+    #[test]
+    fn test_inactive_without_sigil() {
+        let mut suggester = setup(vec![("HOME", "/home/person")]);
+
+        assert!(suggester.get_suggestions(&"HO".into()).is_empty());
+        assert!(suggester.get_suggestions(&"".into()).is_empty());
+        assert_eq!(suggester.get_applicability(&"HO".into()), Applicability::NotApplicable);
+        assert_eq!(suggester.get_applicability(&"$HO".into()), Applicability::Exclusive);
+    }
+
+    #[test]
+    fn test_dollar_prefix() {
+        let mut suggester = setup(vec![("HOME", "/home/person"), ("HOSTNAME", "box"), ("PATH", "/bin")]);
+
+        assert_eq!(replacements(&mut suggester, "$HO"), vec!["$HOME", "$HOSTNAME"]);
+        assert_eq!(replacements(&mut suggester, "$"), vec!["$HOME", "$HOSTNAME", "$PATH"]);
+        assert!(replacements(&mut suggester, "$XYZ").is_empty());
+    }
+
+    #[test]
+    fn test_brace_prefix() {
+        let mut suggester = setup(vec![("HOME", "/home/person"), ("PATH", "/bin")]);
+
+        assert_eq!(replacements(&mut suggester, "${HO"), vec!["${HOME}"]);
+        assert_eq!(replacements(&mut suggester, "${"), vec!["${HOME}", "${PATH}"]);
+    }
+
+    #[test]
+    fn test_prefix_matches_sorted_first() {
+        let mut suggester = setup(vec![("MY_PATH", "a"), ("PATH", "b")]);
+
+        assert_eq!(replacements(&mut suggester, "$PA"), vec!["$PATH", "$MY_PATH"]);
+    }
+
+    #[test]
+    fn test_description_is_value() {
+        let long_value = "x".repeat(100);
+        let mut suggester = VariableSuggester::new(FakeVariableSource {
+            variables: vec![("HOME".to_string(), "/home/person".to_string()), ("LONG".to_string(), long_value)],
+        });
+
+        let suggestions = suggester.get_suggestions(&"$".into());
+        assert_eq!(suggestions.len(), 2);
+        assert_eq!(suggestions[0].s_type, SuggestionType::Variable);
+        assert_eq!(suggestions[0].description.as_deref(), Some("/home/person"));
+
+        let truncated = suggestions[1].description.as_ref().unwrap();
+        assert_eq!(truncated.chars().count(), 40);
+        assert!(truncated.ends_with("..."));
+    }
+}
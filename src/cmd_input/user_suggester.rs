@@ -0,0 +1,104 @@
+use std::path::PathBuf;
+
+use filesystem::FileSystem;
+use itertools::Itertools;
+use log::{debug, trace};
+
+use crate::cmd_input::completion_context::{Applicability, CompletionContext};
+use crate::cmd_input::suggester::{Suggester, Suggestion, SuggestionType};
+
+/// Commands whose non-option arguments are user names
+const USER_COMMANDS: [&str; 1] = ["su"];
+/// Commands which take a user name as the value of their `-u` option
+const USER_OPTION_COMMANDS: [&str; 2] = ["sudo", "doas"];
+
+/// Suggests user names from the passwd database when completing `su <user>` or `sudo -u <user>`
+#[derive(Clone)]
+pub struct UserSuggester<T>
+where
+    T: FileSystem,
+{
+    filesystem:  T,
+    passwd_path: PathBuf,
+}
+
+impl<T: FileSystem> UserSuggester<T> {
+    pub fn new(filesystem: T, passwd_path: PathBuf) -> Self {
+        UserSuggester {
+            filesystem,
+            passwd_path,
+        }
+    }
+
+    /// Create a [UserSuggester] which reads `/etc/passwd`
+    pub fn with_default_paths(filesystem: T) -> Self {
+        Self::new(filesystem, PathBuf::from("/etc/passwd"))
+    }
+
+    /// Return the user names in a passwd file, along with their full name (or home directory
+    /// if the full name is empty)
+    pub(super) fn parse_passwd(contents: &str) -> Vec<(String, String)> {
+        contents
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split(':').collect();
+                if fields.len() < 6 || fields[0].is_empty() {
+                    return None;
+                }
+                let full_name = fields[4].split(',').next().unwrap_or_default();
+                let description = if full_name.is_empty() { fields[5] } else { full_name };
+                Some((fields[0].to_string(), description.to_string()))
+            })
+            .collect()
+    }
+}
+
+impl<T: FileSystem + Send> Suggester for UserSuggester<T> {
+    fn get_suggestions(&mut self, context: &CompletionContext) -> Vec<Suggestion> {
+        debug!("UserSuggester - Getting suggestions for prefix '{}'", context.prefix);
+        let contents = match self.filesystem.read_file_to_string(&self.passwd_path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                debug!("Unable to read '{}': {}", self.passwd_path.display(), e);
+                return vec![];
+            }
+        };
+
+        let suggestions: Vec<Suggestion> = Self::parse_passwd(&contents)
+            .into_iter()
+            .filter(|(name, _)| name.contains(&context.prefix))
+            .map(|(name, description)| Suggestion {
+                is_prefix:   name.starts_with(&context.prefix),
+                replacement: name,
+                s_type:      SuggestionType::User,
+                description: Some(description),
+                rank:        0,
+            })
+            .sorted()
+            .collect();
+        trace!("Found user suggestions: {:?}", suggestions);
+
+        suggestions
+    }
+
+    fn get_applicability(&self, context: &CompletionContext) -> Applicability {
+        let is_user_option = context.previous.as_deref() == Some("-u");
+        if context.is_option() {
+            Applicability::NotApplicable
+        }
+        else if context.is_argument_of(&USER_COMMANDS)
+            || (is_user_option && context.is_argument_of(&USER_OPTION_COMMANDS))
+        {
+            Applicability::Exclusive
+        }
+        else {
+            Applicability::NotApplicable
+        }
+    }
+
+    #[cfg(test)]
+    fn get_get_suggestion_count(&self) -> usize {
+        0
+    }
+}
@@ -0,0 +1,104 @@
+use std::env;
+
+use itertools::Itertools;
+use log::{debug, trace};
+
+use crate::cmd_input::completion_context::{Applicability, CompletionContext};
+use crate::cmd_input::suggester::{Suggester, Suggestion, SuggestionType};
+
+/// Values longer than this are truncated when shown as a suggestion's description
+const MAX_DESCRIPTION_LEN: usize = 40;
+
+/// Anything which can provide a list of variable names along with their current values
+pub trait VariableSource: Send {
+    fn get_variables(&self) -> Vec<(String, String)>;
+}
+
+/// A [VariableSource] backed by the environment of the shell process
+#[derive(Clone)]
+pub struct EnvVariableSource;
+
+impl VariableSource for EnvVariableSource {
+    fn get_variables(&self) -> Vec<(String, String)> {
+        env::vars().collect()
+    }
+}
+
+/// Suggests variable names for tokens beginning with `$` or `${`
+#[derive(Clone)]
+pub struct VariableSuggester<T>
+where
+    T: VariableSource,
+{
+    source: T,
+}
+
+impl<T: VariableSource> VariableSuggester<T> {
+    pub fn new(source: T) -> Self {
+        VariableSuggester { source }
+    }
+
+    /// Split a token such as `${HO` into its sigil (`${`) and the partially typed variable name
+    /// (`HO`). Returns `None` if the token does not refer to a variable.
+    pub(super) fn split_prefix(prefix: &str) -> Option<(&'static str, &str)> {
+        if let Some(name) = prefix.strip_prefix("${") {
+            Some(("${", name))
+        }
+        else {
+            prefix.strip_prefix('$').map(|name| ("$", name))
+        }
+    }
+
+    fn get_description(value: &str) -> String {
+        if value.chars().count() > MAX_DESCRIPTION_LEN {
+            value.chars().take(MAX_DESCRIPTION_LEN - 3).chain("...".chars()).collect()
+        }
+        else {
+            value.to_string()
+        }
+    }
+}
+
+impl<T: VariableSource> Suggester for VariableSuggester<T> {
+    fn get_suggestions(&mut self, context: &CompletionContext) -> Vec<Suggestion> {
+        let (sigil, partial_name) = match Self::split_prefix(&context.prefix) {
+            Some(split) => split,
+            None => return vec![],
+        };
+        debug!("VariableSuggester - Getting suggestions for variable '{}'", partial_name);
+        let closing = if sigil == "${" { "}" } else { "" };
+
+        let suggestions: Vec<Suggestion> = self
+            .source
+            .get_variables()
+            .into_iter()
+            .filter(|(name, _)| name.contains(partial_name))
+            .map(|(name, value)| Suggestion {
+                replacement: format!("{}{}{}", sigil, name, closing),
+                is_prefix:   name.starts_with(partial_name),
+                s_type:      SuggestionType::Variable,
+                description: Some(Self::get_description(&value)),
+                rank:        0,
+            })
+            .sorted()
+            .dedup_by(|a, b| a.replacement == b.replacement)
+            .collect();
+        trace!("Found variable suggestions: {:?}", suggestions);
+
+        suggestions
+    }
+
+    fn get_applicability(&self, context: &CompletionContext) -> Applicability {
+        if Self::split_prefix(&context.prefix).is_some() {
+            Applicability::Exclusive
+        }
+        else {
+            Applicability::NotApplicable
+        }
+    }
+
+    #[cfg(test)]
+    fn get_get_suggestion_count(&self) -> usize {
+        0
+    }
+}
@@ -1,3 +1,5 @@
 mod error;
 
-pub use error::*;
\ No newline at end of file
+// nothing returns the error type yet
+#[allow(unused_imports)]
+pub use error::*;
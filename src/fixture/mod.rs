@@ -1,2 +1,23 @@
 pub mod raw_tty_emulator;
 mod test_raw_tty_emulator;
+
+use std::io;
+
+use crate::cmd_input::{DetectCursorPosAlias, IoWriteAlias};
+use crate::fixture::raw_tty_emulator::RawTTYEmulator;
+
+impl DetectCursorPosAlias for RawTTYEmulator {
+    fn get_cursor_pos(&mut self) -> (usize, usize) {
+        RawTTYEmulator::get_cursor_pos(self)
+    }
+}
+
+impl IoWriteAlias for RawTTYEmulator {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        RawTTYEmulator::write(self, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
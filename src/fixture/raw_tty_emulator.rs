@@ -1,110 +1,306 @@
+use std::fmt;
 use std::io;
 use std::io::ErrorKind;
-use std::ops::{Add, AddAssign, Sub, SubAssign};
-use std::slice::Iter;
-use std::str::FromStr;
-
-use crate::cmd_input::{DetectCursorPosAlias, IoWriteAlias};
-
-enum EscapeType {
-    HOME,
-    UP,
-    DOWN,
-    LEFT,
-    RIGHT,
-    CLEAR_AFTER_CURSOR,
-    IGNORE,
-    UNKNOWN,
+
+/// The size of a terminal made with [RawTTYEmulator::new]
+const DEFAULT_SIZE: (usize, usize) = (80, 24);
+/// Columns between tab stops
+const TAB_WIDTH: usize = 8;
+/// The names of the first 16 colours, as `snapshot` writes them
+const COLOR_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+/// A colour a cell's text or background is drawn in
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Color {
+    #[default]
+    Default,
+    /// one of the 256 colours of the palette, the first 16 being the normal and bright colours
+    Indexed(u8),
+    Rgb(u8, u8, u8),
 }
 
-impl From<u8> for EscapeType {
-    fn from(input: u8) -> Self {
-        match input {
-            b'H' => Self::HOME,
-            b'A' => Self::UP,
-            b'B' => Self::DOWN,
-            b'C' => Self::RIGHT,
-            b'D' => Self::LEFT,
-            b'J' => Self::CLEAR_AFTER_CURSOR,
-            b'?' => Self::IGNORE,
-            _ => Self::UNKNOWN,
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Color::Default => write!(f, "default"),
+            Color::Indexed(n @ 0..=7) => write!(f, "{}", COLOR_NAMES[n as usize]),
+            Color::Indexed(n @ 8..=15) => write!(f, "bright-{}", COLOR_NAMES[n as usize - 8]),
+            Color::Indexed(n) => write!(f, "{}", n),
+            Color::Rgb(r, g, b) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
         }
     }
 }
 
-#[derive(PartialOrd, PartialEq, Ord, Eq, Debug)]
-struct CursorPos(pub usize, pub usize);
-impl Add<(usize, usize)> for CursorPos {
-    type Output = CursorPos;
+/// How a cell's text is drawn, as set by SGR (`ESC [ ... m`) sequences
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Attributes {
+    pub fg:            Color,
+    pub bg:            Color,
+    pub bold:          bool,
+    pub dim:           bool,
+    pub italic:        bool,
+    pub underline:     bool,
+    pub blink:         bool,
+    pub inverse:       bool,
+    pub hidden:        bool,
+    pub strikethrough: bool,
+}
 
-    fn add(self, rhs: (usize, usize)) -> Self::Output {
-        CursorPos(self.0 + rhs.0, self.1 + rhs.1)
+impl fmt::Display for Attributes {
+    /// Writes the attributes which differ from the defaults, e.g. `bold fg=red bg=#102030`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags = [
+            (self.bold, "bold"),
+            (self.dim, "dim"),
+            (self.italic, "italic"),
+            (self.underline, "underline"),
+            (self.blink, "blink"),
+            (self.inverse, "inverse"),
+            (self.hidden, "hidden"),
+            (self.strikethrough, "strikethrough"),
+        ];
+        let mut words: Vec<String> =
+            flags.iter().filter(|(set, _)| *set).map(|(_, name)| name.to_string()).collect();
+        if self.fg != Color::Default {
+            words.push(format!("fg={}", self.fg));
+        }
+        if self.bg != Color::Default {
+            words.push(format!("bg={}", self.bg));
+        }
+        write!(f, "{}", words.join(" "))
     }
 }
-impl AddAssign<(usize, usize)> for CursorPos {
-    fn add_assign(&mut self, rhs: (usize, usize)) {
-        self.0 += rhs.0;
-        self.1 += rhs.1;
-    }
+
+/// A character on the screen and how it's drawn
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Cell {
+    pub c:     char,
+    pub attrs: Attributes,
 }
-impl Sub<(usize, usize)> for CursorPos {
-    type Output = CursorPos;
 
-    fn sub(self, rhs: (usize, usize)) -> Self::Output {
-        let new_0 = if rhs.0 <= self.0 { self.0 - rhs.0 } else { 0 };
-        let new_1 = if rhs.1 <= self.1 { self.1 - rhs.1 } else { 0 };
-        CursorPos(new_0, new_1)
+impl Cell {
+    fn blank(bg: Color) -> Cell {
+        Cell {
+            c:     ' ',
+            attrs: Attributes { bg, ..Attributes::default() },
+        }
     }
-}
-impl SubAssign<(usize, usize)> for CursorPos {
-    fn sub_assign(&mut self, rhs: (usize, usize)) {
-        self.0 -= if rhs.0 <= self.0 { rhs.0 } else { self.0 };
-        self.1 -= if rhs.1 <= self.1 { rhs.1 } else { self.1 };
+
+    fn is_default_blank(&self) -> bool {
+        *self == Cell::blank(Color::Default)
     }
 }
 
-#[derive(PartialOrd, PartialEq, Ord, Eq, Debug)]
+/// Where the parser is in the output, which can end in the middle of an escape sequence or a
+/// character and carry on in the next write
+#[derive(PartialEq, Eq, Debug)]
+enum ParseState {
+    Ground,
+    /// after `ESC`, with any intermediate bytes, e.g. `(` in `ESC ( B`
+    Escape(Vec<u8>),
+    /// after `ESC [`, with the parameter and intermediate bytes so far
+    Csi(Vec<u8>),
+    /// after `ESC ]`, with the string so far and whether it was just followed by `ESC`
+    Osc(Vec<u8>, bool),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct CursorPos(pub usize, pub usize);
+
+/// The position and attributes `ESC 7` and `ESC [ s` save
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct SavedCursor {
+    pos:   CursorPos,
+    attrs: Attributes,
+}
+
+/// A virtual terminal for tests: a fixed size screen of cells, which output written to it is drawn
+/// on as an xterm would draw it. Rows are only stored as far as they have been written to, so
+/// [get_text](RawTTYEmulator::get_text) leaves out the untouched end of the screen. Every
+/// character takes up one column. Nothing else from the crate is used here, so that the
+/// integration tests can include this file too.
+#[derive(PartialEq, Eq, Debug)]
 pub struct RawTTYEmulator {
-    text:       Vec<Vec<char>>,
-    cursor_pos: CursorPos,
+    width:           usize,
+    height:          usize,
+    text:            Vec<Vec<Cell>>,
+    cursor_pos:      CursorPos,
+    /// what newly written characters are drawn with
+    attrs:           Attributes,
+    /// set once a character is written in the last column, so the next one wraps
+    wrap_pending:    bool,
+    autowrap:        bool,
+    cursor_visible:  bool,
+    /// set while the mouse's clicks or movements are reported
+    mouse_reporting: bool,
+    /// the first and last rows scrolled by line feeds, inclusive
+    scroll_region:   (usize, usize),
+    saved_cursor:    Option<SavedCursor>,
+    /// the normal screen's rows while the alternate screen is shown
+    main_screen:     Option<Vec<Vec<Cell>>>,
+    /// the rows scrolled off the top of the normal screen, oldest first
+    scrollback:      Vec<Vec<Cell>>,
+    title:           String,
+    state:           ParseState,
+    /// the bytes of a UTF-8 character which has only been partly written
+    utf8:            Vec<u8>,
+    /// what the terminal has sent back in answer to requests for reports
+    responses:       Vec<u8>,
 }
 
-impl DetectCursorPosAlias for RawTTYEmulator {
-    fn get_cursor_pos(&mut self) -> (usize, usize) {
-        // tty is 1-indexed
-        (self.cursor_pos.0 + 1_usize, self.cursor_pos.1 + 1_usize)
-    }
+fn unsupported(sequence: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, format!("unsupported escape sequence: {:?}", sequence))
+}
+
+fn row_to_string(row: &[Cell]) -> String {
+    row.iter().map(|cell| cell.c).collect()
 }
 
 impl RawTTYEmulator {
+    /// Returns an 80x24 terminal
     pub fn new() -> RawTTYEmulator {
+        RawTTYEmulator::with_size(DEFAULT_SIZE.0, DEFAULT_SIZE.1)
+    }
+
+    pub fn with_size(width: usize, height: usize) -> RawTTYEmulator {
+        assert!(width > 0 && height > 0, "A terminal needs at least one row and column");
         RawTTYEmulator {
-            text:       vec![vec![]],
+            width,
+            height,
+            text: vec![vec![]],
             cursor_pos: CursorPos(0, 0),
+            attrs: Attributes::default(),
+            wrap_pending: false,
+            autowrap: true,
+            cursor_visible: true,
+            mouse_reporting: false,
+            scroll_region: (0, height - 1),
+            saved_cursor: None,
+            main_screen: None,
+            scrollback: vec![],
+            title: String::new(),
+            state: ParseState::Ground,
+            utf8: vec![],
+            responses: vec![],
+        }
+    }
+
+    /// Draw `buf` on the screen. Sequences the emulator doesn't know are errors, so that tests
+    /// notice output it can't show faithfully.
+    pub fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for byte in buf {
+            self.feed(*byte)?;
         }
+        self.extend_to_match_pos();
+        Ok(buf.len())
+    }
+
+    pub fn get_cursor_pos(&self) -> (usize, usize) {
+        // tty is 1-indexed
+        (self.cursor_pos.0 + 1_usize, self.cursor_pos.1 + 1_usize)
+    }
+
+    /// Returns what the terminal would have sent back since this was last called, in answer to
+    /// requests like `ESC [ 6 n` for the cursor position
+    pub fn take_responses(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.responses)
+    }
+
+    /// Returns the size of the screen as (columns, rows)
+    pub fn get_size(&self) -> (usize, usize) {
+        (self.width, self.height)
     }
 
-    pub fn get_text(&self) -> &Vec<Vec<char>> {
-        &self.text
+    pub fn get_text(&self) -> Vec<Vec<char>> {
+        self.text.iter().map(|row| row.iter().map(|cell| cell.c).collect()).collect()
     }
 
-    pub fn get_line(&self) -> &Vec<char> {
-        &self.text[self.cursor_pos.1]
+    pub fn get_line(&self) -> Vec<char> {
+        self.text[self.cursor_pos.1].iter().map(|cell| cell.c).collect()
     }
 
     pub fn get_line_str(&self) -> String {
-        String::from_iter(self.text[self.cursor_pos.1].iter())
+        row_to_string(&self.text[self.cursor_pos.1])
+    }
+
+    /// Returns the cell at the 1-indexed position (`x`, `y`), as [get_cursor_pos] numbers them
+    ///
+    /// [get_cursor_pos]: RawTTYEmulator::get_cursor_pos
+    pub fn get_cell(&self, x: usize, y: usize) -> Cell {
+        self.text
+            .get(y - 1)
+            .and_then(|row| row.get(x - 1))
+            .copied()
+            .unwrap_or_else(|| Cell::blank(Color::Default))
+    }
+
+    /// Returns the attributes newly written characters are drawn with
+    pub fn get_attributes(&self) -> Attributes {
+        self.attrs
+    }
+
+    /// Returns the lines which have scrolled off the top of the screen, oldest first
+    pub fn get_scrollback(&self) -> Vec<String> {
+        self.scrollback.iter().map(|row| row_to_string(row).trim_end().to_string()).collect()
+    }
+
+    pub fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn is_cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    pub fn is_mouse_reporting(&self) -> bool {
+        self.mouse_reporting
+    }
+
+    pub fn is_alternate_screen(&self) -> bool {
+        self.main_screen.is_some()
+    }
+
+    /// Returns the screen as text for comparing against a golden copy. Each row is written
+    /// without its trailing blanks and the rows after the last non-blank one are left out. Where
+    /// the attributes change, they're written in braces, e.g. `{bold fg=red}`, with `{}` going
+    /// back to the defaults, and braces on the screen are doubled.
+    pub fn snapshot(&self) -> String {
+        let rows: Vec<String> = self.text.iter().map(|row| Self::snapshot_row(row)).collect();
+        let used = rows.iter().rposition(|row| !row.is_empty()).map_or(0, |last| last + 1);
+        rows[..used].iter().map(|row| format!("{}\n", row)).collect()
     }
 
-    fn parse_numbers(input: &Vec<char>) -> Result<Vec<i64>, &'static str> {
-        if !input.is_empty() {
-            let result = Vec::from_iter(String::from_iter(input).split(";").map(|x| {
-                i64::from_str(x).expect(format!("Unable to parse expected numeric string: \"{}\"", x).as_str())
-            }));
-            Ok(result)
+    fn snapshot_row(row: &[Cell]) -> String {
+        let used = row.iter().rposition(|cell| !cell.is_default_blank()).map_or(0, |last| last + 1);
+        let mut out = String::new();
+        let mut attrs = Attributes::default();
+        for cell in &row[..used] {
+            if cell.attrs != attrs {
+                attrs = cell.attrs;
+                out += &format!("{{{}}}", attrs);
+            }
+            match cell.c {
+                '{' => out += "{{",
+                '}' => out += "}}",
+                c => out.push(c),
+            }
         }
-        else {
-            Ok(vec![])
+        if attrs != Attributes::default() {
+            out += "{}";
+        }
+        out
+    }
+
+    fn blank(&self) -> Cell {
+        Cell::blank(self.attrs.bg)
+    }
+
+    /// Returns an empty row: one without cells when erasing with the default background, which
+    /// rows are stored as far as they're used anyway, and otherwise a row of coloured blanks
+    fn blank_row(&self) -> Vec<Cell> {
+        match self.blank().is_default_blank() {
+            true => vec![],
+            false => vec![self.blank(); self.width],
         }
     }
 
@@ -113,96 +309,471 @@ impl RawTTYEmulator {
             self.text.push(vec![])
         }
         while self.cursor_pos.0 >= self.text[self.cursor_pos.1].len() {
-            self.text[self.cursor_pos.1].push(' ');
+            self.text[self.cursor_pos.1].push(Cell::blank(Color::Default));
+        }
+    }
+
+    fn extend_to_row(&mut self, row: usize) {
+        while row >= self.text.len() {
+            self.text.push(vec![])
+        }
+    }
+
+    fn move_to(&mut self, x: usize, y: usize) {
+        self.cursor_pos = CursorPos(x.min(self.width - 1), y.min(self.height - 1));
+        self.wrap_pending = false;
+    }
+
+    /// Move up or down by `rows`, stopping at the edge of the scrolling region if the cursor is in
+    /// it, or otherwise at the edge of the screen
+    fn move_vertically(&mut self, rows: isize) {
+        let (top, bottom) = self.scroll_region;
+        let y = self.cursor_pos.1;
+        let (min, max) = match (top..=bottom).contains(&y) {
+            true => (top, bottom),
+            false => (0, self.height - 1),
+        };
+        let y = (y as isize + rows).clamp(min as isize, max as isize) as usize;
+        self.move_to(self.cursor_pos.0, y);
+    }
+
+    /// Scroll the rows in the scrolling region up by one, adding a blank row at the bottom
+    fn scroll_up(&mut self) {
+        let (top, bottom) = self.scroll_region;
+        self.extend_to_row(bottom);
+        let row = self.text.remove(top);
+        if top == 0 && self.main_screen.is_none() {
+            self.scrollback.push(row);
+        }
+        let blank = self.blank_row();
+        self.text.insert(bottom, blank);
+    }
+
+    /// Scroll the rows in the scrolling region down by one, adding a blank row at the top
+    fn scroll_down(&mut self) {
+        let (top, bottom) = self.scroll_region;
+        self.extend_to_row(bottom);
+        self.text.remove(bottom);
+        let blank = self.blank_row();
+        self.text.insert(top, blank);
+    }
+
+    fn line_feed(&mut self) {
+        self.wrap_pending = false;
+        if self.cursor_pos.1 == self.scroll_region.1 {
+            self.scroll_up();
+        }
+        else if self.cursor_pos.1 < self.height - 1 {
+            self.cursor_pos.1 += 1;
+        }
+    }
+
+    fn reverse_line_feed(&mut self) {
+        self.wrap_pending = false;
+        if self.cursor_pos.1 == self.scroll_region.0 {
+            self.scroll_down();
+        }
+        else if self.cursor_pos.1 > 0 {
+            self.cursor_pos.1 -= 1;
         }
     }
 
-    fn handle_escape(&mut self, buf_iter: &mut Iter<u8>) -> Result<(), &'static str> {
-        let mut num_buf = vec![];
-        let mut is_gathering_for_num = false;
-        let mut seq_type = EscapeType::UNKNOWN;
-        while let Some(c) = buf_iter.next() {
-            let c_chr = *c as char;
-            if c_chr == '[' {
-                is_gathering_for_num = true;
+    fn print(&mut self, c: char) {
+        if self.wrap_pending && self.autowrap {
+            self.cursor_pos.0 = 0;
+            self.line_feed();
+        }
+        self.wrap_pending = false;
+        self.extend_to_match_pos();
+        self.text[self.cursor_pos.1][self.cursor_pos.0] = Cell { c, attrs: self.attrs };
+        if self.cursor_pos.0 + 1 < self.width {
+            self.cursor_pos.0 += 1;
+        }
+        else {
+            self.wrap_pending = self.autowrap;
+        }
+    }
+
+    /// Blank the cells from column `from` up to `to` in `row`
+    fn erase_cells(&mut self, row: usize, from: usize, to: usize) {
+        let blank = self.blank();
+        let Some(cells) = self.text.get_mut(row) else {
+            return;
+        };
+        let to = to.min(self.width);
+        if to >= cells.len() && blank.is_default_blank() {
+            cells.truncate(from);
+            return;
+        }
+        if to > cells.len() {
+            cells.resize(to, Cell::blank(Color::Default));
+        }
+        cells[from.min(to)..to].fill(blank);
+    }
+
+    fn erase_rows(&mut self, from: usize, to: usize) {
+        if !self.blank().is_default_blank() && to > 0 {
+            self.extend_to_row(to - 1);
+        }
+        for row in from..to {
+            self.erase_cells(row, 0, self.width);
+        }
+    }
+
+    /// Run a control character
+    fn control(&mut self, byte: u8) {
+        match byte {
+            b'\n' | b'\x0B' | b'\x0C' => self.line_feed(),
+            b'\r' => {
+                self.cursor_pos.0 = 0;
+                self.wrap_pending = false;
             }
-            else if is_gathering_for_num && (('0' <= c_chr && c_chr <= '9') || c_chr == ';') {
-                num_buf.push(c_chr);
+            b'\x08' => self.move_to(self.cursor_pos.0.saturating_sub(1), self.cursor_pos.1),
+            b'\t' => self.move_to((self.cursor_pos.0 / TAB_WIDTH + 1) * TAB_WIDTH, self.cursor_pos.1),
+            // the bell and anything else is ignored
+            _ => {}
+        }
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved_cursor = Some(SavedCursor {
+            pos:   self.cursor_pos,
+            attrs: self.attrs,
+        });
+    }
+
+    fn restore_cursor(&mut self) {
+        let saved = self.saved_cursor.unwrap_or(SavedCursor {
+            pos:   CursorPos(0, 0),
+            attrs: Attributes::default(),
+        });
+        self.move_to(saved.pos.0, saved.pos.1);
+        self.attrs = saved.attrs;
+    }
+
+    fn set_alternate_screen(&mut self, on: bool) {
+        match (on, self.main_screen.is_some()) {
+            (true, false) => {
+                self.main_screen = Some(std::mem::replace(&mut self.text, vec![vec![]]));
             }
-            else {
-                seq_type = EscapeType::from(*c);
-                break;
+            (false, true) => {
+                self.text = self.main_screen.take().unwrap_or_default();
             }
+            _ => {}
         }
+    }
 
-        let nums = Self::parse_numbers(&num_buf)?;
-        let (arg_x, arg_y) = match nums.len() {
-            0 => (1, 1),
-            1 => (nums[0] as usize, nums[0] as usize),
-            _ => (nums[1] as usize, nums[0] as usize),
-        };
+    fn set_private_mode(&mut self, mode: usize, on: bool) {
+        match mode {
+            7 => self.autowrap = on,
+            25 => self.cursor_visible = on,
+            47 | 1047 => self.set_alternate_screen(on),
+            1049 => {
+                if on {
+                    self.save_cursor();
+                    self.set_alternate_screen(true);
+                }
+                else {
+                    self.set_alternate_screen(false);
+                    self.restore_cursor();
+                }
+            }
+            1000 | 1002 | 1003 => self.mouse_reporting = on,
+            // the cursor keys, the mouse's encoding, bracketed paste and so on don't change the screen
+            _ => {}
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = RawTTYEmulator::with_size(self.width, self.height);
+    }
 
-        match seq_type {
-            EscapeType::LEFT => self.cursor_pos -= (arg_x, 0_usize),
-            EscapeType::RIGHT => self.cursor_pos += (arg_x, 0),
-            EscapeType::UP => self.cursor_pos -= (0, arg_y),
-            EscapeType::DOWN => self.cursor_pos += (0, arg_y),
-            // we subtract (1, 1) to account for the fact that cursor::Goto is 1-indexed
-            EscapeType::HOME => self.cursor_pos = CursorPos(arg_x - 1, arg_y - 1),
-            EscapeType::CLEAR_AFTER_CURSOR => {
-                self.text[self.cursor_pos.1].drain(self.cursor_pos.0..);
+    fn escape(&mut self, intermediates: &[u8], byte: u8) -> io::Result<()> {
+        // choosing character sets, e.g. `ESC ( B`, makes no difference to UTF-8 output
+        if !intermediates.is_empty() {
+            return Ok(());
+        }
+        match byte {
+            b'7' => self.save_cursor(),
+            b'8' => self.restore_cursor(),
+            b'D' => self.line_feed(),
+            b'E' => {
+                self.cursor_pos.0 = 0;
+                self.line_feed();
             }
-            EscapeType::IGNORE => {
-                while let Some(c) = buf_iter.next() {
-                    if *c == b'l' || *c == b'h' {
-                        break;
+            b'M' => self.reverse_line_feed(),
+            b'c' => self.reset(),
+            // the keypad's modes
+            b'=' | b'>' => {}
+            _ => return Err(unsupported(format!("\x1b{}", byte as char))),
+        }
+        Ok(())
+    }
+
+    fn osc(&mut self, string: &[u8]) {
+        let string = String::from_utf8_lossy(string);
+        if let Some(("0" | "2", title)) = string.split_once(';') {
+            self.title = title.to_string();
+        }
+    }
+
+    fn sgr(&mut self, params: &[usize]) -> Result<(), ()> {
+        if params.is_empty() {
+            self.attrs = Attributes::default();
+        }
+        let mut i = 0;
+        while i < params.len() {
+            let attrs = &mut self.attrs;
+            match params[i] {
+                0 => *attrs = Attributes::default(),
+                1 => attrs.bold = true,
+                2 => attrs.dim = true,
+                3 => attrs.italic = true,
+                4 | 21 => attrs.underline = true,
+                5 | 6 => attrs.blink = true,
+                7 => attrs.inverse = true,
+                8 => attrs.hidden = true,
+                9 => attrs.strikethrough = true,
+                22 => (attrs.bold, attrs.dim) = (false, false),
+                23 => attrs.italic = false,
+                24 => attrs.underline = false,
+                25 => attrs.blink = false,
+                27 => attrs.inverse = false,
+                28 => attrs.hidden = false,
+                29 => attrs.strikethrough = false,
+                n @ 30..=37 => attrs.fg = Color::Indexed(n as u8 - 30),
+                39 => attrs.fg = Color::Default,
+                n @ 40..=47 => attrs.bg = Color::Indexed(n as u8 - 40),
+                49 => attrs.bg = Color::Default,
+                n @ 90..=97 => attrs.fg = Color::Indexed(n as u8 - 90 + 8),
+                n @ 100..=107 => attrs.bg = Color::Indexed(n as u8 - 100 + 8),
+                n @ (38 | 48) => {
+                    let to_u8 = |i: usize| params.get(i).and_then(|n| u8::try_from(*n).ok()).ok_or(());
+                    let color = match params.get(i + 1) {
+                        Some(5) => {
+                            i += 2;
+                            Color::Indexed(to_u8(i)?)
+                        }
+                        Some(2) => {
+                            i += 4;
+                            Color::Rgb(to_u8(i - 2)?, to_u8(i - 1)?, to_u8(i)?)
+                        }
+                        _ => return Err(()),
+                    };
+                    match n {
+                        38 => attrs.fg = color,
+                        _ => attrs.bg = color,
                     }
                 }
+                _ => return Err(()),
             }
-            EscapeType::UNKNOWN => return Err("unknown escape sequence"),
-        };
-        self.extend_to_match_pos();
-
+            i += 1;
+        }
         Ok(())
     }
-}
 
-impl IoWriteAlias for RawTTYEmulator {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let mut buf_iter = buf.iter();
-        while let Some(c) = buf_iter.next() {
-            match *c {
-                b'\n' => {
-                    if self.cursor_pos.1 == self.text.len() - 1 {
-                        self.text.push(vec![]);
-                    }
-                    self.cursor_pos.1 += 1;
+    fn csi(&mut self, sequence: &[u8], byte: u8) -> io::Result<()> {
+        let text = String::from_utf8_lossy(sequence);
+        let error = || unsupported(format!("\x1b[{}{}", text, byte as char));
+        let (private, rest) = match text.chars().next() {
+            Some(c @ ('?' | '<' | '=' | '>')) => (Some(c), &text[1..]),
+            _ => (None, &text[..]),
+        };
+        let params_end = rest.find(|c: char| !c.is_ascii_digit() && c != ';').unwrap_or(rest.len());
+        let (params, intermediates) = rest.split_at(params_end);
+        // a missing parameter, or 0, means the default
+        let params: Vec<usize> = match params {
+            "" => vec![],
+            _ => params.split(';').map(|n| n.parse().unwrap_or(0)).collect::<Vec<usize>>(),
+        };
+        let arg = |i: usize, default: usize| params.get(i).copied().filter(|n| *n > 0).unwrap_or(default);
+        let (x, y) = (self.cursor_pos.0, self.cursor_pos.1);
+
+        match (private, intermediates, byte) {
+            (None, "", b'A') => self.move_vertically(-(arg(0, 1) as isize)),
+            (None, "", b'B') => self.move_vertically(arg(0, 1) as isize),
+            (None, "", b'C') => self.move_to(x + arg(0, 1), y),
+            (None, "", b'D') => self.move_to(x.saturating_sub(arg(0, 1)), y),
+            (None, "", b'E') => {
+                self.move_vertically(arg(0, 1) as isize);
+                self.cursor_pos.0 = 0;
+            }
+            (None, "", b'F') => {
+                self.move_vertically(-(arg(0, 1) as isize));
+                self.cursor_pos.0 = 0;
+            }
+            (None, "", b'G') => self.move_to(arg(0, 1) - 1, y),
+            (None, "", b'd') => self.move_to(x, arg(0, 1) - 1),
+            // we subtract 1 to account for the fact that cursor::Goto is 1-indexed
+            (None, "", b'H' | b'f') => self.move_to(arg(1, 1) - 1, arg(0, 1) - 1),
+            (None, "", b'J') => match params.first().copied().unwrap_or(0) {
+                0 => {
+                    self.erase_cells(y, x, self.width);
+                    self.erase_rows(y + 1, self.height);
                 }
-                b'\r' => {
-                    self.cursor_pos.0 = 0;
+                1 => {
+                    self.erase_rows(0, y);
+                    self.extend_to_match_pos();
+                    self.erase_cells(y, 0, x + 1);
                 }
-                b'\x1B' => {
-                    match self.handle_escape(&mut buf_iter) {
-                        Err(_) => return Err(io::Error::new(ErrorKind::Other, "Unable to parse escape sequence")),
-                        _ => {}
-                    };
+                2 => self.erase_rows(0, self.height),
+                3 => self.scrollback.clear(),
+                _ => return Err(error()),
+            },
+            (None, "", b'K') => match params.first().copied().unwrap_or(0) {
+                0 => self.erase_cells(y, x, self.width),
+                1 => {
+                    self.extend_to_match_pos();
+                    self.erase_cells(y, 0, x + 1);
                 }
-                _ => {
-                    if self.cursor_pos.0 < self.text[self.cursor_pos.1].len() {
-                        self.text[self.cursor_pos.1][self.cursor_pos.0] = *c as char;
+                2 => self.erase_cells(y, 0, self.width),
+                _ => return Err(error()),
+            },
+            (None, "", b'X') => {
+                self.extend_to_match_pos();
+                self.erase_cells(y, x, x + arg(0, 1));
+            }
+            (None, "", b'@' | b'P') => {
+                self.extend_to_match_pos();
+                let blank = self.blank();
+                let width = self.width;
+                let row = &mut self.text[y];
+                let count = arg(0, 1).min(width - x);
+                if byte == b'@' {
+                    row.splice(x..x, vec![blank; count]);
+                    row.truncate(width);
+                }
+                else {
+                    row.drain(x..(x + count).min(row.len()));
+                    if !blank.is_default_blank() {
+                        row.resize(width, blank);
                     }
-                    else {
-                        self.text[self.cursor_pos.1].push(*c as char);
+                }
+            }
+            (None, "", b'L' | b'M') => {
+                let (top, bottom) = self.scroll_region;
+                if (top..=bottom).contains(&y) {
+                    // the lines from the cursor down are scrolled, as if the region started there
+                    self.scroll_region = (y, bottom);
+                    for _ in 0..arg(0, 1).min(bottom - y + 1) {
+                        match byte {
+                            b'L' => self.scroll_down(),
+                            _ => self.scroll_up(),
+                        }
                     }
-                    self.cursor_pos.0 += 1;
+                    self.scroll_region = (top, bottom);
+                    self.move_to(0, y);
+                }
+            }
+            (None, "", b'S') => (0..arg(0, 1)).for_each(|_| self.scroll_up()),
+            (None, "", b'T') => (0..arg(0, 1)).for_each(|_| self.scroll_down()),
+            (None, "", b'r') => {
+                let (top, bottom) = (arg(0, 1) - 1, arg(1, self.height) - 1);
+                if top < bottom && bottom < self.height {
+                    self.scroll_region = (top, bottom);
+                    self.move_to(0, 0);
                 }
             }
+            (None, "", b'm') => self.sgr(&params).map_err(|_| error())?,
+            (None, "", b's') => self.save_cursor(),
+            (None, "", b'u') => self.restore_cursor(),
+            (Some('?'), "", b'h' | b'l') => {
+                params.iter().for_each(|mode| self.set_private_mode(*mode, byte == b'h'));
+            }
+            (None, "", b'n') => match params.first() {
+                Some(5) => self.responses.extend_from_slice(b"\x1b[0n"),
+                Some(6) => {
+                    let report = format!("\x1b[{};{}R", y + 1, x + 1);
+                    self.responses.extend_from_slice(report.as_bytes());
+                }
+                _ => {}
+            },
+            // other reports, and window operations, which don't change what's on the screen
+            (_, "", b'n' | b'c' | b't') => {}
+            // insert and line feed/new line modes aren't used in raw mode
+            (None, "", b'h' | b'l') => {}
+            _ => return Err(error()),
+        }
+        Ok(())
+    }
+
+    /// Decode a byte of a UTF-8 character, printing it once it's complete, or the replacement
+    /// character if it isn't valid
+    fn utf8_byte(&mut self, byte: u8) {
+        if !self.utf8.is_empty() && byte & 0xC0 != 0x80 {
+            self.utf8.clear();
+            self.print(char::REPLACEMENT_CHARACTER);
+            self.ground(byte);
+            return;
+        }
+        self.utf8.push(byte);
+        let len = match self.utf8[0] {
+            0xC2..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF4 => 4,
+            _ => 1,
+        };
+        if self.utf8.len() < len {
+            return;
+        }
+        let c = std::str::from_utf8(&self.utf8)
+            .ok()
+            .and_then(|s| s.chars().next())
+            .unwrap_or(char::REPLACEMENT_CHARACTER);
+        self.utf8.clear();
+        self.print(c);
+    }
+
+    fn ground(&mut self, byte: u8) {
+        match byte {
+            b'\x1B' => self.state = ParseState::Escape(vec![]),
+            0x00..=0x1F => self.control(byte),
+            0x20..=0x7E => self.print(byte as char),
+            0x7F => {}
+            _ => self.utf8_byte(byte),
         }
-        self.extend_to_match_pos();
-        Ok(0)
     }
 
-    fn flush(&mut self) -> io::Result<()> {
+    fn feed(&mut self, byte: u8) -> io::Result<()> {
+        if !self.utf8.is_empty() && self.state == ParseState::Ground {
+            self.utf8_byte(byte);
+            return Ok(());
+        }
+        match std::mem::replace(&mut self.state, ParseState::Ground) {
+            ParseState::Ground => self.ground(byte),
+            ParseState::Escape(mut intermediates) => match byte {
+                b'[' if intermediates.is_empty() => self.state = ParseState::Csi(vec![]),
+                b']' if intermediates.is_empty() => self.state = ParseState::Osc(vec![], false),
+                0x20..=0x2F => {
+                    intermediates.push(byte);
+                    self.state = ParseState::Escape(intermediates);
+                }
+                0x30..=0x7E => self.escape(&intermediates, byte)?,
+                _ => self.ground(byte),
+            },
+            ParseState::Csi(mut sequence) => match byte {
+                0x20..=0x3F => {
+                    sequence.push(byte);
+                    self.state = ParseState::Csi(sequence);
+                }
+                0x40..=0x7E => self.csi(&sequence, byte)?,
+                b'\x1B' => self.state = ParseState::Escape(vec![]),
+                // control characters in the middle of a sequence are run straight away
+                _ => {
+                    self.control(byte);
+                    self.state = ParseState::Csi(sequence);
+                }
+            },
+            ParseState::Osc(mut string, after_escape) => match byte {
+                b'\x07' => self.osc(&string),
+                b'\\' if after_escape => self.osc(&string),
+                b'\x1B' => self.state = ParseState::Osc(string, true),
+                _ => {
+                    string.push(byte);
+                    self.state = ParseState::Osc(string, false);
+                }
+            },
+        }
         Ok(())
     }
 }
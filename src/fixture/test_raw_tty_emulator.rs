@@ -2,8 +2,11 @@
 mod test_fixture_tests {
     use std::io;
 
-    use crate::cmd_input::{DetectCursorPosAlias, IoWriteAlias};
-    use crate::fixture::raw_tty_emulator::RawTTYEmulator;
+    use pretty_assertions::assert_eq;
+    use termion::{clear, color, cursor, style};
+
+    use crate::fixture::raw_tty_emulator::{Attributes, Color, RawTTYEmulator};
+    use crate::shell::REPAIR_SEQUENCE;
 
     macro_rules! up {
         () => {
@@ -83,7 +86,7 @@ mod test_fixture_tests {
 
     #[test]
     fn test_init() {
-        let mut thing: RawTTYEmulator = RawTTYEmulator::new();
+        let thing: RawTTYEmulator = RawTTYEmulator::new();
 
         assert!(thing.get_line().is_empty());
         assert_eq!(thing.get_text().len(), 1);
@@ -168,12 +171,12 @@ mod test_fixture_tests {
         let mut thing: RawTTYEmulator = RawTTYEmulator::new();
         let init_len = thing.get_text().len();
 
-        thing.write(&[b'\n']).expect("This is a problem");
+        thing.write(b"\n").expect("This is a problem");
         assert_eq!(thing.get_cursor_pos(), (1, 2));
         assert_eq!(thing.get_text().len(), init_len + 1);
 
         thing.write(goto!(10, 10).as_bytes()).expect("This is a problem");
-        thing.write(&[b'\n']).expect("This is a problem");
+        thing.write(b"\n").expect("This is a problem");
         assert_eq!(thing.get_cursor_pos(), (10, 11));
         assert_eq!(thing.get_text().len(), 11);
 
@@ -184,11 +187,11 @@ mod test_fixture_tests {
     fn test_carriage_return() -> io::Result<()> {
         let mut thing: RawTTYEmulator = RawTTYEmulator::new();
 
-        thing.write(&[b'\r']).expect("This is a problem");
+        thing.write(b"\r").expect("This is a problem");
         assert_eq!(thing.get_cursor_pos(), (1, 1));
 
         thing.write(goto!(10, 10).as_bytes()).expect("This is a problem");
-        thing.write(&[b'\r']).expect("This is a problem");
+        thing.write(b"\r").expect("This is a problem");
         assert_eq!(thing.get_cursor_pos(), (1, 10));
 
         Ok(())
@@ -232,4 +235,219 @@ mod test_fixture_tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_attributes_per_cell() -> io::Result<()> {
+        let mut thing = RawTTYEmulator::new();
+
+        let (bold, red, rgb) = (style::Bold, color::Fg(color::Red), color::Bg(color::Rgb(1, 2, 3)));
+        thing.write(format!("a{}{}b{}c{}d", bold, red, style::Reset, rgb).as_bytes())?;
+        let bold_red = Attributes {
+            fg: Color::Indexed(1),
+            bold: true,
+            ..Attributes::default()
+        };
+        assert_eq!(thing.get_cell(1, 1).attrs, Attributes::default());
+        assert_eq!((thing.get_cell(2, 1).c, thing.get_cell(2, 1).attrs), ('b', bold_red));
+        assert_eq!(thing.get_cell(3, 1).attrs, Attributes::default());
+        assert_eq!(thing.get_cell(4, 1).attrs.bg, Color::Rgb(1, 2, 3));
+
+        // 256 colours, bright colours and turning single attributes off
+        thing.write(b"\x1B[0;38;5;200;101;3;4m\x1B[23m")?;
+        let attrs = thing.get_attributes();
+        assert_eq!((attrs.fg, attrs.bg), (Color::Indexed(200), Color::Indexed(9)));
+        assert_eq!((attrs.italic, attrs.underline), (false, true));
+        thing.write(b"\x1B[m")?;
+        assert_eq!(thing.get_attributes(), Attributes::default());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot() -> io::Result<()> {
+        let mut thing = RawTTYEmulator::new();
+
+        let (reset, cyan, green) = (color::Fg(color::Reset), color::Fg(color::Cyan), color::Fg(color::Green));
+        thing.write(format!("{}~/src{} (main){}> {}ls {{a,b}}\r\n\n", reset, cyan, green, reset).as_bytes())?;
+        let (bold, blue) = (style::Bold, color::Bg(color::LightBlue));
+        thing.write(format!("{}{}bold{} plain   \r\n", bold, blue, style::Reset).as_bytes())?;
+        assert_eq!(
+            thing.snapshot(),
+            "~/src{fg=cyan} (main){fg=green}> {}ls {{a,b}}\n\n{bold bg=bright-blue}bold{} plain\n"
+        );
+
+        // trailing blank rows are left out, but coloured blanks aren't blank
+        thing.write(format!("{}   ", color::Bg(color::Rgb(255, 0, 16))).as_bytes())?;
+        assert!(thing.snapshot().ends_with("\n{bg=#ff0010}   {}\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_clear_line() -> io::Result<()> {
+        let mut thing = RawTTYEmulator::new();
+
+        thing.write(b"hello world\x1B[6G\x1B[K")?;
+        assert_eq!(thing.get_line_str(), "hello ");
+        thing.write(b" world\x1B[3G\x1B[1K")?;
+        assert_eq!(thing.get_line_str(), "   lo world");
+        thing.write(format!("{}", clear::CurrentLine).as_bytes())?;
+        assert_eq!(thing.get_line_str(), "   ");
+        assert_eq!(thing.get_cursor_pos(), (3, 1));
+
+        // erasing with a background colour set fills the line with it
+        thing.write(b"\x1B[41m\x1B[K")?;
+        assert_eq!(thing.get_line().len(), 80);
+        assert_eq!(thing.get_cell(80, 1).attrs.bg, Color::Indexed(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_clear_screen() -> io::Result<()> {
+        let mut thing = RawTTYEmulator::with_size(10, 5);
+
+        thing.write(b"one\r\ntwo\r\nthree\x1B[2;2H\x1B[J")?;
+        assert_eq!(thing.snapshot(), "one\nt\n");
+        thing.write(b"\x1B[1J")?;
+        assert_eq!(thing.snapshot(), "");
+        thing.write(b"back\x1B[2J")?;
+        assert_eq!((thing.snapshot().as_str(), thing.get_cursor_pos()), ("", (6, 2)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrapping_and_scrolling() -> io::Result<()> {
+        let mut thing = RawTTYEmulator::with_size(5, 3);
+
+        // the cursor stays in the last column until the next character wraps
+        thing.write(b"abcde")?;
+        assert_eq!(thing.get_cursor_pos(), (5, 1));
+        thing.write(b"fghijklmn")?;
+        assert_eq!(thing.snapshot(), "abcde\nfghij\nklmn\n");
+
+        // writing past the bottom scrolls the screen up
+        thing.write(b"op")?;
+        assert_eq!(thing.snapshot(), "fghij\nklmno\np\n");
+        thing.write(b"\r\n")?;
+        assert_eq!(thing.snapshot(), "klmno\np\n");
+        assert_eq!(thing.get_scrollback(), vec!["abcde", "fghij"]);
+        assert_eq!(thing.get_cursor_pos(), (1, 3));
+
+        // without autowrap, the last column is overwritten
+        thing.write(b"\x1B[?7l123456")?;
+        assert_eq!(thing.snapshot(), "klmno\np\n12346\n");
+
+        // moving the cursor stops at the edges of the screen
+        thing.write(b"\x1B[50;50H")?;
+        assert_eq!(thing.get_cursor_pos(), (5, 3));
+        thing.write(b"\x1B[9A\x1B[9D")?;
+        assert_eq!(thing.get_cursor_pos(), (1, 1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scroll_region() -> io::Result<()> {
+        let mut thing = RawTTYEmulator::with_size(10, 5);
+        thing.write(b"1\r\n2\r\n3\r\n4\r\n5")?;
+
+        // only the rows between the margins scroll, and nothing goes to the scrollback
+        thing.write(b"\x1B[2;4r")?;
+        assert_eq!(thing.get_cursor_pos(), (1, 1));
+        thing.write(b"\x1B[4;1H\nx")?;
+        assert_eq!(thing.snapshot(), "1\n3\n4\nx\n5\n");
+        assert!(thing.get_scrollback().is_empty());
+
+        // moving up stops at the top margin, where a reverse index scrolls down
+        thing.write(b"\x1B[9A\x1BM\ry")?;
+        assert_eq!(thing.snapshot(), "1\ny\n3\n4\n5\n");
+
+        // inserting and deleting lines moves the rest of the region
+        thing.write(b"\x1B[3;1H\x1B[M")?;
+        assert_eq!(thing.snapshot(), "1\ny\n4\n\n5\n");
+        thing.write(b"\x1B[2L")?;
+        assert_eq!(thing.snapshot(), "1\ny\n\n\n5\n");
+
+        // resetting the region scrolls the whole screen again
+        thing.write(b"\x1B[r\x1B[5;1H\n")?;
+        assert_eq!(thing.snapshot(), "y\n\n\n5\n");
+        assert_eq!(thing.get_scrollback(), vec!["1"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_and_delete_chars() -> io::Result<()> {
+        let mut thing = RawTTYEmulator::new();
+
+        thing.write(b"abcdef\x1B[3G\x1B[2P")?;
+        assert_eq!(thing.get_line_str(), "abef");
+        thing.write(b"\x1B[2@")?;
+        assert_eq!(thing.get_line_str(), "ab  ef");
+        thing.write(b"\x1B[5G\x1B[X")?;
+        assert_eq!(thing.get_line_str(), "ab   f");
+        thing.write(b"\r\tx")?;
+        assert_eq!(thing.get_cursor_pos(), (10, 1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_utf8() -> io::Result<()> {
+        let mut thing = RawTTYEmulator::new();
+
+        // characters are decoded even when split between writes
+        let bytes = "é→🦀".as_bytes();
+        thing.write(&bytes[..3])?;
+        thing.write(&bytes[3..6])?;
+        thing.write(&bytes[6..])?;
+        assert_eq!(thing.get_line_str(), "é→🦀 ");
+        assert_eq!(thing.get_cursor_pos(), (4, 1));
+
+        // as are escape sequences
+        thing.write(b"\x1B[")?;
+        thing.write(b"1m!")?;
+        assert!(thing.get_cell(4, 1).attrs.bold);
+
+        thing.write(b"\r\n\xE2\x86x\xFF")?;
+        assert_eq!(thing.get_line_str(), "\u{FFFD}x\u{FFFD} ");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_modes_and_alternate_screen() -> io::Result<()> {
+        let mut thing = RawTTYEmulator::new();
+
+        thing.write(format!("\x1B]0;yeesh\x07shell{}{}", cursor::Hide, style::Italic).as_bytes())?;
+        assert_eq!((thing.get_title(), thing.is_cursor_visible()), ("yeesh", false));
+
+        // a full screen program draws on the alternate screen and crashes, leaving it set up
+        thing.write(b"\x1B[?1049h\x1B[?1000h\x1B[?1h\x1B=\x1B[Hvim")?;
+        assert!(thing.is_alternate_screen());
+        assert_eq!(thing.snapshot(), "{italic}vim{}\n");
+
+        // which the shell puts back as it was
+        thing.write(REPAIR_SEQUENCE.as_bytes())?;
+        assert!(!thing.is_alternate_screen() && thing.is_cursor_visible());
+        assert_eq!(thing.snapshot(), "shell\n");
+        assert_eq!((thing.get_cursor_pos(), thing.get_attributes()), ((6, 1), Attributes::default()));
+
+        thing.write(b"\x1Bc")?;
+        assert_eq!((thing.snapshot().as_str(), thing.get_title()), ("", ""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_sequence() {
+        let mut thing = RawTTYEmulator::new();
+
+        let error = thing.write(b"\x1B[3z").expect_err("The sequence is unknown");
+        assert_eq!(error.to_string(), "unsupported escape sequence: \"\\u{1b}[3z\"");
+        thing.write(b"\x1B[58m").expect_err("The attribute is unknown");
+        thing.write(b"\x1B[38;5m").expect_err("The colour is missing");
+    }
 }
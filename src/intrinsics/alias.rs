@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use log::debug;
+
+use crate::intrinsics::{Intrinsic, IntrinsicIo};
+use crate::shell::Shell;
+
+/// A table of aliases which can be shared between the `alias`/`unalias` intrinsics, the parser
+/// and completion. Clones refer to the same table.
+#[derive(Clone, Default, Debug)]
+pub struct AliasTable {
+    aliases: Arc<RwLock<BTreeMap<String, String>>>,
+}
+
+impl AliasTable {
+    pub fn new() -> Self {
+        AliasTable::default()
+    }
+
+    pub fn set(&self, name: &str, value: &str) {
+        debug!("Setting alias '{}' to '{}'", name, value);
+        self.aliases.write().unwrap().insert(name.to_string(), value.to_string());
+    }
+
+    /// Remove the alias `name`, returning false if there was no such alias
+    pub fn remove(&self, name: &str) -> bool {
+        self.aliases.write().unwrap().remove(name).is_some()
+    }
+
+    pub fn clear(&self) {
+        self.aliases.write().unwrap().clear();
+    }
+
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.aliases.read().unwrap().get(name).cloned()
+    }
+
+    /// Returns every alias and its value, sorted by name
+    pub fn get_all(&self) -> Vec<(String, String)> {
+        self.aliases
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }
+}
+
+/// Quote `value` so that it can be pasted back into the shell
+pub(crate) fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn is_valid_alias_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '/' | '$' | '`' | '=' | '\'' | '"' | ';' | '&' | '|'))
+}
+
+/// `alias`, which defines or displays the aliases of the shell running it
+pub(crate) struct Alias;
+
+impl Intrinsic for Alias {
+    fn get_command(&self) -> &'static str {
+        "alias"
+    }
+
+    fn get_description(&self) -> &'static str {
+        "define or display aliases, e.g. alias ll='ls -l'"
+    }
+
+    fn run(&self, shell: &mut Shell, io: &mut IntrinsicIo, args: &[String]) -> i32 {
+        let aliases = shell.get_aliases();
+        if args.is_empty() {
+            for (name, value) in aliases.get_all() {
+                if writeln!(io.stdout, "alias {}={}", name, quote(&value)).is_err() {
+                    return 1;
+                }
+            }
+            return 0;
+        }
+
+        let mut code = 0;
+        for arg in args {
+            let result = match arg.split_once('=') {
+                Some((name, value)) if is_valid_alias_name(name) => {
+                    aliases.set(name, value);
+                    Ok(())
+                }
+                Some((name, _)) => {
+                    code = 1;
+                    writeln!(io.stderr, "alias: '{}': invalid alias name", name)
+                }
+                None => match aliases.get(arg) {
+                    Some(value) => writeln!(io.stdout, "alias {}={}", arg, quote(&value)),
+                    None => {
+                        code = 1;
+                        writeln!(io.stderr, "alias: {}: not found", arg)
+                    }
+                },
+            };
+            if result.is_err() {
+                return 1;
+            }
+        }
+        code
+    }
+}
+
+/// `unalias`, which removes aliases from the shell running it
+pub(crate) struct Unalias;
+
+impl Intrinsic for Unalias {
+    fn get_command(&self) -> &'static str {
+        "unalias"
+    }
+
+    fn get_description(&self) -> &'static str {
+        "remove aliases; -a removes all of them"
+    }
+
+    fn run(&self, shell: &mut Shell, io: &mut IntrinsicIo, args: &[String]) -> i32 {
+        let aliases = shell.get_aliases();
+        if args.is_empty() {
+            let _ = writeln!(io.stderr, "unalias: usage: unalias [-a] name [name ...]");
+            return 2;
+        }
+        if args[0] == "-a" {
+            aliases.clear();
+            return 0;
+        }
+
+        let mut code = 0;
+        for name in args.iter().filter(|name| !aliases.remove(name)) {
+            code = 1;
+            if writeln!(io.stderr, "unalias: {}: not found", name).is_err() {
+                break;
+            }
+        }
+        code
+    }
+}
@@ -0,0 +1,43 @@
+use crate::intrinsics::{Intrinsic, IntrinsicIo};
+use crate::shell::Shell;
+
+/// `type [-t] name ...`, which describes what running each `name` as a command would run
+pub struct Type;
+
+impl Intrinsic for Type {
+    fn get_command(&self) -> &'static str {
+        "type"
+    }
+
+    fn get_description(&self) -> &'static str {
+        "describe how each name would be run as a command; -t prints a single word"
+    }
+
+    fn run(&self, shell: &mut Shell, io: &mut IntrinsicIo, args: &[String]) -> i32 {
+        let (terse, names) = match args.first().map(String::as_str) {
+            Some("-t") => (true, &args[1..]),
+            _ => (false, args),
+        };
+
+        let mut code = 0;
+        for name in names {
+            let result = match shell.find_command(name) {
+                Some(kind) if terse => writeln!(io.stdout, "{}", kind.word()),
+                Some(kind) => writeln!(io.stdout, "{}", kind.describe(name)),
+                // like the other forms, `-t` fails for a missing command, but quietly
+                None if terse => {
+                    code = 1;
+                    Ok(())
+                }
+                None => {
+                    code = 1;
+                    writeln!(io.stderr, "type: {}: not found", name)
+                }
+            };
+            if result.is_err() {
+                return 1;
+            }
+        }
+        code
+    }
+}
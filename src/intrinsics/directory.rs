@@ -0,0 +1,348 @@
+use std::env;
+use std::path::{Component, Path, PathBuf};
+
+use filesystem::FileSystem;
+
+use crate::intrinsics::OutputIntrinsic;
+use crate::prompt::abbreviate_home;
+use crate::shell::Shell;
+
+/// Returns `path` made absolute relative to `base`, with `.` and `..` removed without following
+/// symlinks, as `cd -L` takes them
+fn normalize(base: &Path, path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::from("/");
+    for component in base.join(path).components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::Normal(name) => normalized.push(name),
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+        }
+    }
+    normalized
+}
+
+/// Returns the working directory as `$PWD` gives it, which may be reached through symlinks, or
+/// as `fs` gives it if `$PWD` isn't set to a directory
+fn working_dir<T: FileSystem>(fs: &T, shell: &Shell) -> PathBuf {
+    match shell.get_variable("PWD") {
+        Some(pwd) if pwd.starts_with('/') && fs.is_dir(&pwd) => PathBuf::from(pwd),
+        _ => fs.current_dir().unwrap_or_else(|_| PathBuf::from("/")),
+    }
+}
+
+/// Change the working directory to `dir`, setting `$PWD` to the new directory and `$OLDPWD` to the
+/// old one. If `physical`, `$PWD` has symlinks resolved, and otherwise `..` in `dir` leads back
+/// to the parent of `$PWD`, rather than the parent of the directory a symlink led to.
+pub(crate) fn change_directory<T: FileSystem>(
+    fs: &T,
+    shell: &mut Shell,
+    command: &str,
+    dir: &str,
+    physical: bool,
+) -> Result<PathBuf, String> {
+    if fs.is_file(dir) {
+        return Err(format!("{}: '{}' is not a directory\n", command, dir));
+    }
+
+    let old = working_dir(fs, shell);
+    let logical = normalize(&old, Path::new(dir));
+    let new = if !physical && fs.set_current_dir(&logical).is_ok() {
+        logical
+    }
+    // the logical path may not exist, e.g. if the directory `$PWD` led to has been moved
+    else if fs.set_current_dir(dir).is_ok() {
+        fs.current_dir().map_err(|e| format!("{}: {}\n", command, e))?
+    }
+    else {
+        return Err(format!("{}: The directory \"{}\" does not exist\n", command, dir));
+    };
+
+    shell.set_variable("OLDPWD", &old.to_string_lossy());
+    shell.set_variable("PWD", &new.to_string_lossy());
+    shell.export_variable("OLDPWD");
+    shell.export_variable("PWD");
+    shell.get_directories().visit(fs, &new);
+    Ok(new)
+}
+
+/// Split the `-L` and `-P` options from the start of `args`, returning whether the last one was
+/// `-P`, and the remaining arguments
+fn parse_link_options<'a>(command: &str, args: &'a [String]) -> Result<(bool, &'a [String]), String> {
+    let mut physical = false;
+    for (i, arg) in args.iter().enumerate() {
+        match arg.as_str() {
+            "--" => return Ok((physical, &args[i + 1..])),
+            // `-` on its own is a directory rather than an option
+            "-" => return Ok((physical, &args[i..])),
+            option if option.starts_with('-') => {
+                for c in option[1..].chars() {
+                    match c {
+                        'L' => physical = false,
+                        'P' => physical = true,
+                        _ => return Err(format!("{}: {}: invalid option\n", command, option)),
+                    }
+                }
+            }
+            _ => return Ok((physical, &args[i..])),
+        }
+    }
+    Ok((physical, &[]))
+}
+
+/// Returns the directory `path` refers to through `$CDPATH`, and whether it was found in one of
+/// its directories (rather than the working directory), or `None` if it isn't found there
+fn search_cdpath<T: FileSystem>(fs: &T, shell: &Shell, path: &str) -> Option<(String, bool)> {
+    let relative = !path.starts_with('/')
+        && !matches!(Path::new(path).components().next(), Some(Component::CurDir | Component::ParentDir));
+    if !relative {
+        return None;
+    }
+    shell.get_variable("CDPATH")?.split(':').find_map(|entry| {
+        let candidate = match entry {
+            "" => path.to_string(),
+            entry => format!("{}/{}", entry.trim_end_matches('/'), path),
+        };
+        fs.is_dir(&candidate).then_some((candidate, !entry.is_empty()))
+    })
+}
+
+/// Returns the value of `$HOME`, looking in the environment if the shell doesn't have it
+fn home_dir(shell: &Shell) -> Option<String> {
+    shell.get_variable("HOME").or_else(|| env::var("HOME").ok())
+}
+
+/// `cd [-L|-P] [dir]`, which changes the working directory to `dir`, `$HOME` if there is no `dir`,
+/// or `$OLDPWD` if `dir` is `-`. Relative directories are looked for in `$CDPATH`.
+pub struct ChangeDirectory<T: FileSystem + Sync + Send> {
+    fs: T,
+}
+
+impl<T: FileSystem + Sync + Send> ChangeDirectory<T> {
+    pub fn new(filesystem: T) -> Self {
+        ChangeDirectory { fs: filesystem }
+    }
+}
+
+impl<T: FileSystem + Sync + Send> OutputIntrinsic for ChangeDirectory<T> {
+    fn get_command(&self) -> &'static str {
+        "cd"
+    }
+
+    fn get_description(&self) -> &'static str {
+        "change the current working directory"
+    }
+
+    fn handler(&self, shell: &mut Shell, args: &[String]) -> Result<String, String> {
+        let (physical, args) = parse_link_options("cd", args)?;
+        // the new directory is printed if it isn't the one given
+        let (dir, print) = match args {
+            [] => (home_dir(shell).ok_or("cd: HOME not set\n")?, false),
+            [dir] if dir == "-" => (shell.get_variable("OLDPWD").ok_or("cd: OLDPWD not set\n")?, true),
+            [dir] => {
+                let mut dir = dir.clone();
+                if dir.starts_with('~') {
+                    dir = home_dir(shell).ok_or("cd: HOME not set\n")? + &dir[1..];
+                }
+                search_cdpath(&self.fs, shell, &dir).unwrap_or((dir, false))
+            }
+            _ => {
+                return Err("cd: too many arguments\n".to_string());
+            }
+        };
+
+        let new = change_directory(&self.fs, shell, "cd", &dir, physical)?;
+        match print {
+            true => Ok(format!("{}\n", new.display())),
+            false => Ok("".to_string()),
+        }
+    }
+}
+
+/// Returns the directory stack, starting with the working directory and followed by the
+/// directories saved by `pushd`, most recent first
+fn full_stack<T: FileSystem>(fs: &T, shell: &Shell) -> Vec<String> {
+    let mut stack = vec![working_dir(fs, shell).to_string_lossy().to_string()];
+    stack.extend(shell.get_directory_stack().iter().cloned());
+    stack
+}
+
+/// Returns the position in a stack of `len` directories given by `arg`: `+N` counts from the
+/// start of the stack, and `-N` from the end. Returns `None` if `arg` isn't a position.
+fn stack_index(command: &str, arg: &str, len: usize) -> Option<Result<usize, String>> {
+    if !arg.starts_with(['+', '-']) || arg.len() < 2 || !arg[1..].chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let out_of_range = || format!("{}: {}: directory stack index out of range\n", command, arg);
+    let n: usize = match arg[1..].parse() {
+        Ok(n) if n < len => n,
+        _ => return Some(Err(out_of_range())),
+    };
+    Some(Ok(if arg.starts_with('+') { n } else { len - 1 - n }))
+}
+
+/// Returns the directory stack on one line, as `pushd` and `popd` print it
+fn describe_stack(stack: &[String], shell: &Shell) -> String {
+    let home = home_dir(shell);
+    let dirs: Vec<String> = stack.iter().map(|dir| abbreviate_home(dir, home.as_deref())).collect();
+    format!("{}\n", dirs.join(" "))
+}
+
+/// `pushd [dir | +N | -N]`, which saves the working directory on the directory stack and changes
+/// to `dir`, rotates the stack to bring its `N`th directory to the top, or with no arguments
+/// swaps the top two directories
+pub struct PushDirectory<T: FileSystem + Sync + Send> {
+    fs: T,
+}
+
+impl<T: FileSystem + Sync + Send> PushDirectory<T> {
+    pub fn new(filesystem: T) -> Self {
+        PushDirectory { fs: filesystem }
+    }
+}
+
+impl<T: FileSystem + Sync + Send> OutputIntrinsic for PushDirectory<T> {
+    fn get_command(&self) -> &'static str {
+        "pushd"
+    }
+
+    fn get_description(&self) -> &'static str {
+        "save the working directory on the directory stack and change to another"
+    }
+
+    fn handler(&self, shell: &mut Shell, args: &[String]) -> Result<String, String> {
+        let mut stack = full_stack(&self.fs, shell);
+        match args {
+            [] if stack.len() < 2 => return Err("pushd: no other directory\n".to_string()),
+            [] => {
+                change_directory(&self.fs, shell, "pushd", &stack[1], false)?;
+                stack.swap(0, 1);
+            }
+            [arg] => match stack_index("pushd", arg, stack.len()) {
+                Some(index) => {
+                    let index = index?;
+                    change_directory(&self.fs, shell, "pushd", &stack[index], false)?;
+                    stack.rotate_left(index);
+                }
+                None => {
+                    let new = change_directory(&self.fs, shell, "pushd", arg, false)?;
+                    stack.insert(0, new.to_string_lossy().to_string());
+                }
+            },
+            _ => return Err("pushd: too many arguments\n".to_string()),
+        }
+
+        shell.set_directory_stack(stack[1..].to_vec());
+        Ok(describe_stack(&stack, shell))
+    }
+}
+
+/// `popd [+N | -N]`, which removes the top directory from the directory stack and changes to
+/// the next, or removes its `N`th directory
+pub struct PopDirectory<T: FileSystem + Sync + Send> {
+    fs: T,
+}
+
+impl<T: FileSystem + Sync + Send> PopDirectory<T> {
+    pub fn new(filesystem: T) -> Self {
+        PopDirectory { fs: filesystem }
+    }
+}
+
+impl<T: FileSystem + Sync + Send> OutputIntrinsic for PopDirectory<T> {
+    fn get_command(&self) -> &'static str {
+        "popd"
+    }
+
+    fn get_description(&self) -> &'static str {
+        "remove a directory from the directory stack, changing to the next if it was the top"
+    }
+
+    fn handler(&self, shell: &mut Shell, args: &[String]) -> Result<String, String> {
+        let mut stack = full_stack(&self.fs, shell);
+        if stack.len() < 2 {
+            return Err("popd: directory stack empty\n".to_string());
+        }
+        let index = match args {
+            [] => 0,
+            [arg] => match stack_index("popd", arg, stack.len()) {
+                Some(index) => index?,
+                None => return Err(format!("popd: {}: invalid argument\n", arg)),
+            },
+            _ => return Err("popd: too many arguments\n".to_string()),
+        };
+
+        if index == 0 {
+            change_directory(&self.fs, shell, "popd", &stack[1], false)?;
+        }
+        stack.remove(index);
+        shell.set_directory_stack(stack[1..].to_vec());
+        Ok(describe_stack(&stack, shell))
+    }
+}
+
+/// `dirs [-clpv] [+N | -N]`, which prints the directory stack, or clears it with `-c`
+pub struct Dirs<T: FileSystem + Sync + Send> {
+    fs: T,
+}
+
+impl<T: FileSystem + Sync + Send> Dirs<T> {
+    pub fn new(filesystem: T) -> Self {
+        Dirs { fs: filesystem }
+    }
+}
+
+impl<T: FileSystem + Sync + Send> OutputIntrinsic for Dirs<T> {
+    fn get_command(&self) -> &'static str {
+        "dirs"
+    }
+
+    fn get_description(&self) -> &'static str {
+        "print the directory stack; -c clears it, -l doesn't abbreviate $HOME, -p and -v print a line each"
+    }
+
+    fn handler(&self, shell: &mut Shell, args: &[String]) -> Result<String, String> {
+        let stack = full_stack(&self.fs, shell);
+        let (mut clear, mut long, mut lines, mut numbered) = (false, false, false, false);
+        let mut selected = None;
+        for arg in args {
+            if let Some(index) = stack_index("dirs", arg, stack.len()) {
+                selected = Some(index?);
+                continue;
+            }
+            match arg.strip_prefix('-') {
+                Some(options) if !options.is_empty() => {
+                    for c in options.chars() {
+                        match c {
+                            'c' => clear = true,
+                            'l' => long = true,
+                            'p' => lines = true,
+                            'v' => (lines, numbered) = (true, true),
+                            _ => return Err(format!("dirs: {}: invalid option\n", arg)),
+                        }
+                    }
+                }
+                _ => return Err(format!("dirs: {}: invalid argument\n", arg)),
+            }
+        }
+
+        if clear {
+            shell.set_directory_stack(vec![]);
+            return Ok("".to_string());
+        }
+        let home = if long { None } else { home_dir(shell) };
+        let dirs: Vec<(usize, String)> = stack
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| selected.is_none() || selected == Some(*i))
+            .map(|(i, dir)| (i, abbreviate_home(dir, home.as_deref())))
+            .collect();
+
+        Ok(match (lines, numbered) {
+            (true, true) => dirs.iter().map(|(i, dir)| format!("{:2}  {}\n", i, dir)).collect(),
+            (true, false) => dirs.iter().map(|(_, dir)| format!("{}\n", dir)).collect(),
+            _ => format!("{}\n", dirs.into_iter().map(|(_, dir)| dir).collect::<Vec<_>>().join(" ")),
+        })
+    }
+}
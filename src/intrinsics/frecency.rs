@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use filesystem::FileSystem;
+use log::{debug, error};
+
+use crate::intrinsics::{change_directory, OutputIntrinsic};
+use crate::shell::Shell;
+
+/// The file the directory database is kept in, relative to `$HOME`
+pub const DIRECTORY_DB_FILE: &str = ".yeesh_dirs";
+/// Once the visits to every directory add up to this, they are all scaled down, so directories
+/// which are no longer visited drop out
+const MAX_TOTAL_VISITS: f64 = 9000.0;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct DirectoryEntry {
+    /// how often the directory has been visited, scaled down as the database ages
+    visits:     f64,
+    /// when the directory was last visited, in seconds since the epoch
+    last_visit: u64,
+}
+
+impl DirectoryEntry {
+    /// Returns how strongly the directory is preferred at `now`: how often it is visited, weighted
+    /// towards recent visits
+    fn frecency(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last_visit);
+        let weight = match age {
+            _ if age < HOUR => 4.0,
+            _ if age < DAY => 2.0,
+            _ if age < WEEK => 0.5,
+            _ => 0.25,
+        };
+        self.visits * weight
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Returns whether `fragments` all appear in `dir`, in order
+fn matches_fragments(dir: &str, fragments: &[String]) -> bool {
+    let mut rest = dir;
+    fragments.iter().all(|fragment| match rest.find(fragment.as_str()) {
+        Some(i) => {
+            rest = &rest[i + fragment.len()..];
+            true
+        }
+        None => false,
+    })
+}
+
+/// The directories the shell has changed to, ranked by frecency, which `z` and completion use to
+/// find the directory meant by a few fragments of its path. Like the ranking sketched in
+/// `MultiWayTrie`, recent visits count for more, but times are kept as seconds since the epoch
+/// so that the database can be saved. Clones refer to the same database.
+#[derive(Clone, Default, Debug)]
+pub struct DirectoryDb {
+    entries: Arc<RwLock<BTreeMap<String, DirectoryEntry>>>,
+    /// the file visits are saved to, if any
+    path:    Arc<RwLock<Option<PathBuf>>>,
+}
+
+impl DirectoryDb {
+    pub fn new() -> Self {
+        DirectoryDb::default()
+    }
+
+    /// Keep the database in the file at `path` in `fs`, loading the directories already there
+    pub fn open<T: FileSystem>(&self, fs: &T, path: &Path) {
+        *self.path.write().unwrap() = Some(path.to_path_buf());
+        self.reload(fs);
+    }
+
+    fn reload<T: FileSystem>(&self, fs: &T) {
+        let path = match self.path.read().unwrap().clone() {
+            Some(path) => path,
+            None => return,
+        };
+        // the file may not have been created yet
+        if let Ok(contents) = fs.read_file_to_string(&path) {
+            self.load(&contents);
+        }
+    }
+
+    /// Replace the directories with those in `contents`, with one `dir|visits|last visit` line for
+    /// each directory
+    pub fn load(&self, contents: &str) {
+        let entries = contents
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.rsplitn(3, '|');
+                let last_visit = fields.next()?.parse().ok()?;
+                let visits = fields.next()?.parse().ok()?;
+                let dir = fields.next()?;
+                Some((dir.to_string(), DirectoryEntry { visits, last_visit }))
+            })
+            .collect();
+        *self.entries.write().unwrap() = entries;
+    }
+
+    /// Returns the directories in the form [DirectoryDb::load] reads
+    pub fn save(&self) -> String {
+        self.entries
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(dir, entry)| format!("{}|{}|{}\n", dir, entry.visits, entry.last_visit))
+            .collect()
+    }
+
+    /// Record a visit to `dir`, saving the database if it is kept in a file. The file is reloaded
+    /// first, so that other shells' visits are kept.
+    pub fn visit<T: FileSystem>(&self, fs: &T, dir: &Path) {
+        self.reload(fs);
+        self.visit_at(&dir.to_string_lossy(), now());
+
+        if let Some(path) = self.path.read().unwrap().as_ref() {
+            debug!("Saving directory database to '{}'", path.display());
+            let result = match fs.is_file(path) {
+                true => fs.overwrite_file(path, self.save()),
+                false => fs.create_file(path, self.save()),
+            };
+            if let Err(e) = result {
+                error!("Unable to save directory database to '{}': {}", path.display(), e);
+            }
+        }
+    }
+
+    pub(crate) fn visit_at(&self, dir: &str, now: u64) {
+        let mut entries = self.entries.write().unwrap();
+        let entry = entries.entry(dir.to_string()).or_insert(DirectoryEntry {
+            visits:     0.0,
+            last_visit: now,
+        });
+        entry.visits += 1.0;
+        entry.last_visit = now;
+
+        if entries.values().map(|entry| entry.visits).sum::<f64>() > MAX_TOTAL_VISITS {
+            debug!("Ageing directory database");
+            entries.values_mut().for_each(|entry| entry.visits *= 0.99);
+            entries.retain(|_, entry| entry.visits >= 1.0);
+        }
+    }
+
+    /// Remove `dir`, returning false if it wasn't in the database
+    pub fn remove(&self, dir: &str) -> bool {
+        self.entries.write().unwrap().remove(dir).is_some()
+    }
+
+    /// Returns the directories containing each of `fragments` in order, with their frecency, most
+    /// frecent first. If no directory matches exactly, the fragments are matched ignoring case.
+    pub fn find(&self, fragments: &[String]) -> Vec<(String, f64)> {
+        self.find_at(fragments, now())
+    }
+
+    pub(crate) fn find_at(&self, fragments: &[String], now: u64) -> Vec<(String, f64)> {
+        let entries = self.entries.read().unwrap();
+        let find = |fragments: &[String], lowercase: bool| -> Vec<(String, f64)> {
+            let mut found: Vec<(String, f64)> = entries
+                .iter()
+                .filter(|(dir, _)| match lowercase {
+                    true => matches_fragments(&dir.to_lowercase(), fragments),
+                    false => matches_fragments(dir, fragments),
+                })
+                .map(|(dir, entry)| (dir.clone(), entry.frecency(now)))
+                .collect();
+            found.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+            found
+        };
+
+        let found = find(fragments, false);
+        if !found.is_empty() {
+            return found;
+        }
+        let lowercase: Vec<String> = fragments.iter().map(|fragment| fragment.to_lowercase()).collect();
+        find(&lowercase, true)
+    }
+}
+
+/// `z [-l] [fragment ...]`, which changes to the most frecent directory containing each fragment
+/// in order, or with `-l` or no fragments lists the matching directories
+pub struct JumpDirectory<T: FileSystem + Sync + Send> {
+    fs: T,
+}
+
+impl<T: FileSystem + Sync + Send> JumpDirectory<T> {
+    pub fn new(filesystem: T) -> Self {
+        JumpDirectory { fs: filesystem }
+    }
+}
+
+impl<T: FileSystem + Sync + Send> OutputIntrinsic for JumpDirectory<T> {
+    fn get_command(&self) -> &'static str {
+        "z"
+    }
+
+    fn get_description(&self) -> &'static str {
+        "change to the most frecent directory matching each fragment; -l lists the matches"
+    }
+
+    fn handler(&self, shell: &mut Shell, args: &[String]) -> Result<String, String> {
+        let (list, fragments) = match args.first().map(String::as_str) {
+            Some("-l") => (true, &args[1..]),
+            Some(option) if option.starts_with('-') && option.len() > 1 => {
+                return Err(format!("z: {}: invalid option\n", option));
+            }
+            _ => (false, args),
+        };
+
+        // directories which have been removed are skipped, but kept in case they come back
+        let found: Vec<(String, f64)> = shell
+            .get_directories()
+            .find(fragments)
+            .into_iter()
+            .filter(|(dir, _)| self.fs.is_dir(dir))
+            .collect();
+
+        if list || fragments.is_empty() {
+            // the best match is listed last, nearest the prompt
+            let lines = found.iter().rev().map(|(dir, frecency)| format!("{:<10.1} {}\n", frecency, dir));
+            return Ok(lines.collect());
+        }
+        match found.first() {
+            Some((dir, _)) => change_directory(&self.fs, shell, "z", dir, false).map(|_| "".to_string()),
+            None => Err(format!("z: no directory matches '{}'\n", fragments.join(" "))),
+        }
+    }
+}
@@ -1,14 +1,74 @@
-use std::borrow::Borrow;
-use std::env;
 use std::fmt::{Debug, Formatter};
+use std::io::{Read, Write};
 
-use filesystem::{FileSystem, OsFileSystem};
-use lazy_static::lazy_static;
+use crate::shell::{status_code, status_from_code, Shell};
+
+/// The streams an intrinsic reads its input from and writes its output to
+pub struct IntrinsicIo<'a> {
+    pub stdin:  &'a mut dyn Read,
+    pub stdout: &'a mut dyn Write,
+    pub stderr: &'a mut dyn Write,
+}
 
 pub trait Intrinsic: Sync + Send {
     fn get_command(&self) -> &'static str;
     fn get_description(&self) -> &'static str;
-    fn handler(&self, args: &[String]) -> Result<String, String>;
+    /// Run the intrinsic with `args` (which don't include the command) in `shell`, returning its
+    /// exit code
+    fn run(&self, shell: &mut Shell, io: &mut IntrinsicIo, args: &[String]) -> i32;
+}
+
+/// An intrinsic which produces all of its output at once, when it finishes: the output if it
+/// succeeds, or an error message if it fails. Wrap it in an [OutputAdapter] to register it.
+pub trait OutputIntrinsic: Sync + Send {
+    fn get_command(&self) -> &'static str;
+    fn get_description(&self) -> &'static str;
+    fn handler(&self, shell: &mut Shell, args: &[String]) -> Result<String, String>;
+}
+
+/// Runs an [OutputIntrinsic] as an [Intrinsic], writing its output to stdout, or its error to
+/// stderr with an exit code of 1
+pub struct OutputAdapter<T: OutputIntrinsic>(pub T);
+
+impl<T: OutputIntrinsic> Intrinsic for OutputAdapter<T> {
+    fn get_command(&self) -> &'static str {
+        self.0.get_command()
+    }
+
+    fn get_description(&self) -> &'static str {
+        self.0.get_description()
+    }
+
+    fn run(&self, shell: &mut Shell, io: &mut IntrinsicIo, args: &[String]) -> i32 {
+        let (stream, output, code) = match self.0.handler(shell, args) {
+            Ok(output) => (&mut io.stdout, output, 0),
+            Err(error) => (&mut io.stderr, error, 1),
+        };
+        match stream.write_all(output.as_bytes()).and_then(|_| stream.flush()) {
+            Ok(()) => code,
+            Err(_) => 1,
+        }
+    }
+}
+
+/// Run `intrinsic` with `input` as its stdin, returning its exit code and what it wrote to stdout
+/// and stderr
+#[cfg(test)]
+pub fn run_buffered(
+    intrinsic: &dyn Intrinsic,
+    shell: &mut Shell,
+    input: &str,
+    args: &[&str],
+) -> (i32, String, String) {
+    let (mut stdout, mut stderr) = (vec![], vec![]);
+    let mut streams = IntrinsicIo {
+        stdin:  &mut input.as_bytes(),
+        stdout: &mut stdout,
+        stderr: &mut stderr,
+    };
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    let code = intrinsic.run(shell, &mut streams, &args);
+    (code, String::from_utf8(stdout).unwrap(), String::from_utf8(stderr).unwrap())
 }
 
 impl Debug for &dyn Intrinsic {
@@ -26,77 +86,40 @@ impl PartialEq for &dyn Intrinsic {
     }
 }
 
-pub(crate) struct ChangeDirectory<T: FileSystem + Sync + Send> {
-    fs: T,
-}
-
-impl<T: FileSystem + Sync + Send> ChangeDirectory<T> {
-    pub fn new(filesystem: T) -> Self {
-        ChangeDirectory { fs: filesystem }
-    }
-}
-
-impl<T: FileSystem + Sync + Send> Intrinsic for ChangeDirectory<T> {
+/// `exit [n]`, which exits the shell with the status `n`, or the status of the last command
+pub(crate) struct ExitShell;
+impl Intrinsic for ExitShell {
     fn get_command(&self) -> &'static str {
-        "cd"
+        "exit"
     }
 
     fn get_description(&self) -> &'static str {
-        "change the current working directory"
+        "exit the shell with a status, by default that of the last command"
     }
 
-    fn handler(&self, args: &[String]) -> Result<String, String> {
-        let mut path = match args.len() {
-            0 => "~".to_string(),
-            1 => args[0].clone(),
+    fn run(&self, shell: &mut Shell, io: &mut IntrinsicIo, args: &[String]) -> i32 {
+        let status = match args {
+            [] => shell.get_last_status(),
+            [code] => match code.parse::<i64>() {
+                Ok(code) => status_from_code(code.rem_euclid(256) as i32),
+                Err(_) => {
+                    // the shell still exits, as it can't tell which status was meant
+                    writeln!(io.stderr, "exit: {}: numeric argument required", code).unwrap_or_default();
+                    status_from_code(2)
+                }
+            },
             _ => {
-                return Err("cd: too many arguments".to_string());
+                writeln!(io.stderr, "exit: too many arguments").unwrap_or_default();
+                return 1;
             }
         };
 
-        if path.starts_with('~') {
-            path = env::var("HOME").or(Err("HOME not set"))? + &path[1..];
-        }
-
-        if self.fs.is_file(&path) {
-            Err(format!("cd: '{}' is not a directory", path))
-        }
-        else if let Ok(()) = self.fs.set_current_dir(&path) {
-            Ok("".to_string())
-        }
-        else {
-            Err(format!("cd: The directory \"{}\" does not exist\r\n", path))
+        match shell.request_exit(status) {
+            Ok(()) => status_code(&status),
+            Err(error) => {
+                writeln!(io.stderr, "exit: {}", error).unwrap_or_default();
+                1
+            }
         }
     }
 }
-
-pub(crate) struct ExitShell(());
-impl Intrinsic for ExitShell {
-    fn get_command(&self) -> &'static str {
-        "exit"
-    }
-
-    fn get_description(&self) -> &'static str {
-        "exit the shell"
-    }
-
-    fn handler(&self, _: &[String]) -> Result<String, String> {
-        Ok("So long and thanks for all the fish.".to_string())
-    }
-}
-
-lazy_static! {
-    pub static ref INTRINSICS: Vec<Box<dyn Intrinsic>> = {
-        vec![
-            Box::new(ChangeDirectory::new(OsFileSystem::new())),
-            Box::new(ExitShell(())),
-        ]
-    };
-}
-
-pub fn find_intrinsic(cmd: &String) -> Option<&dyn Intrinsic> {
-    INTRINSICS
-        .iter()
-        .find(|&intrinsic| intrinsic.get_command() == cmd)
-        .map(|x| x.borrow())
-}
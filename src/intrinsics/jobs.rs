@@ -0,0 +1,30 @@
+use crate::intrinsics::{Intrinsic, IntrinsicIo};
+use crate::shell::Shell;
+
+/// `jobs`, which lists the jobs running in the background, and those which have finished since
+/// they were last reported
+pub struct Jobs;
+
+impl Intrinsic for Jobs {
+    fn get_command(&self) -> &'static str {
+        "jobs"
+    }
+
+    fn get_description(&self) -> &'static str {
+        "list the jobs running in the background"
+    }
+
+    fn run(&self, shell: &mut Shell, io: &mut IntrinsicIo, _: &[String]) -> i32 {
+        let mut lines: Vec<_> =
+            shell.reap_jobs().iter().map(|(job, status)| (job.id, job.describe_finished(status))).collect();
+        lines.extend(shell.get_jobs().get_all().iter().map(|job| (job.id, job.describe_running())));
+        lines.sort();
+
+        for (_, line) in lines {
+            if writeln!(io.stdout, "{}", line).is_err() {
+                return 1;
+            }
+        }
+        0
+    }
+}
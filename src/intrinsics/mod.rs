@@ -1,6 +1,30 @@
+mod alias;
+mod command_type;
+mod directory;
+mod frecency;
 mod intrinsic;
+mod jobs;
+mod registry;
+mod reset;
+mod trap;
 
+#[cfg(test)]
+mod test_alias;
+#[cfg(test)]
+mod test_directory;
+#[cfg(test)]
+mod test_frecency;
 #[cfg(test)]
 mod test_intrinsic;
+#[cfg(test)]
+mod test_registry;
 
+pub use alias::*;
+pub use command_type::*;
+pub use directory::*;
+pub use frecency::*;
 pub use intrinsic::*;
+pub use jobs::*;
+pub use registry::*;
+pub use reset::*;
+pub use trap::*;
@@ -0,0 +1,73 @@
+use std::sync::{Arc, RwLock};
+
+use filesystem::FileSystem;
+use log::debug;
+
+use crate::intrinsics::{
+    Alias, ChangeDirectory, Dirs, ExitShell, Intrinsic, Jobs, JumpDirectory, OutputAdapter, PopDirectory,
+    PushDirectory, ResetTerminal, Trap, Type, Unalias,
+};
+
+/// The intrinsics a shell can run, which can be added and removed while it runs. Clones refer to
+/// the same set, so completion sees intrinsics registered after it started.
+#[derive(Clone, Default)]
+pub struct IntrinsicRegistry {
+    intrinsics: Arc<RwLock<Vec<Arc<dyn Intrinsic>>>>,
+}
+
+impl IntrinsicRegistry {
+    /// Create a registry with no intrinsics
+    pub fn new() -> Self {
+        IntrinsicRegistry::default()
+    }
+
+    /// Create a registry with the intrinsics built into the shell, with `cd` and the directory
+    /// stack changing directory in `filesystem`
+    pub fn with_defaults<T: FileSystem + Clone + Sync + Send + 'static>(filesystem: T) -> Self {
+        let registry = IntrinsicRegistry::new();
+        registry.register(Box::new(OutputAdapter(ChangeDirectory::new(filesystem.clone()))));
+        registry.register(Box::new(OutputAdapter(PushDirectory::new(filesystem.clone()))));
+        registry.register(Box::new(OutputAdapter(PopDirectory::new(filesystem.clone()))));
+        registry.register(Box::new(OutputAdapter(Dirs::new(filesystem.clone()))));
+        registry.register(Box::new(OutputAdapter(JumpDirectory::new(filesystem))));
+        registry.register(Box::new(ExitShell));
+        registry.register(Box::new(Alias));
+        registry.register(Box::new(Unalias));
+        registry.register(Box::new(Type));
+        registry.register(Box::new(Trap));
+        registry.register(Box::new(Jobs));
+        registry.register(Box::new(ResetTerminal));
+        registry
+    }
+
+    /// Add `intrinsic`, replacing any intrinsic with the same command
+    pub fn register(&self, intrinsic: Box<dyn Intrinsic>) {
+        let command = intrinsic.get_command();
+        debug!("Registering intrinsic '{}'", command);
+        let mut intrinsics = self.intrinsics.write().unwrap();
+        intrinsics.retain(|existing| existing.get_command() != command);
+        intrinsics.push(intrinsic.into());
+    }
+
+    /// Remove the intrinsic for `command`, returning false if there was no such intrinsic
+    pub fn remove(&self, command: &str) -> bool {
+        let mut intrinsics = self.intrinsics.write().unwrap();
+        let count = intrinsics.len();
+        intrinsics.retain(|existing| existing.get_command() != command);
+        intrinsics.len() != count
+    }
+
+    pub fn find(&self, command: &str) -> Option<Arc<dyn Intrinsic>> {
+        self.intrinsics
+            .read()
+            .unwrap()
+            .iter()
+            .find(|intrinsic| intrinsic.get_command() == command)
+            .cloned()
+    }
+
+    /// Returns every intrinsic, in the order they were registered
+    pub fn get_all(&self) -> Vec<Arc<dyn Intrinsic>> {
+        self.intrinsics.read().unwrap().clone()
+    }
+}
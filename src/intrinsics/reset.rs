@@ -0,0 +1,35 @@
+use crate::intrinsics::{Intrinsic, IntrinsicIo};
+use crate::shell::{Shell, TerminalState, REPAIR_SEQUENCE};
+
+/// Clears the screen and moves the cursor to the top left
+const CLEAR_SCREEN: &str = "\x1b[H\x1b[2J";
+
+/// `reset`, which recovers a terminal a program has left broken: its settings are put back as
+/// they were when the shell started (or made sane, if they weren't saved), and the screen is
+/// returned to normal and cleared
+pub struct ResetTerminal;
+
+impl Intrinsic for ResetTerminal {
+    fn get_command(&self) -> &'static str {
+        "reset"
+    }
+
+    fn get_description(&self) -> &'static str {
+        "put the terminal's settings and screen back to normal"
+    }
+
+    fn run(&self, shell: &mut Shell, io: &mut IntrinsicIo, _: &[String]) -> i32 {
+        if write!(io.stdout, "{}{}", REPAIR_SEQUENCE, CLEAR_SCREEN).is_err() {
+            return 1;
+        }
+        let settings = shell.get_saved_terminal().or_else(|| TerminalState::save().map(|state| state.sane()));
+        match settings.map(|settings| settings.restore()) {
+            Some(Err(e)) => {
+                writeln!(io.stderr, "reset: {}", e).unwrap_or_default();
+                1
+            }
+            // without a terminal on stdin, there are no settings to put back
+            _ => 0,
+        }
+    }
+}
@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod alias_tests {
+    use crate::intrinsics::{run_buffered, Alias, AliasTable, IntrinsicRegistry, Unalias};
+    use crate::shell::{Shell, VariableStore};
+
+    fn setup() -> (Shell, AliasTable) {
+        let aliases = AliasTable::new();
+        (Shell::new(VariableStore::new(), aliases.clone(), IntrinsicRegistry::new()), aliases)
+    }
+
+    fn output(code: i32, stdout: &str, stderr: &str) -> (i32, String, String) {
+        (code, stdout.to_string(), stderr.to_string())
+    }
+
+    #[test]
+    fn test_alias_intrinsic() {
+        let (mut shell, aliases) = setup();
+
+        assert_eq!(run_buffered(&Alias, &mut shell, "", &["ll=ls -l", "la=ls -a"]), output(0, "", ""));
+        assert_eq!(aliases.get("ll").as_deref(), Some("ls -l"));
+
+        assert_eq!(run_buffered(&Alias, &mut shell, "", &["ll"]), output(0, "alias ll='ls -l'\n", ""));
+        assert_eq!(
+            run_buffered(&Alias, &mut shell, "", &[]),
+            output(0, "alias la='ls -a'\nalias ll='ls -l'\n", "")
+        );
+
+        aliases.set("q", "echo it's");
+        assert_eq!(
+            run_buffered(&Alias, &mut shell, "", &["q"]),
+            output(0, "alias q='echo it'\\''s'\n", "")
+        );
+    }
+
+    #[test]
+    fn test_alias_intrinsic_errors() {
+        let (mut shell, aliases) = setup();
+
+        assert_eq!(
+            run_buffered(&Alias, &mut shell, "", &["nope"]),
+            output(1, "", "alias: nope: not found\n")
+        );
+        // the valid arguments still take effect
+        aliases.set("ll", "ls -l");
+        assert_eq!(
+            run_buffered(&Alias, &mut shell, "", &["a/b=ls", "ok=ls", "ll"]),
+            output(1, "alias ll='ls -l'\n", "alias: 'a/b': invalid alias name\n")
+        );
+        assert_eq!(aliases.get("ok").as_deref(), Some("ls"));
+        assert_eq!(aliases.get("a/b"), None);
+    }
+
+    #[test]
+    fn test_unalias_intrinsic() {
+        let (mut shell, aliases) = setup();
+        aliases.set("a", "ls");
+        aliases.set("b", "ls");
+        aliases.set("c", "ls");
+
+        assert_eq!(run_buffered(&Unalias, &mut shell, "", &["a"]), output(0, "", ""));
+        assert_eq!(aliases.get("a"), None);
+        assert_eq!(run_buffered(&Unalias, &mut shell, "", &["a"]), output(1, "", "unalias: a: not found\n"));
+        assert_eq!(run_buffered(&Unalias, &mut shell, "", &[]).0, 2);
+
+        assert_eq!(run_buffered(&Unalias, &mut shell, "", &["-a"]), output(0, "", ""));
+        assert!(aliases.get_all().is_empty());
+    }
+}
@@ -0,0 +1,170 @@
+#[cfg(test)]
+mod directory_tests {
+    use std::path::PathBuf;
+
+    use filesystem::{FakeFileSystem, FileSystem};
+    use pretty_assertions::assert_eq;
+
+    use crate::intrinsics::{
+        run_buffered, AliasTable, ChangeDirectory, Dirs, IntrinsicRegistry, OutputAdapter, PopDirectory,
+        PushDirectory,
+    };
+    use crate::shell::{Shell, VariableStore};
+
+    struct Fixture {
+        fs:    FakeFileSystem,
+        shell: Shell,
+    }
+
+    impl Fixture {
+        fn new(dirs: &[&str]) -> Self {
+            let fs = FakeFileSystem::new();
+            for dir in dirs {
+                fs.create_dir_all(dir).unwrap();
+            }
+            let mut shell = Shell::new(VariableStore::new(), AliasTable::new(), IntrinsicRegistry::new());
+            shell.set_variable("HOME", "/home/me");
+            Fixture { fs, shell }
+        }
+
+        /// Run `command` with `args`, returning its exit code and what it wrote to stdout and stderr
+        fn run(&mut self, command: &str, args: &[&str]) -> (i32, String, String) {
+            let fs = self.fs.clone();
+            match command {
+                "cd" => run_buffered(&OutputAdapter(ChangeDirectory::new(fs)), &mut self.shell, "", args),
+                "pushd" => run_buffered(&OutputAdapter(PushDirectory::new(fs)), &mut self.shell, "", args),
+                "popd" => run_buffered(&OutputAdapter(PopDirectory::new(fs)), &mut self.shell, "", args),
+                "dirs" => run_buffered(&OutputAdapter(Dirs::new(fs)), &mut self.shell, "", args),
+                _ => unreachable!(),
+            }
+        }
+
+        /// Run `command`, returning what it wrote to stdout, and checking that it succeeded
+        fn output(&mut self, command: &str, args: &[&str]) -> String {
+            let (code, stdout, stderr) = self.run(command, args);
+            assert_eq!((code, stderr.as_str()), (0, ""), "{} {:?}", command, args);
+            stdout
+        }
+
+        fn working_dir(&self) -> (PathBuf, Option<String>) {
+            (self.fs.current_dir().unwrap(), self.shell.get_variable("PWD"))
+        }
+    }
+
+    fn at(dir: &str) -> (PathBuf, Option<String>) {
+        (PathBuf::from(dir), Some(dir.to_string()))
+    }
+
+    #[test]
+    fn test_pwd_and_oldpwd() {
+        let mut fixture = Fixture::new(&["/a/b", "/c"]);
+        fixture.output("cd", &["/a/b"]);
+        assert_eq!(fixture.working_dir(), at("/a/b"));
+        assert_eq!(fixture.shell.get_variable("OLDPWD").as_deref(), Some("/"));
+
+        fixture.output("cd", &["../../c"]);
+        assert_eq!(fixture.working_dir(), at("/c"));
+        assert_eq!(fixture.shell.get_variable("OLDPWD").as_deref(), Some("/a/b"));
+
+        // `cd -` goes back, and prints where it went
+        assert_eq!(fixture.output("cd", &["-"]), "/a/b\n");
+        assert_eq!(fixture.working_dir(), at("/a/b"));
+        assert_eq!(fixture.output("cd", &["-"]), "/c\n");
+
+        fixture.shell.unset_variable("OLDPWD");
+        assert_eq!(fixture.run("cd", &["-"]), (1, "".to_string(), "cd: OLDPWD not set\n".to_string()));
+    }
+
+    #[test]
+    fn test_options() {
+        let mut fixture = Fixture::new(&["/real/dir", "/home/me"]);
+        // the fake filesystem has no symlinks, so `$PWD` stands in for having followed one
+        fixture.shell.set_variable("PWD", "/home/me");
+
+        fixture.output("cd", &["-P", "/real/dir"]);
+        assert_eq!(fixture.working_dir(), at("/real/dir"));
+        fixture.output("cd", &["-PL", "--", ".."]);
+        assert_eq!(fixture.working_dir(), at("/real"));
+        fixture.output("cd", &["-P"]);
+        assert_eq!(fixture.working_dir(), at("/home/me"));
+
+        assert_eq!(fixture.run("cd", &["-x", "/real"]).2, "cd: -x: invalid option\n");
+        assert_eq!(fixture.working_dir(), at("/home/me"));
+    }
+
+    #[test]
+    fn test_cdpath() {
+        let mut fixture = Fixture::new(&["/projects/yeesh/src", "/work/yeesh", "/here/src"]);
+        fixture.shell.set_variable("CDPATH", "/projects:/work/");
+
+        // a directory found through `$CDPATH` is printed
+        assert_eq!(fixture.output("cd", &["yeesh"]), "/projects/yeesh\n");
+        assert_eq!(fixture.output("cd", &["src"]), "");
+        assert_eq!(fixture.working_dir(), at("/projects/yeesh/src"));
+
+        // an empty entry is the working directory, which is then searched first
+        fixture.output("cd", &["/here"]);
+        fixture.shell.set_variable("CDPATH", ":/projects/yeesh");
+        assert_eq!(fixture.output("cd", &["src"]), "");
+        assert_eq!(fixture.working_dir(), at("/here/src"));
+
+        // paths starting with `.`, `..` or `/` aren't searched for
+        assert_eq!(fixture.run("cd", &["./src"]).0, 1);
+        fixture.shell.set_variable("CDPATH", "/work");
+        assert_eq!(fixture.output("cd", &["../../work/yeesh"]), "");
+        assert_eq!(fixture.working_dir(), at("/work/yeesh"));
+    }
+
+    #[test]
+    fn test_pushd_and_popd() {
+        let mut fixture = Fixture::new(&["/a", "/b", "/home/me/c"]);
+        assert_eq!(fixture.output("pushd", &["/a"]), "/a /\n");
+        assert_eq!(fixture.output("pushd", &["/b"]), "/b /a /\n");
+        assert_eq!(fixture.output("pushd", &["/home/me/c"]), "~/c /b /a /\n");
+        assert_eq!(fixture.working_dir(), at("/home/me/c"));
+
+        // with no arguments, the top two directories are swapped
+        assert_eq!(fixture.output("pushd", &[]), "/b ~/c /a /\n");
+        assert_eq!(fixture.working_dir(), at("/b"));
+
+        // `+N` and `-N` rotate the stack
+        assert_eq!(fixture.output("pushd", &["+2"]), "/a / /b ~/c\n");
+        assert_eq!(fixture.output("pushd", &["-0"]), "~/c /a / /b\n");
+        assert_eq!(fixture.working_dir(), at("/home/me/c"));
+        assert_eq!(
+            fixture.run("pushd", &["+4"]).2,
+            "pushd: +4: directory stack index out of range\n"
+        );
+
+        // `popd +N` removes a directory without changing to another
+        assert_eq!(fixture.output("popd", &["+1"]), "~/c / /b\n");
+        assert_eq!(fixture.working_dir(), at("/home/me/c"));
+        assert_eq!(fixture.output("popd", &[]), "/ /b\n");
+        assert_eq!(fixture.working_dir(), at("/"));
+        assert_eq!(fixture.output("popd", &["-0"]), "/\n");
+        assert_eq!(fixture.run("popd", &[]).2, "popd: directory stack empty\n");
+        assert_eq!(fixture.run("pushd", &[]).2, "pushd: no other directory\n");
+
+        // the stack is kept if the directory can't be changed to
+        assert_eq!(fixture.run("pushd", &["/nope"]).0, 1);
+        assert!(fixture.shell.get_directory_stack().is_empty());
+    }
+
+    #[test]
+    fn test_dirs() {
+        let mut fixture = Fixture::new(&["/a", "/home/me/b"]);
+        fixture.output("pushd", &["/a"]);
+        fixture.output("pushd", &["/home/me/b"]);
+
+        assert_eq!(fixture.output("dirs", &[]), "~/b /a /\n");
+        assert_eq!(fixture.output("dirs", &["-l"]), "/home/me/b /a /\n");
+        assert_eq!(fixture.output("dirs", &["-p"]), "~/b\n/a\n/\n");
+        assert_eq!(fixture.output("dirs", &["-lv"]), " 0  /home/me/b\n 1  /a\n 2  /\n");
+        assert_eq!(fixture.output("dirs", &["+1"]), "/a\n");
+        assert_eq!(fixture.output("dirs", &["-0"]), "/\n");
+        assert_eq!(fixture.run("dirs", &["-x"]).0, 1);
+
+        assert_eq!(fixture.output("dirs", &["-c"]), "");
+        assert_eq!(fixture.output("dirs", &[]), "~/b\n");
+    }
+}
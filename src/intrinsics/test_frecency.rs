@@ -0,0 +1,120 @@
+#[cfg(test)]
+mod frecency_tests {
+    use std::path::{Path, PathBuf};
+
+    use filesystem::{FakeFileSystem, FileSystem};
+    use pretty_assertions::assert_eq;
+
+    use crate::intrinsics::{
+        run_buffered, AliasTable, ChangeDirectory, DirectoryDb, IntrinsicRegistry, JumpDirectory, OutputAdapter,
+    };
+    use crate::shell::{Shell, VariableStore};
+
+    const HOUR: u64 = 60 * 60;
+
+    fn fragments(fragments: &[&str]) -> Vec<String> {
+        fragments.iter().map(|f| f.to_string()).collect()
+    }
+
+    fn dirs(found: Vec<(String, f64)>) -> Vec<String> {
+        found.into_iter().map(|(dir, _)| dir).collect()
+    }
+
+    #[test]
+    fn test_ranking() {
+        let db = DirectoryDb::new();
+        let now = 100 * HOUR;
+        for _ in 0..4 {
+            db.visit_at("/old/project", now - 30 * HOUR);
+        }
+        db.visit_at("/new/project", now - HOUR / 2);
+        db.visit_at("/new/project", now - HOUR / 2);
+
+        // recent visits count for more than older ones
+        assert_eq!(dirs(db.find_at(&fragments(&["project"]), now)), vec!["/new/project", "/old/project"]);
+        assert_eq!(db.find_at(&fragments(&["old"]), now), vec![("/old/project".to_string(), 2.0)]);
+        db.visit_at("/old/project", now);
+        assert_eq!(dirs(db.find_at(&fragments(&["project"]), now)), vec!["/old/project", "/new/project"]);
+    }
+
+    #[test]
+    fn test_matching() {
+        let db = DirectoryDb::new();
+        db.visit_at("/src/yeesh/docs", 0);
+        db.visit_at("/src/Other", 0);
+
+        // fragments match in order
+        assert_eq!(dirs(db.find_at(&fragments(&["src", "docs"]), 0)), vec!["/src/yeesh/docs"]);
+        assert!(db.find_at(&fragments(&["docs", "src"]), 0).is_empty());
+        assert_eq!(dirs(db.find_at(&fragments(&[]), 0)).len(), 2);
+
+        // case is only ignored if nothing matches otherwise
+        assert_eq!(dirs(db.find_at(&fragments(&["other"]), 0)), vec!["/src/Other"]);
+        db.visit_at("/other", 0);
+        assert_eq!(dirs(db.find_at(&fragments(&["other"]), 0)), vec!["/other"]);
+    }
+
+    #[test]
+    fn test_ageing() {
+        let db = DirectoryDb::new();
+        db.visit_at("/rare", 0);
+        for _ in 0..9000 {
+            db.visit_at("/common", 0);
+        }
+        assert!(db.find_at(&fragments(&["rare"]), 0).is_empty());
+        assert_eq!(db.find_at(&fragments(&["common"]), 0).len(), 1);
+    }
+
+    #[test]
+    fn test_persistence() {
+        let fs = FakeFileSystem::new();
+        fs.create_dir_all("/home/me").unwrap();
+        let path = Path::new("/home/me/.yeesh_dirs");
+        fs.create_file(path, "/a|3|100\n/b c|1.5|200\nbroken\n").unwrap();
+
+        let db = DirectoryDb::new();
+        db.open(&fs, path);
+        assert_eq!(db.save(), "/a|3|100\n/b c|1.5|200\n");
+
+        // visits are saved, along with those other shells have saved since
+        fs.overwrite_file(path, "/a|3|100\n/d|1|300\n").unwrap();
+        db.visit(&fs, Path::new("/a"));
+        let saved = fs.read_file_to_string(path).unwrap();
+        let lines: Vec<&str> = saved.lines().collect();
+        assert!(lines[0].starts_with("/a|4|"));
+        assert_eq!(lines[1], "/d|1|300");
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[test]
+    fn test_jump() {
+        let fs = FakeFileSystem::new();
+        for dir in ["/src/yeesh/docs", "/work/yeesh", "/removed"] {
+            fs.create_dir_all(dir).unwrap();
+        }
+        let mut shell = Shell::new(VariableStore::new(), AliasTable::new(), IntrinsicRegistry::new());
+        let cd = OutputAdapter(ChangeDirectory::new(fs.clone()));
+        let z = OutputAdapter(JumpDirectory::new(fs.clone()));
+
+        // changing directory records the visit
+        for dir in ["/src/yeesh/docs", "/work/yeesh", "/work/yeesh", "/removed", "/"] {
+            assert_eq!(run_buffered(&cd, &mut shell, "", &[dir]).0, 0);
+        }
+        fs.remove_dir("/removed").unwrap();
+
+        assert_eq!(run_buffered(&z, &mut shell, "", &["yeesh"]).0, 0);
+        assert_eq!(fs.current_dir().unwrap(), PathBuf::from("/work/yeesh"));
+        assert_eq!(run_buffered(&z, &mut shell, "", &["src", "yeesh"]).0, 0);
+        assert_eq!(fs.current_dir().unwrap(), PathBuf::from("/src/yeesh/docs"));
+        assert_eq!(shell.get_variable("OLDPWD").as_deref(), Some("/work/yeesh"));
+
+        assert_eq!(
+            run_buffered(&z, &mut shell, "", &["removed"]),
+            (1, "".to_string(), "z: no directory matches 'removed'\n".to_string())
+        );
+        let (code, stdout, _) = run_buffered(&z, &mut shell, "", &["-l", "yeesh"]);
+        let listed: Vec<&str> = stdout.lines().map(|line| line.split_whitespace().last().unwrap()).collect();
+        assert_eq!((code, listed), (0, vec!["/src/yeesh/docs", "/work/yeesh"]));
+        assert_eq!(run_buffered(&z, &mut shell, "", &["-x"]).0, 1);
+    }
+}
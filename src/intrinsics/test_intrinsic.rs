@@ -1,25 +1,10 @@
-#[cfg(test)]
-use std::borrow::Borrow;
-
-use crate::find_intrinsic;
-use crate::intrinsics::INTRINSICS;
-
-#[test]
-fn test_find_intrinsic() {
-    for intrinsic in INTRINSICS.iter() {
-        let cmd_string = intrinsic.get_command().to_string();
-        let found = find_intrinsic(&cmd_string);
-        assert!(found.is_some());
-        assert_eq!(found.unwrap(), intrinsic.borrow());
-    }
-}
-
 #[cfg(test)]
 mod cd_tests {
     use filesystem::{FakeFileSystem, FileSystem};
     use path_absolutize::Absolutize;
 
-    use crate::intrinsics::{ChangeDirectory, Intrinsic};
+    use crate::intrinsics::{AliasTable, ChangeDirectory, IntrinsicRegistry, OutputIntrinsic};
+    use crate::shell::{Shell, VariableStore};
 
     macro_rules! get_path_str {
         ($variable:expr) => {
@@ -33,9 +18,10 @@ mod cd_tests {
         };
     }
 
-    fn setup() -> (ChangeDirectory<FakeFileSystem>, FakeFileSystem) {
+    fn setup() -> (ChangeDirectory<FakeFileSystem>, FakeFileSystem, Shell) {
         let fs = FakeFileSystem::new();
-        (ChangeDirectory::new(fs.clone()), fs)
+        let shell = Shell::new(VariableStore::new(), AliasTable::new(), IntrinsicRegistry::new());
+        (ChangeDirectory::new(fs.clone()), fs, shell)
     }
 
     fn create_dirs(fs: &FakeFileSystem, dirs: &Vec<&'static str>) {
@@ -45,49 +31,49 @@ mod cd_tests {
     }
 
     fn get_ne_err(path: &'static str) -> String {
-        format!("cd: The directory \"{}\" does not exist\r\n", path)
+        format!("cd: The directory \"{}\" does not exist\n", path)
     }
 
     #[test]
     fn test_error_conditions() {
-        let (cd, fs) = setup();
+        let (cd, fs, mut shell) = setup();
         fs.create_file("file.txt", "bleh").unwrap();
         create_dirs(&fs, &vec!["hello", "there"]);
 
         // test too many arguments
-        let mut res = cd.handler(&["hello".to_string(), "there".to_string()]);
+        let mut res = cd.handler(&mut shell, &["hello".to_string(), "there".to_string()]);
         assert!(res.is_err());
-        assert_eq!(res.err().unwrap(), "cd: too many arguments");
+        assert_eq!(res.err().unwrap(), "cd: too many arguments\n");
 
         // test cd file
-        res = cd.handler(&["file.txt".to_string()]);
+        res = cd.handler(&mut shell, &["file.txt".to_string()]);
         assert!(res.is_err());
-        assert_eq!(res.err().unwrap(), "cd: 'file.txt' is not a directory");
+        assert_eq!(res.err().unwrap(), "cd: 'file.txt' is not a directory\n");
     }
 
     /// Test ChangeDirectory with both absolute and relative paths that don't exist
     #[test]
     fn test_bad_path_handling() {
-        let (cd, fs) = setup();
+        let (cd, fs, mut shell) = setup();
 
         // No directories exist, try CDing to a relative path, no ./
-        let mut res = cd.handler(&["this_doesnt_exist".to_string()]);
+        let mut res = cd.handler(&mut shell, &["this_doesnt_exist".to_string()]);
         assert!(res.is_err());
         assert_eq!(res.err().unwrap(), get_ne_err("this_doesnt_exist"));
 
         // relative path with ./
-        res = cd.handler(&["./this_doesnt_exist".to_string()]);
+        res = cd.handler(&mut shell, &["./this_doesnt_exist".to_string()]);
         assert!(res.is_err());
         assert_eq!(res.err().unwrap(), get_ne_err("./this_doesnt_exist"));
 
         // try an absolute path
-        res = cd.handler(&["/absolutely_not_exist".to_string()]);
+        res = cd.handler(&mut shell, &["/absolutely_not_exist".to_string()]);
         assert!(res.is_err());
         assert_eq!(res.err().unwrap(), get_ne_err("/absolutely_not_exist"));
 
         // try an absolute path that's a child of an existent path
         fs.create_dir("/exists").unwrap();
-        res = cd.handler(&["/exists/doesnt".to_string()]);
+        res = cd.handler(&mut shell, &["/exists/doesnt".to_string()]);
         assert!(res.is_err());
         assert_eq!(res.err().unwrap(), get_ne_err("/exists/doesnt"));
     }
@@ -95,7 +81,7 @@ mod cd_tests {
     #[test]
     /// Test ChangeDirectory with absolute paths that exist
     fn test_good_absolute_path_handling() {
-        let (cd, fs) = setup();
+        let (cd, fs, mut shell) = setup();
         let some_path = "/hello/I/am/a/path";
         let some_other_path = "/hello/I/hello/I/am/a";
         let another_path = "/totally/not/a/path";
@@ -103,94 +89,158 @@ mod cd_tests {
 
         // test absolute path
         let mut args = vec!["/hello/I".to_string()];
-        let mut result = cd.handler(&args);
+        let mut result = cd.handler(&mut shell, &args);
         assert!(result.is_ok());
         assert_eq!(get_path_str!(fs), "/hello/I");
 
         // test absolute path which can also be relative to the current dir (overlaps)
         args = vec!["/hello/I/am/a".to_string()];
-        result = cd.handler(&args);
+        result = cd.handler(&mut shell, &args);
         assert!(result.is_ok());
         assert_eq!(get_path_str!(fs), "/hello/I/am/a");
 
         // test cd to root
         args = vec!["/".to_string()];
-        result = cd.handler(&args);
+        result = cd.handler(&mut shell, &args);
         assert!(result.is_ok());
         assert_eq!(get_path_str!(fs), "/");
 
         // prepare for unrelated dir test
         args = vec![some_path.to_string()];
-        result = cd.handler(&args);
+        result = cd.handler(&mut shell, &args);
         assert!(result.is_ok());
 
         // test changing to absolute path unrelated to current path
         args = vec![another_path.to_string()];
-        result = cd.handler(&args);
+        result = cd.handler(&mut shell, &args);
         assert!(result.is_ok());
         assert_eq!(get_path_str!(fs), another_path);
     }
 
     #[test]
     fn test_good_relative_path_handling() {
-        let (cd, fs) = setup();
+        let (cd, fs, mut shell) = setup();
         let path = "/this/is/a/path/that/exists";
         fs.create_dir_all(path).unwrap();
 
         // test no ./
-        let mut res = cd.handler(&["this".to_string()]);
+        let mut res = cd.handler(&mut shell, &["this".to_string()]);
         assert!(res.is_ok());
         assert_eq!(get_path_str!(fs), "/this");
 
         // test with ./
-        res = cd.handler(&["./is".to_string()]);
+        res = cd.handler(&mut shell, &["./is".to_string()]);
         assert!(res.is_ok());
         assert_eq!(get_path_str!(fs), "/this/is");
 
         // test relative compound path, no ./
-        res = cd.handler(&["a/path".to_string()]);
+        res = cd.handler(&mut shell, &["a/path".to_string()]);
         assert!(res.is_ok());
         assert_eq!(get_path_str!(fs), "/this/is/a/path");
 
         // test relative compount path with ./
-        res = cd.handler(&["./that/exists".to_string()]);
+        res = cd.handler(&mut shell, &["./that/exists".to_string()]);
         assert!(res.is_ok());
         assert_eq!(get_path_str!(fs), "/this/is/a/path/that/exists");
     }
 
     #[test]
     fn test_home_handling() {
-        let (cd, fs) = setup();
+        let (cd, fs, mut shell) = setup();
         let home_dir_path = "/home/Person";
         let home_docs_path = "/home/Person/Documents/stuff";
         create_dirs(&fs, &vec![home_dir_path, home_docs_path]);
+        shell.set_variable("HOME", home_dir_path);
 
         // Test empty argument
         let mut args = vec![];
-        let mut res = temp_env::with_var("HOME", Some(home_dir_path), || cd.handler(&args));
+        let mut res = cd.handler(&mut shell, &args);
         assert!(res.is_ok());
         assert_eq!(get_path_str!(fs), home_dir_path);
 
         // return to root
         args = vec!["/".to_string()];
-        res = cd.handler(&args);
+        res = cd.handler(&mut shell, &args);
         assert!(res.is_ok());
 
         // Test only tilde
         args = vec!["~".to_string()];
-        res = temp_env::with_var("HOME", Some(home_dir_path), || cd.handler(&args));
+        res = cd.handler(&mut shell, &args);
         assert!(res.is_ok());
         assert_eq!(get_path_str!(fs), home_dir_path);
 
         // return to root
         args = vec!["/".to_string()];
-        res = cd.handler(&args);
+        res = cd.handler(&mut shell, &args);
         assert!(res.is_ok());
 
         // Test tilde with subsequent relative path
         args = vec!["~/Documents/stuff".to_string()];
-        res = temp_env::with_var("HOME", Some(home_dir_path), || cd.handler(&args));
+        res = cd.handler(&mut shell, &args);
         assert!(res.is_ok());
         assert_eq!(get_path_str!(fs), home_docs_path);
     }
 }
+
+#[cfg(test)]
+mod type_tests {
+    use filesystem::FakeFileSystem;
+    use pretty_assertions::assert_eq;
+
+    use crate::intrinsics::{run_buffered, AliasTable, IntrinsicRegistry, Type};
+    use crate::shell::{Shell, VariableStore};
+
+    fn setup() -> Shell {
+        let intrinsics = IntrinsicRegistry::with_defaults(FakeFileSystem::new());
+        let mut shell = Shell::new(VariableStore::new(), AliasTable::new(), intrinsics);
+        shell.set_variable("PATH", "");
+        shell.get_aliases().set("ll", "ls -l");
+        shell.run("f() { :; }");
+        shell
+    }
+
+    #[test]
+    fn test_type() {
+        let mut shell = setup();
+        let (code, stdout, _) = run_buffered(&Type, &mut shell, "", &["ll", "if", "cd", "return", "f"]);
+        assert_eq!(code, 0);
+        assert_eq!(
+            stdout,
+            "ll is aliased to `ls -l'\n\
+             if is a shell keyword\n\
+             cd is a shell builtin\n\
+             return is a shell builtin\n\
+             f is a function\n"
+        );
+        let (code, stdout, _) = run_buffered(&Type, &mut shell, "", &["-t", "ll", "if", "cd", "f"]);
+        assert_eq!((code, stdout.as_str()), (0, "alias\nkeyword\nbuiltin\nfunction\n"));
+    }
+
+    #[test]
+    fn test_type_not_found() {
+        let mut shell = setup();
+        assert_eq!(
+            run_buffered(&Type, &mut shell, "", &["cd", "nope"]),
+            (1, "cd is a shell builtin\n".to_string(), "type: nope: not found\n".to_string())
+        );
+        assert_eq!(run_buffered(&Type, &mut shell, "", &["-t", "nope"]), (1, "".to_string(), "".to_string()));
+    }
+}
+
+#[cfg(test)]
+mod reset_tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::intrinsics::{run_buffered, AliasTable, IntrinsicRegistry, ResetTerminal};
+    use crate::shell::{Shell, VariableStore, REPAIR_SEQUENCE};
+
+    #[test]
+    fn test_reset() {
+        let mut shell = Shell::new(VariableStore::new(), AliasTable::new(), IntrinsicRegistry::new());
+        let (code, stdout, stderr) = run_buffered(&ResetTerminal, &mut shell, "", &[]);
+        assert_eq!((code, stderr.as_str()), (0, ""));
+        // the screen is put back to normal, then cleared
+        assert_eq!(stdout, format!("{}\x1b[H\x1b[2J", REPAIR_SEQUENCE));
+        assert!(stdout.contains("\x1b[?1049l") && stdout.contains("\x1b[?25h"));
+    }
+}
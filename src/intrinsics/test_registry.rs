@@ -0,0 +1,109 @@
+#[cfg(test)]
+mod registry_tests {
+    use filesystem::FakeFileSystem;
+    use pretty_assertions::assert_eq;
+
+    use std::io;
+
+    use crate::intrinsics::{
+        run_buffered, Alias, AliasTable, Intrinsic, IntrinsicIo, IntrinsicRegistry, OutputAdapter, OutputIntrinsic,
+    };
+    use crate::shell::{Shell, VariableStore};
+
+    /// An intrinsic which copies its input to its output, after its description
+    struct Echo(&'static str);
+
+    impl Intrinsic for Echo {
+        fn get_command(&self) -> &'static str {
+            "echo-input"
+        }
+
+        fn get_description(&self) -> &'static str {
+            self.0
+        }
+
+        fn run(&self, _: &mut Shell, io: &mut IntrinsicIo, _: &[String]) -> i32 {
+            let result = io.stdout.write_all(self.0.as_bytes()).and_then(|_| io::copy(io.stdin, io.stdout));
+            match result {
+                Ok(_) => 0,
+                Err(_) => 1,
+            }
+        }
+    }
+
+    /// An intrinsic in the style which returns its output at once
+    struct Greet;
+
+    impl OutputIntrinsic for Greet {
+        fn get_command(&self) -> &'static str {
+            "greet"
+        }
+
+        fn get_description(&self) -> &'static str {
+            "say hello"
+        }
+
+        fn handler(&self, _: &mut Shell, args: &[String]) -> Result<String, String> {
+            match args.first() {
+                Some(name) => Ok(format!("hello {}\n", name)),
+                None => Err("greet: who?\n".to_string()),
+            }
+        }
+    }
+
+    fn commands(registry: &IntrinsicRegistry) -> Vec<&'static str> {
+        registry.get_all().iter().map(|i| i.get_command()).collect()
+    }
+
+    #[test]
+    fn test_defaults() {
+        let registry = IntrinsicRegistry::with_defaults(FakeFileSystem::new());
+        assert_eq!(
+            commands(&registry),
+            vec!["cd", "pushd", "popd", "dirs", "z", "exit", "alias", "unalias", "type", "trap", "jobs", "reset"]
+        );
+        for intrinsic in registry.get_all() {
+            let found = registry.find(intrinsic.get_command()).unwrap();
+            assert_eq!(found.get_description(), intrinsic.get_description());
+        }
+        assert!(registry.find("nope").is_none());
+    }
+
+    #[test]
+    fn test_register_and_remove() {
+        let registry = IntrinsicRegistry::new();
+        let shared = registry.clone();
+        registry.register(Box::new(Alias));
+        registry.register(Box::new(Echo("first")));
+        assert_eq!(commands(&shared), vec!["alias", "echo-input"]);
+
+        // registering a command again replaces it
+        shared.register(Box::new(Echo("second")));
+        assert_eq!(commands(&registry), vec!["alias", "echo-input"]);
+        assert_eq!(registry.find("echo-input").unwrap().get_description(), "second");
+
+        assert!(registry.remove("alias"));
+        assert!(!registry.remove("alias"));
+        assert_eq!(commands(&shared), vec!["echo-input"]);
+    }
+
+    #[test]
+    fn test_streams() {
+        let registry = IntrinsicRegistry::new();
+        registry.register(Box::new(Echo("input: ")));
+        registry.register(Box::new(OutputAdapter(Greet)));
+        let mut shell = Shell::new(VariableStore::new(), AliasTable::new(), registry.clone());
+
+        let echo = registry.find("echo-input").unwrap();
+        let output = run_buffered(echo.as_ref(), &mut shell, "a\nb\n", &[]);
+        assert_eq!(output, (0, "input: a\nb\n".to_string(), "".to_string()));
+
+        // the adapter writes the output to stdout, or the error to stderr and fails
+        let greet = registry.find("greet").unwrap();
+        assert_eq!(greet.get_description(), "say hello");
+        let output = run_buffered(greet.as_ref(), &mut shell, "", &["you"]);
+        assert_eq!(output, (0, "hello you\n".to_string(), "".to_string()));
+        let output = run_buffered(greet.as_ref(), &mut shell, "", &[]);
+        assert_eq!(output, (1, "".to_string(), "greet: who?\n".to_string()));
+    }
+}
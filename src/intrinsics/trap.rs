@@ -0,0 +1,104 @@
+use crate::intrinsics::{quote, Intrinsic, IntrinsicIo};
+use crate::shell::{get_signal_name, get_signal_number, Shell, SIGNALS};
+
+/// The conditions other than signals a trap can be set for, each with the other names it can be
+/// given by
+const CONDITIONS: [(&str, &[&str]); 4] = [("EXIT", &["0"]), ("ERR", &[]), ("DEBUG", &[]), ("RETURN", &[])];
+
+/// Returns the name traps for the condition `name` are kept under: one of [CONDITIONS], or the
+/// full name of a signal, e.g. `SIGINT` for `INT` or `2`
+fn find_condition(name: &str) -> Option<&'static str> {
+    CONDITIONS
+        .iter()
+        .find(|(condition, aliases)| *condition == name || aliases.contains(&name))
+        .map(|(condition, _)| *condition)
+        .or_else(|| get_signal_number(name).and_then(get_signal_name))
+}
+
+/// `trap [-lp] [[action] condition ...]`, which runs `action` when each `condition` occurs: a
+/// signal, or `EXIT`, `ERR`, `DEBUG` or `RETURN`. An `action` of `-` resets each condition, and an
+/// empty one ignores the signal. With `-p` or no arguments, the traps are listed, and with `-l`
+/// the signals.
+pub struct Trap;
+
+impl Trap {
+    fn list(&self, shell: &Shell, io: &mut IntrinsicIo, names: &[String]) -> i32 {
+        let mut code = 0;
+        let mut conditions = vec![];
+        for name in names {
+            match find_condition(name) {
+                Some(condition) => conditions.push(condition),
+                None => {
+                    writeln!(io.stderr, "trap: {}: invalid condition", name).unwrap_or_default();
+                    code = 1;
+                }
+            }
+        }
+
+        for (condition, action) in shell.get_traps() {
+            if !names.is_empty() && !conditions.contains(&condition.as_str()) {
+                continue;
+            }
+            if writeln!(io.stdout, "trap -- {} {}", quote(action), condition).is_err() {
+                return 1;
+            }
+        }
+        code
+    }
+
+    fn list_signals(&self, io: &mut IntrinsicIo) -> i32 {
+        for (name, number) in SIGNALS {
+            if writeln!(io.stdout, "{:2}) {}", number, name).is_err() {
+                return 1;
+            }
+        }
+        0
+    }
+}
+
+impl Intrinsic for Trap {
+    fn get_command(&self) -> &'static str {
+        "trap"
+    }
+
+    fn get_description(&self) -> &'static str {
+        "run a command when a signal arrives or the shell exits, or list the commands set to run"
+    }
+
+    fn run(&self, shell: &mut Shell, io: &mut IntrinsicIo, args: &[String]) -> i32 {
+        let args = match args.first().map(String::as_str) {
+            Some("-p") => return self.list(shell, io, &args[1..]),
+            Some("-l") => return self.list_signals(io),
+            Some("--") => &args[1..],
+            _ => args,
+        };
+        let (action, conditions) = match args {
+            [] => return self.list(shell, io, &[]),
+            // a lone condition, or conditions starting with a number, are reset
+            [condition] if find_condition(condition).is_some() => ("-", args),
+            [first, ..] if first.parse::<u32>().is_ok() => ("-", args),
+            [_] => {
+                writeln!(io.stderr, "trap: usage: trap [-lp] [[action] condition ...]").unwrap_or_default();
+                return 2;
+            }
+            [action, conditions @ ..] => (action.as_str(), conditions),
+        };
+
+        let mut code = 0;
+        for name in conditions {
+            match find_condition(name) {
+                Some(condition) if action == "-" => shell.set_trap(condition, None),
+                Some("SIGKILL" | "SIGSTOP") => {
+                    writeln!(io.stderr, "trap: {}: cannot be trapped", name).unwrap_or_default();
+                    code = 1;
+                }
+                Some(condition) => shell.set_trap(condition, Some(action)),
+                None => {
+                    writeln!(io.stderr, "trap: {}: invalid condition", name).unwrap_or_default();
+                    code = 1;
+                }
+            }
+        }
+        code
+    }
+}
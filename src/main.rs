@@ -1,82 +1,136 @@
-#![feature(let_chains)]
 #![feature(iter_intersperse)]
-#![feature(is_some_and)]
 #![allow(dead_code)]
 #![allow(unused_macros)]
 #![allow(non_camel_case_types)]
+// each module keeps its main file under the module's own name, e.g. `shell/shell.rs`
+#![allow(clippy::module_inception)]
+// the token helpers predate taking slices, and are left as they are
+#![allow(clippy::ptr_arg)]
 
+mod args;
 mod cmd_input;
 mod error;
 mod fixture;
 mod intrinsics;
+mod parser;
 mod prompt;
+#[cfg(feature = "python")]
+mod python;
+mod shell;
 
-use std::io;
-use std::io::{stderr, stdin, stdout, Stdout, Write};
-use std::os::unix::process::ExitStatusExt;
-use std::process::{Child, Command, ExitStatus};
+use std::fs::File;
+use std::io::{self, stdin, stdout, BufRead, BufReader, Read, Stdout, Write};
+use std::path::Path;
+use std::process::ExitStatus;
+use std::time::Instant;
+use std::{env, iter, process};
 
 use filesystem::OsFileSystem;
-use intrinsics::find_intrinsic;
+use intrinsics::{AliasTable, IntrinsicRegistry, DIRECTORY_DB_FILE};
 use log::info;
-use prompt::print_prompt;
+use prompt::{get_right_prompt, print_prompt};
 use termion::color;
-use termion::event::Key;
-use termion::input::TermRead;
+use termion::event::{parse_event, Event, Key};
 use termion::raw::{IntoRawMode, RawTerminal};
 
+use crate::args::{Args, Mode, USAGE};
 use crate::cmd_input::CmdInput;
+use crate::shell::{
+    catch_signal, get_startup_files, has_pending_signals, is_hung_up, status_code, status_from_code, ControlFlow,
+    Shell, VariableStore, CMD_DURATION, SHELL_NAME,
+};
 use crate::HandleKeyResult::{CommandStatus, Continue, Exit};
 
-fn dispatch_command(cmd_args: Vec<String>) -> io::Result<Child> {
-    if cmd_args.is_empty() {
-        return Err(io::Error::new(io::ErrorKind::Other, "Empty command"));
+/// How often the line is redrawn while completions are pending, in milliseconds
+const COMPLETION_POLL_INTERVAL_MS: i32 = 80;
+
+/// Stdin read without the buffer [std::io::Stdin] keeps, which [KeyReader] replaces with its own
+struct UnbufferedStdin;
+
+impl Read for UnbufferedStdin {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match unsafe { libc::read(libc::STDIN_FILENO, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) } {
+            -1 => Err(io::Error::last_os_error()),
+            count => Ok(count as usize),
+        }
     }
+}
 
-    Command::new(&cmd_args[0])
-        .args(if cmd_args.len() > 1 { &cmd_args[1..] } else { &[] })
-        .spawn()
+/// Reads keys from stdin, taking everything the terminal has sent at once, so that keys typed ahead
+/// aren't mixed up with the answer to a request for the cursor position. Unlike termion's `Keys`, it
+/// can tell whether any of them are still waiting to be handled, which [wait_for_input] can't see.
+struct KeyReader {
+    reader: BufReader<UnbufferedStdin>,
 }
 
-fn handle_command(stdout: &mut RawTerminal<Stdout>, cmd_input: &mut CmdInput) -> Option<ExitStatus> {
-    let status: ExitStatus;
-
-    let cmd_args = cmd_input.get_cmd();
-    if !cmd_args.is_empty() && let Some(intrinsic) = find_intrinsic(&cmd_args[0]) {
-        if cmd_args.len() == 2 && (cmd_args[1] == "--help" || cmd_args[1] == "-h") {
-            println!("{}\r", intrinsic.get_description());
-            status = ExitStatus::from_raw(0);
-        } else {
-            match intrinsic.handler(&cmd_args[1..]) {
-                Ok(output) => {
-                    write!(stdout, "{}", output).unwrap();
-                    status = ExitStatus::from_raw(0);
-                }
-                Err(err) => {
-                    write!(stderr(), "{}", err).unwrap();
-                    status = ExitStatus::from_raw(1);
-                }
-            }
-        }
-        if intrinsic.get_command() == "exit" {
-            return None;
+impl KeyReader {
+    fn new() -> KeyReader {
+        KeyReader {
+            reader: BufReader::new(UnbufferedStdin),
         }
     }
-    else if !cmd_args.is_empty() {
-        stdout.suspend_raw_mode().unwrap();
-        if let Ok(mut child) = dispatch_command(cmd_args)
-            && let Ok(exit_status) = child.wait() {
-            status = exit_status;
-        } else {
-            status = ExitStatus::from_raw(1);
+
+    /// Returns true if keys have been read from stdin but not returned yet
+    fn has_pending(&self) -> bool {
+        !self.reader.buffer().is_empty()
+    }
+
+    fn next_byte(&mut self) -> Option<io::Result<u8>> {
+        let mut byte = [0];
+        match self.reader.read(&mut byte) {
+            Ok(0) => None,
+            Ok(_) => Some(Ok(byte[0])),
+            Err(e) => Some(Err(e)),
         }
-        stdout.activate_raw_mode().unwrap();
     }
-    else {
-        status = ExitStatus::from_raw(0);
+}
+
+impl Iterator for KeyReader {
+    type Item = io::Result<Key>;
+
+    fn next(&mut self) -> Option<io::Result<Key>> {
+        loop {
+            let first = match self.next_byte()? {
+                Ok(first) => first,
+                Err(e) => return Some(Err(e)),
+            };
+            // an escape which arrived on its own is the Esc key rather than the start of a sequence
+            if first == b'\x1b' && !self.has_pending() {
+                return Some(Ok(Key::Esc));
+            }
+            match parse_event(first, &mut iter::from_fn(|| self.next_byte())) {
+                Ok(Event::Key(key)) => return Some(Ok(key)),
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
     }
+}
 
-    Some(status)
+/// Wait up to `timeout_ms` for stdin to become readable, returning true if it did
+fn wait_for_input(timeout_ms: i32) -> bool {
+    let mut fds = libc::pollfd {
+        fd:      libc::STDIN_FILENO,
+        events:  libc::POLLIN,
+        revents: 0,
+    };
+    unsafe { libc::poll(&mut fds, 1, timeout_ms) > 0 }
+}
+
+fn handle_command(
+    stdout: &mut RawTerminal<Stdout>,
+    cmd_input: &mut CmdInput,
+    shell: &mut Shell,
+) -> Option<ExitStatus> {
+    let line: String = cmd_input.get_input().iter().collect();
+
+    stdout.suspend_raw_mode().unwrap();
+    let start = Instant::now();
+    let status = shell.run(&line);
+    shell.set_variable(CMD_DURATION, &start.elapsed().as_millis().to_string());
+    stdout.activate_raw_mode().unwrap();
+
+    status
 }
 
 enum HandleKeyResult {
@@ -86,61 +140,207 @@ enum HandleKeyResult {
 }
 
 fn handle_key(
-    mut stdout: &mut RawTerminal<Stdout>,
-    mut cmd_input: &mut CmdInput,
+    stdout: &mut RawTerminal<Stdout>,
+    cmd_input: &mut CmdInput,
+    shell: &mut Shell,
     prompt_len: usize,
     val: Key,
 ) -> HandleKeyResult {
     match val {
+        // like `exit`, end of input on an empty line exits, unless it has to warn about jobs first
+        Key::Ctrl('d') if cmd_input.get_input().is_empty() => {
+            write!(stdout, "\r\n").unwrap();
+            match shell.request_exit(shell.get_last_status()) {
+                Ok(()) => Exit,
+                Err(error) => {
+                    write!(stdout, "{}\r\n", error).unwrap();
+                    CommandStatus(shell.get_last_status())
+                }
+            }
+        }
+        // the shell ignores SIGINT, and in raw mode Ctrl-C arrives as a key, which abandons the line
+        Key::Ctrl('c') => {
+            write!(stdout, "^C\r\n").unwrap();
+            shell.set_last_status(status_from_code(128 + libc::SIGINT));
+            CommandStatus(shell.get_last_status())
+        }
         Key::Char('\n') => {
             write!(stdout, "\r\n").unwrap();
-            let rval = if let Some(new_status) = handle_command(&mut stdout, &mut cmd_input) {
+            if let Some(new_status) = handle_command(stdout, cmd_input, shell) {
                 CommandStatus(new_status)
             }
             else {
                 Exit
-            };
-
-            rval
+            }
         }
         _ => {
             cmd_input.insert(val);
-            cmd_input.render_line(&mut stdout, prompt_len).unwrap();
+            cmd_input.render_line(stdout, prompt_len).unwrap();
             Continue
         }
     }
 }
 
-fn main() {
+/// Report the jobs which have finished since the last prompt
+fn report_jobs(stdout: &mut RawTerminal<Stdout>, shell: &mut Shell) {
+    for (job, status) in shell.reap_jobs() {
+        write!(stdout, "{}\r\n", job.describe_finished(&status)).unwrap();
+    }
+}
+
+/// Print the prompt, and set up the right prompt for the line after it, returning how wide the
+/// prompt is
+fn show_prompt(stdout: &mut RawTerminal<Stdout>, cmd_input: &mut CmdInput, shell: &Shell) -> usize {
+    // a terminal which doesn't know its size may report it as 0
+    let (columns, _) = termion::terminal_size().ok().filter(|(columns, _)| *columns > 0).unwrap_or((80, 24));
+    cmd_input.set_right_prompt(get_right_prompt(shell), columns as usize);
+    print_prompt(shell, stdout).unwrap()
+}
+
+/// Read and run commands from the terminal until the shell exits, returning the last status
+fn run_interactive(shell: &mut Shell) -> ExitStatus {
     let mut stdout = stdout().into_raw_mode().unwrap();
-    let stdin = stdin();
     // write!(stdout, "{}", termion::clear::All).unwrap();
     write!(stdout, "\r\n{}Hello, world!\r\n", color::Fg(color::Red)).unwrap();
 
-    let config_str = include_str!("logger_config.yaml");
-    let config = serde_yaml::from_str(config_str).unwrap();
-    log4rs::init_raw_config(config).unwrap();
     let filesystem = OsFileSystem::new();
-
-    info!("hello world");
-
-    let mut cmd_input = CmdInput::new(filesystem);
-    let mut prompt_len: usize = print_prompt(&ExitStatus::from_raw(0), &mut stdout).unwrap();
+    let mut cmd_input = CmdInput::new(
+        filesystem,
+        shell.get_variables().clone(),
+        shell.get_aliases().clone(),
+        shell.get_intrinsics().clone(),
+        shell.get_directories().clone(),
+        shell.get_suggesters().clone(),
+    );
+    let mut prompt_len: usize = show_prompt(&mut stdout, &mut cmd_input, shell);
     stdout.flush().unwrap();
 
-    let _ = handle_key(&mut stdout, &mut cmd_input, prompt_len, Key::Char('\t'));
-    for c in stdin.keys() {
+    let _ = handle_key(&mut stdout, &mut cmd_input, shell, prompt_len, Key::Char('\t'));
+    let mut keys = KeyReader::new();
+    loop {
+        // while completions are running in the background, keep the line updated between keys
+        if cmd_input.is_completion_pending() && !keys.has_pending() && !wait_for_input(COMPLETION_POLL_INTERVAL_MS)
+        {
+            cmd_input.poll_completion();
+            cmd_input.render_line(&mut stdout, prompt_len).unwrap();
+            stdout.flush().unwrap();
+            continue;
+        }
+
+        let c = match keys.next() {
+            Some(c) => c,
+            None => break,
+        };
+        // the terminal has gone away, so the shell leaves without waiting for input
+        if is_hung_up() {
+            shell.set_last_status(status_from_code(128 + libc::SIGHUP));
+            break;
+        }
+        // the traps for signals which arrived while waiting for a key run before it's handled
+        if has_pending_signals() {
+            stdout.suspend_raw_mode().unwrap();
+            let result = shell.run_signal_traps();
+            stdout.activate_raw_mode().unwrap();
+            if let Err(ControlFlow::Exit(status)) = result {
+                shell.set_last_status(status);
+                break;
+            }
+        }
         if let Ok(val) = c {
-            match handle_key(&mut stdout, &mut cmd_input, prompt_len, val) {
+            match handle_key(&mut stdout, &mut cmd_input, shell, prompt_len, val) {
                 Continue => {}
-                CommandStatus(new_status) => {
+                CommandStatus(_) => {
                     cmd_input.clear();
-                    prompt_len = print_prompt(&new_status, &mut stdout).unwrap();
+                    report_jobs(&mut stdout, shell);
+                    prompt_len = show_prompt(&mut stdout, &mut cmd_input, shell);
+                }
+                Exit => {
+                    write!(stdout, "exit\r\n").unwrap();
+                    break;
                 }
-                Exit => break,
             }
         }
 
         stdout.flush().unwrap();
     }
+
+    shell.get_last_status()
+}
+
+/// Run commands from `reader` without a terminal, returning the exit status of the shell
+fn run_non_interactive<R: BufRead>(shell: &mut Shell, reader: R, source_name: &str) -> ExitStatus {
+    match shell.run_source(reader, source_name) {
+        Ok(status) | Err(ControlFlow::Exit(status)) => status,
+        Err(_) => shell.get_last_status(),
+    }
+}
+
+fn main() {
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}: {}\n{}", SHELL_NAME, e, USAGE);
+            process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", USAGE);
+        return;
+    }
+    if args.version {
+        println!("{} {}", SHELL_NAME, env!("CARGO_PKG_VERSION"));
+        return;
+    }
+
+    let config_str = include_str!("logger_config.yaml");
+    let config = serde_yaml::from_str(config_str).unwrap();
+    log4rs::init_raw_config(config).unwrap();
+
+    info!("hello world");
+
+    let intrinsics = IntrinsicRegistry::with_defaults(OsFileSystem::new());
+    let mut shell = Shell::new(VariableStore::from_env(), AliasTable::new(), intrinsics);
+    if let Some(name) = &args.name {
+        shell.set_name(name);
+    }
+    shell.set_positional(args.positional);
+
+    // a login shell is one run with `-l`, or with a name starting with `-` (as `login` does)
+    let login = args.login || env::args().next().is_some_and(|name| name.starts_with('-'));
+    let interactive = args.mode == Mode::Interactive && termion::is_tty(&stdin());
+    shell.set_interactive(interactive);
+    if interactive {
+        if let Err(e) = shell.enable_job_control() {
+            eprintln!("{}: no job control: {}", SHELL_NAME, e);
+        }
+        catch_signal(libc::SIGHUP);
+    }
+    // only directories changed to interactively are worth jumping back to
+    if interactive && let Some(home) = shell.get_variable("HOME") {
+        shell.get_directories().open(&OsFileSystem::new(), &Path::new(&home).join(DIRECTORY_DB_FILE));
+    }
+    let startup_files = get_startup_files(login, interactive && !args.norc, shell.get_variable("HOME").as_deref());
+    if let Err(ControlFlow::Exit(status)) = shell.run_startup_files(&startup_files) {
+        let status = shell.run_exit_trap(status);
+        process::exit(status_code(&status));
+    }
+
+    let status = match args.mode {
+        Mode::Command(command) => run_non_interactive(&mut shell, command.as_bytes(), "-c"),
+        Mode::Script(path) => match File::open(&path) {
+            Ok(file) => run_non_interactive(&mut shell, BufReader::new(file), &path.to_string_lossy()),
+            Err(e) => {
+                eprintln!("{}: {}: {}", SHELL_NAME, path.display(), e);
+                status_from_code(127)
+            }
+        },
+        Mode::Interactive if interactive => run_interactive(&mut shell),
+        Mode::Interactive | Mode::Stdin => run_non_interactive(&mut shell, stdin().lock(), "stdin"),
+    };
+
+    let status = shell.run_exit_trap(status);
+    if interactive {
+        shell.hang_up();
+    }
+    process::exit(status_code(&status));
 }
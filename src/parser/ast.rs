@@ -0,0 +1,264 @@
+use std::fmt;
+use std::rc::Rc;
+
+use crate::parser::{Operator, Word};
+
+/// A complete line (or file) of shell input
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Program {
+    pub commands: CommandList,
+}
+
+/// A sequence of and-or lists separated by `;` or newlines, run one after the other
+pub type CommandList = Vec<AndOrList>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Connector {
+    /// `&&`, run the next command only if the previous one succeeded
+    And,
+    /// `||`, run the next command only if the previous one failed
+    Or,
+}
+
+/// Commands joined by `&&` and `||`, e.g. `make && make install || echo failed`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AndOrList {
+    pub first:      Command,
+    pub rest:       Vec<(Connector, Command)>,
+    /// whether the list ended with `&`, to run it in the background
+    pub background: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Simple(SimpleCommand),
+    /// `{ list; }`
+    BraceGroup(CommandList),
+    /// `( list )`, run without affecting the state of the shell
+    Subshell(CommandList),
+    /// `! command`, inverting the command's exit status
+    Not(Box<Command>),
+    /// `command | command ...`, run at the same time, each reading what the one before it writes
+    Pipeline(Vec<Command>),
+    /// a command whose streams are redirected, e.g. `make > log 2>&1`
+    Redirected(RedirectedCommand),
+    Time(TimeCommand),
+    If(IfCommand),
+    /// `while list; do list; done`
+    While(LoopCommand),
+    /// `until list; do list; done`
+    Until(LoopCommand),
+    For(ForCommand),
+    Case(CaseCommand),
+    FunctionDefinition(FunctionDefinition),
+    /// `py: statement`, or `py:` followed by an indented block, run by the embedded Python
+    /// interpreter
+    Python(String),
+}
+
+/// A `name=value` assignment preceding (or instead of) a command
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Assignment {
+    pub name:  String,
+    pub value: Word,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SimpleCommand {
+    pub assignments: Vec<Assignment>,
+    pub words:       Vec<Word>,
+}
+
+/// A redirection of one of a command's streams, e.g. `2>> log` or `>&2`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Redirection {
+    /// the number of the stream redirected
+    pub fd:       u32,
+    /// one of the redirection operators, which says how the target is opened
+    pub operator: Operator,
+    /// the file, or for `<&` and `>&` the number of the stream, to redirect to
+    pub target:   Word,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RedirectedCommand {
+    pub command:      Box<Command>,
+    /// applied in order, so that `> log 2>&1` sends both streams to `log`
+    pub redirections: Vec<Redirection>,
+}
+
+/// `time [-p] [command]`, reporting how long the command took and the CPU time it used
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimeCommand {
+    /// whether `-p` asked for the POSIX format of the report
+    pub posix:   bool,
+    /// the command to time, or `None` to report only the time taken to do nothing
+    pub command: Option<Box<Command>>,
+}
+
+/// `if list; then list; [elif list; then list;]... [else list;] fi`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IfCommand {
+    /// each condition, and the list to run if it succeeds
+    pub branches:    Vec<(CommandList, CommandList)>,
+    pub else_branch: Option<CommandList>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoopCommand {
+    pub condition: CommandList,
+    pub body:      CommandList,
+}
+
+/// `for name [in word...]; do list; done`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ForCommand {
+    pub variable: String,
+    /// the words to loop over, or `None` to loop over the positional parameters
+    pub words:    Option<Vec<Word>>,
+    pub body:     CommandList,
+}
+
+/// `case word in [(]pattern[|pattern]...) list;; ... esac`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CaseCommand {
+    pub word:  Word,
+    pub items: Vec<CaseItem>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CaseItem {
+    pub patterns: Vec<Word>,
+    pub body:     CommandList,
+}
+
+/// `name() compound-command` or `function name compound-command`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionDefinition {
+    pub name: String,
+    /// shared with the shell's function table, so that defining a function doesn't copy it
+    pub body: Rc<Command>,
+}
+
+impl fmt::Display for Redirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.fd != self.operator.default_fd() {
+            write!(f, "{}", self.fd)?;
+        }
+        write!(f, "{}{}", self.operator.as_str(), self.target)
+    }
+}
+
+/// Writes the commands of `list` on lines of their own
+fn write_list(f: &mut fmt::Formatter<'_>, list: &CommandList) -> fmt::Result {
+    list.iter().try_for_each(|and_or| writeln!(f, "{}", and_or))
+}
+
+/// Writes the list as it could be written in the shell, to be parsed back into the same list,
+/// such as for another shell to run it. Aliases have already been expanded.
+impl fmt::Display for AndOrList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.first)?;
+        for (connector, command) in &self.rest {
+            let connector = match connector {
+                Connector::And => "&&",
+                Connector::Or => "||",
+            };
+            write!(f, " {} {}", connector, command)?;
+        }
+        if self.background {
+            write!(f, " &")?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the command as it could be written in the shell, with each list inside it on lines of
+/// its own, as a Python snippet must end its line
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::Simple(simple) => {
+                let assignments = simple.assignments.iter().map(|a| format!("{}={}", a.name, a.value));
+                let words = simple.words.iter().map(|word| word.to_string());
+                write!(f, "{}", assignments.chain(words).collect::<Vec<_>>().join(" "))
+            }
+            Command::BraceGroup(list) => {
+                writeln!(f, "{{")?;
+                write_list(f, list)?;
+                write!(f, "}}")
+            }
+            Command::Subshell(list) => {
+                writeln!(f, "(")?;
+                write_list(f, list)?;
+                write!(f, ")")
+            }
+            Command::Not(command) => write!(f, "! {}", command),
+            Command::Pipeline(commands) => {
+                let commands: Vec<String> = commands.iter().map(|command| command.to_string()).collect();
+                write!(f, "{}", commands.join(" | "))
+            }
+            Command::Redirected(redirected) => {
+                write!(f, "{}", redirected.command)?;
+                redirected.redirections.iter().try_for_each(|redirection| write!(f, " {}", redirection))
+            }
+            Command::Time(time) => {
+                write!(f, "time")?;
+                if time.posix {
+                    write!(f, " -p")?;
+                }
+                match &time.command {
+                    Some(command) => write!(f, " {}", command),
+                    None => Ok(()),
+                }
+            }
+            Command::If(command) => {
+                for (i, (condition, body)) in command.branches.iter().enumerate() {
+                    writeln!(f, "{}", if i == 0 { "if" } else { "elif" })?;
+                    write_list(f, condition)?;
+                    writeln!(f, "then")?;
+                    write_list(f, body)?;
+                }
+                if let Some(else_branch) = &command.else_branch {
+                    writeln!(f, "else")?;
+                    write_list(f, else_branch)?;
+                }
+                write!(f, "fi")
+            }
+            Command::While(command) | Command::Until(command) => {
+                writeln!(f, "{}", if let Command::While(_) = self { "while" } else { "until" })?;
+                write_list(f, &command.condition)?;
+                writeln!(f, "do")?;
+                write_list(f, &command.body)?;
+                write!(f, "done")
+            }
+            Command::For(command) => {
+                write!(f, "for {}", command.variable)?;
+                if let Some(words) = &command.words {
+                    write!(f, " in")?;
+                    words.iter().try_for_each(|word| write!(f, " {}", word))?;
+                }
+                writeln!(f, "\ndo")?;
+                write_list(f, &command.body)?;
+                write!(f, "done")
+            }
+            Command::Case(command) => {
+                writeln!(f, "case {} in", command.word)?;
+                for item in &command.items {
+                    // the `(` keeps a pattern of `esac` from ending the command
+                    let patterns: Vec<String> = item.patterns.iter().map(|pattern| pattern.to_string()).collect();
+                    writeln!(f, "({})", patterns.join("|"))?;
+                    write_list(f, &item.body)?;
+                    writeln!(f, ";;")?;
+                }
+                write!(f, "esac")
+            }
+            Command::FunctionDefinition(definition) => write!(f, "{}() {}", definition.name, definition.body),
+            Command::Python(code) if !code.contains('\n') => write!(f, "py: {}", code),
+            Command::Python(code) => {
+                write!(f, "py:")?;
+                code.lines().try_for_each(|line| write!(f, "\n    {}", line))
+            }
+        }
+    }
+}
@@ -0,0 +1,554 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::parser::ParseError;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WordPart {
+    /// unquoted text, which is subject to tilde expansion
+    Literal(String),
+    /// text which was single-quoted or escaped, and is used verbatim
+    Quoted(String),
+    /// the contents of a double-quoted string, made up of `Quoted` text and `Parameter`s
+    DoubleQuoted(Vec<WordPart>),
+    /// a parameter expansion such as `$name`, `${name}`, `$1` or `$@`
+    Parameter(String),
+}
+
+/// A shell word, made up of the differently quoted parts which were written next to each other
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Word {
+    pub parts: Vec<WordPart>,
+}
+
+impl Word {
+    pub fn literal(text: &str) -> Word {
+        Word {
+            parts: vec![WordPart::Literal(text.to_string())],
+        }
+    }
+
+    /// Returns the text of the word if it is made up of only unquoted literal text, as is
+    /// required for reserved words, alias names and function names
+    pub fn as_literal(&self) -> Option<&str> {
+        match self.parts.as_slice() {
+            [WordPart::Literal(text)] => Some(text),
+            _ => None,
+        }
+    }
+
+    fn push_literal(&mut self, c: char) {
+        match self.parts.last_mut() {
+            Some(WordPart::Literal(text)) => text.push(c),
+            _ => self.parts.push(WordPart::Literal(c.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for WordPart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WordPart::Literal(text) => write!(f, "{}", text),
+            WordPart::Quoted(text) => write!(f, "'{}'", text.replace('\'', "'\\''")),
+            WordPart::DoubleQuoted(parts) => {
+                write!(f, "\"")?;
+                for part in parts {
+                    match part {
+                        WordPart::Quoted(text) => {
+                            for c in text.chars() {
+                                if matches!(c, '\\' | '"' | '$' | '`') {
+                                    write!(f, "\\")?;
+                                }
+                                write!(f, "{}", c)?;
+                            }
+                        }
+                        part => write!(f, "{}", part)?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            WordPart::Parameter(name) => write!(f, "${{{}}}", name),
+        }
+    }
+}
+
+/// Writes the word as it could be written in the shell, although not necessarily as it was
+impl fmt::Display for Word {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.parts.iter().try_for_each(|part| write!(f, "{}", part))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    /// `;`
+    Semicolon,
+    /// `;;`, ending an item of a `case`
+    DoubleSemicolon,
+    /// `&`
+    Ampersand,
+    /// `|`
+    Pipe,
+    /// `&&`
+    And,
+    /// `||`
+    Or,
+    /// `(`
+    LeftParen,
+    /// `)`
+    RightParen,
+    /// `<`, reading a file
+    Less,
+    /// `>`, writing a file, replacing what it held
+    Great,
+    /// `>>`, appending to a file
+    DoubleGreat,
+    /// `<&`, reading from another of the command's streams
+    LessAnd,
+    /// `>&`, writing to another of the command's streams
+    GreatAnd,
+}
+
+impl Operator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operator::Semicolon => ";",
+            Operator::DoubleSemicolon => ";;",
+            Operator::Ampersand => "&",
+            Operator::Pipe => "|",
+            Operator::And => "&&",
+            Operator::Or => "||",
+            Operator::LeftParen => "(",
+            Operator::RightParen => ")",
+            Operator::Less => "<",
+            Operator::Great => ">",
+            Operator::DoubleGreat => ">>",
+            Operator::LessAnd => "<&",
+            Operator::GreatAnd => ">&",
+        }
+    }
+
+    /// Returns true if the operator redirects one of a command's streams
+    pub fn is_redirection(&self) -> bool {
+        matches!(
+            self,
+            Operator::Less | Operator::Great | Operator::DoubleGreat | Operator::LessAnd | Operator::GreatAnd
+        )
+    }
+
+    /// Returns the stream a redirection applies to when it isn't given a number: stdin for `<` and
+    /// `<&`, and stdout otherwise
+    pub fn default_fd(&self) -> u32 {
+        match self {
+            Operator::Less | Operator::LessAnd => 0,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    Word(Word),
+    Operator(Operator),
+    /// the number of the stream a redirection applies to, written right before it as in `2>`
+    IoNumber(u32),
+    Newline,
+    /// a Python snippet given with `py:`, dedented if it was a block
+    Python(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub kind:   TokenKind,
+    /// the 1-indexed line the token starts on
+    pub line:   usize,
+    /// the 1-indexed column the token starts at
+    pub column: usize,
+    /// the aliases whose expansion produced this token, which may not be expanded again
+    pub(crate) alias_origin: Vec<String>,
+}
+
+fn is_word_end(c: char) -> bool {
+    c.is_whitespace() || matches!(c, ';' | '&' | '|' | '(' | ')' | '<' | '>')
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// The marker which starts a Python snippet in place of a command
+const PYTHON_MARKER: &str = "py:";
+/// Reserved words which are followed by a command
+const COMMAND_PREFIXES: [&str; 10] = ["{", "!", "time", "if", "then", "elif", "else", "while", "until", "do"];
+
+/// Remove the leading whitespace common to all of the non-blank `lines`
+fn dedent(lines: &[String]) -> String {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or(""))
+        .intersperse("\n")
+        .collect()
+}
+
+/// Splits shell input into words and operators, resolving quoting as it goes
+pub struct Lexer<'a> {
+    chars:         Peekable<Chars<'a>>,
+    line:          usize,
+    column:        usize,
+    /// whether the next word would be the start of a command, where `py:` may appear
+    command_start: bool,
+    /// the number of blanks at the start of the current line, so far
+    line_indent:   usize,
+    /// whether only blanks have been read on the current line
+    in_indent:     bool,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Lexer {
+            chars:         input.chars().peekable(),
+            line:          1,
+            column:        1,
+            command_start: true,
+            line_indent:   0,
+            in_indent:     true,
+        }
+    }
+
+    pub fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+        let mut lexer = Lexer::new(input);
+        let mut tokens = vec![];
+        while let Some(token) = lexer.next_token()? {
+            tokens.push(token);
+        }
+
+        Ok(tokens)
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+            self.line_indent = 0;
+            self.in_indent = true;
+        }
+        else {
+            self.column += 1;
+            if self.in_indent && (c == ' ' || c == '\t') {
+                self.line_indent += 1;
+            }
+            else {
+                self.in_indent = false;
+            }
+        }
+        Some(c)
+    }
+
+    fn error(&self, message: &str, line: usize, column: usize, incomplete: bool) -> ParseError {
+        ParseError {
+            message: message.to_string(),
+            line,
+            column,
+            incomplete,
+        }
+    }
+
+    pub fn next_token(&mut self) -> Result<Option<Token>, ParseError> {
+        // skip blanks, comments and escaped newlines
+        loop {
+            match self.chars.peek() {
+                Some(' ' | '\t' | '\r') => {
+                    self.next_char();
+                }
+                Some('#') => {
+                    while self.chars.peek().is_some_and(|c| *c != '\n') {
+                        self.next_char();
+                    }
+                }
+                Some('\\') => {
+                    let mut lookahead = self.chars.clone();
+                    lookahead.next();
+                    if lookahead.peek() != Some(&'\n') {
+                        break;
+                    }
+                    self.next_char();
+                    self.next_char();
+                }
+                _ => break,
+            }
+        }
+
+        let (line, column) = (self.line, self.column);
+        let kind = match self.chars.peek().copied() {
+            None => return Ok(None),
+            Some('\n') => {
+                self.next_char();
+                TokenKind::Newline
+            }
+            Some(';' | '&' | '|' | '(' | ')' | '<' | '>') => TokenKind::Operator(self.read_operator()),
+            Some(_) if self.command_start && self.at_python_marker() => TokenKind::Python(self.read_python()?),
+            Some(_) => {
+                let word = self.read_word()?;
+                match self.as_io_number(&word) {
+                    Some(fd) => TokenKind::IoNumber(fd),
+                    None => TokenKind::Word(word),
+                }
+            }
+        };
+        self.command_start = match &kind {
+            TokenKind::Word(word) => {
+                self.command_start && word.as_literal().is_some_and(|w| COMMAND_PREFIXES.contains(&w))
+            }
+            // the word a redirection is followed by is its target, rather than a command
+            TokenKind::Operator(op) if op.is_redirection() => false,
+            TokenKind::IoNumber(_) => self.command_start,
+            TokenKind::Operator(_) | TokenKind::Newline => true,
+            TokenKind::Python(_) => false,
+        };
+
+        Ok(Some(Token {
+            kind,
+            line,
+            column,
+            alias_origin: vec![],
+        }))
+    }
+
+    /// Returns the number `word` is made up of if a redirection follows it directly, as in `2>`
+    fn as_io_number(&mut self, word: &Word) -> Option<u32> {
+        let text = word.as_literal()?;
+        if !matches!(self.chars.peek(), Some('<' | '>')) || !text.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        text.parse().ok()
+    }
+
+    /// Returns true if the input continues with `py:` followed by a blank or the end of the line
+    fn at_python_marker(&self) -> bool {
+        let mut lookahead = self.chars.clone();
+        PYTHON_MARKER.chars().all(|c| lookahead.next() == Some(c))
+            && lookahead.next().is_none_or(|c| c.is_whitespace())
+    }
+
+    /// Read the rest of the line after `py:` as a Python statement or, if the line is otherwise
+    /// empty, the lines indented further than it which follow as a Python block. A block is
+    /// incomplete until a line which isn't part of it follows.
+    fn read_python(&mut self) -> Result<String, ParseError> {
+        let (line, column, indent) = (self.line, self.column, self.line_indent);
+        for _ in 0..PYTHON_MARKER.len() {
+            self.next_char();
+        }
+        let mut statement = String::new();
+        while let Some(&c) = self.chars.peek() && c != '\n' {
+            statement.push(c);
+            self.next_char();
+        }
+        if !statement.trim().is_empty() {
+            return Ok(statement.trim().to_string());
+        }
+
+        let mut lines = vec![];
+        loop {
+            let mut lookahead = self.chars.clone();
+            if lookahead.next().is_none() {
+                break;
+            }
+            let next_line: String = lookahead.take_while(|c| *c != '\n').collect();
+            let is_last = self.chars.clone().nth(next_line.chars().count() + 1).is_none();
+            let next_indent = next_line.len() - next_line.trim_start_matches([' ', '\t']).len();
+            let in_block = next_indent > indent || next_line.trim().is_empty();
+            if !in_block {
+                break;
+            }
+            if is_last && next_line.trim().is_empty() {
+                return Err(self.error("unterminated Python block", line, column, true));
+            }
+
+            self.next_char();
+            for _ in next_line.chars() {
+                self.next_char();
+            }
+            lines.push(next_line);
+        }
+
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+        if lines.is_empty() {
+            return Err(self.error("expected an indented Python block after 'py:'", line, column, false));
+        }
+        Ok(dedent(&lines))
+    }
+
+    fn read_operator(&mut self) -> Operator {
+        let c = self.next_char().unwrap();
+        match (c, self.chars.peek()) {
+            ('&', Some('&')) => {
+                self.next_char();
+                Operator::And
+            }
+            ('|', Some('|')) => {
+                self.next_char();
+                Operator::Or
+            }
+            (';', Some(';')) => {
+                self.next_char();
+                Operator::DoubleSemicolon
+            }
+            ('>', Some('>')) => {
+                self.next_char();
+                Operator::DoubleGreat
+            }
+            ('<', Some('&')) => {
+                self.next_char();
+                Operator::LessAnd
+            }
+            ('>', Some('&')) => {
+                self.next_char();
+                Operator::GreatAnd
+            }
+            ('&', _) => Operator::Ampersand,
+            ('|', _) => Operator::Pipe,
+            ('(', _) => Operator::LeftParen,
+            (')', _) => Operator::RightParen,
+            ('<', _) => Operator::Less,
+            ('>', _) => Operator::Great,
+            _ => Operator::Semicolon,
+        }
+    }
+
+    fn read_word(&mut self) -> Result<Word, ParseError> {
+        let mut word = Word { parts: vec![] };
+        while let Some(&c) = self.chars.peek() {
+            if is_word_end(c) {
+                break;
+            }
+            match c {
+                '\'' => {
+                    let (line, column) = (self.line, self.column);
+                    self.next_char();
+                    let mut text = String::new();
+                    loop {
+                        match self.next_char() {
+                            Some('\'') => break,
+                            Some(c) => text.push(c),
+                            None => return Err(self.error("unterminated single quote", line, column, true)),
+                        }
+                    }
+                    word.parts.push(WordPart::Quoted(text));
+                }
+                '"' => word.parts.push(self.read_double_quoted()?),
+                '\\' => {
+                    self.next_char();
+                    match self.next_char() {
+                        Some(escaped) => word.parts.push(WordPart::Quoted(escaped.to_string())),
+                        None => word.push_literal('\\'),
+                    }
+                }
+                '$' => {
+                    let part = self.read_parameter()?;
+                    match part {
+                        WordPart::Literal(_) => word.push_literal('$'),
+                        _ => word.parts.push(part),
+                    }
+                }
+                _ => {
+                    self.next_char();
+                    word.push_literal(c);
+                }
+            }
+        }
+
+        Ok(word)
+    }
+
+    fn read_double_quoted(&mut self) -> Result<WordPart, ParseError> {
+        let (line, column) = (self.line, self.column);
+        self.next_char();
+        let mut parts = vec![];
+        let mut text = String::new();
+        loop {
+            match self.chars.peek() {
+                None => return Err(self.error("unterminated double quote", line, column, true)),
+                Some('"') => {
+                    self.next_char();
+                    break;
+                }
+                Some('\\') => {
+                    self.next_char();
+                    match self.next_char() {
+                        Some(c @ ('$' | '"' | '\\' | '`')) => text.push(c),
+                        Some('\n') => {}
+                        Some(c) => {
+                            text.push('\\');
+                            text.push(c);
+                        }
+                        None => return Err(self.error("unterminated double quote", line, column, true)),
+                    }
+                }
+                Some('$') => match self.read_parameter()? {
+                    WordPart::Literal(_) => text.push('$'),
+                    parameter => {
+                        if !text.is_empty() {
+                            parts.push(WordPart::Quoted(std::mem::take(&mut text)));
+                        }
+                        parts.push(parameter);
+                    }
+                },
+                Some(_) => text.push(self.next_char().unwrap()),
+            }
+        }
+        if !text.is_empty() || parts.is_empty() {
+            parts.push(WordPart::Quoted(text));
+        }
+
+        Ok(WordPart::DoubleQuoted(parts))
+    }
+
+    /// Read a parameter expansion starting at a `$`. A `$` which does not start an expansion is
+    /// returned as a `Literal`.
+    fn read_parameter(&mut self) -> Result<WordPart, ParseError> {
+        let (line, column) = (self.line, self.column);
+        self.next_char();
+        match self.chars.peek() {
+            Some('{') => {
+                self.next_char();
+                let mut name = String::new();
+                loop {
+                    match self.next_char() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(self.error("unterminated '${'", line, column, true)),
+                    }
+                }
+                let is_valid = name.chars().all(is_name_char)
+                    || (name.chars().count() == 1 && "@*#?$!-0".contains(name.as_str()));
+                if name.is_empty() || !is_valid {
+                    return Err(self.error(&format!("bad substitution '${{{}}}'", name), line, column, false));
+                }
+                Ok(WordPart::Parameter(name))
+            }
+            Some(&c) if c.is_ascii_digit() || "@*#?$!-".contains(c) => {
+                self.next_char();
+                Ok(WordPart::Parameter(c.to_string()))
+            }
+            Some(&c) if is_name_char(c) => {
+                let mut name = String::new();
+                while let Some(&c) = self.chars.peek() && is_name_char(c) {
+                    name.push(c);
+                    self.next_char();
+                }
+                Ok(WordPart::Parameter(name))
+            }
+            _ => Ok(WordPart::Literal("$".to_string())),
+        }
+    }
+}
@@ -0,0 +1,12 @@
+mod ast;
+mod lexer;
+mod parser;
+
+#[cfg(test)]
+mod test_lexer;
+#[cfg(test)]
+mod test_parser;
+
+pub use ast::*;
+pub use lexer::*;
+pub use parser::*;
@@ -0,0 +1,683 @@
+use std::fmt;
+use std::rc::Rc;
+
+use log::trace;
+
+use crate::intrinsics::AliasTable;
+use crate::parser::{
+    AndOrList, Assignment, CaseCommand, CaseItem, Command, CommandList, Connector, ForCommand, FunctionDefinition,
+    IfCommand, Lexer, LoopCommand, Operator, Program, RedirectedCommand, Redirection, SimpleCommand, TimeCommand,
+    Token, TokenKind, Word, WordPart,
+};
+
+/// Words which have a special meaning when they appear unquoted at the start of a command
+pub const RESERVED_WORDS: [&str; 18] = [
+    "{", "}", "!", "time", "function", "if", "then", "elif", "else", "fi", "while", "until", "do", "done", "for",
+    "in", "case", "esac",
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub message:    String,
+    /// the 1-indexed line of the offending input
+    pub line:       usize,
+    /// the 1-indexed column of the offending input
+    pub column:     usize,
+    /// true if the input ended early, so that more input could make it valid
+    pub incomplete: bool,
+}
+
+impl ParseError {
+    /// Returns the error followed by the offending line of `input`, with a caret under the column
+    /// the error refers to
+    pub fn display_with_source(&self, input: &str) -> String {
+        match input.lines().nth(self.line - 1) {
+            Some(line) => format!("{}\n  {}\n  {}^", self, line, " ".repeat(self.column - 1)),
+            None => self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "syntax error at line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// Returns true if `name` can be the name of a variable or function
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Returns the assignment `word` represents if it is of the form `name=value`
+fn as_assignment(word: &Word) -> Option<Assignment> {
+    let (name, value) = match word.parts.first() {
+        Some(WordPart::Literal(text)) => text.split_once('=')?,
+        _ => return None,
+    };
+    if !is_valid_name(name) {
+        return None;
+    }
+
+    let mut parts = vec![];
+    if !value.is_empty() {
+        parts.push(WordPart::Literal(value.to_string()));
+    }
+    parts.extend_from_slice(&word.parts[1..]);
+    Some(Assignment {
+        name:  name.to_string(),
+        value: Word { parts },
+    })
+}
+
+/// Returns `command` with `redirections` applied to it, if there are any
+fn redirect(command: Command, redirections: Vec<Redirection>) -> Command {
+    if redirections.is_empty() {
+        return command;
+    }
+    Command::Redirected(RedirectedCommand {
+        command: Box::new(command),
+        redirections,
+    })
+}
+
+/// A recursive descent parser which turns shell input into a [Program]. Aliases are expanded as
+/// the input is parsed, so that an alias may contain anything a command line could.
+pub struct Parser<'a> {
+    tokens:          Vec<Token>,
+    pos:             usize,
+    aliases:         Option<&'a AliasTable>,
+    /// the index of a token following an alias which ended in a blank, and so which should also
+    /// be checked for an alias
+    alias_check_pos: Option<usize>,
+    /// the line and column just past the end of the input
+    end:             (usize, usize),
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(input: &str) -> Result<Self, ParseError> {
+        let last_line = input.rsplit('\n').next().unwrap_or_default();
+        Ok(Parser {
+            tokens:          Lexer::tokenize(input)?,
+            pos:             0,
+            aliases:         None,
+            alias_check_pos: None,
+            end:             (input.matches('\n').count() + 1, last_line.chars().count() + 1),
+        })
+    }
+
+    pub fn with_aliases(mut self, aliases: &'a AliasTable) -> Self {
+        self.aliases = Some(aliases);
+        self
+    }
+
+    pub fn parse_program(&mut self) -> Result<Program, ParseError> {
+        let commands = self.parse_list(&[])?;
+        if let Some(token) = self.peek() {
+            return Err(self.unexpected(token, None));
+        }
+        trace!("Parsed program: {:?}", commands);
+
+        Ok(Program { commands })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_operator(&self) -> Option<Operator> {
+        match self.peek()?.kind {
+            TokenKind::Operator(op) => Some(op),
+            _ => None,
+        }
+    }
+
+    /// Returns the reserved word at the current position, if there is one
+    fn peek_reserved(&self) -> Option<&'static str> {
+        match &self.peek()?.kind {
+            TokenKind::Word(word) => {
+                let literal = word.as_literal()?;
+                RESERVED_WORDS.into_iter().find(|reserved| *reserved == literal)
+            }
+            _ => None,
+        }
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.peek().is_some_and(|t| t.kind == TokenKind::Newline) {
+            self.pos += 1;
+        }
+    }
+
+    fn unexpected(&self, token: &Token, expected: Option<&str>) -> ParseError {
+        let description = match &token.kind {
+            TokenKind::Word(word) => match word.as_literal() {
+                Some(literal) => format!("'{}'", literal),
+                None => "word".to_string(),
+            },
+            TokenKind::Operator(op) => format!("'{}'", op.as_str()),
+            TokenKind::IoNumber(fd) => format!("'{}'", fd),
+            TokenKind::Newline => "newline".to_string(),
+            TokenKind::Python(_) => "Python snippet".to_string(),
+        };
+        let message = match expected {
+            Some(expected) => format!("unexpected {}, expected {}", description, expected),
+            None => format!("unexpected {}", description),
+        };
+        ParseError {
+            message,
+            line:       token.line,
+            column:     token.column,
+            incomplete: false,
+        }
+    }
+
+    fn unexpected_end(&self, expected: &str) -> ParseError {
+        ParseError {
+            message:    format!("unexpected end of input, expected {}", expected),
+            line:       self.end.0,
+            column:     self.end.1,
+            incomplete: true,
+        }
+    }
+
+    /// Returns an error describing the current token, or the end of the input
+    fn unexpected_here(&self, expected: &str) -> ParseError {
+        match self.peek() {
+            Some(token) => self.unexpected(token, Some(expected)),
+            None => self.unexpected_end(expected),
+        }
+    }
+
+    /// Replace the word at the current position with its alias, returning true if it was replaced
+    fn expand_alias(&mut self) -> Result<bool, ParseError> {
+        let (aliases, token) = match (self.aliases, self.peek()) {
+            (Some(aliases), Some(token)) => (aliases, token),
+            _ => return Ok(false),
+        };
+        let name = match &token.kind {
+            TokenKind::Word(word) => match word.as_literal() {
+                Some(name) => name.to_string(),
+                None => return Ok(false),
+            },
+            _ => return Ok(false),
+        };
+        // an alias is not expanded again within its own expansion
+        if token.alias_origin.contains(&name) {
+            return Ok(false);
+        }
+        let value = match aliases.get(&name) {
+            Some(value) => value,
+            None => return Ok(false),
+        };
+        trace!("Expanding alias '{}' to '{}'", name, value);
+
+        let mut origin = token.alias_origin.clone();
+        origin.push(name);
+        let (line, column) = (token.line, token.column);
+        let mut replacement = Lexer::tokenize(&value).map_err(|e| ParseError {
+            message: format!("in alias '{}': {}", origin.last().unwrap(), e.message),
+            line,
+            column,
+            ..e
+        })?;
+        for token in &mut replacement {
+            token.line = line;
+            token.column = column;
+            token.alias_origin = origin.clone();
+        }
+
+        let count = replacement.len();
+        self.tokens.splice(self.pos..self.pos + 1, replacement);
+        if let Some(check_pos) = self.alias_check_pos && check_pos > self.pos {
+            self.alias_check_pos = Some(check_pos + count - 1);
+        }
+        if value.ends_with(char::is_whitespace) {
+            self.alias_check_pos = Some(self.pos + count);
+        }
+
+        Ok(true)
+    }
+
+    /// Parse and-or lists until the end of the input, one of the `terminators` reserved words, or
+    /// an operator which ends a list (`)` or `;;`)
+    fn parse_list(&mut self, terminators: &[&str]) -> Result<CommandList, ParseError> {
+        let mut list = vec![];
+        loop {
+            self.skip_newlines();
+            let at_end = match self.peek_operator() {
+                Some(Operator::RightParen | Operator::DoubleSemicolon) => true,
+                _ => self.peek().is_none() || self.peek_reserved().is_some_and(|r| terminators.contains(&r)),
+            };
+            if at_end {
+                break;
+            }
+            let mut and_or = self.parse_and_or()?;
+
+            let separated = match self.peek().map(|t| &t.kind) {
+                Some(TokenKind::Operator(Operator::Semicolon) | TokenKind::Newline) => true,
+                Some(TokenKind::Operator(Operator::Ampersand)) => {
+                    and_or.background = true;
+                    true
+                }
+                _ => false,
+            };
+            list.push(and_or);
+            if !separated {
+                break;
+            }
+            self.pos += 1;
+        }
+
+        Ok(list)
+    }
+
+    /// Parse a list inside a compound command, which must contain at least one command
+    fn parse_compound_list(&mut self, terminators: &[&str]) -> Result<CommandList, ParseError> {
+        let list = self.parse_list(terminators)?;
+        if list.is_empty() {
+            return Err(self.unexpected_here("a command"));
+        }
+
+        Ok(list)
+    }
+
+    /// Consume the reserved word `word`, or fail if it isn't next
+    fn expect_reserved(&mut self, word: &str) -> Result<(), ParseError> {
+        if self.peek_reserved() != Some(word) {
+            return Err(self.unexpected_here(&format!("'{}'", word)));
+        }
+        self.pos += 1;
+
+        Ok(())
+    }
+
+    fn expect_operator(&mut self, op: Operator) -> Result<(), ParseError> {
+        if self.peek_operator() != Some(op) {
+            return Err(self.unexpected_here(&format!("'{}'", op.as_str())));
+        }
+        self.pos += 1;
+
+        Ok(())
+    }
+
+    fn parse_and_or(&mut self) -> Result<AndOrList, ParseError> {
+        let first = self.parse_pipeline()?;
+        let mut rest = vec![];
+        loop {
+            let connector = match self.peek_operator() {
+                Some(Operator::And) => Connector::And,
+                Some(Operator::Or) => Connector::Or,
+                _ => break,
+            };
+            self.pos += 1;
+            self.skip_newlines();
+            rest.push((connector, self.parse_pipeline()?));
+        }
+
+        Ok(AndOrList {
+            first,
+            rest,
+            background: false,
+        })
+    }
+
+    /// Parse `[!] command [| command]...`, where `!` applies to the whole pipeline
+    fn parse_pipeline(&mut self) -> Result<Command, ParseError> {
+        match self.peek_reserved() {
+            Some("!") => {
+                self.pos += 1;
+                return Ok(Command::Not(Box::new(self.parse_pipeline()?)));
+            }
+            Some("time") => return self.parse_time(),
+            _ => {}
+        }
+        let mut commands = vec![self.parse_command()?];
+        while self.peek_operator() == Some(Operator::Pipe) {
+            self.pos += 1;
+            self.skip_newlines();
+            commands.push(self.parse_command()?);
+        }
+
+        match commands.len() {
+            1 => Ok(commands.pop().unwrap()),
+            _ => Ok(Command::Pipeline(commands)),
+        }
+    }
+
+    fn parse_command(&mut self) -> Result<Command, ParseError> {
+        let mut expanded_alias = false;
+        loop {
+            match self.peek_reserved() {
+                Some("!") => {
+                    self.pos += 1;
+                    return Ok(Command::Not(Box::new(self.parse_command()?)));
+                }
+                Some("time") => return self.parse_time(),
+                Some("function") => return self.parse_function_keyword(),
+                Some(_) => return self.parse_compound_command(),
+                None => {}
+            }
+            if !self.expand_alias()? {
+                break;
+            }
+            expanded_alias = true;
+        }
+
+        match self.peek().map(|t| &t.kind) {
+            Some(TokenKind::Word(word)) => {
+                let is_function = self.tokens.get(self.pos + 1).map(|t| &t.kind)
+                    == Some(&TokenKind::Operator(Operator::LeftParen));
+                if is_function && let Some(name) = word.as_literal() {
+                    let name = name.to_string();
+                    self.pos += 1;
+                    return self.parse_function_body(name);
+                }
+                self.parse_simple_command()
+            }
+            Some(TokenKind::IoNumber(_)) => self.parse_simple_command(),
+            Some(TokenKind::Operator(op)) if op.is_redirection() => self.parse_simple_command(),
+            Some(TokenKind::Operator(Operator::LeftParen)) => self.parse_compound_command(),
+            Some(TokenKind::Python(code)) => {
+                let code = code.clone();
+                self.pos += 1;
+                Ok(Command::Python(code))
+            }
+            // an alias which expanded to nothing leaves an empty command
+            _ if expanded_alias => Ok(Command::Simple(SimpleCommand {
+                assignments: vec![],
+                words:       vec![],
+            })),
+            _ => Err(self.unexpected_here("a command")),
+        }
+    }
+
+    /// Parse `time [-p] [command]`, where the command may be left out
+    fn parse_time(&mut self) -> Result<Command, ParseError> {
+        self.pos += 1;
+        let posix = match self.peek().map(|t| &t.kind) {
+            Some(TokenKind::Word(word)) => word.as_literal() == Some("-p"),
+            _ => false,
+        };
+        if posix {
+            self.pos += 1;
+        }
+        let command = match self.peek().map(|t| &t.kind) {
+            None | Some(TokenKind::Newline) => None,
+            Some(TokenKind::Operator(op)) if *op != Operator::LeftParen && !op.is_redirection() => None,
+            // the whole pipeline is timed, not only its first command
+            _ => Some(Box::new(self.parse_pipeline()?)),
+        };
+        Ok(Command::Time(TimeCommand { posix, command }))
+    }
+
+    /// Parse the redirection at the current position, if there is one
+    fn parse_redirection(&mut self) -> Result<Option<Redirection>, ParseError> {
+        let fd = match self.peek().map(|t| &t.kind) {
+            Some(TokenKind::IoNumber(fd)) => Some(*fd),
+            _ => None,
+        };
+        let operator = match self.tokens.get(self.pos + fd.is_some() as usize).map(|t| &t.kind) {
+            Some(TokenKind::Operator(op)) if op.is_redirection() => *op,
+            _ => return Ok(None),
+        };
+        self.pos += 1 + fd.is_some() as usize;
+        let target = match self.peek().map(|t| &t.kind) {
+            Some(TokenKind::Word(word)) => word.clone(),
+            _ => return Err(self.unexpected_here("a file to redirect to")),
+        };
+        self.pos += 1;
+
+        Ok(Some(Redirection {
+            fd: fd.unwrap_or(operator.default_fd()),
+            operator,
+            target,
+        }))
+    }
+
+    /// Parse the redirections following a compound command, returning the command with them
+    fn parse_redirections(&mut self, command: Command) -> Result<Command, ParseError> {
+        let mut redirections = vec![];
+        while let Some(redirection) = self.parse_redirection()? {
+            redirections.push(redirection);
+        }
+        Ok(redirect(command, redirections))
+    }
+
+    fn parse_simple_command(&mut self) -> Result<Command, ParseError> {
+        let mut assignments = vec![];
+        let mut words = vec![];
+        let mut redirections = vec![];
+        loop {
+            let check_alias = words.is_empty() || self.alias_check_pos == Some(self.pos);
+            if check_alias {
+                if self.alias_check_pos == Some(self.pos) {
+                    self.alias_check_pos = None;
+                }
+                while self.expand_alias()? {}
+            }
+
+            if let Some(redirection) = self.parse_redirection()? {
+                redirections.push(redirection);
+                continue;
+            }
+            let word = match self.peek().map(|t| &t.kind) {
+                Some(TokenKind::Word(word)) => word,
+                _ => break,
+            };
+            if words.is_empty() && let Some(assignment) = as_assignment(word) {
+                assignments.push(assignment);
+            }
+            else {
+                words.push(word.clone());
+            }
+            self.pos += 1;
+        }
+
+        Ok(redirect(Command::Simple(SimpleCommand { assignments, words }), redirections))
+    }
+
+    /// Parse `function name [()] compound-command`
+    fn parse_function_keyword(&mut self) -> Result<Command, ParseError> {
+        self.pos += 1;
+        let name = match self.peek().map(|t| &t.kind) {
+            Some(TokenKind::Word(word)) if word.as_literal().is_some() => word.as_literal().unwrap().to_string(),
+            _ => return Err(self.unexpected_here("a function name")),
+        };
+        self.pos += 1;
+        if self.peek_operator() == Some(Operator::LeftParen) {
+            return self.parse_function_body(name);
+        }
+
+        self.skip_newlines();
+        let body = self.parse_compound_command()?;
+        Ok(Command::FunctionDefinition(FunctionDefinition {
+            name,
+            body: Rc::new(body),
+        }))
+    }
+
+    /// Parse the `() compound-command` following a function's name
+    fn parse_function_body(&mut self, name: String) -> Result<Command, ParseError> {
+        if RESERVED_WORDS.contains(&name.as_str()) {
+            let token = &self.tokens[self.pos - 1];
+            return Err(self.unexpected(token, Some("a function name")));
+        }
+        self.pos += 1;
+        self.expect_operator(Operator::RightParen)?;
+        self.skip_newlines();
+
+        let body = self.parse_compound_command()?;
+        Ok(Command::FunctionDefinition(FunctionDefinition {
+            name,
+            body: Rc::new(body),
+        }))
+    }
+
+    /// Parse a compound command, and any redirections following it
+    fn parse_compound_command(&mut self) -> Result<Command, ParseError> {
+        let command = self.parse_bare_compound_command()?;
+        self.parse_redirections(command)
+    }
+
+    fn parse_bare_compound_command(&mut self) -> Result<Command, ParseError> {
+        if self.peek_operator() == Some(Operator::LeftParen) {
+            self.pos += 1;
+            let list = self.parse_compound_list(&[])?;
+            self.expect_operator(Operator::RightParen)?;
+            return Ok(Command::Subshell(list));
+        }
+
+        let reserved = match self.peek_reserved() {
+            Some(reserved) => reserved,
+            None => return Err(self.unexpected_here("a compound command")),
+        };
+        match reserved {
+            "{" => {
+                self.pos += 1;
+                let list = self.parse_compound_list(&["}"])?;
+                self.expect_reserved("}")?;
+                Ok(Command::BraceGroup(list))
+            }
+            "if" => self.parse_if(),
+            "while" | "until" => {
+                self.pos += 1;
+                let condition = self.parse_compound_list(&["do"])?;
+                let body = self.parse_do_group()?;
+                let loop_command = LoopCommand { condition, body };
+                if reserved == "while" {
+                    Ok(Command::While(loop_command))
+                }
+                else {
+                    Ok(Command::Until(loop_command))
+                }
+            }
+            "for" => self.parse_for(),
+            "case" => self.parse_case(),
+            _ => Err(self.unexpected_here("a command")),
+        }
+    }
+
+    /// Parse `do list done`
+    fn parse_do_group(&mut self) -> Result<CommandList, ParseError> {
+        self.expect_reserved("do")?;
+        let body = self.parse_compound_list(&["done"])?;
+        self.expect_reserved("done")?;
+
+        Ok(body)
+    }
+
+    fn parse_if(&mut self) -> Result<Command, ParseError> {
+        self.pos += 1;
+        let mut branches = vec![];
+        loop {
+            let condition = self.parse_compound_list(&["then"])?;
+            self.expect_reserved("then")?;
+            let body = self.parse_compound_list(&["elif", "else", "fi"])?;
+            branches.push((condition, body));
+
+            if self.peek_reserved() != Some("elif") {
+                break;
+            }
+            self.pos += 1;
+        }
+
+        let else_branch = if self.peek_reserved() == Some("else") {
+            self.pos += 1;
+            Some(self.parse_compound_list(&["fi"])?)
+        }
+        else {
+            None
+        };
+        self.expect_reserved("fi")?;
+
+        Ok(Command::If(IfCommand { branches, else_branch }))
+    }
+
+    fn parse_for(&mut self) -> Result<Command, ParseError> {
+        self.pos += 1;
+        let variable = match self.peek().map(|t| &t.kind) {
+            Some(TokenKind::Word(word)) if word.as_literal().is_some_and(is_valid_name) => {
+                word.as_literal().unwrap().to_string()
+            }
+            _ => return Err(self.unexpected_here("a variable name")),
+        };
+        self.pos += 1;
+        self.skip_newlines();
+
+        let mut words = None;
+        if self.peek_reserved() == Some("in") {
+            self.pos += 1;
+            let mut in_words = vec![];
+            while let Some(TokenKind::Word(word)) = self.peek().map(|t| &t.kind) {
+                in_words.push(word.clone());
+                self.pos += 1;
+            }
+            words = Some(in_words);
+        }
+        match self.peek().map(|t| &t.kind) {
+            Some(TokenKind::Operator(Operator::Semicolon) | TokenKind::Newline) => self.pos += 1,
+            _ if words.is_none() => {}
+            _ => return Err(self.unexpected_here("';' or a newline")),
+        }
+        self.skip_newlines();
+        let body = self.parse_do_group()?;
+
+        Ok(Command::For(ForCommand { variable, words, body }))
+    }
+
+    fn parse_case(&mut self) -> Result<Command, ParseError> {
+        self.pos += 1;
+        let word = match self.peek().map(|t| &t.kind) {
+            Some(TokenKind::Word(word)) => word.clone(),
+            _ => return Err(self.unexpected_here("a word")),
+        };
+        self.pos += 1;
+        self.skip_newlines();
+        self.expect_reserved("in")?;
+
+        let mut items = vec![];
+        loop {
+            self.skip_newlines();
+            if self.peek_reserved() == Some("esac") {
+                break;
+            }
+            if self.peek_operator() == Some(Operator::LeftParen) {
+                self.pos += 1;
+            }
+
+            let mut patterns = vec![];
+            loop {
+                match self.peek().map(|t| &t.kind) {
+                    Some(TokenKind::Word(pattern)) => patterns.push(pattern.clone()),
+                    _ => return Err(self.unexpected_here("a pattern")),
+                }
+                self.pos += 1;
+                if self.peek_operator() != Some(Operator::Pipe) {
+                    break;
+                }
+                self.pos += 1;
+            }
+            self.expect_operator(Operator::RightParen)?;
+
+            let body = self.parse_list(&["esac"])?;
+            items.push(CaseItem { patterns, body });
+            if self.peek_operator() != Some(Operator::DoubleSemicolon) {
+                break;
+            }
+            self.pos += 1;
+        }
+        self.skip_newlines();
+        self.expect_reserved("esac")?;
+
+        Ok(Command::Case(CaseCommand { word, items }))
+    }
+}
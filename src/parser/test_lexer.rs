@@ -0,0 +1,196 @@
+#[cfg(test)]
+mod lexer_tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::parser::{Lexer, Operator, TokenKind, Word, WordPart};
+
+    fn kinds(input: &str) -> Vec<TokenKind> {
+        Lexer::tokenize(input).unwrap().into_iter().map(|t| t.kind).collect()
+    }
+
+    fn word(parts: Vec<WordPart>) -> TokenKind {
+        TokenKind::Word(Word { parts })
+    }
+
+    fn literal(text: &str) -> TokenKind {
+        TokenKind::Word(Word::literal(text))
+    }
+
+    #[test]
+    fn test_words_and_operators() {
+        assert_eq!(
+            kinds("ls -l;echo a&&b || c\n"),
+            vec![
+                literal("ls"),
+                literal("-l"),
+                TokenKind::Operator(Operator::Semicolon),
+                literal("echo"),
+                literal("a"),
+                TokenKind::Operator(Operator::And),
+                literal("b"),
+                TokenKind::Operator(Operator::Or),
+                literal("c"),
+                TokenKind::Newline,
+            ]
+        );
+        assert_eq!(
+            kinds("f(){ x; }"),
+            vec![
+                literal("f"),
+                TokenKind::Operator(Operator::LeftParen),
+                TokenKind::Operator(Operator::RightParen),
+                literal("{"),
+                literal("x"),
+                TokenKind::Operator(Operator::Semicolon),
+                literal("}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redirections() {
+        assert_eq!(
+            kinds("a|b <in 2>>log >&2 x2>y 3<&0"),
+            vec![
+                literal("a"),
+                TokenKind::Operator(Operator::Pipe),
+                literal("b"),
+                TokenKind::Operator(Operator::Less),
+                literal("in"),
+                TokenKind::IoNumber(2),
+                TokenKind::Operator(Operator::DoubleGreat),
+                literal("log"),
+                TokenKind::Operator(Operator::GreatAnd),
+                literal("2"),
+                literal("x2"),
+                TokenKind::Operator(Operator::Great),
+                literal("y"),
+                TokenKind::IoNumber(3),
+                TokenKind::Operator(Operator::LessAnd),
+                literal("0"),
+            ]
+        );
+        // a number is only the stream of a redirection written right before it
+        assert_eq!(
+            kinds("echo 2 >x '2'>y"),
+            vec![
+                literal("echo"),
+                literal("2"),
+                TokenKind::Operator(Operator::Great),
+                literal("x"),
+                word(vec![WordPart::Quoted("2".to_string())]),
+                TokenKind::Operator(Operator::Great),
+                literal("y"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comments_and_continuations() {
+        assert_eq!(kinds("echo a # b c"), vec![literal("echo"), literal("a")]);
+        assert_eq!(kinds("echo a#b"), vec![literal("echo"), literal("a#b")]);
+        assert_eq!(kinds("echo \\\n  a"), vec![literal("echo"), literal("a")]);
+    }
+
+    #[test]
+    fn test_quoting() {
+        assert_eq!(
+            kinds(r#"a'b c'"d $x"\ e"#),
+            vec![word(vec![
+                WordPart::Literal("a".to_string()),
+                WordPart::Quoted("b c".to_string()),
+                WordPart::DoubleQuoted(vec![
+                    WordPart::Quoted("d ".to_string()),
+                    WordPart::Parameter("x".to_string()),
+                ]),
+                WordPart::Quoted(" ".to_string()),
+                WordPart::Literal("e".to_string()),
+            ])]
+        );
+        assert_eq!(
+            kinds(r#""a\"b\n" '$x'"#),
+            vec![
+                word(vec![WordPart::DoubleQuoted(vec![WordPart::Quoted("a\"b\\n".to_string())])]),
+                word(vec![WordPart::Quoted("$x".to_string())]),
+            ]
+        );
+        assert_eq!(
+            kinds(r#""""#),
+            vec![word(vec![WordPart::DoubleQuoted(vec![WordPart::Quoted("".to_string())])])]
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let words: Vec<String> = Lexer::tokenize(r#"a'b c'"d $x \"y\"" $1 'it'\''s'"#)
+            .unwrap()
+            .into_iter()
+            .map(|token| match token.kind {
+                TokenKind::Word(word) => word.to_string(),
+                kind => panic!("not a word: {:?}", kind),
+            })
+            .collect();
+        // each part is quoted separately, so words may not be written as they were
+        assert_eq!(words, vec![r#"a'b c'"d ${x} \"y\"""#, "${1}", r#"'it'''\''''s'"#]);
+    }
+
+    #[test]
+    fn test_parameters() {
+        assert_eq!(
+            kinds("$HOME/x ${a}b $1$# $"),
+            vec![
+                word(vec![WordPart::Parameter("HOME".to_string()), WordPart::Literal("/x".to_string())]),
+                word(vec![WordPart::Parameter("a".to_string()), WordPart::Literal("b".to_string())]),
+                word(vec![WordPart::Parameter("1".to_string()), WordPart::Parameter("#".to_string())]),
+                literal("$"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = Lexer::tokenize("echo 'abc").unwrap_err();
+        assert_eq!((err.line, err.column, err.incomplete), (1, 6, true));
+
+        let err = Lexer::tokenize("echo\n  \"abc").unwrap_err();
+        assert_eq!((err.line, err.column, err.incomplete), (2, 3, true));
+
+        let err = Lexer::tokenize("echo ${a b}").unwrap_err();
+        assert_eq!((err.line, err.column, err.incomplete), (1, 6, false));
+    }
+
+    #[test]
+    fn test_python() {
+        let python = |code: &str| TokenKind::Python(code.to_string());
+        assert_eq!(kinds("py: print('$x' \"it's\"); y = {1: 2} # c"), vec![python(
+            "print('$x' \"it's\"); y = {1: 2} # c"
+        )]);
+        assert_eq!(kinds("a; py: x\n"), vec![
+            literal("a"),
+            TokenKind::Operator(Operator::Semicolon),
+            python("x"),
+            TokenKind::Newline,
+        ]);
+        // only at the start of a command
+        assert_eq!(kinds("echo py: x"), vec![literal("echo"), literal("py:"), literal("x")]);
+        assert_eq!(kinds("if py: x"), vec![literal("if"), python("x")]);
+        assert_eq!(kinds("py:x"), vec![literal("py:x")]);
+
+        // a block is made of the lines indented further than the `py:` line
+        let input = "py:\n    for i in range(2):\n\n        print(i)\necho";
+        assert_eq!(kinds(input), vec![
+            python("for i in range(2):\n\n    print(i)"),
+            TokenKind::Newline,
+            literal("echo"),
+        ]);
+        let input = "if a; then\n  py:\n    x = 1\n  fi";
+        assert_eq!(kinds(input)[5..], [python("x = 1"), TokenKind::Newline, literal("fi")]);
+        assert_eq!(kinds("py:\n  x = 1"), vec![python("x = 1")]);
+
+        // a block may continue on the lines after the end of the input
+        let err = Lexer::tokenize("py:\n  x = 1\n").unwrap_err();
+        assert_eq!((err.line, err.column, err.incomplete), (1, 1, true));
+        let err = Lexer::tokenize("py:\necho").unwrap_err();
+        assert_eq!((err.line, err.column, err.incomplete), (1, 1, false));
+    }
+}
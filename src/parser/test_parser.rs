@@ -0,0 +1,537 @@
+#[cfg(test)]
+mod parser_tests {
+    use std::rc::Rc;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::intrinsics::AliasTable;
+    use crate::parser::{
+        AndOrList, Assignment, CaseCommand, CaseItem, Command, Connector, ForCommand, FunctionDefinition,
+        IfCommand, LoopCommand, Operator, ParseError, Parser, Program, RedirectedCommand, Redirection,
+        SimpleCommand, TimeCommand, Word, WordPart,
+    };
+
+    fn parse(input: &str) -> Result<Program, ParseError> {
+        Parser::new(input)?.parse_program()
+    }
+
+    fn parse_with_aliases(input: &str, aliases: &AliasTable) -> Program {
+        Parser::new(input).unwrap().with_aliases(aliases).parse_program().unwrap()
+    }
+
+    fn simple(words: &[&str]) -> Command {
+        Command::Simple(SimpleCommand {
+            assignments: vec![],
+            words:       words.iter().map(|w| Word::literal(w)).collect(),
+        })
+    }
+
+    fn single(command: Command) -> AndOrList {
+        AndOrList {
+            first:      command,
+            rest:       vec![],
+            background: false,
+        }
+    }
+
+    /// Returns the words of each simple command in `program`, for checking alias expansion
+    fn words(program: &Program) -> Vec<Vec<String>> {
+        program
+            .commands
+            .iter()
+            .map(|list| match &list.first {
+                Command::Simple(simple) => simple
+                    .words
+                    .iter()
+                    .map(|w| w.as_literal().map_or("<quoted>".to_string(), |w| w.to_string()))
+                    .collect(),
+                _ => panic!("expected a simple command"),
+            })
+            .collect()
+    }
+
+    fn split(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_lists() {
+        let program = parse("a 1; b\n\nc && d || e;").unwrap();
+        assert_eq!(
+            program.commands,
+            vec![
+                single(simple(&["a", "1"])),
+                single(simple(&["b"])),
+                AndOrList {
+                    first:      simple(&["c"]),
+                    rest:       vec![(Connector::And, simple(&["d"])), (Connector::Or, simple(&["e"]))],
+                    background: false,
+                },
+            ]
+        );
+        assert!(parse("").unwrap().commands.is_empty());
+        assert!(parse("  # just a comment\n").unwrap().commands.is_empty());
+    }
+
+    #[test]
+    fn test_background() {
+        let program = parse("a & b && c& d").unwrap();
+        let background: Vec<bool> = program.commands.iter().map(|list| list.background).collect();
+        assert_eq!(background, vec![true, true, false]);
+        assert_eq!(program.commands[1].rest.len(), 1);
+
+        assert!(parse("a &\nb &").unwrap().commands.iter().all(|list| list.background));
+        assert!(parse("& a").is_err());
+        assert!(parse("a & && b").is_err());
+    }
+
+    #[test]
+    fn test_assignments() {
+        let program = parse("a=1 b= cmd c=2").unwrap();
+        assert_eq!(
+            program.commands,
+            vec![single(Command::Simple(SimpleCommand {
+                assignments: vec![
+                    Assignment {
+                        name:  "a".to_string(),
+                        value: Word::literal("1"),
+                    },
+                    Assignment {
+                        name:  "b".to_string(),
+                        value: Word { parts: vec![] },
+                    },
+                ],
+                words:       vec![Word::literal("cmd"), Word::literal("c=2")],
+            }))]
+        );
+        assert_eq!(words(&parse("1a=b").unwrap()), vec![vec!["1a=b"]]);
+    }
+
+    #[test]
+    fn test_function_definitions() {
+        let echo_param = Command::Simple(SimpleCommand {
+            assignments: vec![],
+            words:       vec![
+                Word::literal("echo"),
+                Word {
+                    parts: vec![WordPart::Parameter("1".to_string())],
+                },
+            ],
+        });
+        let expected = Command::FunctionDefinition(FunctionDefinition {
+            name: "greet".to_string(),
+            body: Rc::new(Command::BraceGroup(vec![single(simple(&["echo", "hi"])), single(echo_param)])),
+        });
+
+        for input in [
+            "greet() { echo hi; echo $1; }",
+            "greet ( ) {\n  echo hi\n  echo $1\n}",
+            "function greet { echo hi; echo $1; }",
+            "function greet() { echo hi; echo $1; }",
+        ] {
+            assert_eq!(parse(input).unwrap().commands, vec![single(expected.clone())], "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_brace_group() {
+        let program = parse("{ a; b; } && c").unwrap();
+        assert_eq!(
+            program.commands,
+            vec![AndOrList {
+                first:      Command::BraceGroup(vec![single(simple(&["a"])), single(simple(&["b"]))]),
+                rest:       vec![(Connector::And, simple(&["c"]))],
+                background: false,
+            }]
+        );
+        // `}` is only reserved at the start of a command
+        assert_eq!(words(&parse("echo } {").unwrap()), vec![vec!["echo", "}", "{"]]);
+    }
+
+    #[test]
+    fn test_if() {
+        let program = parse("if a; then b; elif c\nthen d; else e; fi").unwrap();
+        assert_eq!(
+            program.commands,
+            vec![single(Command::If(IfCommand {
+                branches:    vec![
+                    (vec![single(simple(&["a"]))], vec![single(simple(&["b"]))]),
+                    (vec![single(simple(&["c"]))], vec![single(simple(&["d"]))]),
+                ],
+                else_branch: Some(vec![single(simple(&["e"]))]),
+            }))]
+        );
+        // reserved words are only recognised at the start of a command
+        let program = parse("if echo then; then echo fi; fi").unwrap();
+        assert_eq!(
+            program.commands,
+            vec![single(Command::If(IfCommand {
+                branches:    vec![(
+                    vec![single(simple(&["echo", "then"]))],
+                    vec![single(simple(&["echo", "fi"]))],
+                )],
+                else_branch: None,
+            }))]
+        );
+    }
+
+    #[test]
+    fn test_loops() {
+        let loop_command = LoopCommand {
+            condition: vec![single(simple(&["a"]))],
+            body:      vec![single(simple(&["b"])), single(simple(&["c"]))],
+        };
+        assert_eq!(
+            parse("while a; do b; c; done").unwrap().commands,
+            vec![single(Command::While(loop_command.clone()))]
+        );
+        assert_eq!(
+            parse("until a\ndo\n  b\n  c\ndone").unwrap().commands,
+            vec![single(Command::Until(loop_command))]
+        );
+    }
+
+    #[test]
+    fn test_for() {
+        assert_eq!(
+            parse("for x in a b; do c; done").unwrap().commands,
+            vec![single(Command::For(ForCommand {
+                variable: "x".to_string(),
+                words:    Some(vec![Word::literal("a"), Word::literal("b")]),
+                body:     vec![single(simple(&["c"]))],
+            }))]
+        );
+        for input in ["for x; do c; done", "for x do c; done", "for x\ndo c; done"] {
+            assert_eq!(
+                parse(input).unwrap().commands,
+                vec![single(Command::For(ForCommand {
+                    variable: "x".to_string(),
+                    words:    None,
+                    body:     vec![single(simple(&["c"]))],
+                }))],
+                "{}",
+                input
+            );
+        }
+        assert!(parse("for x in; do c; done").is_ok());
+    }
+
+    #[test]
+    fn test_case() {
+        let program = parse("case $x in\n  a|b) echo ab;;\n  (*) ;;\n  c) echo c\nesac").unwrap();
+        assert_eq!(
+            program.commands,
+            vec![single(Command::Case(CaseCommand {
+                word:  Word {
+                    parts: vec![WordPart::Parameter("x".to_string())],
+                },
+                items: vec![
+                    CaseItem {
+                        patterns: vec![Word::literal("a"), Word::literal("b")],
+                        body:     vec![single(simple(&["echo", "ab"]))],
+                    },
+                    CaseItem {
+                        patterns: vec![Word::literal("*")],
+                        body:     vec![],
+                    },
+                    CaseItem {
+                        patterns: vec![Word::literal("c")],
+                        body:     vec![single(simple(&["echo", "c"]))],
+                    },
+                ],
+            }))]
+        );
+        assert!(parse("case x in esac").is_ok());
+    }
+
+    #[test]
+    fn test_subshell_and_not() {
+        assert_eq!(
+            parse("! (a; b) || c").unwrap().commands,
+            vec![AndOrList {
+                first:      Command::Not(Box::new(Command::Subshell(vec![
+                    single(simple(&["a"])),
+                    single(simple(&["b"])),
+                ]))),
+                rest:       vec![(Connector::Or, simple(&["c"]))],
+                background: false,
+            }]
+        );
+        assert!(matches!(
+            &parse("f() (a)").unwrap().commands[0].first,
+            Command::FunctionDefinition(FunctionDefinition { body, .. }) if matches!(**body, Command::Subshell(_))
+        ));
+    }
+
+    fn redirection(fd: u32, operator: Operator, target: &str) -> Redirection {
+        Redirection {
+            fd,
+            operator,
+            target: Word::literal(target),
+        }
+    }
+
+    fn redirected(command: Command, redirections: Vec<Redirection>) -> Command {
+        Command::Redirected(RedirectedCommand {
+            command: Box::new(command),
+            redirections,
+        })
+    }
+
+    #[test]
+    fn test_pipelines() {
+        assert_eq!(
+            parse("! a | b 1 |\n c && d").unwrap().commands,
+            vec![AndOrList {
+                first:      Command::Not(Box::new(Command::Pipeline(vec![
+                    simple(&["a"]),
+                    simple(&["b", "1"]),
+                    simple(&["c"]),
+                ]))),
+                rest:       vec![(Connector::And, simple(&["d"]))],
+                background: false,
+            }]
+        );
+        assert_eq!(
+            parse("{ a; } | (b)").unwrap().commands,
+            vec![single(Command::Pipeline(vec![
+                Command::BraceGroup(vec![single(simple(&["a"]))]),
+                Command::Subshell(vec![single(simple(&["b"]))]),
+            ]))]
+        );
+        assert!(parse("a |").unwrap_err().incomplete);
+        assert_eq!(parse("a | | b").unwrap_err().message, "unexpected '|', expected a command");
+    }
+
+    #[test]
+    fn test_redirections() {
+        assert_eq!(
+            parse("a >out x 2>>log <in; >empty; b 2>&1 3<&0").unwrap().commands,
+            vec![
+                single(redirected(
+                    simple(&["a", "x"]),
+                    vec![
+                        redirection(1, Operator::Great, "out"),
+                        redirection(2, Operator::DoubleGreat, "log"),
+                        redirection(0, Operator::Less, "in"),
+                    ]
+                )),
+                single(redirected(simple(&[]), vec![redirection(1, Operator::Great, "empty")])),
+                single(redirected(
+                    simple(&["b"]),
+                    vec![redirection(2, Operator::GreatAnd, "1"), redirection(3, Operator::LessAnd, "0")]
+                )),
+            ]
+        );
+        // the redirections after a compound command apply to all of it
+        assert_eq!(
+            parse("if a; then b; fi >out | c").unwrap().commands,
+            vec![single(Command::Pipeline(vec![
+                redirected(
+                    Command::If(IfCommand {
+                        branches:    vec![(vec![single(simple(&["a"]))], vec![single(simple(&["b"]))])],
+                        else_branch: None,
+                    }),
+                    vec![redirection(1, Operator::Great, "out")]
+                ),
+                simple(&["c"]),
+            ]))]
+        );
+
+        let err = parse("a > ;").unwrap_err();
+        assert_eq!(err.message, "unexpected ';', expected a file to redirect to");
+        assert_eq!(err.column, 5);
+        assert!(parse("a 2>").unwrap_err().incomplete);
+    }
+
+    #[test]
+    fn test_time() {
+        let time = |posix, command: Option<Command>| {
+            Command::Time(TimeCommand {
+                posix,
+                command: command.map(Box::new),
+            })
+        };
+        assert_eq!(
+            parse("time a b && time -p ! c").unwrap().commands,
+            vec![AndOrList {
+                first:      time(false, Some(simple(&["a", "b"]))),
+                rest:       vec![(Connector::And, time(true, Some(Command::Not(Box::new(simple(&["c"]))))))],
+                background: false,
+            }]
+        );
+        assert_eq!(
+            parse("time; time (a)").unwrap().commands,
+            vec![
+                single(time(false, None)),
+                single(time(false, Some(Command::Subshell(vec![single(simple(&["a"]))])))),
+            ]
+        );
+        // the whole pipeline is timed
+        assert_eq!(
+            parse("time -p a | ! b").unwrap().commands,
+            vec![single(time(true, Some(Command::Pipeline(vec![
+                simple(&["a"]),
+                Command::Not(Box::new(simple(&["b"]))),
+            ]))))]
+        );
+        assert_eq!(
+            parse("! time a | b").unwrap().commands,
+            vec![single(Command::Not(Box::new(time(
+                false,
+                Some(Command::Pipeline(vec![simple(&["a"]), simple(&["b"])]))
+            ))))]
+        );
+        // `time` is only reserved at the start of a command
+        assert_eq!(parse("echo time").unwrap().commands, vec![single(simple(&["echo", "time"]))]);
+    }
+
+    #[test]
+    fn test_python() {
+        assert_eq!(
+            parse("a && py: print(1)").unwrap().commands,
+            vec![AndOrList {
+                first:      simple(&["a"]),
+                rest:       vec![(Connector::And, Command::Python("print(1)".to_string()))],
+                background: false,
+            }]
+        );
+        // the rest of the line is Python, even if it looks like shell
+        assert_eq!(
+            parse("py: x; y").unwrap().commands,
+            vec![single(Command::Python("x; y".to_string()))]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = parse("echo a; ; b").unwrap_err();
+        assert_eq!(err.message, "unexpected ';', expected a command");
+        assert_eq!((err.line, err.column, err.incomplete), (1, 9, false));
+
+        let err = parse("f() { echo a }").unwrap_err();
+        assert_eq!(err.message, "unexpected end of input, expected '}'");
+        assert_eq!((err.line, err.column, err.incomplete), (1, 15, true));
+
+        let err = parse("a &&").unwrap_err();
+        assert!(err.incomplete);
+
+        let err = parse("f() echo").unwrap_err();
+        assert_eq!(err.message, "unexpected 'echo', expected a compound command");
+        assert_eq!(err.column, 5);
+
+        let err = parse("}").unwrap_err();
+        assert_eq!(err.message, "unexpected '}', expected a command");
+        assert_eq!(err.to_string(), "syntax error at line 1, column 1: unexpected '}', expected a command");
+
+        let err = parse("if true; then\n  echo a\nfi fi").unwrap_err();
+        assert_eq!(err.message, "unexpected 'fi'");
+        assert_eq!((err.line, err.column), (3, 4));
+
+        let err = parse("while true; echo; done").unwrap_err();
+        assert_eq!(err.message, "unexpected 'done', expected a command");
+        assert_eq!(err.column, 19);
+
+        let err = parse("if true; then").unwrap_err();
+        assert_eq!(err.message, "unexpected end of input, expected a command");
+        assert!(err.incomplete);
+
+        let err = parse("for 1x in a; do b; done").unwrap_err();
+        assert_eq!(err.message, "unexpected '1x', expected a variable name");
+        assert_eq!(err.column, 5);
+
+        let err = parse("case a in b) c;; d e) f;; esac").unwrap_err();
+        assert_eq!(err.message, "unexpected 'e', expected ')'");
+        assert_eq!(err.column, 20);
+    }
+
+    #[test]
+    fn test_display_with_source() {
+        let input = "echo a\n  ( echo b; ) )";
+        let err = parse(input).unwrap_err();
+        assert_eq!(
+            err.display_with_source(input),
+            "syntax error at line 2, column 15: unexpected ')'\n    ( echo b; ) )\n                ^"
+        );
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let inputs = [
+            "a=1 b=\"$x y\" echo 'it s' \\$HOME ~/x && ! false || time -p sleep 1 &",
+            "f() { local x; echo \"${1}\"; } ; g() ( cd /; pwd )",
+            "if a; then b; elif c; then d; else e; fi; while a; do b; done; until a; do b; done",
+            "for i in 1 2 do; do echo $i; done; for i do echo $i; done; for i in; do :; done",
+            "case $x in a|b) echo ab;; (esac) ;; *) echo other; esac",
+            "time; f && py: print(1)\nif a; then py:\n    for i in range(2):\n        print(i)\nfi",
+            "! a <in 2>&1 | { b; } >>out | c 2>'e r' 1>&2; >x; 3<&0 d | py: print(1)",
+        ];
+        for input in inputs {
+            let program = parse(input).unwrap();
+            let text: String = program.commands.iter().map(|list| format!("{}\n", list)).collect();
+            assert_eq!(parse(&text).unwrap(), program, "{} was written as:\n{}", input, text);
+        }
+        assert_eq!(
+            parse("if a; then b && c; fi &").unwrap().commands[0].to_string(),
+            "if\na\nthen\nb && c\nfi &"
+        );
+    }
+
+    #[test]
+    fn test_alias_first_word_only() {
+        let aliases = AliasTable::new();
+        aliases.set("ll", "ls -l");
+
+        assert_eq!(words(&parse_with_aliases("ll /tmp", &aliases)), vec![split("ls -l /tmp")]);
+        assert_eq!(words(&parse_with_aliases("echo ll", &aliases)), vec![split("echo ll")]);
+        assert_eq!(words(&parse_with_aliases("'ll'", &aliases)), vec![split("<quoted>")]);
+        assert_eq!(
+            words(&parse_with_aliases("ll; echo && ll", &aliases)),
+            vec![split("ls -l"), split("echo")]
+        );
+    }
+
+    #[test]
+    fn test_alias_recursive() {
+        let aliases = AliasTable::new();
+        aliases.set("ll", "l -l");
+        aliases.set("l", "ls --color");
+
+        assert_eq!(words(&parse_with_aliases("ll /tmp", &aliases)), vec![split("ls --color -l /tmp")]);
+    }
+
+    #[test]
+    fn test_alias_self_reference() {
+        let aliases = AliasTable::new();
+        aliases.set("ls", "ls --color");
+        aliases.set("a", "b");
+        aliases.set("b", "a x");
+
+        assert_eq!(words(&parse_with_aliases("ls /tmp", &aliases)), vec![split("ls --color /tmp")]);
+        assert_eq!(words(&parse_with_aliases("a", &aliases)), vec![split("a x")]);
+        assert_eq!(words(&parse_with_aliases("b", &aliases)), vec![split("b x")]);
+    }
+
+    #[test]
+    fn test_alias_trailing_blank() {
+        let aliases = AliasTable::new();
+        aliases.set("sudo", "sudo ");
+        aliases.set("ll", "ls -l");
+        aliases.set("nohang", "timeout 5");
+
+        assert_eq!(words(&parse_with_aliases("sudo ll /root", &aliases)), vec![split("sudo ls -l /root")]);
+        assert_eq!(words(&parse_with_aliases("sudo sudo ll", &aliases)), vec![split("sudo sudo ls -l")]);
+        assert_eq!(words(&parse_with_aliases("nohang ll", &aliases)), vec![split("timeout 5 ll")]);
+    }
+
+    #[test]
+    fn test_alias_containing_operators() {
+        let aliases = AliasTable::new();
+        aliases.set("greet", "echo 'hello there'; echo bye");
+        aliases.set("nothing", "");
+
+        let program = parse_with_aliases("nothing greet", &aliases);
+        assert_eq!(words(&program), vec![split("echo <quoted>"), split("echo bye")]);
+        assert_eq!(words(&parse_with_aliases("nothing", &aliases)), vec![Vec::<String>::new()]);
+    }
+}
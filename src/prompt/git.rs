@@ -0,0 +1,676 @@
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::os::unix::ffi::OsStringExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use lazy_static::lazy_static;
+use log::debug;
+
+use crate::prompt::git_ignore::IgnoreRules;
+use crate::prompt::git_index::{read_index, IndexEntry};
+use crate::prompt::git_objects::{oid_to_hex, parse_commit, parse_oid, parse_tree, ObjectDb, ObjectKind, Oid};
+
+/// How long working out the status of a repository may take, after which the prompt is shown with
+/// the counts found so far
+pub const GIT_STATUS_BUDGET: Duration = Duration::from_millis(100);
+
+/// How many symbolic refs may be followed to reach a commit
+const MAX_SYMBOLIC_REFS: usize = 5;
+
+const FILE_TYPE_MASK: u32 = 0o170000;
+const SYMLINK: u32 = 0o120000;
+/// The mode of a submodule, whose work tree is left to its own repository
+const GITLINK: u32 = 0o160000;
+
+/// Returns the last value of `key` in `[section "subsection"]` of a git config file
+fn get_config_value(config: &str, section: &str, subsection: Option<&str>, key: &str) -> Option<String> {
+    let mut in_section = false;
+    let mut value = None;
+    for line in config.lines().map(str::trim) {
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header.split(']').next().unwrap_or_default();
+            let (name, sub) = match header.split_once(' ') {
+                Some((name, sub)) => (name, Some(sub.trim().trim_matches('"'))),
+                None => (header, None),
+            };
+            in_section = name.eq_ignore_ascii_case(section) && sub == subsection;
+            continue;
+        }
+        // `key = value`, where the value may be quoted or followed by a comment
+        if in_section
+            && let Some((name, rest)) = line.split_once('=')
+            && name.trim().eq_ignore_ascii_case(key)
+        {
+            let rest = rest.trim();
+            let found = match rest.strip_prefix('"') {
+                Some(quoted) => quoted.split('"').next().unwrap_or_default(),
+                None => rest.split(['#', ';']).next().unwrap_or_default().trim(),
+            };
+            value = Some(found.to_string());
+        }
+    }
+    value
+}
+
+/// Where a repository's files are
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Repository {
+    pub work_tree:  PathBuf,
+    pub git_dir:    PathBuf,
+    /// where the objects and refs shared between work trees are, which is `git_dir` except in
+    /// linked work trees
+    pub common_dir: PathBuf,
+}
+
+impl Repository {
+    /// Find the repository `dir` is in, looking for a `.git` in it and then in each of its parents
+    pub fn discover(dir: &Path) -> Option<Self> {
+        for work_tree in dir.ancestors() {
+            let dot_git = work_tree.join(".git");
+            let git_dir = if dot_git.is_dir() {
+                dot_git
+            }
+            // linked work trees and submodules have a file of `gitdir: path` instead
+            else if dot_git.is_file() {
+                let contents = fs::read_to_string(&dot_git).ok()?;
+                work_tree.join(contents.strip_prefix("gitdir:")?.trim())
+            }
+            else {
+                continue;
+            };
+
+            let common_dir = match fs::read_to_string(git_dir.join("commondir")) {
+                Ok(common_dir) => git_dir.join(common_dir.trim()),
+                Err(_) => git_dir.clone(),
+            };
+            return Some(Repository {
+                work_tree: work_tree.to_path_buf(),
+                git_dir,
+                common_dir,
+            });
+        }
+        None
+    }
+
+    /// Returns what the ref called `name`, such as `HEAD` or `refs/heads/main`, contains
+    fn read_ref(&self, name: &str) -> Option<String> {
+        // `HEAD` and the likes of `MERGE_HEAD` belong to the work tree, while other refs are shared
+        let dir = match name.starts_with("refs/") {
+            true => &self.common_dir,
+            false => &self.git_dir,
+        };
+        if let Ok(contents) = fs::read_to_string(dir.join(name)) {
+            return Some(contents.trim().to_string());
+        }
+
+        let packed = fs::read_to_string(self.common_dir.join("packed-refs")).ok()?;
+        packed
+            .lines()
+            .filter(|line| !line.starts_with('#') && !line.starts_with('^'))
+            .find_map(|line| match line.split_once(' ') {
+                Some((oid, packed_name)) if packed_name == name => Some(oid.to_string()),
+                _ => None,
+            })
+    }
+
+    /// Returns the commit `name` refers to, following symbolic refs
+    pub fn resolve(&self, name: &str) -> Option<Oid> {
+        let mut name = name.to_string();
+        for _ in 0..MAX_SYMBOLIC_REFS {
+            let contents = self.read_ref(&name)?;
+            match contents.strip_prefix("ref:") {
+                Some(target) => name = target.trim().to_string(),
+                None => return parse_oid(&contents),
+            }
+        }
+        None
+    }
+
+    /// Returns the branch checked out, or the branch being rebased, or `None` if the HEAD is
+    /// detached
+    pub fn get_branch(&self) -> Option<String> {
+        let head = self.read_ref("HEAD")?;
+        let target = match head.strip_prefix("ref:") {
+            Some(target) => target.trim().to_string(),
+            None => ["rebase-merge/head-name", "rebase-apply/head-name"]
+                .iter()
+                .find_map(|file| fs::read_to_string(self.git_dir.join(file)).ok())?
+                .trim()
+                .to_string(),
+        };
+        Some(target.strip_prefix("refs/heads/").unwrap_or(&target).to_string())
+    }
+
+    /// Returns the ref `branch` tracks, from the repository's config
+    pub fn get_upstream(&self, branch: &str) -> Option<String> {
+        let config = fs::read_to_string(self.common_dir.join("config")).ok()?;
+        let remote = get_config_value(&config, "branch", Some(branch), "remote")?;
+        let merge = get_config_value(&config, "branch", Some(branch), "merge")?;
+        match remote.as_str() {
+            "." => Some(merge),
+            _ => Some(format!("refs/remotes/{}/{}", remote, merge.strip_prefix("refs/heads/").unwrap_or(&merge))),
+        }
+    }
+
+    /// Returns the operation in progress, such as a rebase or merge, with how far a rebase has got
+    pub fn get_operation(&self) -> Option<String> {
+        let read = |file: &str| fs::read_to_string(self.git_dir.join(file)).ok();
+        let progress = |step: Option<String>, total: Option<String>| match (step, total) {
+            (Some(step), Some(total)) => format!(" {}/{}", step.trim(), total.trim()),
+            _ => "".to_string(),
+        };
+
+        if self.git_dir.join("rebase-merge").is_dir() {
+            return Some(format!("REBASE{}", progress(read("rebase-merge/msgnum"), read("rebase-merge/end"))));
+        }
+        if self.git_dir.join("rebase-apply").is_dir() {
+            let operation = match self.git_dir.join("rebase-apply/applying").exists() {
+                true => "AM",
+                false => "REBASE",
+            };
+            let progress = progress(read("rebase-apply/next"), read("rebase-apply/last"));
+            return Some(format!("{}{}", operation, progress));
+        }
+        [
+            ("MERGE_HEAD", "MERGING"),
+            ("CHERRY_PICK_HEAD", "CHERRY-PICKING"),
+            ("REVERT_HEAD", "REVERTING"),
+            ("BISECT_LOG", "BISECTING"),
+        ]
+        .iter()
+        .find(|(file, _)| self.git_dir.join(file).exists())
+        .map(|(_, operation)| operation.to_string())
+    }
+
+    /// Returns the patterns for files to leave out of the untracked count, other than those in
+    /// `.gitignore` files
+    fn get_exclude_rules(&self) -> IgnoreRules {
+        let mut rules = IgnoreRules::new();
+        let home = std::env::var("HOME").unwrap_or_default();
+        let config = fs::read_to_string(self.common_dir.join("config")).unwrap_or_default();
+        let user_config = fs::read_to_string(Path::new(&home).join(".gitconfig")).unwrap_or_default();
+
+        let excludes_file = get_config_value(&config, "core", None, "excludesFile")
+            .or_else(|| get_config_value(&user_config, "core", None, "excludesFile"))
+            .map(|file| match file.strip_prefix("~/") {
+                Some(file) => Path::new(&home).join(file),
+                None => PathBuf::from(file),
+            })
+            .unwrap_or_else(|| match std::env::var("XDG_CONFIG_HOME") {
+                Ok(config_home) if !config_home.is_empty() => Path::new(&config_home).join("git/ignore"),
+                _ => Path::new(&home).join(".config/git/ignore"),
+            });
+        rules.add_file("", &excludes_file);
+        rules.add_file("", &self.common_dir.join("info/exclude"));
+        rules
+    }
+}
+
+/// What a prompt shows about the repository it's in
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GitStatus {
+    /// the branch checked out, or `None` if the HEAD is detached
+    pub branch:     Option<String>,
+    pub head:       Option<Oid>,
+    /// how many commits the branch has which its upstream doesn't, and the other way around
+    pub ahead:      usize,
+    pub behind:     usize,
+    /// files with changes added to the index
+    pub staged:     usize,
+    /// files with changes in the work tree which haven't been added to the index
+    pub modified:   usize,
+    pub untracked:  usize,
+    /// files with merge conflicts
+    pub conflicted: usize,
+    pub operation:  Option<String>,
+    /// false if the time budget ran out before all the counts were known
+    pub complete:   bool,
+}
+
+impl fmt::Display for GitStatus {
+    /// Write the status as `branch ↑ahead↓behind +staged !modified ?untracked xconflicted|OPERATION`,
+    /// leaving out the counts which are 0, and with `…` if some are missing
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.branch, &self.head) {
+            (Some(branch), _) => write!(f, "{}", branch)?,
+            (None, Some(head)) => write!(f, "({})", &oid_to_hex(head)[..7])?,
+            (None, None) => write!(f, "(unknown)")?,
+        }
+        if self.ahead > 0 || self.behind > 0 {
+            write!(f, " ")?;
+        }
+        if self.ahead > 0 {
+            write!(f, "↑{}", self.ahead)?;
+        }
+        if self.behind > 0 {
+            write!(f, "↓{}", self.behind)?;
+        }
+        let counts = [(self.staged, '+'), (self.modified, '!'), (self.untracked, '?'), (self.conflicted, 'x')];
+        for (count, symbol) in counts {
+            if count > 0 {
+                write!(f, " {}{}", symbol, count)?;
+            }
+        }
+        if !self.complete {
+            write!(f, " …")?;
+        }
+        if let Some(operation) = &self.operation {
+            write!(f, "|{}", operation)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns when the commit `oid` was made and its parents, reading it if it hasn't been already. A
+/// commit which can't be read, as in a shallow clone, is taken to have no parents.
+fn read_commit<'a>(
+    odb: &ObjectDb,
+    oid: &Oid,
+    commits: &'a mut HashMap<Oid, (i64, Vec<Oid>)>,
+) -> &'a (i64, Vec<Oid>) {
+    commits.entry(*oid).or_insert_with(|| match odb.read(oid) {
+        Ok((ObjectKind::Commit, data)) => match parse_commit(&data) {
+            Some(commit) => (commit.time, commit.parents),
+            None => (0, vec![]),
+        },
+        _ => (0, vec![]),
+    })
+}
+
+/// Returns how many commits can be reached from `ours` but not from `theirs`, and the other way
+/// around, or `None` if it took past `deadline`. Commits are walked newest first from both sides,
+/// marking which side reaches them, until all that's left to walk is reached from both.
+fn count_ahead_behind(odb: &ObjectDb, ours: Oid, theirs: Oid, deadline: Instant) -> Option<(usize, usize)> {
+    const OURS: u8 = 1;
+    const THEIRS: u8 = 2;
+    const BOTH: u8 = OURS | THEIRS;
+
+    let mut commits = HashMap::new();
+    let mut flags: HashMap<Oid, u8> = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    for (oid, side) in [(ours, OURS), (theirs, THEIRS)] {
+        *flags.entry(oid).or_insert(0) |= side;
+        queue.push((read_commit(odb, &oid, &mut commits).0, oid));
+    }
+
+    while queue.iter().any(|(_, oid)| flags[oid] != BOTH) {
+        if Instant::now() > deadline {
+            return None;
+        }
+        let (_, oid) = queue.pop()?;
+        let side = flags[&oid];
+        for parent in read_commit(odb, &oid, &mut commits).1.clone() {
+            let parent_side = flags.entry(parent).or_insert(0);
+            if *parent_side | side != *parent_side {
+                *parent_side |= side;
+                queue.push((read_commit(odb, &parent, &mut commits).0, parent));
+            }
+        }
+    }
+    // commits walked from one side may turn out to be reached from the other, through those left
+    while let Some((_, oid)) = queue.pop() {
+        for parent in read_commit(odb, &oid, &mut commits).1.clone() {
+            if let Some(parent_side) = flags.get_mut(&parent)
+                && *parent_side != BOTH
+            {
+                *parent_side = BOTH;
+                queue.push((commits[&parent].0, parent));
+            }
+        }
+    }
+
+    let count = |side: u8| flags.values().filter(|flags| **flags == side).count();
+    Some((count(OURS), count(THEIRS)))
+}
+
+/// Add the files in the tree `oid` to `files` by their path under `prefix`, returning `None` if it
+/// couldn't be read or took past `deadline`
+fn read_tree_files(
+    odb: &ObjectDb,
+    oid: &Oid,
+    prefix: &str,
+    files: &mut HashMap<String, (u32, Oid)>,
+    deadline: Instant,
+) -> Option<()> {
+    if Instant::now() > deadline {
+        return None;
+    }
+    let data = match odb.read(oid) {
+        Ok((ObjectKind::Tree, data)) => data,
+        _ => return None,
+    };
+    for entry in parse_tree(&data)? {
+        let path = format!("{}{}", prefix, entry.name);
+        match entry.is_tree() {
+            true => read_tree_files(odb, &entry.oid, &format!("{}/", path), files, deadline)?,
+            false => {
+                files.insert(path, (entry.mode, entry.oid));
+            }
+        }
+    }
+    Some(())
+}
+
+/// Returns how many files in the index differ from the commit `head`, leaving out those with
+/// conflicts, or `None` if it took past `deadline`
+fn count_staged(odb: &ObjectDb, head: Option<Oid>, entries: &[IndexEntry], deadline: Instant) -> Option<usize> {
+    let mut files = HashMap::new();
+    if let Some(head) = head {
+        let commit = match odb.read(&head) {
+            Ok((ObjectKind::Commit, data)) => parse_commit(&data)?,
+            _ => return None,
+        };
+        read_tree_files(odb, &commit.tree, "", &mut files, deadline)?;
+    }
+
+    let mut staged = 0;
+    for entry in entries.iter().filter(|entry| entry.stage == 0) {
+        match files.remove(&entry.path) {
+            Some((mode, oid)) if mode == entry.mode && oid == entry.oid => {}
+            _ => staged += 1,
+        }
+    }
+    // what's left was deleted, unless it has a conflict
+    let conflicted: HashSet<&str> =
+        entries.iter().filter(|entry| entry.stage != 0).map(|entry| entry.path.as_str()).collect();
+    Some(staged + files.keys().filter(|path| !conflicted.contains(path.as_str())).count())
+}
+
+fn hash_blob(contents: &[u8]) -> Oid {
+    let mut hasher = sha1_smol::Sha1::new();
+    hasher.update(format!("blob {}\0", contents.len()).as_bytes());
+    hasher.update(contents);
+    hasher.digest().bytes()
+}
+
+/// Returns whether the file for `entry` in `work_tree` differs from the index, which was written
+/// at `index_mtime`
+fn is_modified(work_tree: &Path, entry: &IndexEntry, index_mtime: (u32, u32)) -> bool {
+    let path = work_tree.join(&entry.path);
+    let metadata = match fs::symlink_metadata(&path) {
+        Ok(metadata) => metadata,
+        Err(_) => return true,
+    };
+    let is_symlink = entry.mode & FILE_TYPE_MASK == SYMLINK;
+    if metadata.file_type().is_symlink() != is_symlink || metadata.is_dir() {
+        return true;
+    }
+    if !is_symlink && (metadata.mode() & 0o100 != 0) != (entry.mode & 0o100 != 0) {
+        return true;
+    }
+    // the index only keeps the lower 32 bits of sizes and times
+    if metadata.len() as u32 != entry.size {
+        return true;
+    }
+    // a file changed in the same instant as the index was written could have changed again since,
+    // so only its contents can tell
+    let mtime = (metadata.mtime() as u32, metadata.mtime_nsec() as u32);
+    if mtime == entry.mtime && entry.mtime < index_mtime {
+        return false;
+    }
+
+    let contents = match is_symlink {
+        true => fs::read_link(&path).map(|target| target.into_os_string().into_vec()),
+        false => fs::read(&path),
+    };
+    match contents {
+        Ok(contents) => hash_blob(&contents) != entry.oid,
+        Err(_) => true,
+    }
+}
+
+/// Counts the untracked files in a work tree, taking each directory with nothing tracked in it as
+/// one
+struct UntrackedScan<'a> {
+    work_tree:    &'a Path,
+    tracked:      HashSet<&'a str>,
+    /// the directories with tracked files in them
+    tracked_dirs: HashSet<&'a str>,
+    rules:        IgnoreRules,
+    deadline:     Instant,
+}
+
+impl<'a> UntrackedScan<'a> {
+    fn new(repo: &'a Repository, entries: &'a [IndexEntry], deadline: Instant) -> Self {
+        let tracked = entries.iter().map(|entry| entry.path.as_str()).collect();
+        let tracked_dirs = entries
+            .iter()
+            .flat_map(|entry| entry.path.match_indices('/').map(|(i, _)| &entry.path[..i]))
+            .collect();
+        UntrackedScan {
+            work_tree: &repo.work_tree,
+            tracked,
+            tracked_dirs,
+            rules: repo.get_exclude_rules(),
+            deadline,
+        }
+    }
+
+    /// Returns the files in the directory `prefix`, which is relative to the top of the work tree
+    /// and ends with `/` unless it's empty, by their paths, and whether each is a directory. The
+    /// directory's `.gitignore` is added to the rules.
+    fn read_dir(&mut self, prefix: &str) -> Option<Vec<(String, bool)>> {
+        if Instant::now() > self.deadline {
+            return None;
+        }
+        let dir = self.work_tree.join(prefix);
+        self.rules.add_file(prefix, &dir.join(".gitignore"));
+        let files = fs::read_dir(&dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| entry.file_name() != ".git")
+            .map(|entry| {
+                let path = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+                // symlinks to directories are tracked as symlinks, so aren't followed
+                (path, entry.file_type().map(|file_type| file_type.is_dir()).unwrap_or(false))
+            })
+            .collect();
+        Some(files)
+    }
+
+    /// Returns how many files and directories under `prefix` are untracked and not ignored
+    fn count(&mut self, prefix: &str) -> Option<usize> {
+        let rules = self.rules.len();
+        let mut untracked = 0;
+        for (path, is_dir) in self.read_dir(prefix)? {
+            if self.tracked.contains(path.as_str()) || self.rules.is_ignored(&path, is_dir) {
+                continue;
+            }
+            if !is_dir {
+                untracked += 1;
+            }
+            else if self.tracked_dirs.contains(path.as_str()) {
+                untracked += self.count(&format!("{}/", path))?;
+            }
+            else if self.has_untracked(&format!("{}/", path))? {
+                untracked += 1;
+            }
+        }
+        self.rules.truncate(rules);
+        Some(untracked)
+    }
+
+    /// Returns whether there's anything in the untracked directory `prefix` which isn't ignored
+    fn has_untracked(&mut self, prefix: &str) -> Option<bool> {
+        // another repository inside this one shows up even if it's empty
+        if self.work_tree.join(prefix).join(".git").exists() {
+            return Some(true);
+        }
+        let rules = self.rules.len();
+        let mut found = false;
+        for (path, is_dir) in self.read_dir(prefix)? {
+            if self.rules.is_ignored(&path, is_dir) {
+                continue;
+            }
+            if !is_dir || self.has_untracked(&format!("{}/", path))? {
+                found = true;
+                break;
+            }
+        }
+        self.rules.truncate(rules);
+        Some(found)
+    }
+}
+
+/// Returns how many files in the work tree differ from the index, and how many are untracked, or
+/// `None` if it took past `deadline`
+fn scan_work_tree(
+    repo: &Repository,
+    entries: &[IndexEntry],
+    index_mtime: Option<SystemTime>,
+    deadline: Instant,
+) -> Option<(usize, usize)> {
+    let index_mtime = index_mtime
+        .and_then(|mtime| mtime.duration_since(UNIX_EPOCH).ok())
+        .map(|mtime| (mtime.as_secs() as u32, mtime.subsec_nanos()))
+        .unwrap_or((0, 0));
+
+    let mut modified = 0;
+    let checked = entries.iter().filter(|entry| entry.stage == 0 && !entry.skip && entry.mode != GITLINK);
+    for (i, entry) in checked.enumerate() {
+        if i % 64 == 0 && Instant::now() > deadline {
+            return None;
+        }
+        if is_modified(&repo.work_tree, entry, index_mtime) {
+            modified += 1;
+        }
+    }
+
+    let untracked = UntrackedScan::new(repo, entries, deadline).count("")?;
+    Some((modified, untracked))
+}
+
+/// What the counts of a [GitStatus] which only change with the index and commits depend on
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CacheKey {
+    index_mtime: Option<SystemTime>,
+    index_size:  Option<u64>,
+    head:        Option<Oid>,
+    upstream:    Option<Oid>,
+}
+
+/// The status last worked out for a repository
+#[derive(Clone)]
+struct CacheEntry {
+    key:       CacheKey,
+    /// the counts [count_changes] worked out, which are only complete if it had the time
+    changes:   GitStatus,
+    /// the entries of the index, which are only read again once it changes
+    entries:   Arc<Vec<IndexEntry>>,
+    /// the modified and untracked files in the work tree, or `None` if checking it ran out of time
+    work_tree: Option<(usize, usize)>,
+}
+
+lazy_static! {
+    /// The status last worked out for each repository, by its git directory
+    static ref CACHE: Mutex<HashMap<PathBuf, CacheEntry>> = Mutex::new(HashMap::new());
+}
+
+/// Returns the status of the repository `dir` is in, or `None` if it isn't in one
+pub fn get_git_status(dir: &Path) -> Option<GitStatus> {
+    get_git_status_within(dir, GIT_STATUS_BUDGET)
+}
+
+/// Work out the counts of a [GitStatus] which only change with the index and commits: reading
+/// the index, and comparing it with `HEAD`, and `HEAD` with `upstream`. Reading the index and the
+/// objects' pack indexes of a big repository takes a while too, so if they run past `deadline`,
+/// the counts are left out.
+fn count_changes(
+    repo: &Repository,
+    head: Option<Oid>,
+    upstream: Option<Oid>,
+    deadline: Instant,
+) -> (GitStatus, Arc<Vec<IndexEntry>>) {
+    debug!("Working out git status of '{}'", repo.work_tree.display());
+    let mut status = GitStatus::default();
+    let entries = Arc::new(read_index(&repo.git_dir.join("index")).unwrap_or_default());
+    let odb = match Instant::now() > deadline {
+        true => None,
+        false => ObjectDb::open(&repo.common_dir.join("objects"), deadline),
+    };
+    let odb = match odb {
+        Some(odb) => odb,
+        None => return (status, entries),
+    };
+
+    status.complete = true;
+    if let (Some(head), Some(upstream)) = (head, upstream) {
+        match count_ahead_behind(&odb, head, upstream, deadline) {
+            Some((ahead, behind)) => (status.ahead, status.behind) = (ahead, behind),
+            None => status.complete = false,
+        }
+    }
+    match count_staged(&odb, head, &entries, deadline) {
+        Some(staged) => status.staged = staged,
+        None => status.complete = false,
+    }
+    let conflicted: HashSet<&str> =
+        entries.iter().filter(|entry| entry.stage != 0).map(|entry| entry.path.as_str()).collect();
+    status.conflicted = conflicted.len();
+    (status, entries)
+}
+
+/// Returns the status of the repository `dir` is in, taking no longer than about `budget`.
+///
+/// The index is only read, and compared with the commits, when its modification time or size,
+/// `HEAD` or upstream change, or when that ran out of time before, otherwise the counts from last
+/// time are used. The work tree is checked each time, unless that ran out of time before with the
+/// same index and after those counts were known, so that a huge repository only holds up the first
+/// prompt after each change to its index.
+pub fn get_git_status_within(dir: &Path, budget: Duration) -> Option<GitStatus> {
+    let deadline = Instant::now() + budget;
+    let repo = Repository::discover(dir)?;
+    let branch = repo.get_branch();
+    let head = repo.resolve("HEAD");
+    let upstream = branch.as_deref().and_then(|branch| repo.get_upstream(branch));
+    let upstream = upstream.and_then(|upstream| repo.resolve(&upstream));
+
+    let index = fs::metadata(repo.git_dir.join("index")).ok();
+    let key = CacheKey {
+        index_mtime: index.as_ref().and_then(|metadata| metadata.modified().ok()),
+        index_size:  index.as_ref().map(|metadata| metadata.len()),
+        head,
+        upstream,
+    };
+
+    let mut cache = CACHE.lock().unwrap();
+    let cached = cache.get(&repo.git_dir).filter(|cached| cached.key == key);
+    let (changes, entries) = match cached {
+        Some(cached) if cached.changes.complete => (cached.changes.clone(), cached.entries.clone()),
+        _ => count_changes(&repo, head, upstream, deadline),
+    };
+    // while the counts above were missing, the work tree didn't get its share of the time
+    let work_tree = match cached {
+        Some(cached) if cached.changes.complete && cached.work_tree.is_none() => None,
+        _ => {
+            let work_tree = scan_work_tree(&repo, &entries, key.index_mtime, deadline);
+            if work_tree.is_none() {
+                debug!("Ran out of time checking the work tree of '{}'", repo.work_tree.display());
+            }
+            work_tree
+        }
+    };
+
+    let mut status = changes.clone();
+    status.branch = branch;
+    status.head = head;
+    status.operation = repo.get_operation();
+    match work_tree {
+        Some((modified, untracked)) => (status.modified, status.untracked) = (modified, untracked),
+        None => status.complete = false,
+    }
+
+    let cached = CacheEntry {
+        key,
+        changes,
+        entries,
+        work_tree,
+    };
+    cache.insert(repo.git_dir.clone(), cached);
+    Some(status)
+}
@@ -0,0 +1,161 @@
+use std::fs;
+use std::path::Path;
+
+use regex::Regex;
+
+/// A line of a `.gitignore` file
+#[derive(Clone, Debug)]
+struct IgnorePattern {
+    /// the directory of the file the pattern came from, relative to the work tree and ending with
+    /// `/`, or empty for the top of the work tree
+    base:      String,
+    regex:     Regex,
+    /// a `!` pattern, which includes again what an earlier pattern ignored
+    negated:   bool,
+    /// a pattern ending with `/`, which only matches directories
+    dir_only:  bool,
+    /// a pattern without a `/` other than at the end, which matches names at any depth
+    name_only: bool,
+}
+
+/// Translate a gitignore glob into a regex matching the whole of a path
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    let chars: Vec<char> = glob.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let at_start = i == 0 || chars[i - 1] == '/';
+                match chars.get(i + 2) {
+                    // `**/` matches any number of directories, including none
+                    Some('/') if at_start => {
+                        regex += "(?:.*/)?";
+                        i += 3;
+                        continue;
+                    }
+                    // a trailing `/**` matches everything inside
+                    None if at_start => regex += ".*",
+                    _ => regex += "[^/]*",
+                }
+                i += 2;
+                continue;
+            }
+            '*' => regex += "[^/]*",
+            '?' => regex += "[^/]",
+            '[' => match chars[i + 1..].iter().skip(1).position(|c| *c == ']') {
+                Some(end) => {
+                    let class: String = chars[i + 1..i + 2 + end].iter().collect();
+                    let class = match class.strip_prefix('!') {
+                        Some(class) => format!("^{}", class),
+                        None => class,
+                    };
+                    regex += &format!("[{}]", class.replace('\\', "\\\\").replace('[', "\\["));
+                    i += end + 3;
+                    continue;
+                }
+                None => regex += "\\[",
+            },
+            '\\' if i + 1 < chars.len() => {
+                regex += &regex::escape(&chars[i + 1].to_string());
+                i += 1;
+            }
+            c => regex += &regex::escape(&c.to_string()),
+        }
+        i += 1;
+    }
+    regex + "$"
+}
+
+impl IgnorePattern {
+    fn parse(base: &str, line: &str) -> Option<Self> {
+        // trailing spaces are ignored unless escaped
+        let mut line = line.trim_end_matches(['\r', '\n']);
+        while line.ends_with(' ') && !line.ends_with("\\ ") {
+            line = &line[..line.len() - 1];
+        }
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+        let name_only = !line.contains('/');
+        let glob = line.strip_prefix('/').unwrap_or(line);
+        Some(IgnorePattern {
+            base: base.to_string(),
+            regex: Regex::new(&glob_to_regex(glob)).ok()?,
+            negated,
+            dir_only,
+            name_only,
+        })
+    }
+
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let relative = match path.strip_prefix(self.base.as_str()) {
+            Some(relative) => relative,
+            None => return false,
+        };
+        match self.name_only {
+            true => self.regex.is_match(relative.rsplit('/').next().unwrap_or_default()),
+            false => self.regex.is_match(relative),
+        }
+    }
+}
+
+/// The patterns deciding which untracked files are ignored, from `.gitignore` files and the
+/// repository's `info/exclude`. Patterns read later take precedence.
+#[derive(Clone, Debug, Default)]
+pub struct IgnoreRules {
+    patterns: Vec<IgnorePattern>,
+}
+
+impl IgnoreRules {
+    pub fn new() -> Self {
+        IgnoreRules::default()
+    }
+
+    /// Add the patterns in `contents`, which apply to the files in `base`, relative to the top of the
+    /// work tree and ending with `/` unless it's empty
+    pub fn add_patterns(&mut self, base: &str, contents: &str) {
+        self.patterns.extend(contents.lines().filter_map(|line| IgnorePattern::parse(base, line)));
+    }
+
+    /// Add the patterns in the file at `path`, if there is one
+    pub fn add_file(&mut self, base: &str, path: &Path) {
+        if let Ok(contents) = fs::read_to_string(path) {
+            self.add_patterns(base, &contents);
+        }
+    }
+
+    /// Returns how many patterns there are, so that patterns added after can later be removed with
+    /// [IgnoreRules::truncate]
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        self.patterns.truncate(len);
+    }
+
+    /// Returns whether `path`, relative to the top of the work tree, is ignored
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        match self.patterns.iter().rev().find(|pattern| pattern.matches(path, is_dir)) {
+            Some(pattern) => !pattern.negated,
+            None => false,
+        }
+    }
+}
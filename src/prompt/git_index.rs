@@ -0,0 +1,115 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::prompt::git_objects::Oid;
+
+/// Entries with this flag are assumed to be unchanged in the work tree
+const ASSUME_VALID: u16 = 0x8000;
+const EXTENDED: u16 = 0x4000;
+/// Set in the extended flags of entries left out of a sparse checkout
+const SKIP_WORKTREE: u16 = 0x4000;
+
+/// A file in the index, which is what the next commit will be made from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub path:  String,
+    /// when the file was last changed, as seconds and nanoseconds, when it was added to the index
+    pub mtime: (u32, u32),
+    pub size:  u32,
+    pub mode:  u32,
+    pub oid:   Oid,
+    /// 0 normally, or 1 to 3 for the sides of a merge conflict
+    pub stage: u8,
+    /// set if the work tree shouldn't be checked for changes to the file
+    pub skip:  bool,
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_u16(data: &[u8], at: usize) -> io::Result<u16> {
+    let bytes = data.get(at..at + 2).ok_or_else(|| invalid_data("truncated index"))?;
+    Ok(u16::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u32(data: &[u8], at: usize) -> io::Result<u32> {
+    let bytes = data.get(at..at + 4).ok_or_else(|| invalid_data("truncated index"))?;
+    Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+/// Read the index file at `path`, in any of versions 2 to 4
+pub fn read_index(path: &Path) -> io::Result<Vec<IndexEntry>> {
+    parse_index(&fs::read(path)?)
+}
+
+pub fn parse_index(data: &[u8]) -> io::Result<Vec<IndexEntry>> {
+    if data.get(..4) != Some(b"DIRC") {
+        return Err(invalid_data("not an index file"));
+    }
+    let version = read_u32(data, 4)?;
+    if !(2..=4).contains(&version) {
+        return Err(invalid_data("unsupported index version"));
+    }
+    let count = read_u32(data, 8)? as usize;
+
+    let mut entries: Vec<IndexEntry> = Vec::with_capacity(count);
+    let mut pos = 12;
+    for _ in 0..count {
+        let start = pos;
+        let mtime = (read_u32(data, pos + 8)?, read_u32(data, pos + 12)?);
+        let mode = read_u32(data, pos + 24)?;
+        let size = read_u32(data, pos + 36)?;
+        let oid = data.get(pos + 40..pos + 60).ok_or_else(|| invalid_data("truncated index"))?;
+        let flags = read_u16(data, pos + 60)?;
+        pos += 62;
+        let mut extended_flags = 0;
+        if version >= 3 && flags & EXTENDED != 0 {
+            extended_flags = read_u16(data, pos)?;
+            pos += 2;
+        }
+
+        // in version 4, paths are written as how much to remove from the end of the last path, then
+        // what to add after it
+        let mut strip = 0_usize;
+        if version == 4 {
+            loop {
+                let byte = *data.get(pos).ok_or_else(|| invalid_data("truncated index"))?;
+                pos += 1;
+                strip = (strip << 7) | (byte & 0x7f) as usize;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+                strip += 1;
+            }
+        }
+        let length = data.get(pos..).and_then(|rest| rest.iter().position(|byte| *byte == 0));
+        let nul = pos + length.ok_or_else(|| invalid_data("truncated index"))?;
+        let name = String::from_utf8_lossy(&data[pos..nul]);
+        let path = match version {
+            4 => {
+                let previous = entries.last().map(|entry| entry.path.as_str()).unwrap_or_default();
+                let kept = previous.len().checked_sub(strip).ok_or_else(|| invalid_data("bad path"))?;
+                format!("{}{}", previous.get(..kept).ok_or_else(|| invalid_data("bad path"))?, name)
+            }
+            _ => name.to_string(),
+        };
+        pos = match version {
+            4 => nul + 1,
+            // entries are padded with NULs to a multiple of 8 bytes
+            _ => start + (nul - start + 8) / 8 * 8,
+        };
+
+        entries.push(IndexEntry {
+            path,
+            mtime,
+            size,
+            mode,
+            oid: oid.try_into().unwrap(),
+            stage: ((flags >> 12) & 3) as u8,
+            skip: flags & ASSUME_VALID != 0 || extended_flags & SKIP_WORKTREE != 0,
+        });
+    }
+    Ok(entries)
+}
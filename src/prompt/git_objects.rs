@@ -0,0 +1,370 @@
+use std::cmp::Ordering;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use flate2::read::ZlibDecoder;
+use log::debug;
+
+/// The name of an object, which is the SHA-1 of its contents
+pub type Oid = [u8; 20];
+
+/// How many deltas may be applied to reach an object, past which the pack is taken to be corrupt
+const MAX_DELTA_DEPTH: usize = 64;
+
+pub fn parse_oid(hex: &str) -> Option<Oid> {
+    let hex = hex.as_bytes();
+    if hex.len() != 40 {
+        return None;
+    }
+    let mut oid = [0; 20];
+    for (i, byte) in oid.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(std::str::from_utf8(&hex[i * 2..i * 2 + 2]).ok()?, 16).ok()?;
+    }
+    Some(oid)
+}
+
+pub fn oid_to_hex(oid: &Oid) -> String {
+    oid.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+fn read_byte<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// Read a variable length number as used in deltas, with the least significant 7 bits first
+fn read_size(data: &[u8], pos: &mut usize) -> io::Result<usize> {
+    let mut size = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*pos).ok_or_else(|| invalid_data("truncated delta"))?;
+        *pos += 1;
+        size |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(size);
+        }
+    }
+}
+
+/// Build an object by applying `delta` to `base`
+fn apply_delta(base: &[u8], delta: &[u8]) -> io::Result<Vec<u8>> {
+    let mut pos = 0;
+    if read_size(delta, &mut pos)? != base.len() {
+        return Err(invalid_data("delta base has the wrong size"));
+    }
+    let size = read_size(delta, &mut pos)?;
+    let mut object = Vec::with_capacity(size);
+
+    while let Some(&op) = delta.get(pos) {
+        pos += 1;
+        if op & 0x80 != 0 {
+            // copy from the base, with the bytes of the offset and size present given by the bits of `op`
+            let mut fields = [0_usize; 7];
+            for (i, field) in fields.iter_mut().enumerate() {
+                if op & (1 << i) != 0 {
+                    *field = *delta.get(pos).ok_or_else(|| invalid_data("truncated delta"))? as usize;
+                    pos += 1;
+                }
+            }
+            let offset = fields[..4].iter().rev().fold(0, |offset, byte| offset << 8 | byte);
+            let size = match fields[4..].iter().rev().fold(0, |size, byte| size << 8 | byte) {
+                0 => 0x10000,
+                size => size,
+            };
+            let copied = base
+                .get(offset..offset + size)
+                .ok_or_else(|| invalid_data("delta copies past its base"))?;
+            object.extend_from_slice(copied);
+        }
+        else if op != 0 {
+            let inserted = delta.get(pos..pos + op as usize).ok_or_else(|| invalid_data("truncated delta"))?;
+            object.extend_from_slice(inserted);
+            pos += op as usize;
+        }
+        else {
+            return Err(invalid_data("unknown delta instruction"));
+        }
+    }
+
+    if object.len() != size {
+        return Err(invalid_data("delta result has the wrong size"));
+    }
+    Ok(object)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectKind {
+    Commit,
+    Tree,
+    Blob,
+    Tag,
+}
+
+impl ObjectKind {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "commit" => Some(ObjectKind::Commit),
+            "tree" => Some(ObjectKind::Tree),
+            "blob" => Some(ObjectKind::Blob),
+            "tag" => Some(ObjectKind::Tag),
+            _ => None,
+        }
+    }
+
+    fn from_pack_type(kind: u8) -> Option<Self> {
+        match kind {
+            1 => Some(ObjectKind::Commit),
+            2 => Some(ObjectKind::Tree),
+            3 => Some(ObjectKind::Blob),
+            4 => Some(ObjectKind::Tag),
+            _ => None,
+        }
+    }
+}
+
+/// A version 2 `.idx` file, which gives where in its pack each object starts
+struct PackIndex {
+    pack:  PathBuf,
+    data:  Vec<u8>,
+    count: usize,
+}
+
+const FANOUT_START: usize = 8;
+const OIDS_START: usize = FANOUT_START + 256 * 4;
+
+impl PackIndex {
+    fn open(path: &Path) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        if data.len() < OIDS_START || data[..4] != *b"\xfftOc" || read_u32(&data, 4) != 2 {
+            return Err(invalid_data("not a version 2 pack index"));
+        }
+        let count = read_u32(&data, FANOUT_START + 255 * 4) as usize;
+        if data.len() < OIDS_START + count * 28 {
+            return Err(invalid_data("truncated pack index"));
+        }
+        Ok(PackIndex {
+            pack: path.with_extension("pack"),
+            data,
+            count,
+        })
+    }
+
+    fn get_oid(&self, i: usize) -> &[u8] {
+        &self.data[OIDS_START + i * 20..OIDS_START + (i + 1) * 20]
+    }
+
+    /// Returns where `oid` starts in the pack, if it's there
+    fn find(&self, oid: &Oid) -> Option<u64> {
+        let start = match oid[0] {
+            0 => 0,
+            byte => read_u32(&self.data, FANOUT_START + (byte as usize - 1) * 4) as usize,
+        };
+        let end = read_u32(&self.data, FANOUT_START + oid[0] as usize * 4) as usize;
+        let (mut low, mut high) = (start, end);
+        let i = loop {
+            if low >= high {
+                return None;
+            }
+            let middle = (low + high) / 2;
+            match self.get_oid(middle).cmp(&oid[..]) {
+                Ordering::Less => low = middle + 1,
+                Ordering::Greater => high = middle,
+                Ordering::Equal => break middle,
+            }
+        };
+
+        let offset = read_u32(&self.data, OIDS_START + self.count * 24 + i * 4);
+        if offset & 0x8000_0000 == 0 {
+            return Some(offset as u64);
+        }
+        // offsets past 2GiB are kept in a table of their own
+        let at = OIDS_START + self.count * 28 + (offset & 0x7fff_ffff) as usize * 8;
+        let large = self.data.get(at..at + 8)?;
+        Some(u64::from_be_bytes(large.try_into().unwrap()))
+    }
+}
+
+/// The objects in a repository, both loose and packed
+pub struct ObjectDb {
+    objects_dir: PathBuf,
+    packs:       Vec<PackIndex>,
+}
+
+impl ObjectDb {
+    /// Open the objects in `objects_dir`, reading the index of each pack, or return `None` if
+    /// that took past `deadline`
+    pub fn open(objects_dir: &Path, deadline: Instant) -> Option<Self> {
+        let mut packs = vec![];
+        let entries = fs::read_dir(objects_dir.join("pack")).into_iter().flatten().flatten();
+        let paths = entries.map(|entry| entry.path());
+        for path in paths.filter(|path| path.extension().is_some_and(|extension| extension == "idx")) {
+            if Instant::now() > deadline {
+                return None;
+            }
+            match PackIndex::open(&path) {
+                Ok(index) => packs.push(index),
+                Err(e) => debug!("Skipping pack index '{}': {}", path.display(), e),
+            }
+        }
+        Some(ObjectDb {
+            objects_dir: objects_dir.to_path_buf(),
+            packs,
+        })
+    }
+
+    pub fn read(&self, oid: &Oid) -> io::Result<(ObjectKind, Vec<u8>)> {
+        self.read_at_depth(oid, 0)
+    }
+
+    fn read_at_depth(&self, oid: &Oid, depth: usize) -> io::Result<(ObjectKind, Vec<u8>)> {
+        for pack in &self.packs {
+            if let Some(offset) = pack.find(oid) {
+                return self.read_packed(pack, offset, depth);
+            }
+        }
+        self.read_loose(oid)
+    }
+
+    fn read_loose(&self, oid: &Oid) -> io::Result<(ObjectKind, Vec<u8>)> {
+        let hex = oid_to_hex(oid);
+        let file = File::open(self.objects_dir.join(&hex[..2]).join(&hex[2..]))?;
+        let mut contents = vec![];
+        ZlibDecoder::new(file).read_to_end(&mut contents)?;
+
+        // a header of `kind size`, ending with a NUL
+        let nul = contents.iter().position(|byte| *byte == 0).ok_or_else(|| invalid_data("no object header"))?;
+        let header = String::from_utf8_lossy(&contents[..nul]).to_string();
+        let kind = header
+            .split(' ')
+            .next()
+            .and_then(ObjectKind::from_name)
+            .ok_or_else(|| invalid_data("unknown object kind"))?;
+        contents.drain(..=nul);
+        Ok((kind, contents))
+    }
+
+    fn read_packed(&self, pack: &PackIndex, offset: u64, depth: usize) -> io::Result<(ObjectKind, Vec<u8>)> {
+        if depth > MAX_DELTA_DEPTH {
+            return Err(invalid_data("delta chain too long"));
+        }
+        let mut file = BufReader::new(File::open(&pack.pack)?);
+        file.seek(SeekFrom::Start(offset))?;
+
+        // the type, then the size with the least significant bits first
+        let mut byte = read_byte(&mut file)?;
+        let kind = (byte >> 4) & 7;
+        let mut size = (byte & 0x0f) as u64;
+        let mut shift = 4;
+        while byte & 0x80 != 0 {
+            byte = read_byte(&mut file)?;
+            size |= ((byte & 0x7f) as u64) << shift;
+            shift += 7;
+        }
+
+        match kind {
+            // a delta against the object a given distance before this one in the pack
+            6 => {
+                let mut byte = read_byte(&mut file)?;
+                let mut distance = (byte & 0x7f) as u64;
+                while byte & 0x80 != 0 {
+                    byte = read_byte(&mut file)?;
+                    distance = ((distance + 1) << 7) | (byte & 0x7f) as u64;
+                }
+                let delta = inflate(&mut file, size)?;
+                let base_offset = offset.checked_sub(distance).ok_or_else(|| invalid_data("bad delta base"))?;
+                let (kind, base) = self.read_packed(pack, base_offset, depth + 1)?;
+                Ok((kind, apply_delta(&base, &delta)?))
+            }
+            // a delta against the object with a given name
+            7 => {
+                let mut base_oid = [0; 20];
+                file.read_exact(&mut base_oid)?;
+                let delta = inflate(&mut file, size)?;
+                let (kind, base) = self.read_at_depth(&base_oid, depth + 1)?;
+                Ok((kind, apply_delta(&base, &delta)?))
+            }
+            kind => {
+                let kind = ObjectKind::from_pack_type(kind).ok_or_else(|| invalid_data("unknown object type"))?;
+                Ok((kind, inflate(&mut file, size)?))
+            }
+        }
+    }
+}
+
+fn inflate<R: Read>(reader: &mut R, size: u64) -> io::Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(size as usize);
+    ZlibDecoder::new(reader).take(size).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Commit {
+    pub tree:    Oid,
+    pub parents: Vec<Oid>,
+    /// when the commit was made, in seconds since the epoch
+    pub time:    i64,
+}
+
+pub fn parse_commit(data: &[u8]) -> Option<Commit> {
+    let text = String::from_utf8_lossy(data);
+    let mut tree = None;
+    let mut parents = vec![];
+    let mut time = 0;
+    for line in text.lines().take_while(|line| !line.is_empty()) {
+        match line.split_once(' ') {
+            Some(("tree", oid)) => tree = parse_oid(oid),
+            Some(("parent", oid)) => parents.extend(parse_oid(oid)),
+            // `committer name <email> time zone`
+            Some(("committer", committer)) => {
+                time = committer.rsplit(' ').nth(1).and_then(|time| time.parse().ok()).unwrap_or(0);
+            }
+            _ => {}
+        }
+    }
+    Some(Commit {
+        tree: tree?,
+        parents,
+        time,
+    })
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeEntry {
+    pub mode: u32,
+    pub name: String,
+    pub oid:  Oid,
+}
+
+impl TreeEntry {
+    pub fn is_tree(&self) -> bool {
+        self.mode == 0o40000
+    }
+}
+
+pub fn parse_tree(data: &[u8]) -> Option<Vec<TreeEntry>> {
+    let mut entries = vec![];
+    let mut rest = data;
+    // `mode name`, a NUL, then the object's name as 20 bytes
+    while !rest.is_empty() {
+        let space = rest.iter().position(|byte| *byte == b' ')?;
+        let nul = space + rest[space..].iter().position(|byte| *byte == 0)?;
+        let mode = u32::from_str_radix(std::str::from_utf8(&rest[..space]).ok()?, 8).ok()?;
+        let name = String::from_utf8_lossy(&rest[space + 1..nul]).to_string();
+        let oid = rest.get(nul + 1..nul + 21)?.try_into().ok()?;
+        entries.push(TreeEntry { mode, name, oid });
+        rest = &rest[nul + 21..];
+    }
+    Some(entries)
+}
@@ -1,3 +1,12 @@
+mod git;
+mod git_ignore;
+mod git_index;
+mod git_objects;
 mod prompt;
+#[cfg(test)]
+mod test_git;
+#[cfg(test)]
+mod test_prompt;
 
+pub use git::*;
 pub use prompt::*;
@@ -1,29 +1,365 @@
+use std::ffi::CStr;
 use std::io;
 use std::io::Write;
+use std::path::Path;
 use std::process::ExitStatus;
+use std::time::Duration;
 
 use termion::color;
 
-pub fn print_prompt<T>(status: &ExitStatus, out: &mut T) -> io::Result<usize>
-where
-    T: Write,
-{
-    let path = if let Ok(dir) = std::env::current_dir() {
-        format!("{}", dir.display())
+use crate::prompt::{get_git_status, GitStatus};
+use crate::shell::{format_duration, status_code, Shell, CMD_DURATION};
+
+/// The prompt used if `$PS1` isn't set: the working directory and the status of its repository,
+/// how long the last command took if it was slow and its exit status if it failed, then `>` in
+/// green if the last command succeeded and red if it failed
+pub const DEFAULT_PS1: &str = "\\C{reset}\\w\\C{cyan}\\g\\C{yellow}\\D\\C{red}\\S\\C{status}> \\C{reset}";
+/// How long a command has to take, in milliseconds, for `\D` to show it, unless
+/// `$CMD_DURATION_THRESHOLD` is set
+const DEFAULT_DURATION_THRESHOLD_MS: u64 = 2000;
+
+/// What the escapes in a prompt template stand for
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PromptInfo {
+    pub user:     String,
+    /// the full host name, of which `\h` gives the part up to the first `.`
+    pub host:     String,
+    pub cwd:      String,
+    pub home:     Option<String>,
+    pub status:   ExitStatus,
+    /// the number of jobs running in the background
+    pub jobs:     usize,
+    /// the local time, as hours, minutes and seconds
+    pub time:     (u32, u32, u32),
+    pub root:     bool,
+    /// how many of the trailing directories of `\w` to keep, from `$PROMPT_DIRTRIM`; 0 keeps them all
+    pub dir_trim: usize,
+    /// the status of the repository the working directory is in, if it's in one
+    pub git:      Option<GitStatus>,
+    /// how long the last command took, if it took long enough to show
+    pub duration: Option<Duration>,
+}
+
+fn get_host_name() -> String {
+    let mut buf = [0 as libc::c_char; 256];
+    match unsafe { libc::gethostname(buf.as_mut_ptr(), buf.len() - 1) } {
+        0 => unsafe { CStr::from_ptr(buf.as_ptr()) }.to_string_lossy().to_string(),
+        _ => "".to_string(),
     }
-    else {
-        "".to_string()
+}
+
+/// Returns the name of the user running the shell, from the passwd database
+fn get_user_name() -> Option<String> {
+    let passwd = unsafe { libc::getpwuid(libc::geteuid()) };
+    if passwd.is_null() {
+        return None;
+    }
+    Some(unsafe { CStr::from_ptr((*passwd).pw_name) }.to_string_lossy().to_string())
+}
+
+fn get_local_time() -> (u32, u32, u32) {
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        libc::localtime_r(&now, &mut tm);
+    }
+    (tm.tm_hour as u32, tm.tm_min as u32, tm.tm_sec as u32)
+}
+
+/// Returns how long the last command took, from `$CMD_DURATION`, if it was at least
+/// `$CMD_DURATION_THRESHOLD` milliseconds
+fn get_slow_duration(shell: &Shell) -> Option<Duration> {
+    let threshold = shell.get_variable("CMD_DURATION_THRESHOLD").and_then(|ms| ms.parse().ok());
+    let duration: u64 = shell.get_variable(CMD_DURATION)?.parse().ok()?;
+    match duration >= threshold.unwrap_or(DEFAULT_DURATION_THRESHOLD_MS) {
+        true => Some(Duration::from_millis(duration)),
+        false => None,
+    }
+}
+
+impl PromptInfo {
+    /// Gather what a prompt may show about `shell` and its surroundings now
+    pub fn from_shell(shell: &Shell) -> Self {
+        let cwd = shell
+            .get_variable("PWD")
+            .or_else(|| std::env::current_dir().ok().map(|dir| dir.to_string_lossy().to_string()))
+            .unwrap_or_default();
+        PromptInfo {
+            user: shell.get_variable("USER").or_else(get_user_name).unwrap_or_default(),
+            host: get_host_name(),
+            cwd,
+            home: shell.get_variable("HOME"),
+            status: shell.get_last_status(),
+            jobs: shell.get_jobs().get_all().len(),
+            time: get_local_time(),
+            root: unsafe { libc::geteuid() } == 0,
+            dir_trim: shell.get_variable("PROMPT_DIRTRIM").and_then(|n| n.parse().ok()).unwrap_or(0),
+            git: None,
+            duration: get_slow_duration(shell),
+        }
+    }
+
+    /// Returns the working directory with `$HOME` written as `~`, and only the last `dir_trim`
+    /// directories kept, after `...`
+    fn get_short_cwd(&self) -> String {
+        let cwd = abbreviate_home(&self.cwd, self.home.as_deref());
+        if self.dir_trim == 0 {
+            return cwd;
+        }
+
+        let (start, dirs) = match cwd.strip_prefix('~') {
+            Some(rest) => ("~", rest),
+            None => ("", cwd.as_str()),
+        };
+        let parts: Vec<&str> = dirs.split('/').filter(|part| !part.is_empty()).collect();
+        if parts.len() <= self.dir_trim {
+            return cwd;
+        }
+        format!("{}/.../{}", start, parts[parts.len() - self.dir_trim..].join("/"))
+    }
+}
+
+/// A prompt ready to be written to the terminal
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Prompt {
+    pub text:  String,
+    /// how many columns the last line of the prompt takes up, leaving out escape sequences
+    pub width: usize,
+}
+
+/// Returns the escape sequence which sets the foreground colour to `name`, or `None` if there is
+/// no such colour
+fn get_color(name: &str, info: &PromptInfo) -> Option<String> {
+    let color = match name {
+        "reset" => color::Fg(color::Reset).to_string(),
+        "black" => color::Fg(color::Black).to_string(),
+        "red" => color::Fg(color::Red).to_string(),
+        "green" => color::Fg(color::Green).to_string(),
+        "yellow" => color::Fg(color::Yellow).to_string(),
+        "blue" => color::Fg(color::Blue).to_string(),
+        "magenta" => color::Fg(color::Magenta).to_string(),
+        "cyan" => color::Fg(color::Cyan).to_string(),
+        "white" => color::Fg(color::White).to_string(),
+        "bold" => termion::style::Bold.to_string(),
+        "status" if info.status.success() => color::Fg(color::Green).to_string(),
+        "status" => color::Fg(color::Red).to_string(),
+        _ => return None,
     };
-    write!(out, "{}{}", color::Fg(color::Reset), path)?;
-    if status.success() {
-        write!(out, "{}", color::Fg(color::Green))?;
+    Some(color)
+}
+
+/// Characters drawn over the one before them: combining marks, variation selectors and the
+/// zero width spaces and joiners
+const ZERO_WIDTH: [(char, char); 11] = [
+    ('\u{0300}', '\u{036f}'),
+    ('\u{0483}', '\u{0489}'),
+    ('\u{1160}', '\u{11ff}'),
+    ('\u{1ab0}', '\u{1aff}'),
+    ('\u{1dc0}', '\u{1dff}'),
+    ('\u{200b}', '\u{200f}'),
+    ('\u{2060}', '\u{2064}'),
+    ('\u{20d0}', '\u{20ff}'),
+    ('\u{fe00}', '\u{fe0f}'),
+    ('\u{fe20}', '\u{fe2f}'),
+    ('\u{feff}', '\u{feff}'),
+];
+/// Characters a terminal draws two columns wide: the East Asian wide and fullwidth ones and emoji
+const DOUBLE_WIDTH: [(char, char); 17] = [
+    ('\u{1100}', '\u{115f}'),
+    ('\u{231a}', '\u{231b}'),
+    ('\u{2329}', '\u{232a}'),
+    ('\u{2e80}', '\u{303e}'),
+    ('\u{3041}', '\u{33ff}'),
+    ('\u{3400}', '\u{4dbf}'),
+    ('\u{4e00}', '\u{a4cf}'),
+    ('\u{a960}', '\u{a97f}'),
+    ('\u{ac00}', '\u{d7a3}'),
+    ('\u{f900}', '\u{faff}'),
+    ('\u{fe10}', '\u{fe19}'),
+    ('\u{fe30}', '\u{fe6f}'),
+    ('\u{ff00}', '\u{ff60}'),
+    ('\u{ffe0}', '\u{ffe6}'),
+    ('\u{1f300}', '\u{1f64f}'),
+    ('\u{1f680}', '\u{1f6ff}'),
+    ('\u{1f900}', '\u{1f9ff}'),
+];
+
+/// Returns `dir` with `$HOME` at its start written as `~`
+pub fn abbreviate_home(dir: &str, home: Option<&str>) -> String {
+    match home.filter(|home| !home.is_empty() && *home != "/") {
+        Some(home) if dir == home => "~".to_string(),
+        Some(home) if dir.starts_with(home) && dir[home.len()..].starts_with('/') => {
+            format!("~{}", &dir[home.len()..])
+        }
+        _ => dir.to_string(),
+    }
+}
+
+/// Returns how many columns a terminal uses to draw `c`
+pub fn char_width(c: char) -> usize {
+    let within = |ranges: &[(char, char)]| ranges.iter().any(|(first, last)| (*first..=*last).contains(&c));
+    if c.is_control() || within(&ZERO_WIDTH) || ('\u{e0100}'..='\u{e01ef}').contains(&c) {
+        0
+    }
+    else if within(&DOUBLE_WIDTH) || ('\u{20000}'..='\u{3fffd}').contains(&c) {
+        2
     }
     else {
-        write!(out, "{}", color::Fg(color::Red))?;
+        1
+    }
+}
+
+/// Returns how many columns the last line of `text` takes up on a terminal, leaving out escape
+/// sequences and anything between the `\x01` and `\x02` markers `\[` and `\]` leave
+pub fn visible_width(text: &str) -> usize {
+    let line = text.rsplit('\n').next().unwrap_or_default();
+    let mut width = 0;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x01' => {
+                chars.by_ref().take_while(|c| *c != '\x02').for_each(drop);
+            }
+            // a control sequence, ending with a letter, or an OSC, ending with BEL or ESC `\`
+            '\x1b' => match chars.next() {
+                Some('[') => {
+                    chars.by_ref().take_while(|c| !('@'..='~').contains(c)).for_each(drop);
+                }
+                Some(']') => {
+                    chars.by_ref().take_while(|c| *c != '\x07' && *c != '\x1b').for_each(drop);
+                }
+                _ => {}
+            },
+            '\r' => width = 0,
+            c if c.is_control() => {}
+            _ => width += char_width(c),
+        }
+    }
+    width
+}
+
+/// Expand the escapes in a `$PS1` style `template`:
+///
+/// * `\u` the user, `\h` the host up to the first `.`, `\H` the whole host
+/// * `\w` the working directory, abbreviated with `~` and `$PROMPT_DIRTRIM`, `\W` its last part
+/// * `\?` the exit code of the last command, `\S` the same as ` [127]` if it isn't 0 and nothing
+///   otherwise, `\j` the number of jobs
+/// * `\g` the status of the repository the working directory is in as ` (branch ...)`, or nothing
+///   outside a repository
+/// * `\D` how long the last command took as ` 4.2s`, if it took at least
+///   `$CMD_DURATION_THRESHOLD` milliseconds (2000 by default), or nothing
+/// * `\t` the time as `HH:MM:SS`, `\A` as `HH:MM`
+/// * `\$` `#` for root and `$` otherwise, `\n` a newline, `\e` an escape, `\a` a bell,
+///   `\\` a backslash
+/// * `\C{colour}` a colour (`red`, `green`, `reset`, etc.), where `status` is green if the last
+///   command succeeded and red otherwise
+/// * `\[` and `\]` around escape sequences which take up no columns
+pub fn expand_prompt(template: &str, info: &PromptInfo) -> Prompt {
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('u') => text += &info.user,
+            Some('h') => text += info.host.split('.').next().unwrap_or_default(),
+            Some('H') => text += &info.host,
+            Some('w') => text += &info.get_short_cwd(),
+            Some('W') => {
+                let cwd = info.get_short_cwd();
+                text += match cwd.as_str() {
+                    "/" | "~" => &cwd,
+                    _ => cwd.rsplit('/').next().unwrap_or_default(),
+                };
+            }
+            Some('?') => text += &status_code(&info.status).to_string(),
+            Some('S') => {
+                if !info.status.success() {
+                    text += &format!(" [{}]", status_code(&info.status));
+                }
+            }
+            Some('j') => text += &info.jobs.to_string(),
+            Some('g') => {
+                if let Some(git) = &info.git {
+                    text += &format!(" ({})", git);
+                }
+            }
+            Some('D') => {
+                if let Some(duration) = info.duration {
+                    text += &format!(" {}", format_duration(duration));
+                }
+            }
+            Some('t') => text += &format!("{:02}:{:02}:{:02}", info.time.0, info.time.1, info.time.2),
+            Some('A') => text += &format!("{:02}:{:02}", info.time.0, info.time.1),
+            Some('$') => text.push(if info.root { '#' } else { '$' }),
+            Some('n') => text.push('\n'),
+            Some('e') => text.push('\x1b'),
+            Some('a') => text.push('\x07'),
+            Some('\\') => text.push('\\'),
+            Some('[') => text.push('\x01'),
+            Some(']') => text.push('\x02'),
+            Some('C') if chars.peek() == Some(&'{') => {
+                chars.next();
+                let name: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                match get_color(&name, info) {
+                    Some(color) => text += &color,
+                    None => text += &format!("\\C{{{}}}", name),
+                }
+            }
+            Some(c) => {
+                text.push('\\');
+                text.push(c);
+            }
+            None => text.push('\\'),
+        }
     }
-    let prompt = format!("> ",);
 
-    write!(out, "{}", prompt)?;
-    write!(out, "{}", color::Fg(color::Reset))?;
-    Ok(prompt.len() + path.len())
+    let width = visible_width(&text);
+    // the markers only matter for working out the width
+    Prompt {
+        text: text.replace(['\x01', '\x02'], ""),
+        width,
+    }
+}
+
+fn expand_for_shell(template: &str, shell: &Shell) -> Prompt {
+    let mut info = PromptInfo::from_shell(shell);
+    // reading the repository takes a while, so it's only done for prompts which show it
+    if template.contains("\\g") {
+        info.git = get_git_status(Path::new(&info.cwd));
+    }
+    expand_prompt(template, &info)
+}
+
+/// Returns the prompt shown before reading a command, from `$PS1`
+pub fn get_prompt(shell: &Shell) -> Prompt {
+    let template = shell.get_variable("PS1").unwrap_or_else(|| DEFAULT_PS1.to_string());
+    expand_for_shell(&template, shell)
+}
+
+/// Returns the prompt shown at the right of the line a command is read on, from `$RPS1`, or `None`
+/// if there isn't one
+pub fn get_right_prompt(shell: &Shell) -> Option<Prompt> {
+    let template = shell.get_variable("RPS1").filter(|template| !template.is_empty())?;
+    let prompt = expand_for_shell(&template, shell);
+    // only the last line can be shown to the right of the command
+    let last_line = prompt.text.rsplit('\n').next().unwrap_or_default().to_string();
+    Some(Prompt {
+        text:  last_line,
+        width: prompt.width,
+    })
+}
+
+/// Write the prompt for `shell` to `out`, returning how many columns the line it leaves the
+/// cursor on takes up
+pub fn print_prompt<T>(shell: &Shell, out: &mut T) -> io::Result<usize>
+where
+    T: Write,
+{
+    let prompt = get_prompt(shell);
+    // the terminal is in raw mode, so each line of the prompt has to return to the first column
+    write!(out, "{}", prompt.text.replace('\n', "\r\n"))?;
+    Ok(prompt.width)
 }
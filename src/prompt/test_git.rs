@@ -0,0 +1,236 @@
+#[cfg(test)]
+mod git_tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::process::{Command, Output};
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::prompt::git_ignore::IgnoreRules;
+    use crate::prompt::{get_git_status_within, GitStatus};
+
+    /// A repository made with `git` in a directory of its own
+    struct Repo {
+        dir: PathBuf,
+    }
+
+    impl Repo {
+        fn new(test: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("yeesh-git-{}-{}", test, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            let repo = Repo { dir };
+            repo.git(&["init", "-q", "-b", "main"]);
+            repo
+        }
+
+        fn run_git(&self, args: &[&str]) -> Output {
+            Command::new("git")
+                .args(args)
+                .current_dir(&self.dir)
+                .env("GIT_CONFIG_NOSYSTEM", "1")
+                .env("GIT_CONFIG_GLOBAL", "/dev/null")
+                .env("GIT_AUTHOR_NAME", "Test")
+                .env("GIT_AUTHOR_EMAIL", "test@example.com")
+                .env("GIT_COMMITTER_NAME", "Test")
+                .env("GIT_COMMITTER_EMAIL", "test@example.com")
+                .output()
+                .unwrap()
+        }
+
+        /// Run `git` with `args`, checking that it succeeded, and returning what it printed
+        fn git(&self, args: &[&str]) -> String {
+            let output = self.run_git(args);
+            assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        }
+
+        fn write(&self, path: &str, contents: &str) {
+            let path = self.dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        fn commit(&self, message: &str) {
+            self.git(&["add", "-A"]);
+            self.git(&["commit", "-q", "-m", message]);
+        }
+
+        fn status(&self) -> GitStatus {
+            self.status_in(&self.dir)
+        }
+
+        fn status_in(&self, dir: &Path) -> GitStatus {
+            get_git_status_within(dir, Duration::from_secs(10)).unwrap()
+        }
+    }
+
+    /// Returns a file of numbered lines, with `line` changed to `change`, so that versions of it are
+    /// packed as deltas of each other
+    fn numbered_lines(line: usize, change: &str) -> String {
+        (0..200).map(|i| if i == line { format!("{}\n", change) } else { format!("line {}\n", i) }).collect()
+    }
+
+    #[test]
+    fn test_counts() {
+        let repo = Repo::new("counts");
+        let status = repo.status();
+        assert_eq!((status.branch.as_deref(), status.head, status.complete), (Some("main"), None, true));
+        assert_eq!(status.to_string(), "main");
+
+        repo.write("a.txt", "a\n");
+        repo.write("b.txt", "b\n");
+        repo.write("sub/c.txt", "c\n");
+        repo.write(".gitignore", "*.log\nbuild/\n");
+        repo.write("sub/.gitignore", "*.tmp\n");
+        assert_eq!(repo.status().untracked, 4);
+        repo.commit("first");
+        assert_eq!(repo.status().to_string(), "main");
+
+        repo.write("a.txt", "changed\n");
+        repo.write("b.txt", "staged\n");
+        repo.git(&["add", "b.txt"]);
+        repo.git(&["rm", "-q", "--cached", "sub/c.txt"]);
+        repo.write("new.txt", "");
+        repo.write("x.log", "");
+        repo.write("build/out", "");
+        repo.write("sub/y.tmp", "");
+        repo.write("y.tmp", "");
+        // a directory with nothing tracked in it counts once, unless everything in it is ignored
+        repo.write("newdir/deeper/file", "");
+        repo.write("logs/z.log", "");
+        let status = repo.status();
+        assert_eq!((status.staged, status.modified, status.untracked), (2, 1, 4));
+        assert_eq!(status.to_string(), "main +2 !1 ?4");
+
+        // a change which keeps the size has to be found from the contents
+        repo.write("b.txt", "STAGED\n");
+        assert_eq!(repo.status().modified, 2);
+        fs::remove_file(repo.dir.join("a.txt")).unwrap();
+        assert_eq!(repo.status().modified, 2);
+
+        // the status is the same from inside the repository
+        assert_eq!(repo.status_in(&repo.dir.join("newdir/deeper")), repo.status());
+    }
+
+    #[test]
+    fn test_ahead_behind() {
+        let repo = Repo::new("ahead-behind");
+        repo.write("file", &numbered_lines(0, "first"));
+        repo.commit("first");
+        repo.git(&["branch", "base"]);
+        repo.git(&["branch", "-q", "-u", "base"]);
+        for i in 1..4 {
+            repo.write("file", &numbered_lines(i, "ours"));
+            repo.commit("ours");
+        }
+        repo.git(&["checkout", "-q", "base"]);
+        repo.write("file", &numbered_lines(100, "theirs"));
+        repo.commit("theirs");
+        repo.git(&["checkout", "-q", "main"]);
+
+        let status = repo.status();
+        assert_eq!((status.ahead, status.behind, status.staged), (3, 1, 0));
+        assert_eq!(status.to_string(), "main ↑3↓1");
+
+        // once packed, objects are read from the pack, mostly as deltas, and refs from `packed-refs`
+        repo.git(&["gc", "-q", "--aggressive"]);
+        assert!(!repo.dir.join(".git/refs/heads/main").exists());
+        let packed = repo.status();
+        assert_eq!(packed, status);
+
+        repo.git(&["update-index", "--index-version", "4"]);
+        repo.write("file", &numbered_lines(150, "changed"));
+        repo.git(&["add", "file"]);
+        assert_eq!(repo.status().to_string(), "main ↑3↓1 +1");
+    }
+
+    #[test]
+    fn test_detached_and_operations() {
+        let repo = Repo::new("operations");
+        repo.write("file", "base\n");
+        repo.commit("base");
+        repo.git(&["checkout", "-q", "-b", "other"]);
+        repo.write("file", "other\n");
+        repo.commit("other");
+        repo.git(&["checkout", "-q", "main"]);
+        repo.write("file", "main\n");
+        repo.commit("main");
+
+        repo.git(&["checkout", "-q", "--detach"]);
+        let short = repo.git(&["rev-parse", "--short=7", "HEAD"]);
+        let status = repo.status();
+        assert_eq!(status.branch, None);
+        assert_eq!(status.to_string(), format!("({})", short));
+        repo.git(&["checkout", "-q", "main"]);
+
+        assert!(!repo.run_git(&["merge", "-q", "other"]).status.success());
+        let status = repo.status();
+        assert_eq!((status.conflicted, status.staged), (1, 0));
+        assert_eq!(status.to_string(), "main x1|MERGING");
+        repo.git(&["merge", "--abort"]);
+
+        // the branch being rebased is shown rather than the detached HEAD
+        assert!(!repo.run_git(&["rebase", "-q", "other"]).status.success());
+        let status = repo.status();
+        assert_eq!((status.branch.as_deref(), status.operation.as_deref()), (Some("main"), Some("REBASE 1/1")));
+    }
+
+    #[test]
+    fn test_budget() {
+        let repo = Repo::new("budget");
+        repo.write("file", "contents\n");
+        repo.commit("first");
+
+        let status = get_git_status_within(&repo.dir, Duration::ZERO).unwrap();
+        assert!(!status.complete);
+        assert!(status.to_string().ends_with(" …"));
+        // counts which ran out of time are tried again at the next prompt, with the same index
+        repo.write("untracked", "");
+        let status = repo.status();
+        assert!(status.complete);
+        assert_eq!(status.to_string(), "main ?1");
+
+        repo.git(&["add", "untracked"]);
+        let status = repo.status();
+        assert!(status.complete);
+        assert_eq!(status.to_string(), "main +1");
+
+        // a change to the index is noticed by its size, even if its modification time is the same
+        let (index, saved) = (repo.dir.join(".git/index"), repo.dir.join("saved-index"));
+        fs::copy(&index, &saved).unwrap();
+        Command::new("touch").arg("-r").arg(&index).arg(&saved).status().unwrap();
+        repo.write("other", "");
+        repo.git(&["add", "other"]);
+        Command::new("touch").arg("-r").arg(&saved).arg(&index).status().unwrap();
+        assert_eq!(repo.status().to_string(), "main +2 ?1");
+    }
+
+    #[test]
+    fn test_not_a_repository() {
+        let dir = std::env::temp_dir().join(format!("yeesh-git-none-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        assert_eq!(get_git_status_within(&dir, Duration::from_secs(1)), None);
+    }
+
+    #[test]
+    fn test_ignore_rules() {
+        let mut rules = IgnoreRules::new();
+        rules.add_patterns("", "# comment\n*.o\n!keep.o\n/build\nlogs/\ndoc/**/*.pdf\na?c\n[!x]y\n\\#hash\n");
+        rules.add_patterns("sub/", "local\n/anchored\n");
+
+        let ignored = |path: &str, is_dir: bool| rules.is_ignored(path, is_dir);
+        assert!(ignored("x.o", false) && ignored("deep/x.o", false));
+        assert!(!ignored("keep.o", false) && !ignored("deep/keep.o", false));
+        assert!(ignored("build", true) && ignored("build", false) && !ignored("src/build", true));
+        assert!(ignored("logs", true) && ignored("src/logs", true) && !ignored("logs", false));
+        assert!(ignored("doc/a.pdf", false) && ignored("doc/a/b/c.pdf", false) && !ignored("a.pdf", false));
+        assert!(ignored("abc", false) && !ignored("a/c", false));
+        assert!(ignored("ay", false) && !ignored("xy", false));
+        assert!(ignored("#hash", false));
+
+        assert!(ignored("sub/local", false) && ignored("sub/a/local", false) && !ignored("local", false));
+        assert!(ignored("sub/anchored", false) && !ignored("sub/a/anchored", false));
+    }
+}
@@ -0,0 +1,113 @@
+#[cfg(test)]
+mod prompt_tests {
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+    use termion::color;
+
+    use crate::prompt::{abbreviate_home, expand_prompt, visible_width, PromptInfo};
+
+    fn info() -> PromptInfo {
+        PromptInfo {
+            user:     "me".to_string(),
+            host:     "box.example.com".to_string(),
+            cwd:      "/home/me/src/yeesh".to_string(),
+            home:     Some("/home/me".to_string()),
+            status:   ExitStatus::from_raw(0),
+            jobs:     2,
+            time:     (9, 5, 3),
+            root:     false,
+            dir_trim: 0,
+            git:      None,
+            duration: None,
+        }
+    }
+
+    fn expand(template: &str, info: &PromptInfo) -> (String, usize) {
+        let prompt = expand_prompt(template, info);
+        (prompt.text, prompt.width)
+    }
+
+    #[test]
+    fn test_escapes() {
+        let info = info();
+        assert_eq!(expand("\\u@\\h:\\w\\$ ", &info), ("me@box:~/src/yeesh$ ".to_string(), 20));
+        assert_eq!(expand("\\H \\W \\j \\?", &info).0, "box.example.com yeesh 2 0");
+        assert_eq!(expand("\\t \\A \\\\ \\x", &info).0, "09:05:03 09:05 \\ \\x");
+
+        let root = PromptInfo {
+            root: true,
+            status: ExitStatus::from_raw(3 << 8),
+            ..info.clone()
+        };
+        assert_eq!(expand("\\? \\$", &root).0, "3 #");
+        assert_eq!(expand("\\w\\S>", &info).0, "~/src/yeesh>");
+        assert_eq!(expand("\\w\\S>", &root).0, "~/src/yeesh [3]>");
+        let killed = PromptInfo {
+            status: ExitStatus::from_raw(libc::SIGSEGV),
+            ..info.clone()
+        };
+        assert_eq!(expand("\\S", &killed).0, " [139]");
+
+        // the duration is only shown if the command was slow
+        assert_eq!(expand("\\w\\D>", &info).0, "~/src/yeesh>");
+        let slow = PromptInfo {
+            duration: Some(Duration::from_millis(4250)),
+            ..info.clone()
+        };
+        assert_eq!(expand("\\w\\D>", &slow), ("~/src/yeesh 4.2s>".to_string(), 17));
+
+        // only the last line counts towards the width
+        assert_eq!(expand("\\w\\n> ", &info), ("~/src/yeesh\n> ".to_string(), 2));
+    }
+
+    #[test]
+    fn test_working_dir() {
+        let mut info = info();
+        info.dir_trim = 2;
+        assert_eq!(expand("\\w", &info).0, "~/src/yeesh");
+        info.dir_trim = 1;
+        assert_eq!(expand("\\w", &info).0, "~/.../yeesh");
+        info.dir_trim = 2;
+        info.cwd = "/var/log/app/today".to_string();
+        assert_eq!(expand("\\w", &info).0, "/.../app/today");
+        info.cwd = "/home/me".to_string();
+        assert_eq!(expand("\\w \\W", &info).0, "~ ~");
+        info.cwd = "/home/meme".to_string();
+        assert_eq!(expand("\\w", &info).0, "/home/meme");
+        info.cwd = "/".to_string();
+        assert_eq!(expand("\\W", &info).0, "/");
+    }
+
+    #[test]
+    fn test_colors_and_width() {
+        let mut info = info();
+        let green = color::Fg(color::Green).to_string();
+        let red = color::Fg(color::Red).to_string();
+        let reset = color::Fg(color::Reset).to_string();
+
+        assert_eq!(expand("\\C{status}> \\C{reset}", &info), (format!("{}> {}", green, reset), 2));
+        info.status = ExitStatus::from_raw(1 << 8);
+        assert_eq!(expand("\\C{status}>", &info), (format!("{}>", red), 1));
+        assert_eq!(expand("\\C{nope}>", &info), ("\\C{nope}>".to_string(), 9));
+
+        // `\[` and `\]` mark text which takes up no columns, and are left out
+        assert_eq!(expand("\\[\\e]0;title\\a\\]$ ", &info), ("\x1b]0;title\x07$ ".to_string(), 2));
+        assert_eq!(visible_width("\x1b]0;title\x07\x1b[1;31mab\rc"), 1);
+
+        // wide characters take up two columns and combining marks none
+        assert_eq!(visible_width("日本 ~/é🦀> "), 12);
+        assert_eq!(visible_width("e\u{301}\u{200d}"), 1);
+    }
+
+    #[test]
+    fn test_abbreviate_home() {
+        assert_eq!(abbreviate_home("/home/me/src", Some("/home/me")), "~/src");
+        assert_eq!(abbreviate_home("/home/me", Some("/home/me")), "~");
+        assert_eq!(abbreviate_home("/home/meg", Some("/home/me")), "/home/meg");
+        assert_eq!(abbreviate_home("/src", Some("/")), "/src");
+        assert_eq!(abbreviate_home("/src", None), "/src");
+    }
+}
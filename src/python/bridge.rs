@@ -0,0 +1,148 @@
+use std::cell::RefCell;
+
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyString;
+
+use crate::cmd_input::SuggesterRegistry;
+use crate::intrinsics::IntrinsicRegistry;
+
+/// What Python snippets can do to the shell running them
+pub trait ShellBridge {
+    /// Returns the value of a variable or parameter, as `$name` gives in the shell
+    fn get_variable(&self, name: &str) -> Option<String>;
+    /// Set the variable `name`, returning an error if it isn't a valid variable name
+    fn set_variable(&mut self, name: &str, value: &str) -> Result<(), String>;
+    fn unset_variable(&mut self, name: &str);
+    /// Run `command` as if it had been typed at the prompt, capturing its output
+    fn run_command(&mut self, command: &str) -> CommandResult;
+    /// Returns the intrinsics of the shell, which snippets can add to
+    fn get_intrinsics(&self) -> IntrinsicRegistry;
+    /// Returns the suggesters of the shell, which snippets can add completers to
+    fn get_suggesters(&self) -> SuggesterRegistry;
+}
+
+thread_local! {
+    /// The shells running snippets, innermost last, as a snippet can run a command which runs
+    /// another snippet
+    static BRIDGES: RefCell<Vec<*mut dyn ShellBridge>> = RefCell::new(vec![]);
+}
+
+/// Makes a shell available to snippets until it is dropped
+pub struct BridgeGuard<'a> {
+    _shell: std::marker::PhantomData<&'a mut dyn ShellBridge>,
+}
+
+impl<'a> BridgeGuard<'a> {
+    pub fn new(shell: &'a mut dyn ShellBridge) -> Self {
+        let shell: *mut (dyn ShellBridge + 'a) = shell;
+        // the pointer is removed from BRIDGES when the guard is dropped, before 'a ends
+        let shell: *mut (dyn ShellBridge + 'static) = unsafe { std::mem::transmute(shell) };
+        BRIDGES.with(|bridges| bridges.borrow_mut().push(shell));
+        BridgeGuard {
+            _shell: std::marker::PhantomData,
+        }
+    }
+}
+
+impl Drop for BridgeGuard<'_> {
+    fn drop(&mut self) {
+        BRIDGES.with(|bridges| bridges.borrow_mut().pop());
+    }
+}
+
+/// Call `f` with the shell running the current snippet
+pub(crate) fn with_shell<T>(f: impl FnOnce(&mut dyn ShellBridge) -> T) -> PyResult<T> {
+    let shell = BRIDGES.with(|bridges| bridges.borrow().last().copied());
+    match shell {
+        // the guard which pushed the pointer holds the only borrow of the shell, and is still alive
+        Some(shell) => Ok(f(unsafe { &mut *shell })),
+        None => Err(PyRuntimeError::new_err("not running in a shell")),
+    }
+}
+
+/// The result of a shell command run with `${command}`. It is true if the command succeeded, and
+/// converts to a string as its output.
+#[pyclass]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommandResult {
+    #[pyo3(get)]
+    pub stdout: String,
+    #[pyo3(get)]
+    pub stderr: String,
+    #[pyo3(get)]
+    pub status: i32,
+}
+
+#[pymethods]
+impl CommandResult {
+    fn __str__(&self) -> String {
+        self.stdout.clone()
+    }
+
+    fn __repr__(&self, py: Python) -> PyResult<String> {
+        let repr = |text: &str| -> PyResult<String> { Ok(PyString::new(py, text).repr()?.to_str()?.to_string()) };
+        Ok(format!(
+            "CommandResult(stdout={}, stderr={}, status={})",
+            repr(&self.stdout)?,
+            repr(&self.stderr)?,
+            self.status
+        ))
+    }
+
+    fn __bool__(&self) -> bool {
+        self.status == 0
+    }
+
+    /// The lines of the output, without their line endings
+    fn lines(&self) -> Vec<String> {
+        self.stdout.lines().map(|line| line.to_string()).collect()
+    }
+}
+
+/// The shell's variables, as a mapping. Unset variables read as empty, as they expand in the
+/// shell, and assigned values are converted to strings.
+#[pyclass]
+struct ShellVariables;
+
+#[pymethods]
+impl ShellVariables {
+    fn __getitem__(&self, name: &str) -> PyResult<String> {
+        with_shell(|shell| shell.get_variable(name).unwrap_or_default())
+    }
+
+    fn __setitem__(&self, name: &str, value: &PyAny) -> PyResult<()> {
+        let value = value.str()?.to_str()?;
+        with_shell(|shell| shell.set_variable(name, value))?.map_err(PyValueError::new_err)
+    }
+
+    fn __delitem__(&self, name: &str) -> PyResult<()> {
+        with_shell(|shell| shell.unset_variable(name))
+    }
+
+    fn __contains__(&self, name: &str) -> PyResult<bool> {
+        with_shell(|shell| shell.get_variable(name).is_some())
+    }
+}
+
+/// The object snippets reach the shell through, once `$` syntax has been rewritten
+#[pyclass]
+pub struct Bridge {
+    #[pyo3(get)]
+    vars: Py<ShellVariables>,
+}
+
+impl Bridge {
+    pub fn new(py: Python) -> PyResult<Self> {
+        Ok(Bridge {
+            vars: Py::new(py, ShellVariables)?,
+        })
+    }
+}
+
+#[pymethods]
+impl Bridge {
+    fn run(&self, command: &str) -> PyResult<CommandResult> {
+        with_shell(|shell| shell.run_command(command))
+    }
+}
@@ -0,0 +1,106 @@
+use log::debug;
+use pyo3::exceptions::PySystemExit;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::python::{add_module, preprocess, Bridge, BridgeGuard, ShellBridge, BRIDGE_NAME};
+
+/// The name Python gives snippets in tracebacks
+const SNIPPET_NAME: &str = "<yeesh>";
+
+/// What a Python snippet wrote, and the status it finished with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PythonOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// 0 if the snippet ran to the end, the code given to `sys.exit`, or 1 if it raised
+    pub status: i32,
+}
+
+/// An embedded Python interpreter, whose global variables, imports and definitions persist from
+/// one snippet to the next
+pub struct PythonInterpreter {
+    globals: Py<PyDict>,
+}
+
+impl PythonInterpreter {
+    pub fn new() -> Self {
+        Python::with_gil(|py| {
+            add_module(py).unwrap();
+            let globals = PyDict::new(py);
+            globals.set_item("__name__", "__main__").unwrap();
+            globals.set_item("__builtins__", py.import("builtins").unwrap()).unwrap();
+            globals.set_item(BRIDGE_NAME, Py::new(py, Bridge::new(py).unwrap()).unwrap()).unwrap();
+            PythonInterpreter {
+                globals: globals.into(),
+            }
+        })
+    }
+
+    /// Run `code` for `shell`, capturing what it writes to `sys.stdout` and `sys.stderr`. A
+    /// single line is run as at the Python prompt, so the value of an expression is printed.
+    pub fn run(&self, code: &str, shell: &mut dyn ShellBridge) -> PythonOutput {
+        let code = preprocess(code);
+        debug!("Running Python snippet '{}'", code);
+        let _guard = BridgeGuard::new(shell);
+        Python::with_gil(|py| {
+            self.run_captured(py, &code).unwrap_or_else(|e| PythonOutput {
+                stdout: String::new(),
+                stderr: format!("py: {}\n", e),
+                status: 1,
+            })
+        })
+    }
+
+    fn run_captured(&self, py: Python, code: &str) -> PyResult<PythonOutput> {
+        let sys = py.import("sys")?;
+        let io = py.import("io")?;
+        let saved = (sys.getattr("stdout")?, sys.getattr("stderr")?);
+        let (stdout, stderr) = (io.call_method0("StringIO")?, io.call_method0("StringIO")?);
+        sys.setattr("stdout", stdout)?;
+        sys.setattr("stderr", stderr)?;
+
+        let status = match self.exec(py, code) {
+            Ok(()) => 0,
+            Err(e) if e.is_instance_of::<PySystemExit>(py) => exit_status(py, &e),
+            Err(e) => {
+                // printed to the captured sys.stderr
+                e.print(py);
+                1
+            }
+        };
+
+        sys.setattr("stdout", saved.0)?;
+        sys.setattr("stderr", saved.1)?;
+        Ok(PythonOutput {
+            stdout: stdout.call_method0("getvalue")?.extract()?,
+            stderr: stderr.call_method0("getvalue")?.extract()?,
+            status,
+        })
+    }
+
+    fn exec(&self, py: Python, code: &str) -> PyResult<()> {
+        let mode = if code.contains('\n') { "exec" } else { "single" };
+        let builtins = py.import("builtins")?;
+        let compiled = builtins.getattr("compile")?.call1((code, SNIPPET_NAME, mode))?;
+        builtins.getattr("exec")?.call1((compiled, self.globals.as_ref(py)))?;
+        Ok(())
+    }
+}
+
+/// Returns the status given to `sys.exit`, as Python itself would exit with. Any other value
+/// is written to `sys.stderr` and gives a status of 1.
+fn exit_status(py: Python, exit: &PyErr) -> i32 {
+    let code = match exit.value(py).getattr("code") {
+        Ok(code) => code,
+        Err(_) => return 1,
+    };
+    if code.is_none() {
+        return 0;
+    }
+    code.extract().unwrap_or_else(|_| {
+        let message = format!("{}\n", code);
+        let _ = py.import("sys").and_then(|sys| sys.getattr("stderr")?.call_method1("write", (message,)));
+        1
+    })
+}
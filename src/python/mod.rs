@@ -0,0 +1,19 @@
+// the code pyo3's `#[pyfunction]` and `#[pymethods]` generate predates the 2024 edition
+#![allow(unsafe_op_in_unsafe_fn, non_local_definitions)]
+
+mod bridge;
+mod interpreter;
+mod plugins;
+mod preprocess;
+
+#[cfg(test)]
+mod test_interpreter;
+#[cfg(test)]
+mod test_plugins;
+#[cfg(test)]
+mod test_preprocess;
+
+pub use bridge::*;
+pub use interpreter::*;
+pub use plugins::*;
+pub use preprocess::*;
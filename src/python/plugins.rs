@@ -0,0 +1,180 @@
+use log::error;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple};
+use pyo3::wrap_pyfunction;
+
+use crate::cmd_input::completion_context::{Applicability, CompletionContext};
+use crate::cmd_input::suggester::{Suggester, Suggestion, SuggestionType};
+use crate::intrinsics::{OutputAdapter, OutputIntrinsic};
+use crate::python::{with_shell, BridgeGuard};
+use crate::shell::Shell;
+
+/// The name of the module snippets import to extend the shell
+pub const MODULE_NAME: &str = "yeesh";
+
+/// Decorators defined in the module on top of its functions
+const DECORATORS: &str = r#"
+def intrinsic(name, description=""):
+    """Register the decorated function as an intrinsic called `name`"""
+    def register(handler):
+        register_intrinsic(name, description, handler)
+        return handler
+    return register
+
+def completer(command=None):
+    """Register the decorated function as a completer, for the arguments of `command` if given"""
+    def register(function):
+        register_completer(function, command)
+        return function
+    return register
+"#;
+
+/// An intrinsic whose handler is a Python function, called with the list of arguments. What the
+/// function returns, unless it is `None`, is the output of the intrinsic, and an exception makes
+/// the intrinsic fail.
+pub struct PythonIntrinsic {
+    command:     &'static str,
+    description: &'static str,
+    handler:     PyObject,
+}
+
+impl PythonIntrinsic {
+    pub fn new(command: &str, description: &str, handler: PyObject) -> Self {
+        // `Intrinsic` gives its names as static strings, and registered intrinsics are never freed
+        PythonIntrinsic {
+            command: Box::leak(command.into()),
+            description: Box::leak(description.into()),
+            handler,
+        }
+    }
+
+    fn call(&self, py: Python, args: &[String]) -> PyResult<String> {
+        let result = self.handler.call1(py, (args.to_vec(),))?;
+        if result.is_none(py) {
+            return Ok(String::new());
+        }
+
+        let mut output = result.as_ref(py).str()?.to_str()?.to_string();
+        if !output.is_empty() && !output.ends_with('\n') {
+            output.push('\n');
+        }
+        Ok(output)
+    }
+}
+
+impl OutputIntrinsic for PythonIntrinsic {
+    fn get_command(&self) -> &'static str {
+        self.command
+    }
+
+    fn get_description(&self) -> &'static str {
+        self.description
+    }
+
+    fn handler(&self, shell: &mut Shell, args: &[String]) -> Result<String, String> {
+        // let the handler reach the shell running it, e.g. with `$name`
+        let _bridge = BridgeGuard::new(shell);
+        Python::with_gil(|py| self.call(py, args)).map_err(|e| format!("{}: {}\n", self.command, e))
+    }
+}
+
+/// A suggester which calls a Python function with the prefix being completed and a dict of the
+/// `command`, `previous` token and `token_idx`. The function returns the replacements, each
+/// either a string or a `(replacement, description)` tuple.
+pub struct PythonSuggester {
+    /// the command whose arguments this completes, or `None` for every token
+    command:  Option<String>,
+    function: PyObject,
+}
+
+impl PythonSuggester {
+    pub fn new(function: PyObject, command: Option<String>) -> Self {
+        PythonSuggester { command, function }
+    }
+
+    fn call(&self, py: Python, context: &CompletionContext) -> PyResult<Vec<Suggestion>> {
+        let details = PyDict::new(py);
+        details.set_item("command", &context.command)?;
+        details.set_item("previous", &context.previous)?;
+        details.set_item("token_idx", context.token_idx)?;
+        let results = self.function.call1(py, (context.prefix.as_str(), details))?;
+
+        let mut suggestions = vec![];
+        for result in results.as_ref(py).iter()? {
+            let result = result?;
+            let (replacement, description) = match result.downcast::<PyTuple>() {
+                Ok(tuple) => tuple.extract::<(String, String)>().map(|(r, d)| (r, Some(d)))?,
+                Err(_) => (result.extract::<String>()?, None),
+            };
+            suggestions.push(Suggestion::new(
+                replacement,
+                &context.prefix,
+                SuggestionType::Registered,
+                description,
+            ));
+        }
+        Ok(suggestions)
+    }
+}
+
+impl Suggester for PythonSuggester {
+    fn get_suggestions(&mut self, context: &CompletionContext) -> Vec<Suggestion> {
+        Python::with_gil(|py| self.call(py, context)).unwrap_or_else(|e| {
+            error!("Python completer failed for prefix '{}': {}", context.prefix, e);
+            vec![]
+        })
+    }
+
+    fn get_applicability(&self, context: &CompletionContext) -> Applicability {
+        match &self.command {
+            Some(command) if !context.is_argument_of(&[command]) => Applicability::NotApplicable,
+            _ => Applicability::Applicable,
+        }
+    }
+
+    #[cfg(test)]
+    fn get_get_suggestion_count(&self) -> usize {
+        0
+    }
+}
+
+fn check_callable(function: &PyAny) -> PyResult<()> {
+    if function.is_callable() {
+        Ok(())
+    }
+    else {
+        Err(PyTypeError::new_err(format!("'{}' object is not callable", function.get_type().name()?)))
+    }
+}
+
+/// `yeesh.register_intrinsic(name, description, handler)`
+#[pyfunction]
+#[pyo3(name = "register_intrinsic")]
+fn py_register_intrinsic(py: Python, name: &str, description: &str, handler: PyObject) -> PyResult<()> {
+    check_callable(handler.as_ref(py))?;
+    if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '/') {
+        return Err(PyValueError::new_err(format!("'{}': not a valid command name", name)));
+    }
+    let intrinsic = PythonIntrinsic::new(name, description, handler);
+    with_shell(|shell| shell.get_intrinsics().register(Box::new(OutputAdapter(intrinsic))))
+}
+
+/// `yeesh.register_completer(function, command=None)`
+#[pyfunction]
+#[pyo3(name = "register_completer", signature = (function, command = None))]
+fn py_register_completer(py: Python, function: PyObject, command: Option<String>) -> PyResult<()> {
+    check_callable(function.as_ref(py))?;
+    let suggester = PythonSuggester::new(function, command);
+    with_shell(|shell| shell.get_suggesters().register(Box::new(suggester)))
+}
+
+/// Create the `yeesh` module and make it importable
+pub fn add_module(py: Python) -> PyResult<()> {
+    let module = PyModule::new(py, MODULE_NAME)?;
+    module.add_function(wrap_pyfunction!(py_register_intrinsic, module)?)?;
+    module.add_function(wrap_pyfunction!(py_register_completer, module)?)?;
+    py.run(DECORATORS, Some(module.dict()), None)?;
+    py.import("sys")?.getattr("modules")?.set_item(MODULE_NAME, module)?;
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// The name of the object through which snippets reach the shell
+pub const BRIDGE_NAME: &str = "__yeesh__";
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Returns `text` as a Python string literal
+fn quote(text: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            _ if c.is_control() => quoted.push_str(&format!("\\x{:02x}", c as u32)),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Rewrite the shell syntax in a Python snippet into calls on the bridge: `$name` reads or
+/// assigns the shell variable `name` (or a parameter such as `$1` or `$?`), and `${command}`
+/// runs `command` and gives its result. `$` in strings and comments is left alone, as is a `${`
+/// without a matching `}`, which Python then reports.
+pub fn preprocess(code: &str) -> String {
+    let mut output = String::new();
+    let mut chars = code.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '#' => {
+                output.push(c);
+                while let Some(c) = chars.next_if(|c| *c != '\n') {
+                    output.push(c);
+                }
+            }
+            '\'' | '"' => copy_string(c, &mut chars, &mut output),
+            '$' => output.push_str(&rewrite_dollar(&mut chars)),
+            _ => output.push(c),
+        }
+    }
+
+    output
+}
+
+/// Copy a string literal whose opening `quote` has been read. Prefixes such as `f` or `rb` are
+/// copied as names beforehand, and backslashes escape the next character even in raw strings, as
+/// far as finding the end of the string goes.
+fn copy_string(quote: char, chars: &mut Peekable<Chars>, output: &mut String) {
+    output.push(quote);
+    let mut lookahead = chars.clone();
+    let triple = lookahead.next() == Some(quote) && lookahead.next() == Some(quote);
+    if triple {
+        output.push(chars.next().unwrap());
+        output.push(chars.next().unwrap());
+    }
+    else if chars.peek() == Some(&quote) {
+        // the empty string
+        output.push(chars.next().unwrap());
+        return;
+    }
+
+    let mut closing = 0;
+    while let Some(c) = chars.next() {
+        output.push(c);
+        if c == '\\' {
+            output.extend(chars.next());
+            closing = 0;
+        }
+        else if c == quote {
+            closing += 1;
+            if !triple || closing == 3 {
+                return;
+            }
+        }
+        else if c == '\n' && !triple {
+            return;
+        }
+        else {
+            closing = 0;
+        }
+    }
+}
+
+/// Rewrite the shell syntax following a `$`
+fn rewrite_dollar(chars: &mut Peekable<Chars>) -> String {
+    match chars.peek() {
+        Some('{') => {
+            let mut lookahead = chars.clone();
+            lookahead.next();
+            let mut command = String::new();
+            let mut depth = 0;
+            for c in lookahead.by_ref() {
+                match c {
+                    '{' => depth += 1,
+                    '}' if depth == 0 => {
+                        *chars = lookahead;
+                        return format!("{}.run({})", BRIDGE_NAME, quote(command.trim()));
+                    }
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+                command.push(c);
+            }
+            "$".to_string()
+        }
+        Some('?') => {
+            chars.next();
+            format!("{}.vars[\"?\"]", BRIDGE_NAME)
+        }
+        Some(&c) if is_name_char(c) => {
+            let mut lookahead = chars.clone();
+            let mut name = String::new();
+            while let Some(c) = lookahead.next_if(|c| is_name_char(*c)) {
+                name.push(c);
+            }
+            // positional parameters are all digits, as in `$1`, and nothing else starts with one,
+            // so something like `$1x` is left for Python to report
+            if c.is_ascii_digit() && !name.chars().all(|c| c.is_ascii_digit()) {
+                return "$".to_string();
+            }
+            *chars = lookahead;
+            format!("{}.vars[{}]", BRIDGE_NAME, quote(&name))
+        }
+        _ => "$".to_string(),
+    }
+}
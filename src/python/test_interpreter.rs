@@ -0,0 +1,134 @@
+#[cfg(test)]
+mod interpreter_tests {
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::cmd_input::SuggesterRegistry;
+    use crate::intrinsics::IntrinsicRegistry;
+    use crate::python::{CommandResult, PythonInterpreter, PythonOutput, ShellBridge};
+
+    /// A shell whose commands print their own text, and fail if they start with `false`
+    #[derive(Default)]
+    struct FakeShell {
+        variables:  HashMap<String, String>,
+        intrinsics: IntrinsicRegistry,
+        suggesters: SuggesterRegistry,
+    }
+
+    impl ShellBridge for FakeShell {
+        fn get_variable(&self, name: &str) -> Option<String> {
+            self.variables.get(name).cloned()
+        }
+
+        fn set_variable(&mut self, name: &str, value: &str) -> Result<(), String> {
+            if name == "1" {
+                return Err("'1': not a valid identifier".to_string());
+            }
+            self.variables.insert(name.to_string(), value.to_string());
+            Ok(())
+        }
+
+        fn unset_variable(&mut self, name: &str) {
+            self.variables.remove(name);
+        }
+
+        fn run_command(&mut self, command: &str) -> CommandResult {
+            CommandResult {
+                stdout: format!("{}\n", command),
+                stderr: String::new(),
+                status: command.starts_with("false") as i32,
+            }
+        }
+
+        fn get_intrinsics(&self) -> IntrinsicRegistry {
+            self.intrinsics.clone()
+        }
+
+        fn get_suggesters(&self) -> SuggesterRegistry {
+            self.suggesters.clone()
+        }
+    }
+
+    fn output(stdout: &str, stderr: &str, status: i32) -> PythonOutput {
+        PythonOutput {
+            stdout: stdout.to_string(),
+            stderr: stderr.to_string(),
+            status,
+        }
+    }
+
+    #[test]
+    fn test_state_persists() {
+        let interpreter = PythonInterpreter::new();
+        let mut shell = FakeShell::default();
+        assert_eq!(interpreter.run("import math; x = 2", &mut shell), output("", "", 0));
+        assert_eq!(interpreter.run("def double(n):\n    return n * 2\n", &mut shell), output("", "", 0));
+        assert_eq!(interpreter.run("print(double(x), math.floor(2.5))", &mut shell), output("4 2\n", "", 0));
+    }
+
+    #[test]
+    fn test_expression_is_printed() {
+        let interpreter = PythonInterpreter::new();
+        let mut shell = FakeShell::default();
+        assert_eq!(interpreter.run("1 + 2", &mut shell), output("3\n", "", 0));
+        assert_eq!(interpreter.run("None", &mut shell), output("", "", 0));
+        // only a single line is run as at the prompt
+        assert_eq!(interpreter.run("1 + 2\n'unprinted'", &mut shell), output("", "", 0));
+    }
+
+    #[test]
+    fn test_errors() {
+        let interpreter = PythonInterpreter::new();
+        let mut shell = FakeShell::default();
+        let result = interpreter.run("print('before')\nraise ValueError('bad value')", &mut shell);
+        assert_eq!(result.stdout, "before\n");
+        assert!(result.stderr.contains("ValueError: bad value"));
+        assert_eq!(result.status, 1);
+
+        let result = interpreter.run("if True print(1)", &mut shell);
+        assert!(result.stderr.contains("SyntaxError"));
+        assert_eq!(result.status, 1);
+    }
+
+    #[test]
+    fn test_exit() {
+        let interpreter = PythonInterpreter::new();
+        let mut shell = FakeShell::default();
+        assert_eq!(interpreter.run("import sys; sys.exit(3)", &mut shell), output("", "", 3));
+        assert_eq!(interpreter.run("sys.exit()", &mut shell), output("", "", 0));
+        assert_eq!(interpreter.run("sys.exit('failed')", &mut shell), output("", "failed\n", 1));
+    }
+
+    #[test]
+    fn test_variables() {
+        let interpreter = PythonInterpreter::new();
+        let mut shell = FakeShell::default();
+        shell.variables.insert("a".to_string(), "1".to_string());
+        assert_eq!(interpreter.run("$b = int($a) + 1; $c = $unset + 'x'", &mut shell), output("", "", 0));
+        assert_eq!(shell.variables.get("b").map(String::as_str), Some("2"));
+        assert_eq!(shell.variables.get("c").map(String::as_str), Some("x"));
+
+        assert_eq!(interpreter.run("$b += '0'; del $a", &mut shell), output("", "", 0));
+        assert_eq!(shell.variables.get("b").map(String::as_str), Some("20"));
+        let code = "'a' in __yeesh__.vars, 'b' in __yeesh__.vars";
+        assert_eq!(interpreter.run(code, &mut shell), output("(False, True)\n", "", 0));
+
+        let result = interpreter.run("$1 = 'x'", &mut shell);
+        assert!(result.stderr.contains("ValueError: '1': not a valid identifier"));
+        let result = interpreter.run("print($1x)", &mut shell);
+        assert!(result.stderr.contains("SyntaxError"));
+    }
+
+    #[test]
+    fn test_commands() {
+        let interpreter = PythonInterpreter::new();
+        let mut shell = FakeShell::default();
+        let code = "r = ${echo {a}}\nprint(r.status, repr(str(r)), r.lines(), bool(${false}))";
+        assert_eq!(interpreter.run(code, &mut shell), output("0 'echo {a}\\n' ['echo {a}'] False\n", "", 0));
+        assert_eq!(
+            interpreter.run("${ls}", &mut shell),
+            output("CommandResult(stdout='ls\\n', stderr='', status=0)\n", "", 0)
+        );
+    }
+}
@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod plugins_tests {
+    use pretty_assertions::assert_eq;
+    use pyo3::prelude::*;
+
+    use crate::cmd_input::completion_context::{Applicability, CompletionContext};
+    use crate::cmd_input::suggester::{Suggester, SuggestionType};
+    use crate::intrinsics::{run_buffered, AliasTable, IntrinsicRegistry};
+    use crate::python::{PythonInterpreter, PythonSuggester};
+    use crate::shell::{Shell, VariableStore};
+
+    #[test]
+    fn test_intrinsic() {
+        let interpreter = PythonInterpreter::new();
+        let mut shell = Shell::new(VariableStore::new(), AliasTable::new(), IntrinsicRegistry::new());
+        let code = "import yeesh\n\
+                    @yeesh.intrinsic('plugins_test_greet', 'greet someone')\n\
+                    def greet(args):\n    \
+                        if not args:\n        raise ValueError('who?')\n    \
+                        return 'hello ' + ' '.join(args) + $suffix\n\
+                    yeesh.register_intrinsic('plugins_test_quiet', '', lambda args: None)";
+        assert_eq!(interpreter.run(code, &mut shell).status, 0);
+
+        // the handler runs in the shell which runs the intrinsic
+        shell.set_variable("suffix", "!");
+        let greet = shell.get_intrinsics().find("plugins_test_greet").unwrap();
+        assert_eq!(greet.get_description(), "greet someone");
+        let output = run_buffered(greet.as_ref(), &mut shell, "", &["a", "b"]);
+        assert_eq!(output, (0, "hello a b!\n".to_string(), "".to_string()));
+        let output = run_buffered(greet.as_ref(), &mut shell, "", &[]);
+        assert_eq!(output, (1, "".to_string(), "plugins_test_greet: ValueError: who?\n".to_string()));
+        let quiet = shell.get_intrinsics().find("plugins_test_quiet").unwrap();
+        assert_eq!(run_buffered(quiet.as_ref(), &mut shell, "", &[]), (0, "".to_string(), "".to_string()));
+
+        // registering again replaces the intrinsic
+        let code = "yeesh.register_intrinsic('plugins_test_quiet', 'replaced', print)";
+        assert_eq!(interpreter.run(code, &mut shell).status, 0);
+        let quiet = shell.get_intrinsics().find("plugins_test_quiet").unwrap();
+        assert_eq!(quiet.get_description(), "replaced");
+
+        let output = interpreter.run("yeesh.register_intrinsic('a b', '', print)", &mut shell);
+        assert!(output.stderr.contains("ValueError: 'a b': not a valid command name"));
+        let output = interpreter.run("yeesh.register_intrinsic('x', '', 'print')", &mut shell);
+        assert!(output.stderr.contains("TypeError: 'str' object is not callable"));
+    }
+
+    #[test]
+    fn test_completer() {
+        PythonInterpreter::new();
+        let function = Python::with_gil(|py| {
+            py.eval(
+                "lambda prefix, context: [prefix + 'a', (prefix + 'b', str(context['previous']))]",
+                None,
+                None,
+            )
+            .unwrap()
+            .into()
+        });
+        let mut suggester = PythonSuggester::new(function, Some("git".to_string()));
+
+        let context = CompletionContext {
+            prefix:    "x".to_string(),
+            command:   Some("git".to_string()),
+            previous:  Some("git".to_string()),
+            token_idx: 1,
+        };
+        assert_eq!(suggester.get_applicability(&context), Applicability::Applicable);
+        let suggestions = suggester.get_suggestions(&context);
+        let replacements: Vec<_> = suggestions.iter().map(|s| s.replacement.as_str()).collect();
+        assert_eq!(replacements, vec!["xa", "xb"]);
+        assert_eq!(suggestions[1].description.as_deref(), Some("git"));
+        assert_eq!(suggestions[0].s_type, SuggestionType::Registered);
+
+        assert_eq!(suggester.get_applicability(&"x".into()), Applicability::NotApplicable);
+
+        let function = Python::with_gil(|py| py.eval("lambda prefix, context: 1 / 0", None, None).unwrap().into());
+        let mut suggester = PythonSuggester::new(function, None);
+        assert!(suggester.get_suggestions(&"x".into()).is_empty());
+    }
+
+    #[test]
+    fn test_register_completer() {
+        let interpreter = PythonInterpreter::new();
+        let mut shell = Shell::new(VariableStore::new(), AliasTable::new(), IntrinsicRegistry::new());
+        let code = "import yeesh\n\
+                    @yeesh.completer('git')\n\
+                    def complete(prefix, context):\n    \
+                        return [prefix + 'a']";
+        assert_eq!(interpreter.run(code, &mut shell).status, 0);
+
+        // the completer belongs to the shell which ran the snippet
+        let suggesters = shell.get_suggesters().get_all();
+        assert_eq!(suggesters.len(), 1);
+        let context = CompletionContext {
+            prefix:    "x".to_string(),
+            command:   Some("git".to_string()),
+            previous:  Some("git".to_string()),
+            token_idx: 1,
+        };
+        let suggestions = suggesters[0].get_suggester().lock().unwrap().get_suggestions(&context);
+        assert_eq!(suggestions[0].replacement, "xa");
+        let other = Shell::new(VariableStore::new(), AliasTable::new(), IntrinsicRegistry::new());
+        assert!(other.get_suggesters().get_all().is_empty());
+    }
+}
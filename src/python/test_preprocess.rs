@@ -0,0 +1,38 @@
+#[cfg(test)]
+mod preprocess_tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::python::preprocess;
+
+    #[test]
+    fn test_variables() {
+        assert_eq!(preprocess("$HOME"), "__yeesh__.vars[\"HOME\"]");
+        assert_eq!(
+            preprocess("$x = $y.upper() + $12"),
+            "__yeesh__.vars[\"x\"] = __yeesh__.vars[\"y\"].upper() + __yeesh__.vars[\"12\"]"
+        );
+        // a parameter can't be a number followed by a name
+        assert_eq!(preprocess("$1x + $_1"), "$1x + __yeesh__.vars[\"_1\"]");
+        assert_eq!(preprocess("print($?)"), "print(__yeesh__.vars[\"?\"])");
+        assert_eq!(preprocess("a = $ + 1"), "a = $ + 1");
+    }
+
+    #[test]
+    fn test_commands() {
+        assert_eq!(preprocess("r = ${ls -l}"), "r = __yeesh__.run(\"ls -l\")");
+        assert_eq!(
+            preprocess("${ f() { echo \"$1\"; }; f }.stdout"),
+            "__yeesh__.run(\"f() { echo \\\"$1\\\"; }; f\").stdout"
+        );
+        assert_eq!(preprocess("${echo 'a\tb'}"), "__yeesh__.run(\"echo 'a\\tb'\")");
+        assert_eq!(preprocess("${echo"), "${echo");
+    }
+
+    #[test]
+    fn test_strings_and_comments() {
+        assert_eq!(preprocess("'$x' + \"${y}\" # $z"), "'$x' + \"${y}\" # $z");
+        assert_eq!(preprocess("r'\\'$x' + $y"), "r'\\'$x' + __yeesh__.vars[\"y\"]");
+        assert_eq!(preprocess("'''it's\n$x''' + ''.join($y)"), "'''it's\n$x''' + ''.join(__yeesh__.vars[\"y\"])");
+        assert_eq!(preprocess("\"\"$x"), "\"\"__yeesh__.vars[\"x\"]");
+    }
+}
@@ -0,0 +1,47 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::process::ExitStatus;
+use std::rc::Rc;
+
+use crate::shell::{anonymous_file, Shell, Streams};
+
+/// What a command wrote while its output was captured
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapturedOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub status: ExitStatus,
+}
+
+/// Returns everything written to `file` from the start
+fn read_back(mut file: &File) -> io::Result<String> {
+    let mut output = vec![];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_end(&mut output)?;
+    Ok(String::from_utf8_lossy(&output).into_owned())
+}
+
+impl Shell {
+    /// Run `command` as if it had been typed at the prompt, capturing what it writes to standard
+    /// output and standard error rather than letting it reach the terminal. They are captured in
+    /// files rather than pipes, so that jobs it starts in the background, which keep them open,
+    /// don't hold it up.
+    pub fn run_captured(&mut self, command: &str) -> io::Result<CapturedOutput> {
+        let (stdout, stderr) = (Rc::new(anonymous_file()?), Rc::new(anonymous_file()?));
+        let mut streams = self.replace_streams(Streams::default());
+        let saved = streams.clone();
+        streams.set(1, Some(stdout.clone()));
+        streams.set(2, Some(stderr.clone()));
+        self.replace_streams(streams);
+
+        // `exit` can't leave the shell from here, so only its status is kept
+        let status = self.run(command).unwrap_or_else(|| self.get_last_status());
+
+        self.replace_streams(saved);
+        Ok(CapturedOutput {
+            stdout: read_back(&stdout)?,
+            stderr: read_back(&stderr)?,
+            status,
+        })
+    }
+}
@@ -0,0 +1,138 @@
+use crate::parser::{Word, WordPart};
+use crate::shell::pattern::{to_pattern, PatternChar};
+use crate::shell::Shell;
+
+/// Accumulates the fields a word expands to
+#[derive(Default)]
+struct Fields {
+    fields:    Vec<String>,
+    current:   String,
+    /// whether `current` is a field, even if it is empty (as with `""`)
+    has_field: bool,
+}
+
+impl Fields {
+    fn push_quoted(&mut self, text: &str) {
+        self.current.push_str(text);
+        self.has_field = true;
+    }
+
+    /// End the current field, if there is one
+    fn split(&mut self) {
+        if self.has_field {
+            self.fields.push(std::mem::take(&mut self.current));
+            self.has_field = false;
+        }
+    }
+
+    /// Add the result of an unquoted expansion, splitting it into fields on whitespace
+    fn push_unquoted(&mut self, value: &str) {
+        if value.starts_with(char::is_whitespace) {
+            self.split();
+        }
+        let mut pieces = value.split_whitespace().peekable();
+        while let Some(piece) = pieces.next() {
+            self.push_quoted(piece);
+            if pieces.peek().is_some() {
+                self.split();
+            }
+        }
+        if value.ends_with(char::is_whitespace) {
+            self.split();
+        }
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        self.split();
+        self.fields
+    }
+}
+
+impl Shell {
+    /// Expand `words` into the fields making up a command: parameters are substituted, unquoted
+    /// expansions are split on whitespace and a leading `~` is replaced with `$HOME`
+    pub fn expand_words(&self, words: &[Word]) -> Vec<String> {
+        words.iter().flat_map(|word| self.expand_fields(word)).collect()
+    }
+
+    fn expand_fields(&self, word: &Word) -> Vec<String> {
+        let mut fields = Fields::default();
+        for (idx, part) in word.parts.iter().enumerate() {
+            match part {
+                WordPart::Literal(text) if idx == 0 => fields.push_quoted(&self.expand_tilde(text)),
+                WordPart::Literal(text) | WordPart::Quoted(text) => fields.push_quoted(text),
+                WordPart::Parameter(name) if name == "@" || name == "*" => {
+                    for (idx, param) in self.get_positional().iter().enumerate() {
+                        if idx > 0 {
+                            fields.split();
+                        }
+                        fields.push_unquoted(param);
+                    }
+                }
+                WordPart::Parameter(name) => fields.push_unquoted(&self.get_parameter(name).unwrap_or_default()),
+                WordPart::DoubleQuoted(parts) => {
+                    for part in parts {
+                        match part {
+                            // "$@" expands to each positional parameter as a separate field
+                            WordPart::Parameter(name) if name == "@" => {
+                                for (idx, param) in self.get_positional().iter().enumerate() {
+                                    if idx > 0 {
+                                        fields.fields.push(std::mem::take(&mut fields.current));
+                                    }
+                                    fields.push_quoted(param);
+                                }
+                            }
+                            _ => fields.push_quoted(&self.expand_part(part)),
+                        }
+                    }
+                }
+            }
+        }
+
+        fields.finish()
+    }
+
+    /// Expand `word` into a single string, without field splitting, as for the value of an
+    /// assignment
+    pub fn expand_word(&self, word: &Word) -> String {
+        word.parts
+            .iter()
+            .enumerate()
+            .map(|(idx, part)| match part {
+                WordPart::Literal(text) if idx == 0 => self.expand_tilde(text),
+                _ => self.expand_part(part),
+            })
+            .collect()
+    }
+
+    /// Expand `word` into a pattern, as for a `case` item. Wildcards which were quoted, or came
+    /// from a quoted expansion, match literally.
+    pub fn expand_pattern(&self, word: &Word) -> Vec<PatternChar> {
+        word.parts
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, part)| match part {
+                WordPart::Literal(text) if idx == 0 => to_pattern(&self.expand_tilde(text), false),
+                WordPart::Literal(_) | WordPart::Parameter(_) => to_pattern(&self.expand_part(part), false),
+                WordPart::Quoted(_) | WordPart::DoubleQuoted(_) => to_pattern(&self.expand_part(part), true),
+            })
+            .collect()
+    }
+
+    fn expand_part(&self, part: &WordPart) -> String {
+        match part {
+            WordPart::Literal(text) | WordPart::Quoted(text) => text.clone(),
+            WordPart::Parameter(name) => self.get_parameter(name).unwrap_or_default(),
+            WordPart::DoubleQuoted(parts) => parts.iter().map(|part| self.expand_part(part)).collect(),
+        }
+    }
+
+    fn expand_tilde(&self, text: &str) -> String {
+        if (text == "~" || text.starts_with("~/")) && let Some(home) = self.get_variable("HOME") {
+            home + &text[1..]
+        }
+        else {
+            text.to_string()
+        }
+    }
+}
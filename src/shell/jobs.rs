@@ -0,0 +1,252 @@
+use std::io;
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+
+use log::debug;
+
+use crate::parser::{AndOrList, Command, Connector, SimpleCommand};
+use crate::shell::{get_signal_name, ignore_job_control_signals, status_from_code};
+
+/// A list of commands running in the background, started with `&`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Job {
+    /// the number of the job, as `%1` would refer to it
+    pub id:      usize,
+    /// the process running the job, which leads its process group if the shell has job control
+    pub pid:     libc::pid_t,
+    pub command: String,
+    /// whether the job was stopped, e.g. by Ctrl-Z while it was in the foreground
+    pub stopped: bool,
+}
+
+impl Job {
+    /// Describes the job while it is still running, as `jobs` lists it
+    pub fn describe_running(&self) -> String {
+        match self.stopped {
+            true => format!("[{}]  {:<8}  {}", self.id, "Stopped", self.command),
+            false => format!("[{}]  {:<8}  {} &", self.id, "Running", self.command),
+        }
+    }
+
+    /// Describes the job finishing with `status`, as the shell reports it
+    pub fn describe_finished(&self, status: &ExitStatus) -> String {
+        let state = match (status.code(), status.signal()) {
+            (Some(0), _) => "Done".to_string(),
+            (Some(code), _) => format!("Exit {}", code),
+            (None, Some(signal)) => match get_signal_name(signal) {
+                Some(name) => name.to_string(),
+                None => format!("Signal {}", signal),
+            },
+            (None, None) => "Done".to_string(),
+        };
+        format!("[{}]  {:<8}  {}", self.id, state, self.command)
+    }
+}
+
+/// The background jobs of a shell
+#[derive(Clone, Debug, Default)]
+pub struct JobTable {
+    jobs: Vec<Job>,
+}
+
+impl JobTable {
+    /// Add a job for the process `pid`, numbered one more than the highest numbered job
+    pub fn add(&mut self, pid: libc::pid_t, command: String) -> &Job {
+        let id = self.jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+        debug!("Started job {} ({}): {}", id, pid, command);
+        self.jobs.push(Job {
+            id,
+            pid,
+            command,
+            stopped: false,
+        });
+        self.jobs.last().unwrap()
+    }
+
+    /// Add a job for the process `pid`, which was stopped while running in the foreground
+    pub fn add_stopped(&mut self, pid: libc::pid_t, command: String) -> &Job {
+        self.add(pid, command);
+        let job = self.jobs.last_mut().unwrap();
+        job.stopped = true;
+        job
+    }
+
+    pub fn get_all(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Remove the jobs which have finished, without waiting for the others, returning each with
+    /// its exit status
+    pub fn reap(&mut self) -> Vec<(Job, ExitStatus)> {
+        let mut finished = vec![];
+        self.jobs.retain_mut(|job| {
+            let mut status = 0;
+            match unsafe { libc::waitpid(job.pid, &mut status, libc::WNOHANG | libc::WCONTINUED) } {
+                0 => true,
+                pid if pid == job.pid && libc::WIFCONTINUED(status) => {
+                    job.stopped = false;
+                    true
+                }
+                pid if pid == job.pid => {
+                    finished.push((job.clone(), ExitStatus::from_raw(status)));
+                    false
+                }
+                // the process can't be waited for, e.g. it was reaped elsewhere
+                _ => false,
+            }
+        });
+        finished
+    }
+
+    /// Send `SIGHUP` to each job, and `SIGCONT` so that stopped jobs see it, as the shell exits
+    pub fn hang_up(&self) {
+        for job in &self.jobs {
+            debug!("Hanging up job {} ({})", job.id, job.pid);
+            for signal in [libc::SIGHUP, libc::SIGCONT] {
+                // a job started without job control isn't in a process group of its own
+                if unsafe { libc::kill(-job.pid, signal) } != 0 {
+                    unsafe { libc::kill(job.pid, signal) };
+                }
+            }
+        }
+    }
+}
+
+/// The terminal and process groups of a shell with job control, which runs each command in a
+/// process group of its own and lets the one in the foreground have the terminal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JobControl {
+    /// the process group of the shell, which has the terminal while it reads commands
+    pub shell_pgid:    libc::pid_t,
+    /// the process group the shell was started in, which gets the terminal back when it exits
+    pub original_pgid: libc::pid_t,
+}
+
+impl JobControl {
+    /// Put the shell in a process group of its own, in the foreground of the terminal on stdin,
+    /// ignoring the signals the terminal sends
+    pub fn start() -> io::Result<Self> {
+        let terminal = libc::STDIN_FILENO;
+        // a shell started in the background waits to be brought to the foreground, rather than
+        // take the terminal from whichever job has it
+        loop {
+            let foreground = unsafe { libc::tcgetpgrp(terminal) };
+            if foreground < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let pgid = unsafe { libc::getpgrp() };
+            if foreground == pgid {
+                break;
+            }
+            unsafe { libc::kill(-pgid, libc::SIGTTIN) };
+        }
+
+        ignore_job_control_signals();
+        let original_pgid = unsafe { libc::getpgrp() };
+        let shell_pgid = unsafe { libc::getpid() };
+        // a session leader is already the leader of its process group
+        if original_pgid != shell_pgid && unsafe { libc::setpgid(0, 0) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if unsafe { libc::tcsetpgrp(terminal, shell_pgid) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(JobControl {
+            shell_pgid,
+            original_pgid,
+        })
+    }
+
+    /// Put the started process `pid` in a process group of its own, in the foreground. The
+    /// process does this too, as it doesn't know whether it or the shell will run first.
+    pub fn give_terminal(&self, pid: libc::pid_t) {
+        unsafe {
+            libc::setpgid(pid, pid);
+            libc::tcsetpgrp(libc::STDIN_FILENO, pid);
+        }
+    }
+
+    /// Put the shell back in the foreground, once the command in it has finished or stopped
+    pub fn take_terminal(&self) {
+        unsafe { libc::tcsetpgrp(libc::STDIN_FILENO, self.shell_pgid) };
+    }
+
+    /// Return the shell to the process group it started in, with the terminal, as it exits
+    pub fn finish(&self) {
+        unsafe {
+            libc::setpgid(0, self.original_pgid);
+            libc::tcsetpgrp(libc::STDIN_FILENO, self.original_pgid);
+        }
+    }
+}
+
+/// Wait for the process `pid` to exit or be stopped, returning its exit status, or `None` if it
+/// was stopped
+pub fn wait_for_process(pid: libc::pid_t) -> Option<ExitStatus> {
+    let mut status = 0;
+    loop {
+        match unsafe { libc::waitpid(pid, &mut status, libc::WUNTRACED) } {
+            -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => continue,
+            -1 => return Some(status_from_code(1)),
+            _ if libc::WIFSTOPPED(status) => return None,
+            _ => return Some(ExitStatus::from_raw(status)),
+        }
+    }
+}
+
+fn describe_simple(command: &SimpleCommand) -> String {
+    command
+        .assignments
+        .iter()
+        .map(|a| format!("{}={}", a.name, a.value))
+        .chain(command.words.iter().map(|word| word.to_string()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn describe_command(command: &Command) -> String {
+    match command {
+        Command::Simple(simple) => describe_simple(simple),
+        Command::BraceGroup(_) => "{ ... }".to_string(),
+        Command::Subshell(_) => "( ... )".to_string(),
+        Command::Not(command) => format!("! {}", describe_command(command)),
+        Command::Pipeline(commands) => describe_pipeline(commands),
+        Command::Redirected(redirected) => {
+            let redirections = redirected.redirections.iter().map(|redirection| format!(" {}", redirection));
+            describe_command(&redirected.command) + &redirections.collect::<String>()
+        }
+        Command::Time(time) => match &time.command {
+            Some(command) => format!("time {}", describe_command(command)),
+            None => "time".to_string(),
+        },
+        Command::If(_) => "if ...".to_string(),
+        Command::While(_) => "while ...".to_string(),
+        Command::Until(_) => "until ...".to_string(),
+        Command::For(command) => format!("for {} ...", command.variable),
+        Command::Case(command) => format!("case {} ...", command.word),
+        Command::FunctionDefinition(definition) => format!("{}() ...", definition.name),
+        Command::Python(_) => "py: ...".to_string(),
+    }
+}
+
+/// Describes the commands of a pipeline briefly, as the command of a job
+pub fn describe_pipeline(commands: &[Command]) -> String {
+    commands.iter().map(describe_command).collect::<Vec<_>>().join(" | ")
+}
+
+/// Describes `list` briefly, as the command of a job
+pub fn describe_and_or(list: &AndOrList) -> String {
+    let mut description = describe_command(&list.first);
+    for (connector, command) in &list.rest {
+        let connector = match connector {
+            Connector::And => "&&",
+            Connector::Or => "||",
+        };
+        description += &format!(" {} {}", connector, describe_command(command));
+    }
+    description
+}
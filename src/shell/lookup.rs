@@ -0,0 +1,89 @@
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::parser::RESERVED_WORDS;
+use crate::shell::Shell;
+
+/// The builtins run by the shell itself rather than through an intrinsic, as they change how it
+/// runs the commands around them
+pub const SPECIAL_BUILTINS: [&str; 8] =
+    ["local", "return", "break", "continue", "source", ".", "builtin", "command"];
+
+/// What a command name refers to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CommandKind {
+    /// an alias, with its value
+    Alias(String),
+    /// a reserved word such as `if`
+    Keyword,
+    /// a special builtin or an intrinsic
+    Builtin,
+    Function,
+    /// an executable, found in `$PATH` unless the name contains a `/`
+    External(PathBuf),
+}
+
+impl CommandKind {
+    /// Describes running `name` as this kind of command, as `type` does
+    pub fn describe(&self, name: &str) -> String {
+        match self {
+            CommandKind::Alias(value) => format!("{} is aliased to `{}'", name, value),
+            CommandKind::Keyword => format!("{} is a shell keyword", name),
+            CommandKind::Builtin => format!("{} is a shell builtin", name),
+            CommandKind::Function => format!("{} is a function", name),
+            CommandKind::External(path) => format!("{} is {}", name, path.display()),
+        }
+    }
+
+    /// Returns a single word for this kind of command, as `type -t` gives
+    pub fn word(&self) -> &'static str {
+        match self {
+            CommandKind::Alias(_) => "alias",
+            CommandKind::Keyword => "keyword",
+            CommandKind::Builtin => "builtin",
+            CommandKind::Function => "function",
+            CommandKind::External(_) => "file",
+        }
+    }
+}
+
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+/// Returns the executable `name` runs: `name` itself if it contains a `/`, and otherwise the first
+/// executable called `name` in the `:` separated directories of `search_path`
+pub fn find_executable(name: &str, search_path: &str) -> Option<PathBuf> {
+    if name.contains('/') {
+        return Some(PathBuf::from(name)).filter(|path| is_executable(path));
+    }
+    search_path
+        .split(':')
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| Path::new(dir).join(name))
+        .find(|candidate| is_executable(candidate))
+}
+
+impl Shell {
+    /// Returns what running `name` as a command would run, in the order the shell looks for it,
+    /// or `None` if there is no such command
+    pub fn find_command(&self, name: &str) -> Option<CommandKind> {
+        if let Some(value) = self.get_aliases().get(name) {
+            Some(CommandKind::Alias(value))
+        }
+        else if RESERVED_WORDS.contains(&name) {
+            Some(CommandKind::Keyword)
+        }
+        else if SPECIAL_BUILTINS.contains(&name) || self.get_intrinsics().find(name).is_some() {
+            Some(CommandKind::Builtin)
+        }
+        else if self.get_function(name).is_some() {
+            Some(CommandKind::Function)
+        }
+        else {
+            let search_path = self.get_variable("PATH").unwrap_or_default();
+            find_executable(name, &search_path).map(CommandKind::External)
+        }
+    }
+}
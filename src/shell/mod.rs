@@ -0,0 +1,35 @@
+mod capture;
+mod expand;
+mod jobs;
+mod lookup;
+mod pattern;
+#[cfg(feature = "python")]
+mod python;
+mod shell;
+mod signals;
+mod source;
+mod startup;
+mod streams;
+mod terminal;
+mod timing;
+mod variables;
+
+#[cfg(test)]
+mod test_pattern;
+#[cfg(test)]
+mod test_shell;
+#[cfg(test)]
+mod test_signals;
+#[cfg(test)]
+mod test_timing;
+
+pub use jobs::*;
+pub use lookup::*;
+pub use shell::*;
+pub use signals::*;
+pub use source::*;
+pub use startup::*;
+pub use streams::*;
+pub use terminal::*;
+pub use timing::*;
+pub use variables::*;
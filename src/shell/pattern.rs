@@ -0,0 +1,83 @@
+/// A character of a shell pattern. Quoted characters always match themselves, while unquoted
+/// `*`, `?` and `[` are wildcards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PatternChar {
+    pub c:      char,
+    pub quoted: bool,
+}
+
+impl PatternChar {
+    fn is_special(&self, c: char) -> bool {
+        !self.quoted && self.c == c
+    }
+}
+
+/// Build a pattern from `text`, with every character either quoted or unquoted
+pub fn to_pattern(text: &str, quoted: bool) -> Vec<PatternChar> {
+    text.chars().map(|c| PatternChar { c, quoted }).collect()
+}
+
+/// Returns true if `text` matches the whole of `pattern`
+pub fn matches_pattern(pattern: &[PatternChar], text: &str) -> bool {
+    let text: Vec<char> = text.chars().collect();
+    match_from(pattern, &text)
+}
+
+fn match_from(pattern: &[PatternChar], text: &[char]) -> bool {
+    let first = match pattern.first() {
+        Some(first) => first,
+        None => return text.is_empty(),
+    };
+
+    if first.is_special('*') {
+        let rest = &pattern[1..];
+        return (0..=text.len()).any(|skip| match_from(rest, &text[skip..]));
+    }
+    if text.is_empty() {
+        return false;
+    }
+    if first.is_special('?') {
+        return match_from(&pattern[1..], &text[1..]);
+    }
+    if first.is_special('[') && let Some((len, matched)) = match_bracket(pattern, text[0]) {
+        return matched && match_from(&pattern[len..], &text[1..]);
+    }
+
+    first.c == text[0] && match_from(&pattern[1..], &text[1..])
+}
+
+/// Match `c` against the bracket expression (e.g. `[a-z_]` or `[!0-9]`) at the start of
+/// `pattern`. Returns the length of the expression and whether it matched, or `None` if the `[`
+/// is not closed and so is matched literally.
+fn match_bracket(pattern: &[PatternChar], c: char) -> Option<(usize, bool)> {
+    let mut idx = 1;
+    let negated = pattern.get(idx).is_some_and(|p| p.is_special('!') || p.is_special('^'));
+    if negated {
+        idx += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        let current = pattern.get(idx)?;
+        // a `]` straight after the opening bracket is part of the set
+        if current.is_special(']') && !first {
+            break;
+        }
+        first = false;
+
+        let is_range = pattern.get(idx + 1).is_some_and(|p| p.is_special('-'))
+            && pattern.get(idx + 2).is_some_and(|p| !p.is_special(']'));
+        if is_range {
+            let end = pattern[idx + 2].c;
+            matched |= current.c <= c && c <= end;
+            idx += 3;
+        }
+        else {
+            matched |= current.c == c;
+            idx += 1;
+        }
+    }
+
+    Some((idx + 1, matched != negated))
+}
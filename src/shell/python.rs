@@ -0,0 +1,46 @@
+use crate::cmd_input::SuggesterRegistry;
+use crate::intrinsics::IntrinsicRegistry;
+use crate::parser::is_valid_name;
+use crate::python::{CommandResult, ShellBridge};
+use crate::shell::{status_code, Shell, SHELL_NAME};
+
+impl ShellBridge for Shell {
+    fn get_variable(&self, name: &str) -> Option<String> {
+        self.get_parameter(name)
+    }
+
+    fn set_variable(&mut self, name: &str, value: &str) -> Result<(), String> {
+        if !is_valid_name(name) {
+            return Err(format!("'{}': not a valid identifier", name));
+        }
+        Shell::set_variable(self, name, value);
+        Ok(())
+    }
+
+    fn unset_variable(&mut self, name: &str) {
+        Shell::unset_variable(self, name)
+    }
+
+    fn get_intrinsics(&self) -> IntrinsicRegistry {
+        Shell::get_intrinsics(self).clone()
+    }
+
+    fn get_suggesters(&self) -> SuggesterRegistry {
+        Shell::get_suggesters(self).clone()
+    }
+
+    fn run_command(&mut self, command: &str) -> CommandResult {
+        match self.run_captured(command) {
+            Ok(output) => CommandResult {
+                stdout: output.stdout,
+                stderr: output.stderr,
+                status: status_code(&output.status),
+            },
+            Err(e) => CommandResult {
+                stdout: String::new(),
+                stderr: format!("{}: unable to capture the output of '{}': {}\n", SHELL_NAME, command, e),
+                status: 1,
+            },
+        }
+    }
+}
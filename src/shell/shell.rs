@@ -0,0 +1,1359 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::{env, io, mem};
+use std::io::{BufReader, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, Command as Process, ExitStatus, Stdio};
+use std::rc::Rc;
+use std::time::Instant;
+
+use log::{debug, trace};
+
+use crate::cmd_input::SuggesterRegistry;
+use crate::intrinsics::{quote, AliasTable, DirectoryDb, Intrinsic, IntrinsicIo, IntrinsicRegistry};
+use crate::parser::{
+    is_valid_name, AndOrList, CaseCommand, Command, CommandList, Connector, ForCommand, IfCommand, LoopCommand,
+    Operator, ParseError, Parser, Program, RedirectedCommand, Redirection, SimpleCommand, TimeCommand,
+};
+#[cfg(feature = "python")]
+use crate::python::PythonInterpreter;
+use crate::shell::pattern::matches_pattern;
+use crate::shell::{
+    anonymous_file, catch_signal, default_signal, describe_and_or, describe_pipeline, describe_termination,
+    format_timing, get_cpu_times, get_signal_name, get_signal_number, ignore_signal, is_job_control_signal, pipe,
+    reset_job_control_signals, take_pending_signals, wait_for_process, CommandKind, Job, JobControl, JobTable,
+    SourceLocation, Stream, Streams, TerminalState, VariableStore, MAX_STREAM, SPECIAL_BUILTINS,
+};
+
+/// The name of the shell, used in error messages and as the default `$0`
+pub const SHELL_NAME: &str = "yeesh";
+/// Function calls nested deeper than this fail, rather than overflowing the stack
+const MAX_CALL_DEPTH: usize = 1000;
+
+/// Create the `ExitStatus` of a process which exited with `code`
+pub fn status_from_code(code: i32) -> ExitStatus {
+    ExitStatus::from_raw((code & 0xff) << 8)
+}
+
+/// Returns the numeric form of `status`, as given by `$?`
+pub fn status_code(status: &ExitStatus) -> i32 {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => code,
+        (None, Some(signal)) => 128 + signal,
+        (None, None) => 1,
+    }
+}
+
+/// Something which stops commands from running in sequence
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControlFlow {
+    /// `return` from the running function
+    Return(ExitStatus),
+    /// `break` out of this many enclosing loops
+    Break(usize),
+    /// `continue` the loop this many levels out
+    Continue(usize),
+    /// exit the shell
+    Exit(ExitStatus),
+    /// a command in the foreground was interrupted by Ctrl-C, which abandons the rest of the
+    /// command line, as the shell itself ignores it
+    Interrupt(ExitStatus),
+}
+
+pub type ExecResult = Result<ExitStatus, ControlFlow>;
+
+/// The local variables and positional parameters of a running function
+#[derive(Clone, Default)]
+struct Frame {
+    locals:     HashMap<String, String>,
+    positional: Vec<String>,
+    /// how many loops are running in this function, which `break` and `continue` may exit
+    loop_depth: usize,
+}
+
+/// Holds the state of the shell (variables, functions, aliases and intrinsics) and executes
+/// commands. Intrinsics are given the shell running them, to read and change that state.
+pub struct Shell {
+    /// the name given by `$0`
+    name:         String,
+    /// the shell's own executable, which runs its jobs
+    executable:   PathBuf,
+    variables:    VariableStore,
+    aliases:      AliasTable,
+    intrinsics:   IntrinsicRegistry,
+    functions:    HashMap<String, Rc<Command>>,
+    /// a frame for each running function, after the first which holds the shell's own positional
+    /// parameters
+    frames:       Vec<Frame>,
+    /// how many files are being run by `source`, which `return` may stop
+    source_depth: usize,
+    /// where the command being run was read from, while commands are read from a file
+    location:     Option<SourceLocation>,
+    last_status:  ExitStatus,
+    /// whether commands are being read from a terminal
+    interactive:  bool,
+    jobs:         JobTable,
+    /// the process of the last job started in the background, given by `$!`
+    last_job_pid: Option<libc::pid_t>,
+    /// whether `exit` has warned that jobs are running, so the next `exit` leaves anyway
+    warned_jobs:  bool,
+    /// set when `exit` has been run, to the status the shell should exit with
+    exit_status:  Option<ExitStatus>,
+    /// the command to run for each condition which has a trap, e.g. `EXIT` or `SIGINT`
+    traps:        BTreeMap<String, String>,
+    /// whether a trap's action is running, during which the `DEBUG`, `ERR` and `RETURN` traps don't
+    in_trap:      bool,
+    /// how many conditions, `!` commands, and commands before the end of a `&&` or `||` list are
+    /// running, whose failures don't run the `ERR` trap
+    err_exempt:   usize,
+    /// the directories saved by `pushd`, most recent first, not including the working directory
+    dir_stack:    Vec<String>,
+    /// the process groups of the shell and its terminal, if it has job control
+    job_control:  Option<JobControl>,
+    /// the settings of the terminal when job control started, which `reset` puts back
+    terminal:     Option<TerminalState>,
+    /// the directories changed to, which `z` finds directories in
+    directories:  DirectoryDb,
+    /// the suggesters added while the shell runs, which completion consults alongside its own
+    suggesters:   SuggesterRegistry,
+    /// where the commands being run read and write, as redirections and pipes have sent them
+    streams:      Streams,
+    /// the interpreter for `py:` snippets, started when the first one runs
+    #[cfg(feature = "python")]
+    python:       Option<Rc<PythonInterpreter>>,
+}
+
+/// A command in a pipeline which runs a program, with its words expanded
+struct ExternalCommand<'a> {
+    args:         Vec<String>,
+    environment:  Vec<(String, String)>,
+    redirections: &'a [Redirection],
+}
+
+/// Start the program `cmd_args` with `streams` as its standard streams. With job control, it
+/// joins the process group `pgid`, or leads one of its own if that is 0.
+fn dispatch_command(
+    cmd_args: &[String],
+    environment: Vec<(String, String)>,
+    streams: &Streams,
+    pgid: Option<libc::pid_t>,
+) -> io::Result<Child> {
+    if cmd_args.is_empty() {
+        return Err(io::Error::other("Empty command"));
+    }
+
+    let mut process = Process::new(&cmd_args[0]);
+    process.args(&cmd_args[1..]).env_clear().envs(environment);
+    streams.apply_to(&mut process)?;
+    if let Some(pgid) = pgid {
+        // the command runs in the foreground in a process group of its own (or its pipeline's),
+        // where the keys typed at the terminal signal it as they would if the shell weren't
+        // ignoring them
+        let setup = move || {
+            unsafe {
+                libc::setpgid(0, pgid);
+                libc::tcsetpgrp(libc::STDIN_FILENO, libc::getpgrp());
+            }
+            reset_job_control_signals();
+            Ok(())
+        };
+        unsafe { process.pre_exec(setup) };
+    }
+    process.spawn()
+}
+
+/// Returns the message to report when `name` couldn't be run because of `error`, and the exit
+/// status to give: 127 if there's no such command, or 126 if it can't be run
+fn describe_spawn_error(name: &str, error: &io::Error) -> (String, i32) {
+    let is_path = name.contains('/');
+    match error.kind() {
+        io::ErrorKind::NotFound if is_path => ("No such file or directory".to_string(), 127),
+        io::ErrorKind::NotFound => ("command not found".to_string(), 127),
+        _ if is_path && Path::new(name).is_dir() => ("Is a directory".to_string(), 126),
+        io::ErrorKind::PermissionDenied => ("Permission denied".to_string(), 126),
+        _ => (error.to_string(), 126),
+    }
+}
+
+impl Shell {
+    pub fn new(variables: VariableStore, aliases: AliasTable, intrinsics: IntrinsicRegistry) -> Self {
+        Shell {
+            name:         SHELL_NAME.to_string(),
+            executable:   env::current_exe().unwrap_or_else(|_| PathBuf::from(SHELL_NAME)),
+            variables,
+            aliases,
+            intrinsics,
+            functions:    HashMap::new(),
+            frames:       vec![Frame::default()],
+            source_depth: 0,
+            location:     None,
+            last_status:  status_from_code(0),
+            interactive:  false,
+            jobs:         JobTable::default(),
+            last_job_pid: None,
+            warned_jobs:  false,
+            exit_status:  None,
+            traps:        BTreeMap::new(),
+            in_trap:      false,
+            err_exempt:   0,
+            dir_stack:    vec![],
+            job_control:  None,
+            terminal:     None,
+            directories:  DirectoryDb::new(),
+            suggesters:   SuggesterRegistry::new(),
+            streams:      Streams::default(),
+            #[cfg(feature = "python")]
+            python:       None,
+        }
+    }
+
+    /// Returns the value of the variable `name`, looking in the locals of running functions
+    /// (innermost first) before the global variables
+    pub fn get_variable(&self, name: &str) -> Option<String> {
+        self.frames
+            .iter()
+            .rev()
+            .find_map(|frame| frame.locals.get(name).cloned())
+            .or_else(|| self.variables.get(name))
+    }
+
+    /// Set the variable `name`, in the innermost function it is local to if there is one, and
+    /// globally otherwise
+    pub fn set_variable(&mut self, name: &str, value: &str) {
+        match self.frames.iter_mut().rev().find_map(|frame| frame.locals.get_mut(name)) {
+            Some(local) => *local = value.to_string(),
+            None => self.variables.set(name, value),
+        }
+    }
+
+    /// Returns the value of a parameter: a variable, a positional parameter such as `1`, or a
+    /// special parameter such as `?`
+    pub fn get_parameter(&self, name: &str) -> Option<String> {
+        match name {
+            "?" => Some(status_code(&self.last_status).to_string()),
+            "#" => Some(self.get_positional().len().to_string()),
+            "$" => Some(std::process::id().to_string()),
+            "0" => Some(self.name.clone()),
+            "@" | "*" => Some(self.get_positional().join(" ")),
+            "!" => self.last_job_pid.map(|pid| pid.to_string()),
+            "-" => None,
+            _ if name.chars().all(|c| c.is_ascii_digit()) => {
+                let idx: usize = name.parse().ok()?;
+                self.get_positional().get(idx.checked_sub(1)?).cloned()
+            }
+            _ => self.get_variable(name),
+        }
+    }
+
+    /// Pass the global variable `name` on to the environment of the commands the shell runs
+    pub fn export_variable(&mut self, name: &str) {
+        self.variables.export(name);
+    }
+
+    /// Remove the variable `name`, from the innermost function it is local to if there is one,
+    /// and globally otherwise
+    pub fn unset_variable(&mut self, name: &str) {
+        match self.frames.iter_mut().rev().find(|frame| frame.locals.contains_key(name)) {
+            Some(frame) => {
+                frame.locals.remove(name);
+            }
+            None => {
+                self.variables.unset(name);
+            }
+        }
+    }
+
+    /// Set the name given by `$0`, such as the path of the script being run
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    /// Set the executable which runs the shell's jobs, when it isn't the running one
+    pub fn set_executable(&mut self, executable: &Path) {
+        self.executable = executable.to_path_buf();
+    }
+
+    /// Returns the positional parameters of the running function, or of the shell
+    pub fn get_positional(&self) -> &[String] {
+        &self.frames.last().unwrap().positional
+    }
+
+    /// Set the shell's own positional parameters
+    pub fn set_positional(&mut self, args: Vec<String>) {
+        self.frames[0].positional = args;
+    }
+
+    /// Replace the positional parameters of the running function, or of the shell, returning
+    /// the old ones
+    fn replace_positional(&mut self, args: Vec<String>) -> Vec<String> {
+        mem::replace(&mut self.frames.last_mut().unwrap().positional, args)
+    }
+
+    pub fn get_function(&self, name: &str) -> Option<Rc<Command>> {
+        self.functions.get(name).cloned()
+    }
+
+    /// Returns the global variables, without the locals of running functions
+    pub fn get_variables(&self) -> &VariableStore {
+        &self.variables
+    }
+
+    pub fn get_aliases(&self) -> &AliasTable {
+        &self.aliases
+    }
+
+    pub fn get_intrinsics(&self) -> &IntrinsicRegistry {
+        &self.intrinsics
+    }
+
+    /// Returns the directories saved by `pushd`, most recent first
+    pub fn get_directory_stack(&self) -> &[String] {
+        &self.dir_stack
+    }
+
+    pub fn set_directory_stack(&mut self, dirs: Vec<String>) {
+        self.dir_stack = dirs;
+    }
+
+    pub fn get_directories(&self) -> &DirectoryDb {
+        &self.directories
+    }
+
+    pub fn get_suggesters(&self) -> &SuggesterRegistry {
+        &self.suggesters
+    }
+
+    /// Returns where the command being run was read from, if it came from a file
+    pub fn get_location(&self) -> Option<&SourceLocation> {
+        self.location.as_ref()
+    }
+
+    /// Set where the command being run was read from, returning where it was before
+    pub fn replace_location(&mut self, location: Option<SourceLocation>) -> Option<SourceLocation> {
+        mem::replace(&mut self.location, location)
+    }
+
+    /// Send the standard streams of the commands run from now on to `streams`, returning where
+    /// they went before
+    pub fn replace_streams(&mut self, streams: Streams) -> Streams {
+        mem::replace(&mut self.streams, streams)
+    }
+
+    /// Returns the standard output of the command being run, wherever it is redirected
+    pub fn stdout(&self) -> Stream {
+        self.streams.stdout()
+    }
+
+    /// Returns the standard error of the command being run, wherever it is redirected
+    pub fn stderr(&self) -> Stream {
+        self.streams.stderr()
+    }
+
+    pub fn get_last_status(&self) -> ExitStatus {
+        self.last_status
+    }
+
+    pub fn set_last_status(&mut self, status: ExitStatus) {
+        self.last_status = status;
+    }
+
+    pub fn is_interactive(&self) -> bool {
+        self.interactive
+    }
+
+    /// Set whether commands are read from a terminal, in which case the shell reports on its
+    /// jobs, and warns before exiting while they are running
+    pub fn set_interactive(&mut self, interactive: bool) {
+        self.interactive = interactive;
+    }
+
+    pub fn get_jobs(&self) -> &JobTable {
+        &self.jobs
+    }
+
+    /// Run each command in a process group of its own, giving it the terminal while it runs in
+    /// the foreground, and ignore the signals the terminal sends to the shell itself
+    pub fn enable_job_control(&mut self) -> io::Result<()> {
+        self.job_control = Some(JobControl::start()?);
+        self.terminal = TerminalState::save();
+        Ok(())
+    }
+
+    /// Returns the settings of the terminal when the shell started, if it has job control
+    pub fn get_saved_terminal(&self) -> Option<TerminalState> {
+        self.terminal
+    }
+
+    /// Send `SIGHUP` to the jobs which are still running, and give the terminal back to the
+    /// process group the shell started in, as an interactive shell exits
+    pub fn hang_up(&mut self) {
+        self.jobs.reap();
+        self.jobs.hang_up();
+        if let Some(job_control) = self.job_control.take() {
+            job_control.finish();
+        }
+    }
+
+    /// Remove the jobs which have finished, returning each with its exit status
+    pub fn reap_jobs(&mut self) -> Vec<(Job, ExitStatus)> {
+        self.jobs.reap()
+    }
+
+    /// Ask the shell to exit with `status` once the running intrinsic returns. The first time
+    /// this is asked while an interactive shell has running jobs, it refuses with a warning.
+    pub fn request_exit(&mut self, status: ExitStatus) -> Result<(), String> {
+        self.jobs.reap();
+        if self.interactive && !self.jobs.is_empty() && !self.warned_jobs {
+            self.warned_jobs = true;
+            return Err("There are running jobs.".to_string());
+        }
+        self.exit_status = Some(status);
+        Ok(())
+    }
+
+    pub fn get_traps(&self) -> &BTreeMap<String, String> {
+        &self.traps
+    }
+
+    /// Run `action` when `condition` occurs, or remove the trap for `condition` if `action` is
+    /// `None`. A signal with a trap is caught, unless the action is empty, which ignores it.
+    pub fn set_trap(&mut self, condition: &str, action: Option<&str>) {
+        if let Some(signal) = get_signal_number(condition) {
+            match action {
+                Some("") => ignore_signal(signal),
+                Some(_) => catch_signal(signal),
+                None => self.restore_signal(signal),
+            }
+        }
+        match action {
+            Some(action) => self.traps.insert(condition.to_string(), action.to_string()),
+            None => self.traps.remove(condition),
+        };
+    }
+
+    /// Handle `signal` as the shell does without a trap for it
+    fn restore_signal(&self, signal: libc::c_int) {
+        match signal {
+            _ if self.job_control.is_some() && is_job_control_signal(signal) => ignore_signal(signal),
+            libc::SIGHUP if self.interactive => catch_signal(signal),
+            _ => default_signal(signal),
+        }
+    }
+
+    /// Run the action of the trap for `condition`, if there is one, leaving `$?` as it was.
+    /// Only `exit` in the action has an effect beyond it.
+    fn run_trap(&mut self, condition: &str) -> Result<(), ControlFlow> {
+        let action = match self.traps.get(condition) {
+            Some(action) if !action.is_empty() => action.clone(),
+            _ => return Ok(()),
+        };
+        let (status, in_trap) = (self.last_status, mem::replace(&mut self.in_trap, true));
+        let result = match self.parse(&action) {
+            Ok(program) => self.execute(&program),
+            Err(e) => {
+                self.report_error(e.display_with_source(&action));
+                Ok(status_from_code(2))
+            }
+        };
+        self.in_trap = in_trap;
+        self.last_status = status;
+        match result {
+            Err(flow @ ControlFlow::Exit(_)) => Err(flow),
+            _ => Ok(()),
+        }
+    }
+
+    /// Run the traps for the signals caught since they last ran. This is done between commands
+    /// rather than as the signals arrive, so that a trap never sees the shell half way through
+    /// changing something.
+    pub fn run_signal_traps(&mut self) -> Result<(), ControlFlow> {
+        for signal in take_pending_signals() {
+            if let Some(name) = get_signal_name(signal) {
+                self.run_trap(name)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether the `DEBUG` and `ERR` traps run for commands now. As in bash without
+    /// `set -T` and `set -E`, they don't run for the commands in functions, or in other traps.
+    fn is_traced(&self) -> bool {
+        self.frames.len() == 1 && !self.in_trap
+    }
+
+    /// Run the `EXIT` trap, if there is one, as the shell exits with `status`. Returns the status
+    /// to exit with, which is `status` unless the trap runs `exit`.
+    pub fn run_exit_trap(&mut self, status: ExitStatus) -> ExitStatus {
+        // the trap only runs once, even if it runs `exit`
+        let action = match self.traps.remove("EXIT") {
+            Some(action) => action,
+            None => return status,
+        };
+        self.last_status = status;
+        match self.run(&action) {
+            Some(_) => status,
+            None => self.last_status,
+        }
+    }
+
+    /// Parse and execute a line of input, returning its exit status, or `None` if the shell
+    /// should exit
+    pub fn run(&mut self, input: &str) -> Option<ExitStatus> {
+        let result = match self.parse(input) {
+            Ok(program) => self.execute(&program),
+            Err(e) => {
+                self.report_error(e.display_with_source(input));
+                Ok(status_from_code(2))
+            }
+        };
+
+        match result {
+            Ok(status) | Err(ControlFlow::Return(status) | ControlFlow::Interrupt(status)) => {
+                self.last_status = status;
+                Some(status)
+            }
+            Err(ControlFlow::Break(_) | ControlFlow::Continue(_)) => Some(self.last_status),
+            Err(ControlFlow::Exit(status)) => {
+                self.last_status = status;
+                None
+            }
+        }
+    }
+
+    /// Parse `input`, expanding aliases
+    pub fn parse(&self, input: &str) -> Result<Program, ParseError> {
+        Parser::new(input)?.with_aliases(&self.aliases).parse_program()
+    }
+
+    pub fn execute(&mut self, program: &Program) -> ExecResult {
+        self.exec_list(&program.commands)
+    }
+
+    fn exec_list(&mut self, list: &CommandList) -> ExecResult {
+        let mut status = status_from_code(0);
+        for and_or in list {
+            status = if and_or.background {
+                self.exec_background(and_or)
+            }
+            else {
+                self.exec_and_or(and_or)?
+            };
+        }
+
+        Ok(status)
+    }
+
+    fn exec_and_or(&mut self, list: &AndOrList) -> ExecResult {
+        // only the last command of the list runs the `ERR` trap if it fails
+        let mut status = match list.rest.is_empty() {
+            true => self.exec_command(&list.first)?,
+            false => self.exec_exempt(|shell| shell.exec_command(&list.first))?,
+        };
+        for (i, (connector, command)) in list.rest.iter().enumerate() {
+            let should_run = match connector {
+                Connector::And => status.success(),
+                Connector::Or => !status.success(),
+            };
+            if should_run {
+                status = match i + 1 == list.rest.len() {
+                    true => self.exec_command(command)?,
+                    false => self.exec_exempt(|shell| shell.exec_command(command))?,
+                };
+            }
+        }
+
+        Ok(status)
+    }
+
+    /// Run a command whose failure doesn't run the `ERR` trap, as for the condition of an `if`
+    fn exec_exempt<F>(&mut self, run: F) -> ExecResult
+    where
+        F: FnOnce(&mut Self) -> ExecResult,
+    {
+        self.err_exempt += 1;
+        let result = run(self);
+        self.err_exempt -= 1;
+        result
+    }
+
+    /// Returns commands which give another instance of the shell the functions of this one, and
+    /// the variables its environment doesn't, including the locals of running functions
+    fn describe_state(&self) -> String {
+        let mut variables: BTreeMap<String, String> = self.variables.get_unexported().into_iter().collect();
+        for frame in &self.frames {
+            variables.extend(frame.locals.clone());
+        }
+        let mut script = String::new();
+        for (name, value) in variables.iter().filter(|(name, _)| is_valid_name(name)) {
+            script += &format!("{}={}\n", name, quote(value));
+        }
+        for (name, body) in &self.functions {
+            script += &format!("{}() {}\n", name, body);
+        }
+        script
+    }
+
+    /// Start `list` in a child process, as a job, without waiting for it. The job is run by
+    /// another instance of the shell, given the state it needs, rather than by a copy of this one
+    /// made by `fork`, which could run into a lock held by one of the threads it doesn't have,
+    /// such as one completing a path on a hung mount, and wait for it forever.
+    fn exec_background(&mut self, list: &AndOrList) -> ExitStatus {
+        let job = AndOrList {
+            background: false,
+            ..list.clone()
+        };
+        let mut process = Process::new(&self.executable);
+        process
+            .arg("-c")
+            .arg(format!("{}{}\n", self.describe_state(), job))
+            .arg(&self.name)
+            .args(self.get_positional())
+            .env_clear()
+            .envs(self.variables.get_exported());
+        if self.job_control.is_some() {
+            // a job has a process group of its own, which never has the terminal. Both processes
+            // set it, so that it's in place whichever of them runs first.
+            let setup = || {
+                unsafe { libc::setpgid(0, 0) };
+                reset_job_control_signals();
+                Ok(())
+            };
+            unsafe { process.pre_exec(setup) };
+        }
+        let started = self.streams.apply_to(&mut process).and_then(|_| {
+            // the job has no terminal to read from
+            process.stdin(Stdio::null()).spawn()
+        });
+        let pid = match started {
+            Ok(child) => child.id() as libc::pid_t,
+            Err(e) => {
+                self.report_error(format_args!("unable to start a job: {}", e));
+                return status_from_code(1);
+            }
+        };
+        if self.job_control.is_some() {
+            unsafe { libc::setpgid(pid, pid) };
+        }
+
+        let id = self.jobs.add(pid, describe_and_or(list)).id;
+        if self.interactive {
+            writeln!(self.stderr(), "[{}] {}", id, pid).unwrap_or_default();
+        }
+        self.warned_jobs = false;
+        self.last_job_pid = Some(pid);
+        self.last_status = status_from_code(0);
+        self.last_status
+    }
+
+    fn exec_command(&mut self, command: &Command) -> ExecResult {
+        let traced = self.is_traced();
+        if traced && let Command::Simple(_) | Command::Pipeline(_) = command {
+            self.run_trap("DEBUG")?;
+        }
+
+        let status = match command {
+            Command::Simple(simple) => self.exec_simple(simple)?,
+            Command::BraceGroup(list) => self.exec_list(list)?,
+            Command::Subshell(list) => self.exec_subshell(|shell| shell.exec_list(list))?,
+            Command::Not(command) => {
+                status_from_code(self.exec_exempt(|shell| shell.exec_command(command))?.success() as i32)
+            }
+            Command::Pipeline(commands) => self.exec_pipeline(commands)?,
+            Command::Redirected(redirected) => self.exec_redirected(redirected)?,
+            Command::Time(time) => self.exec_time(time)?,
+            Command::If(if_command) => self.exec_if(if_command)?,
+            Command::While(loop_command) => self.exec_loop(loop_command, false)?,
+            Command::Until(loop_command) => self.exec_loop(loop_command, true)?,
+            Command::For(for_command) => self.exec_for(for_command)?,
+            Command::Case(case_command) => self.exec_case(case_command)?,
+            Command::FunctionDefinition(definition) => {
+                debug!("Defining function '{}'", definition.name);
+                self.functions.insert(definition.name.clone(), definition.body.clone());
+                status_from_code(0)
+            }
+            Command::Python(code) => self.exec_python(code),
+        };
+        self.last_status = status;
+
+        let is_simple = matches!(command, Command::Simple(_) | Command::Subshell(_) | Command::Pipeline(_));
+        if traced && is_simple && !status.success() && self.err_exempt == 0 {
+            self.run_trap("ERR")?;
+        }
+        self.run_signal_traps()?;
+        Ok(status)
+    }
+
+    /// Run the command of `time`, then report on stderr how long it took and the CPU time it and
+    /// the processes it waited for used
+    fn exec_time(&mut self, time: &TimeCommand) -> ExecResult {
+        let (start, cpu) = (Instant::now(), get_cpu_times());
+        let result = match &time.command {
+            Some(command) => self.exec_command(command),
+            None => Ok(status_from_code(0)),
+        };
+        // the report is printed even if the command returned from a function or broke out of a loop
+        let report = format_timing(start.elapsed(), get_cpu_times() - cpu, time.posix);
+        write!(self.stderr(), "{}", report).unwrap_or_default();
+        result
+    }
+
+    /// Run a Python snippet in the shell's interpreter, printing what it wrote
+    #[cfg(feature = "python")]
+    fn exec_python(&mut self, code: &str) -> ExitStatus {
+        // the snippet may run commands in this shell, including more snippets
+        let interpreter = self.python.get_or_insert_with(|| Rc::new(PythonInterpreter::new())).clone();
+        let output = interpreter.run(code, self);
+        let (mut stdout, mut stderr) = (self.stdout(), self.stderr());
+        write!(stdout, "{}", output.stdout).unwrap_or_default();
+        write!(stderr, "{}", output.stderr).unwrap_or_default();
+        stdout.flush().unwrap_or_default();
+        status_from_code(output.status)
+    }
+
+    #[cfg(not(feature = "python"))]
+    fn exec_python(&mut self, _code: &str) -> ExitStatus {
+        let message = "py: this shell was built without Python support";
+        self.report_error(message);
+        status_from_code(1)
+    }
+
+    /// Run `run` with a copy of the shell's state, as a subshell, so that it can't change the
+    /// shell's variables, functions or working directory
+    fn exec_subshell<F>(&mut self, run: F) -> ExecResult
+    where
+        F: FnOnce(&mut Self) -> ExecResult,
+    {
+        // traps aren't inherited, but an `EXIT` trap set in the subshell runs as it ends
+        let traps = mem::take(&mut self.traps);
+        let copy = self.variables.copy();
+        let variables = std::mem::replace(&mut self.variables, copy);
+        let functions = self.functions.clone();
+        let frames = self.frames.clone();
+        let dir_stack = self.dir_stack.clone();
+        let working_dir = env::current_dir();
+
+        let result = run(self);
+        let status = match result {
+            Ok(status) | Err(ControlFlow::Return(status) | ControlFlow::Exit(status)) => status,
+            Err(ControlFlow::Interrupt(status)) => status,
+            Err(ControlFlow::Break(_) | ControlFlow::Continue(_)) => self.last_status,
+        };
+        let status = self.run_exit_trap(status);
+
+        self.traps = traps;
+        self.variables = variables;
+        self.functions = functions;
+        self.frames = frames;
+        self.dir_stack = dir_stack;
+        if let Ok(working_dir) = working_dir {
+            env::set_current_dir(working_dir).unwrap_or_default();
+        }
+        // an interruption abandons the rest of the command line outside the subshell too
+        match result {
+            Err(ControlFlow::Interrupt(_)) => Err(ControlFlow::Interrupt(status)),
+            _ => Ok(status),
+        }
+    }
+
+    /// Run a pipeline, each command reading what the one before it writes, returning the status of
+    /// the last. Programs run at the same time, in one process group. The other commands run in
+    /// the shell one after the other, each as a subshell, and what they write is kept in a file
+    /// until the command after them reads it, so that none of them waits for another to read.
+    fn exec_pipeline(&mut self, commands: &[Command]) -> ExecResult {
+        let terminal = self.job_control.and_then(|_| TerminalState::save());
+        let (mut children, mut last, mut interrupted) = (vec![], None, None);
+        let mut input = self.streams.get(0).cloned();
+        for (i, command) in commands.iter().enumerate() {
+            let program = self.as_program(command);
+            let mut streams = self.streams.clone();
+            streams.set(0, input.take());
+            if i + 1 < commands.len() {
+                let output = match program {
+                    // a program can start reading what the one before it writes straight away
+                    Some(_) => pipe(),
+                    None => anonymous_file().and_then(|file| Ok((file.try_clone()?, file))),
+                };
+                let (reader, writer) = match output {
+                    Ok(output) => output,
+                    Err(e) => {
+                        let message = format!("unable to create a pipe: {}", e);
+                        self.report_error(message);
+                        last = Some(status_from_code(1));
+                        break;
+                    }
+                };
+                input = Some(Rc::new(reader));
+                streams.set(1, Some(Rc::new(writer)));
+            }
+
+            last = match program {
+                Some(program) => {
+                    let leader = children.first().map_or(0, |child: &Child| child.id() as libc::pid_t);
+                    let started = self.redirect_streams(&mut streams, program.redirections).and_then(|_| {
+                        let pgid = self.job_control.map(|_| leader);
+                        self.start_program(&program.args, program.environment, &streams, pgid)
+                    });
+                    match started {
+                        Ok(child) => {
+                            // the shell puts it in the group too, before the next process joins it
+                            if self.job_control.is_some() {
+                                let pid = child.id() as libc::pid_t;
+                                unsafe { libc::setpgid(pid, if leader == 0 { pid } else { leader }) };
+                            }
+                            children.push(child);
+                            None
+                        }
+                        Err(status) => Some(status),
+                    }
+                }
+                None => {
+                    let saved = mem::replace(&mut self.streams, streams);
+                    let result = self.exec_subshell(|shell| shell.exec_command(command));
+                    self.streams = saved;
+                    if let Some(input) = &input {
+                        input.as_ref().rewind().unwrap_or_default();
+                    }
+                    match result {
+                        Ok(status) => Some(status),
+                        Err(flow) => {
+                            interrupted = Some(flow);
+                            break;
+                        }
+                    }
+                }
+            };
+        }
+        // the shell mustn't hold on to a pipe, or the program reading it would never see it end
+        drop(input);
+
+        let result = self.wait_for_foreground(children, last, describe_pipeline(commands), terminal);
+        match interrupted {
+            Some(flow) => Err(flow),
+            None => result,
+        }
+    }
+
+    /// Returns `command` expanded ready to start if it runs a program, rather than something the
+    /// shell runs itself
+    fn as_program<'a>(&self, command: &'a Command) -> Option<ExternalCommand<'a>> {
+        let (simple, redirections) = match command {
+            Command::Simple(simple) => (simple, &[][..]),
+            Command::Redirected(RedirectedCommand { command, redirections }) => match command.as_ref() {
+                Command::Simple(simple) => (simple, redirections.as_slice()),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let args = self.expand_words(&simple.words);
+        let name = args.first()?;
+        if SPECIAL_BUILTINS.contains(&name.as_str()) || self.intrinsics.find(name).is_some() {
+            return None;
+        }
+        if self.functions.contains_key(name) {
+            return None;
+        }
+
+        let mut environment = self.variables.get_exported();
+        environment.extend(simple.assignments.iter().map(|a| (a.name.clone(), self.expand_word(&a.value))));
+        Some(ExternalCommand {
+            args,
+            environment,
+            redirections,
+        })
+    }
+
+    /// Run a command with its streams redirected, putting the shell's streams back afterwards
+    fn exec_redirected(&mut self, command: &RedirectedCommand) -> ExecResult {
+        let mut streams = self.streams.clone();
+        if let Err(status) = self.redirect_streams(&mut streams, &command.redirections) {
+            return Ok(status);
+        }
+        let saved = mem::replace(&mut self.streams, streams);
+        let result = self.exec_command(&command.command);
+        self.streams = saved;
+        result
+    }
+
+    /// Apply `redirections` to `streams` in order. If a file can't be opened, the error is
+    /// reported and the status to give returned.
+    fn redirect_streams(&self, streams: &mut Streams, redirections: &[Redirection]) -> Result<(), ExitStatus> {
+        for redirection in redirections {
+            let fd = redirection.fd as usize;
+            let target = self.expand_word(&redirection.target);
+            let source = target.parse::<usize>().ok().filter(|source| *source <= MAX_STREAM);
+            let bad_fd = || Err(io::Error::from_raw_os_error(libc::EBADF));
+            let file = match (redirection.operator, source) {
+                _ if fd > MAX_STREAM => bad_fd(),
+                (Operator::LessAnd | Operator::GreatAnd, Some(source)) => streams.duplicate(source),
+                (Operator::LessAnd | Operator::GreatAnd, None) => bad_fd(),
+                (Operator::Less, _) => File::open(&target),
+                (Operator::DoubleGreat, _) => OpenOptions::new().append(true).create(true).open(&target),
+                _ => File::create(&target),
+            };
+            match file {
+                Ok(file) => streams.set(fd, Some(Rc::new(file))),
+                Err(e) => {
+                    let name = if fd > MAX_STREAM { redirection.fd.to_string() } else { target };
+                    self.report_error(format_args!("{}: {}", name, e));
+                    return Err(status_from_code(1));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn exec_if(&mut self, command: &IfCommand) -> ExecResult {
+        for (condition, body) in &command.branches {
+            if self.exec_exempt(|shell| shell.exec_list(condition))?.success() {
+                return self.exec_list(body);
+            }
+        }
+
+        match &command.else_branch {
+            Some(body) => self.exec_list(body),
+            None => Ok(status_from_code(0)),
+        }
+    }
+
+    /// Run `body` as the body of a loop, returning `None` if the loop should stop
+    fn exec_loop_body(&mut self, body: &CommandList) -> Result<Option<ExitStatus>, ControlFlow> {
+        let frame = self.frames.last_mut().unwrap();
+        frame.loop_depth += 1;
+        let result = self.exec_list(body);
+        self.frames.last_mut().unwrap().loop_depth -= 1;
+
+        match result {
+            Ok(status) => Ok(Some(status)),
+            Err(ControlFlow::Break(1)) => Ok(None),
+            Err(ControlFlow::Break(levels)) => Err(ControlFlow::Break(levels - 1)),
+            Err(ControlFlow::Continue(1)) => Ok(Some(self.last_status)),
+            Err(ControlFlow::Continue(levels)) => Err(ControlFlow::Continue(levels - 1)),
+            Err(flow) => Err(flow),
+        }
+    }
+
+    /// Run a `while` loop, or an `until` loop if `until` is true
+    fn exec_loop(&mut self, command: &LoopCommand, until: bool) -> ExecResult {
+        let mut status = status_from_code(0);
+        while self.exec_exempt(|shell| shell.exec_list(&command.condition))?.success() != until {
+            match self.exec_loop_body(&command.body)? {
+                Some(body_status) => status = body_status,
+                None => return Ok(status_from_code(0)),
+            }
+        }
+
+        Ok(status)
+    }
+
+    fn exec_for(&mut self, command: &ForCommand) -> ExecResult {
+        let values = match &command.words {
+            Some(words) => self.expand_words(words),
+            None => self.get_positional().to_vec(),
+        };
+
+        let mut status = status_from_code(0);
+        for value in values {
+            self.set_variable(&command.variable, &value);
+            match self.exec_loop_body(&command.body)? {
+                Some(body_status) => status = body_status,
+                None => return Ok(status_from_code(0)),
+            }
+        }
+
+        Ok(status)
+    }
+
+    fn exec_case(&mut self, command: &CaseCommand) -> ExecResult {
+        let word = self.expand_word(&command.word);
+        for item in &command.items {
+            let matches = item
+                .patterns
+                .iter()
+                .any(|pattern| matches_pattern(&self.expand_pattern(pattern), &word));
+            if matches {
+                return self.exec_list(&item.body);
+            }
+        }
+
+        Ok(status_from_code(0))
+    }
+
+    fn exec_simple(&mut self, command: &SimpleCommand) -> ExecResult {
+        let args = self.expand_words(&command.words);
+        let assignments: Vec<(String, String)> = command
+            .assignments
+            .iter()
+            .map(|a| (a.name.clone(), self.expand_word(&a.value)))
+            .collect();
+        trace!("Executing {:?} with assignments {:?}", args, assignments);
+
+        if args.is_empty() {
+            for (name, value) in assignments {
+                self.set_variable(&name, &value);
+            }
+            return Ok(status_from_code(0));
+        }
+
+        if let Some(result) = self.run_builtin(&args, &assignments) {
+            return result;
+        }
+        if let Some(body) = self.get_function(&args[0]) {
+            return self.call_function(&body, args[1..].to_vec(), assignments);
+        }
+
+        self.run_external(&args, assignments)
+    }
+
+    /// Run `args` if it names a special builtin or an intrinsic, returning `None` otherwise
+    fn run_builtin(&mut self, args: &[String], assignments: &[(String, String)]) -> Option<ExecResult> {
+        let result = match args[0].as_str() {
+            "local" => Ok(self.builtin_local(&args[1..])),
+            "return" => self.builtin_return(&args[1..]),
+            "break" | "continue" => self.builtin_loop_control(&args[0], &args[1..]),
+            "source" | "." => self.builtin_source(&args[0], &args[1..]),
+            "builtin" => self.builtin_builtin(&args[1..]),
+            "command" => self.builtin_command(&args[1..], assignments),
+            name => {
+                let intrinsic = self.intrinsics.find(name)?;
+                self.run_intrinsic(intrinsic.as_ref(), args)
+            }
+        };
+        Some(result)
+    }
+
+    /// Run `intrinsic` with the standard streams of the command being run
+    fn run_intrinsic(&mut self, intrinsic: &dyn Intrinsic, args: &[String]) -> ExecResult {
+        let (mut stdin, mut stdout) = (self.streams.stdin(), self.streams.stdout());
+        let mut stderr = self.error_stream();
+        let mut streams = IntrinsicIo {
+            stdin:  &mut stdin,
+            stdout: &mut stdout,
+            stderr: &mut stderr,
+        };
+        let code = if args.len() == 2 && (args[1] == "--help" || args[1] == "-h") {
+            writeln!(streams.stdout, "{}", intrinsic.get_description()).map_or(1, |_| 0)
+        }
+        else {
+            intrinsic.run(self, &mut streams, &args[1..])
+        };
+        stdout.flush().unwrap_or_default();
+        let status = status_from_code(code);
+
+        if let Some(status) = self.exit_status.take() {
+            return Err(ControlFlow::Exit(status));
+        }
+        Ok(status)
+    }
+
+    /// Run `body` with `args` as its positional parameters, and any `assignments` preceding the
+    /// call as local variables
+    fn call_function(
+        &mut self,
+        body: &Command,
+        args: Vec<String>,
+        assignments: Vec<(String, String)>,
+    ) -> ExecResult {
+        if self.frames.len() > MAX_CALL_DEPTH {
+            let message = format!("maximum function nesting level exceeded ({})", MAX_CALL_DEPTH);
+            self.report_error(message);
+            return Ok(status_from_code(1));
+        }
+
+        self.frames.push(Frame {
+            locals:     assignments.into_iter().collect(),
+            positional: args,
+            loop_depth: 0,
+        });
+        let result = self.exec_command(body);
+        // the trap runs in the function, with its positional parameters
+        let trap = match self.in_trap {
+            true => Ok(()),
+            false => self.run_trap("RETURN"),
+        };
+        self.frames.pop();
+        trap?;
+
+        match result {
+            Err(ControlFlow::Return(status)) => Ok(status),
+            result => result,
+        }
+    }
+
+    fn run_external(&mut self, args: &[String], assignments: Vec<(String, String)>) -> ExecResult {
+        let mut environment = self.variables.get_exported();
+        environment.extend(assignments);
+
+        // a command in the foreground may change the terminal's settings, and not put them back
+        let terminal = self.job_control.and_then(|_| TerminalState::save());
+        match self.start_program(args, environment, &self.streams, self.job_control.map(|_| 0)) {
+            Ok(child) => self.wait_for_foreground(vec![child], None, args.join(" "), terminal),
+            Err(status) => Ok(status),
+        }
+    }
+
+    /// Start the program `args` with `streams`, in the process group `pgid` as [dispatch_command]
+    /// does. If it can't be started, the error is reported and the status to give returned.
+    fn start_program(
+        &self,
+        args: &[String],
+        environment: Vec<(String, String)>,
+        streams: &Streams,
+        pgid: Option<libc::pid_t>,
+    ) -> Result<Child, ExitStatus> {
+        dispatch_command(args, environment, streams, pgid).map_err(|e| {
+            debug!("Unable to run '{}': {}", args[0], e);
+            // the process may have taken the terminal before it failed to run the command
+            if let Some(job_control) = self.job_control {
+                job_control.take_terminal();
+            }
+            let (message, code) = describe_spawn_error(&args[0], &e);
+            self.report_error(format_args!("{}: {}", args[0], message));
+            status_from_code(code)
+        })
+    }
+
+    /// Wait for the processes started to run a command in the foreground, the first of which
+    /// leads their process group if the shell has job control. Returns the status of the last, or
+    /// `last` if the command ended with something the shell ran itself. If the processes are
+    /// stopped, they carry on as a job running `command`.
+    fn wait_for_foreground(
+        &mut self,
+        children: Vec<Child>,
+        last: Option<ExitStatus>,
+        command: String,
+        terminal: Option<TerminalState>,
+    ) -> ExecResult {
+        let (job_control, pgid) = match (self.job_control, children.first()) {
+            (Some(job_control), Some(leader)) => (job_control, leader.id() as libc::pid_t),
+            _ => {
+                let mut status = status_from_code(0);
+                for mut child in children {
+                    status = self.report_termination(child.wait().unwrap_or(status_from_code(1)));
+                }
+                return Ok(last.unwrap_or(status));
+            }
+        };
+
+        job_control.give_terminal(pgid);
+        let mut statuses = vec![];
+        for child in &children {
+            match wait_for_process(child.id() as libc::pid_t) {
+                Some(status) => statuses.push(status),
+                None => break,
+            }
+        }
+        job_control.take_terminal();
+        if let Some(terminal) = terminal {
+            terminal.restore_after_command();
+        }
+
+        if statuses.len() < children.len() {
+            // stopped by Ctrl-Z, so it carries on as a job
+            let description = self.jobs.add_stopped(pgid, command).describe_running();
+            writeln!(self.stderr(), "\n{}", description).unwrap_or_default();
+            self.warned_jobs = false;
+            return Ok(status_from_code(128 + libc::SIGTSTP));
+        }
+        if let Some(status) = statuses.iter().find(|status| status.signal() == Some(libc::SIGINT)) {
+            // the terminal echoed `^C`, and the next prompt should start on a line of its own
+            writeln!(self.stderr()).unwrap_or_default();
+            return Err(ControlFlow::Interrupt(last.unwrap_or(*status)));
+        }
+        let mut status = status_from_code(0);
+        for child_status in statuses {
+            status = self.report_termination(child_status);
+        }
+        Ok(last.unwrap_or(status))
+    }
+
+    /// Report how a command ended if it was killed by a signal, returning its `status`
+    fn report_termination(&self, status: ExitStatus) -> ExitStatus {
+        if let Some(description) = describe_termination(&status) {
+            writeln!(self.stderr(), "{}", description).unwrap_or_default();
+        }
+        status
+    }
+
+    /// `local name[=value] ...`, declare variables local to the running function
+    fn builtin_local(&mut self, args: &[String]) -> ExitStatus {
+        if self.frames.len() == 1 {
+            writeln!(self.error_stream(), "local: can only be used in a function").unwrap_or_default();
+            return status_from_code(1);
+        }
+
+        let mut status = status_from_code(0);
+        for arg in args {
+            let (name, value) = arg.split_once('=').unwrap_or((arg, ""));
+            if !is_valid_name(name) {
+                writeln!(self.error_stream(), "local: '{}': not a valid identifier", arg).unwrap_or_default();
+                status = status_from_code(1);
+                continue;
+            }
+            let frame = self.frames.last_mut().unwrap();
+            frame.locals.insert(name.to_string(), value.to_string());
+        }
+
+        status
+    }
+
+    /// `return [n]`, return from the running function or sourced file with status `n`, or the
+    /// status of the last command
+    fn builtin_return(&mut self, args: &[String]) -> ExecResult {
+        if self.frames.len() == 1 && self.source_depth == 0 {
+            let message = "return: can only `return' from a function or sourced script";
+            writeln!(self.error_stream(), "{}", message).unwrap_or_default();
+            return Ok(status_from_code(1));
+        }
+
+        let status = match args.first().map(|arg| arg.parse::<i32>()) {
+            None => self.last_status,
+            Some(Ok(code)) => status_from_code(code),
+            Some(Err(_)) => {
+                let message = format!("return: {}: numeric argument required", args[0]);
+                writeln!(self.error_stream(), "{}", message).unwrap_or_default();
+                status_from_code(2)
+            }
+        };
+
+        Err(ControlFlow::Return(status))
+    }
+
+    /// `break [n]` or `continue [n]`, applied to the `n`th enclosing loop
+    fn builtin_loop_control(&mut self, command: &str, args: &[String]) -> ExecResult {
+        let loop_depth = self.frames.last().unwrap().loop_depth;
+        if loop_depth == 0 {
+            let message = "only meaningful in a `for', `while', or `until' loop";
+            writeln!(self.error_stream(), "{}: {}", command, message).unwrap_or_default();
+            return Ok(status_from_code(0));
+        }
+
+        let levels = match args.first().map(|arg| arg.parse::<usize>()) {
+            None => 1,
+            Some(Ok(levels)) if levels > 0 => levels.min(loop_depth),
+            _ => {
+                let message = format!("{}: {}: loop count out of range", command, args[0]);
+                writeln!(self.error_stream(), "{}", message).unwrap_or_default();
+                return Ok(status_from_code(1));
+            }
+        };
+
+        if command == "break" {
+            Err(ControlFlow::Break(levels))
+        }
+        else {
+            Err(ControlFlow::Continue(levels))
+        }
+    }
+
+    /// Run the commands in the file at `path`, as `source` does. `return` at the top level of the
+    /// file stops reading it.
+    pub fn source_file(&mut self, path: &Path) -> ExecResult {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                self.report_error(format_args!("{}: {}", path.display(), e));
+                return Ok(status_from_code(1));
+            }
+        };
+
+        self.source_depth += 1;
+        let result = self.run_source(BufReader::new(file), &path.to_string_lossy());
+        self.source_depth -= 1;
+        result
+    }
+
+    /// Returns the file `source name` reads: `name` itself if it contains a `/`, and otherwise
+    /// the first file called `name` in `$PATH`, falling back to the current directory
+    fn find_source_file(&self, name: &str) -> PathBuf {
+        if !name.contains('/') && let Some(path) = self.get_variable("PATH") {
+            let found = path
+                .split(':')
+                .filter(|dir| !dir.is_empty())
+                .map(|dir| Path::new(dir).join(name))
+                .find(|candidate| candidate.is_file());
+            if let Some(found) = found {
+                return found;
+            }
+        }
+        PathBuf::from(name)
+    }
+
+    /// `source file [arg ...]` or `. file [arg ...]`, run the commands in `file` in the current
+    /// shell, with `arg`s as the positional parameters while it runs
+    fn builtin_source(&mut self, command: &str, args: &[String]) -> ExecResult {
+        let name = match args.first() {
+            Some(name) => name,
+            None => {
+                writeln!(self.error_stream(), "{}: filename argument required", command).unwrap_or_default();
+                return Ok(status_from_code(2));
+            }
+        };
+        let path = self.find_source_file(name);
+
+        let result = match args.len() {
+            1 => self.source_file(&path),
+            _ => {
+                let saved = self.replace_positional(args[1..].to_vec());
+                let result = self.source_file(&path);
+                self.replace_positional(saved);
+                result
+            }
+        };
+        if !self.in_trap {
+            self.run_trap("RETURN")?;
+        }
+        result
+    }
+
+    /// `builtin name [arg ...]`, run the builtin or intrinsic `name`, even if a function has the
+    /// same name
+    fn builtin_builtin(&mut self, args: &[String]) -> ExecResult {
+        if args.is_empty() {
+            return Ok(status_from_code(0));
+        }
+        self.run_builtin(args, &[]).unwrap_or_else(|| {
+            writeln!(self.error_stream(), "builtin: {}: not a shell builtin", args[0]).unwrap_or_default();
+            Ok(status_from_code(1))
+        })
+    }
+
+    /// `command name [arg ...]`, run `name` ignoring any function with that name, or `command -v`
+    /// and `command -V`, describe how each name would be run briefly or in full
+    fn builtin_command(&mut self, args: &[String], assignments: &[(String, String)]) -> ExecResult {
+        match args.first().map(String::as_str) {
+            None => Ok(status_from_code(0)),
+            Some("-v") => Ok(self.describe_commands(&args[1..], false)),
+            Some("-V") => Ok(self.describe_commands(&args[1..], true)),
+            Some(_) => match self.run_builtin(args, assignments) {
+                Some(result) => result,
+                None => self.run_external(args, assignments.to_vec()),
+            },
+        }
+    }
+
+    /// Print how each of `names` would be run, as `command -v` (or `command -V` if `verbose`) does
+    fn describe_commands(&self, names: &[String], verbose: bool) -> ExitStatus {
+        let (mut stdout, mut status) = (self.stdout(), status_from_code(0));
+        for name in names {
+            let result = match self.find_command(name) {
+                Some(kind) if verbose => writeln!(stdout, "{}", kind.describe(name)),
+                Some(CommandKind::Alias(value)) => writeln!(stdout, "alias {}={}", name, quote(&value)),
+                Some(CommandKind::External(path)) => writeln!(stdout, "{}", path.display()),
+                Some(_) => writeln!(stdout, "{}", name),
+                None => {
+                    status = status_from_code(1);
+                    match verbose {
+                        true => writeln!(self.error_stream(), "command: {}: not found", name),
+                        false => Ok(()),
+                    }
+                }
+            };
+            if result.is_err() {
+                return status_from_code(1);
+            }
+        }
+        stdout.flush().unwrap_or_default();
+        status
+    }
+}
@@ -0,0 +1,149 @@
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// The signals an interactive shell ignores, so that Ctrl-C, Ctrl-\ and Ctrl-Z only reach the
+/// commands it runs, and so that it can hand the terminal to them and take it back
+const JOB_CONTROL_SIGNALS: [libc::c_int; 5] =
+    [libc::SIGINT, libc::SIGQUIT, libc::SIGTSTP, libc::SIGTTIN, libc::SIGTTOU];
+
+/// Set when the shell is sent `SIGHUP`, usually because its terminal has gone away
+static HANGUP: AtomicBool = AtomicBool::new(false);
+/// A bit for each signal which has been caught since the shell last ran the traps for them
+static PENDING: AtomicU64 = AtomicU64::new(0);
+
+/// The signals the shell knows by name, with their numbers on this system
+pub const SIGNALS: [(&str, libc::c_int); 28] = [
+    ("SIGHUP", libc::SIGHUP),
+    ("SIGINT", libc::SIGINT),
+    ("SIGQUIT", libc::SIGQUIT),
+    ("SIGILL", libc::SIGILL),
+    ("SIGTRAP", libc::SIGTRAP),
+    ("SIGABRT", libc::SIGABRT),
+    ("SIGBUS", libc::SIGBUS),
+    ("SIGFPE", libc::SIGFPE),
+    ("SIGKILL", libc::SIGKILL),
+    ("SIGUSR1", libc::SIGUSR1),
+    ("SIGSEGV", libc::SIGSEGV),
+    ("SIGUSR2", libc::SIGUSR2),
+    ("SIGPIPE", libc::SIGPIPE),
+    ("SIGALRM", libc::SIGALRM),
+    ("SIGTERM", libc::SIGTERM),
+    ("SIGCHLD", libc::SIGCHLD),
+    ("SIGCONT", libc::SIGCONT),
+    ("SIGSTOP", libc::SIGSTOP),
+    ("SIGTSTP", libc::SIGTSTP),
+    ("SIGTTIN", libc::SIGTTIN),
+    ("SIGTTOU", libc::SIGTTOU),
+    ("SIGURG", libc::SIGURG),
+    ("SIGXCPU", libc::SIGXCPU),
+    ("SIGXFSZ", libc::SIGXFSZ),
+    ("SIGVTALRM", libc::SIGVTALRM),
+    ("SIGPROF", libc::SIGPROF),
+    ("SIGWINCH", libc::SIGWINCH),
+    ("SIGSYS", libc::SIGSYS),
+];
+
+/// Returns the name of `signal`, e.g. `SIGSEGV`, or `None` if it isn't one the shell knows
+pub fn get_signal_name(signal: libc::c_int) -> Option<&'static str> {
+    SIGNALS.iter().find(|(_, number)| *number == signal).map(|(name, _)| *name)
+}
+
+/// Returns the number of the signal called `name`, with or without its `SIG` prefix and in any
+/// case, or given as its number
+pub fn get_signal_number(name: &str) -> Option<libc::c_int> {
+    if let Ok(number) = name.parse() {
+        return get_signal_name(number).map(|_| number);
+    }
+    let name = name.to_ascii_uppercase();
+    let name = name.strip_prefix("SIG").unwrap_or(&name);
+    SIGNALS.iter().find(|(known, _)| known[3..] == *name).map(|(_, number)| *number)
+}
+
+/// Returns what to report when a command was killed by a signal, e.g. `[SIGSEGV] core dumped`,
+/// or `None` if it exited, or was interrupted from the terminal or by writing to a closed pipe,
+/// which speak for themselves
+pub fn describe_termination(status: &ExitStatus) -> Option<String> {
+    let signal = status.signal()?;
+    if signal == libc::SIGINT || signal == libc::SIGPIPE {
+        return None;
+    }
+    let name = match get_signal_name(signal) {
+        Some(name) => name.to_string(),
+        None => format!("signal {}", signal),
+    };
+    match status.core_dumped() {
+        true => Some(format!("[{}] core dumped", name)),
+        false => Some(format!("[{}]", name)),
+    }
+}
+
+/// Ignore the signals the terminal sends, as an interactive shell does
+pub fn ignore_job_control_signals() {
+    for signal in JOB_CONTROL_SIGNALS {
+        unsafe { libc::signal(signal, libc::SIG_IGN) };
+    }
+}
+
+/// Restore the default handling of the signals the shell ignores, in a process it has started.
+/// This runs between `fork` and `exec`, so it may only make async-signal-safe calls.
+pub fn reset_job_control_signals() {
+    for signal in JOB_CONTROL_SIGNALS {
+        unsafe { libc::signal(signal, libc::SIG_DFL) };
+    }
+}
+
+/// Returns whether the shell ignores `signal` while it has job control
+pub fn is_job_control_signal(signal: libc::c_int) -> bool {
+    JOB_CONTROL_SIGNALS.contains(&signal)
+}
+
+/// Only records the signal, for the shell to act on once it's safe to
+extern "C" fn on_signal(signal: libc::c_int) {
+    if signal == libc::SIGHUP {
+        HANGUP.store(true, Ordering::SeqCst);
+    }
+    if (0..64).contains(&signal) {
+        PENDING.fetch_or(1 << signal, Ordering::SeqCst);
+    }
+}
+
+fn set_handler(signal: libc::c_int, handler: libc::sighandler_t) {
+    let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+    action.sa_sigaction = handler;
+    unsafe {
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(signal, &action, std::ptr::null_mut());
+    }
+}
+
+/// Catch `signal` rather than be killed by it, so that the shell can run its trap, or for
+/// `SIGHUP`, pass it on to its jobs. Reading from the terminal is interrupted by it rather than
+/// restarted.
+pub fn catch_signal(signal: libc::c_int) {
+    set_handler(signal, on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t);
+}
+
+pub fn ignore_signal(signal: libc::c_int) {
+    set_handler(signal, libc::SIG_IGN);
+}
+
+pub fn default_signal(signal: libc::c_int) {
+    set_handler(signal, libc::SIG_DFL);
+}
+
+/// Returns whether signals have been caught since [take_pending_signals] last took them
+pub fn has_pending_signals() -> bool {
+    PENDING.load(Ordering::SeqCst) != 0
+}
+
+/// Returns the signals caught since this was last called, in order of their numbers
+pub fn take_pending_signals() -> Vec<libc::c_int> {
+    let pending = PENDING.swap(0, Ordering::SeqCst);
+    (0..64).filter(|signal| pending & (1 << signal) != 0).collect()
+}
+
+/// Returns whether the shell has been sent `SIGHUP` since it started catching it
+pub fn is_hung_up() -> bool {
+    HANGUP.load(Ordering::SeqCst)
+}
@@ -0,0 +1,146 @@
+use std::fmt::Display;
+use std::io::{self, BufRead, Write};
+
+use crate::parser::ParseError;
+use crate::shell::{status_from_code, ControlFlow, ExecResult, Shell, Stream, SHELL_NAME};
+
+/// Where a command was read from, when it came from a file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub name: String,
+    /// the line the command starts on
+    pub line: usize,
+}
+
+/// The shell's stderr, which starts each line written to it with where the command being run was
+/// read from, if it came from a file
+pub struct ErrorStream {
+    stream:     Stream,
+    prefix:     Option<String>,
+    line_start: bool,
+}
+
+impl Write for ErrorStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for line in buf.split_inclusive(|&byte| byte == b'\n') {
+            if self.line_start && let Some(prefix) = &self.prefix {
+                self.stream.write_all(prefix.as_bytes())?;
+            }
+            self.stream.write_all(line)?;
+            self.line_start = line.ends_with(b"\n");
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl Shell {
+    /// Returns the stream the shell's builtins and intrinsics write their errors to
+    pub fn error_stream(&self) -> ErrorStream {
+        let prefix = self
+            .get_location()
+            .map(|location| format!("{}: {}:{}: ", SHELL_NAME, location.name, location.line));
+        ErrorStream {
+            stream: self.stderr(),
+            prefix,
+            line_start: true,
+        }
+    }
+
+    /// Report an error of the shell's own, saying where the command being run was read from if
+    /// it came from a file
+    pub fn report_error(&self, message: impl Display) {
+        match self.get_location() {
+            Some(_) => writeln!(self.error_stream(), "{}", message),
+            None => writeln!(self.stderr(), "{}: {}", SHELL_NAME, message),
+        }
+        .unwrap_or_default();
+    }
+
+    /// Note that the command being run starts on `line` of `source_name`
+    fn set_line(&mut self, source_name: &str, line: usize) {
+        let location = SourceLocation {
+            name: source_name.to_string(),
+            line,
+        };
+        self.replace_location(Some(location));
+    }
+
+    /// Report a syntax error in a command starting on line `start_line` of `source_name`
+    fn report_syntax_error(&self, source_name: &str, start_line: usize, error: &ParseError) {
+        let line = start_line + error.line - 1;
+        let location = format!("{}:{}:{}", source_name, line, error.column);
+        writeln!(self.stderr(), "{}: {}: {}", SHELL_NAME, location, error.message).unwrap_or_default();
+    }
+
+    /// Read and execute commands from `reader` a line at a time, reading more lines while a
+    /// command is incomplete. Reading stops at a syntax error, `return` or `exit`, and errors are
+    /// reported as coming from `source_name`.
+    pub fn run_source<R: BufRead>(&mut self, reader: R, source_name: &str) -> ExecResult {
+        let outer = self.get_location().cloned();
+        let result = self.read_source(reader, source_name);
+        self.replace_location(outer);
+        result
+    }
+
+    fn read_source<R: BufRead>(&mut self, reader: R, source_name: &str) -> ExecResult {
+        let mut buffer = String::new();
+        let mut start_line = 1;
+        let mut status = status_from_code(0);
+        for (idx, line) in reader.lines().enumerate() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    writeln!(self.stderr(), "{}: {}: {}", SHELL_NAME, source_name, e).unwrap_or_default();
+                    return Ok(status_from_code(1));
+                }
+            };
+            if buffer.is_empty() {
+                start_line = idx + 1;
+            }
+            buffer.push_str(&line);
+            buffer.push('\n');
+
+            let program = match self.parse(&buffer) {
+                Ok(program) => program,
+                Err(e) if e.incomplete => continue,
+                Err(e) => {
+                    self.report_syntax_error(source_name, start_line, &e);
+                    return Ok(status_from_code(2));
+                }
+            };
+            buffer.clear();
+
+            self.set_line(source_name, start_line);
+            status = match self.execute(&program) {
+                Ok(status) => status,
+                Err(ControlFlow::Return(status)) => return Ok(status),
+                Err(ControlFlow::Break(_) | ControlFlow::Continue(_)) => self.get_last_status(),
+                Err(flow @ (ControlFlow::Exit(_) | ControlFlow::Interrupt(_))) => return Err(flow),
+            };
+        }
+
+        if buffer.is_empty() {
+            return Ok(status);
+        }
+        // a `py:` block only ends at the end of the input, which a line of it couldn't know
+        let rest = buffer.trim_end_matches('\n');
+        let program = match self.parse(rest) {
+            Ok(program) if !rest.ends_with('\\') => program,
+            _ => {
+                let e = self.parse(&buffer).unwrap_err();
+                self.report_syntax_error(source_name, start_line, &e);
+                return Ok(status_from_code(2));
+            }
+        };
+        self.set_line(source_name, start_line);
+        match self.execute(&program) {
+            Ok(status) | Err(ControlFlow::Return(status)) => Ok(status),
+            Err(ControlFlow::Break(_) | ControlFlow::Continue(_)) => Ok(self.get_last_status()),
+            Err(flow @ (ControlFlow::Exit(_) | ControlFlow::Interrupt(_))) => Err(flow),
+        }
+    }
+}
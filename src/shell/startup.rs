@@ -0,0 +1,40 @@
+use std::path::{Path, PathBuf};
+
+use crate::shell::{status_from_code, ExecResult, Shell};
+
+/// The profile run by every login shell, before the user's own
+pub const SYSTEM_PROFILE: &str = "/etc/yeesh_profile";
+/// The rc file run by every interactive shell, before the user's own
+pub const SYSTEM_RC: &str = "/etc/yeeshrc";
+/// The user's profile, relative to `$HOME`
+pub const USER_PROFILE: &str = ".yeesh_profile";
+/// The user's rc file, relative to `$HOME`
+pub const USER_RC: &str = ".yeeshrc";
+
+/// Returns the files a shell runs as it starts, in order: the profiles if it is a login shell,
+/// then the rc files if `rc` is set (for an interactive shell, unless `--norc` was given)
+pub fn get_startup_files(login: bool, rc: bool, home: Option<&str>) -> Vec<PathBuf> {
+    let mut files = vec![];
+    if login {
+        files.push(PathBuf::from(SYSTEM_PROFILE));
+        files.extend(home.map(|home| Path::new(home).join(USER_PROFILE)));
+    }
+    if rc {
+        files.push(PathBuf::from(SYSTEM_RC));
+        files.extend(home.map(|home| Path::new(home).join(USER_RC)));
+    }
+    files
+}
+
+impl Shell {
+    /// Run each of the startup `files` which exists through `source`. A syntax error stops the
+    /// file it is in, but not the files after it. Returns the last status, or `Exit` if a file
+    /// exits the shell.
+    pub fn run_startup_files(&mut self, files: &[PathBuf]) -> ExecResult {
+        let mut status = status_from_code(0);
+        for file in files.iter().filter(|file| file.is_file()) {
+            status = self.source_file(file)?;
+        }
+        Ok(status)
+    }
+}
@@ -0,0 +1,128 @@
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::fd::{AsFd, OwnedFd};
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The highest numbered stream a redirection may apply to, stderr
+pub const MAX_STREAM: usize = 2;
+
+/// Where the standard streams of the commands the shell runs go, which redirections and pipes
+/// may send somewhere other than the shell's own
+#[derive(Clone, Debug, Default)]
+pub struct Streams {
+    /// the file each of stdin, stdout and stderr goes to, or `None` for the shell's own
+    files: [Option<Rc<File>>; MAX_STREAM + 1],
+}
+
+impl Streams {
+    /// Returns the file the stream `fd` goes to, or `None` if it is the shell's own
+    pub fn get(&self, fd: usize) -> Option<&Rc<File>> {
+        self.files[fd].as_ref()
+    }
+
+    /// Send the stream `fd` to `file`, or back to the shell's own if it is `None`
+    pub fn set(&mut self, fd: usize, file: Option<Rc<File>>) {
+        self.files[fd] = file;
+    }
+
+    /// Returns another handle on wherever the stream `fd` goes, as `>&` copies it
+    pub fn duplicate(&self, fd: usize) -> io::Result<File> {
+        if let Some(file) = &self.files[fd] {
+            return file.try_clone();
+        }
+        // what the shell has written to it so far comes first
+        io::stdout().flush()?;
+        let fd = match fd {
+            0 => io::stdin().as_fd().try_clone_to_owned()?,
+            1 => io::stdout().as_fd().try_clone_to_owned()?,
+            _ => io::stderr().as_fd().try_clone_to_owned()?,
+        };
+        Ok(File::from(fd))
+    }
+
+    /// Returns the stream `fd` for a process the shell starts
+    pub fn stdio(&self, fd: usize) -> io::Result<Stdio> {
+        match &self.files[fd] {
+            Some(file) => Ok(Stdio::from(file.try_clone()?)),
+            None => Ok(Stdio::inherit()),
+        }
+    }
+
+    /// Give `process` these streams as its stdin, stdout and stderr
+    pub fn apply_to(&self, process: &mut Command) -> io::Result<()> {
+        process.stdin(self.stdio(0)?).stdout(self.stdio(1)?).stderr(self.stdio(2)?);
+        Ok(())
+    }
+
+    pub fn stdin(&self) -> Stream {
+        self.stream(0)
+    }
+
+    pub fn stdout(&self) -> Stream {
+        self.stream(1)
+    }
+
+    pub fn stderr(&self) -> Stream {
+        self.stream(2)
+    }
+
+    fn stream(&self, fd: usize) -> Stream {
+        Stream {
+            fd,
+            file: self.files[fd].clone(),
+        }
+    }
+}
+
+/// One of the standard streams, which reads or writes wherever [Streams] sent it
+pub struct Stream {
+    fd:   usize,
+    file: Option<Rc<File>>,
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &self.file {
+            Some(file) => file.as_ref().read(buf),
+            None => io::stdin().read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &self.file {
+            Some(file) => file.as_ref().write(buf),
+            None if self.fd == 2 => io::stderr().write(buf),
+            None => io::stdout().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &self.file {
+            Some(file) => file.as_ref().flush(),
+            None if self.fd == 2 => io::stderr().flush(),
+            None => io::stdout().flush(),
+        }
+    }
+}
+
+/// Returns the reading and writing ends of a new pipe
+pub fn pipe() -> io::Result<(File, File)> {
+    let (reader, writer) = io::pipe()?;
+    Ok((OwnedFd::from(reader).into(), OwnedFd::from(writer).into()))
+}
+
+/// Returns a new file which is removed as soon as it is created, so that it's gone once the
+/// handles on it are closed
+pub fn anonymous_file() -> io::Result<File> {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let name = format!("yeesh-{}-{}", std::process::id(), COUNT.fetch_add(1, Ordering::Relaxed));
+    let path = env::temp_dir().join(name);
+    let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+    fs::remove_file(&path)?;
+    Ok(file)
+}
@@ -0,0 +1,88 @@
+use std::io::{self, Write};
+
+use log::debug;
+
+// expands to a literal, so that it can be part of other sequences
+macro_rules! reset_sequence {
+    () => {
+        concat!("\x1b[?25h", "\x1b[0m", "\x1b[?1000l\x1b[?1002l\x1b[?1003l\x1b[?1006l", "\x1b[?1l\x1b>")
+    };
+}
+
+/// Shows the cursor, resets colours and other text attributes, turns off mouse reporting and
+/// returns the cursor and keypad keys to sending their normal sequences, none of which changes
+/// anything on a terminal which is already that way
+pub const RESET_SEQUENCE: &str = reset_sequence!();
+
+/// Leaves the alternate screen as well as sending [RESET_SEQUENCE]: everything a full-screen
+/// program like `vim` or `less` changes and may not have put back. Leaving the alternate screen
+/// also moves the cursor back to where it was saved, so this is only for a terminal known to be
+/// left broken.
+pub const REPAIR_SEQUENCE: &str = concat!("\x1b[?1049l", reset_sequence!());
+
+/// The settings of the terminal on stdin, as `stty` shows them
+#[derive(Clone, Copy)]
+pub struct TerminalState {
+    termios: libc::termios,
+}
+
+impl TerminalState {
+    /// Returns the terminal's settings, or `None` if stdin isn't a terminal
+    pub fn save() -> Option<Self> {
+        let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+        match unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) } {
+            0 => Some(TerminalState { termios }),
+            _ => None,
+        }
+    }
+
+    /// Put the terminal's settings back as they were saved, once any output has been written
+    pub fn restore(&self) -> io::Result<()> {
+        match unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &self.termios) } {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    /// Returns the settings with line editing, echo, signal keys and output processing on, as
+    /// `stty sane` turns them on
+    pub fn sane(&self) -> Self {
+        let mut termios = self.termios;
+        termios.c_iflag |= libc::ICRNL | libc::IXON | libc::BRKINT;
+        termios.c_iflag &= !(libc::INLCR | libc::IGNCR);
+        termios.c_oflag |= libc::OPOST | libc::ONLCR;
+        termios.c_lflag |= libc::ICANON | libc::ECHO | libc::ECHOE | libc::ECHOK | libc::ISIG | libc::IEXTEN;
+        termios.c_cc[libc::VMIN] = 1;
+        termios.c_cc[libc::VTIME] = 0;
+        TerminalState { termios }
+    }
+
+    /// Returns whether the settings are the same as `other`'s
+    pub fn matches(&self, other: &TerminalState) -> bool {
+        let (a, b) = (&self.termios, &other.termios);
+        a.c_iflag == b.c_iflag
+            && a.c_oflag == b.c_oflag
+            && a.c_cflag == b.c_cflag
+            && a.c_lflag == b.c_lflag
+            && a.c_cc == b.c_cc
+    }
+
+    /// Put the terminal back as it was saved before a command ran in the foreground. A command
+    /// may leave the cursor hidden or mouse reporting on without touching the settings, so those
+    /// are always reset. If the command left the settings changed, it most likely crashed in the
+    /// middle of taking over the screen, so the alternate screen is left too.
+    pub fn restore_after_command(&self) {
+        let left = match TerminalState::save() {
+            Some(left) => left,
+            None => return,
+        };
+        let mut stdout = io::stdout();
+        if left.matches(self) {
+            write!(stdout, "{}", RESET_SEQUENCE).and_then(|_| stdout.flush()).unwrap_or_default();
+            return;
+        }
+        debug!("Repairing the terminal after a command left it changed");
+        write!(stdout, "{}", REPAIR_SEQUENCE).and_then(|_| stdout.flush()).unwrap_or_default();
+        self.restore().unwrap_or_default();
+    }
+}
@@ -0,0 +1,52 @@
+#[cfg(test)]
+mod pattern_tests {
+    use crate::shell::pattern::{matches_pattern, to_pattern, PatternChar};
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        matches_pattern(&to_pattern(pattern, false), text)
+    }
+
+    #[test]
+    fn test_literal() {
+        assert!(matches("abc", "abc"));
+        assert!(!matches("abc", "abcd"));
+        assert!(!matches("abc", "ab"));
+        assert!(matches("", ""));
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(matches("*", ""));
+        assert!(matches("*.rs", "main.rs"));
+        assert!(!matches("*.rs", "main.rc"));
+        assert!(matches("a*b*c", "aXbYbZc"));
+        assert!(matches("??", "ab"));
+        assert!(!matches("??", "abc"));
+        assert!(matches("h?llo*", "hello world"));
+    }
+
+    #[test]
+    fn test_brackets() {
+        assert!(matches("[abc]", "b"));
+        assert!(!matches("[abc]", "d"));
+        assert!(matches("[a-c]x", "cx"));
+        assert!(matches("[!a-c]", "d"));
+        assert!(!matches("[^a-c]", "a"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[a-]", "-"));
+        // an unclosed bracket is literal
+        assert!(matches("[ab", "[ab"));
+    }
+
+    #[test]
+    fn test_quoted() {
+        let mut pattern = to_pattern("*", true);
+        pattern.extend(to_pattern("*", false));
+        assert!(matches_pattern(&pattern, "*abc"));
+        assert!(!matches_pattern(&pattern, "abc"));
+
+        let quoted = [PatternChar { c: '?', quoted: true }];
+        assert!(matches_pattern(&quoted, "?"));
+        assert!(!matches_pattern(&quoted, "a"));
+    }
+}
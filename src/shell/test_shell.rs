@@ -0,0 +1,750 @@
+#[cfg(test)]
+mod shell_tests {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::process::ExitStatusExt;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, Instant};
+
+    use filesystem::{FakeFileSystem, FileSystem};
+    use pretty_assertions::assert_eq;
+
+    use crate::intrinsics::{run_buffered, AliasTable, IntrinsicRegistry, Trap};
+    use crate::parser::{Parser, Word};
+    use crate::shell::{
+        get_startup_files, status_code, status_from_code, wait_for_process, CommandKind, ControlFlow, JobTable,
+        Shell, VariableStore,
+    };
+
+    fn setup() -> Shell {
+        let intrinsics = IntrinsicRegistry::with_defaults(FakeFileSystem::new());
+        Shell::new(VariableStore::new(), AliasTable::new(), intrinsics)
+    }
+
+    /// Returns the shell's executable, which cargo builds next to the directory of the tests, to
+    /// run jobs with rather than the tests
+    fn shell_executable() -> PathBuf {
+        let deps = std::env::current_exe().unwrap().parent().unwrap().to_path_buf();
+        deps.parent().unwrap().join(env!("CARGO_PKG_NAME"))
+    }
+
+    /// Run `input`, returning its numeric exit status
+    fn run(shell: &mut Shell, input: &str) -> i32 {
+        status_code(&shell.run(input).unwrap())
+    }
+
+    fn expand(shell: &Shell, input: &str) -> Vec<String> {
+        let program = Parser::new(&format!("x {}", input)).unwrap().parse_program().unwrap();
+        match &program.commands[0].first {
+            crate::parser::Command::Simple(simple) => shell.expand_words(&simple.words[1..]),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_assignment_and_expansion() {
+        let mut shell = setup();
+        assert_eq!(run(&mut shell, "a=1 b='two words'; c=$a$b"), 0);
+        assert_eq!(shell.get_variable("c").as_deref(), Some("1two words"));
+
+        assert_eq!(expand(&shell, "$a ${a}x '$a' \"$b\""), vec!["1", "1x", "$a", "two words"]);
+        assert_eq!(expand(&shell, "$b"), vec!["two", "words"]);
+        assert_eq!(expand(&shell, "x$b\"\" \"\" $unset"), vec!["xtwo", "words", ""]);
+    }
+
+    #[test]
+    fn test_positional_parameters() {
+        let mut shell = setup();
+        shell.set_positional(vec!["a b".to_string(), "c".to_string()]);
+
+        assert_eq!(expand(&shell, "$# $1 $2 $3"), vec!["2", "a", "b", "c"]);
+        assert_eq!(expand(&shell, "\"$@\""), vec!["a b", "c"]);
+        assert_eq!(expand(&shell, "\"<$@>\""), vec!["<a b", "c>"]);
+        assert_eq!(expand(&shell, "\"$*\" $@"), vec!["a b c", "a", "b", "c"]);
+        // `${00}` is the positional parameter numbered 0, which doesn't exist, unlike `$0`
+        assert_eq!(expand(&shell, "\"${00}\" ${01}"), vec!["", "a", "b"]);
+
+        shell.set_positional(vec![]);
+        assert!(expand(&shell, "\"$@\"").is_empty());
+    }
+
+    #[test]
+    fn test_tilde() {
+        let mut shell = setup();
+        shell.set_variable("HOME", "/home/me");
+
+        assert_eq!(expand(&shell, "~ ~/x '~' a~ ~user"), vec!["/home/me", "/home/me/x", "~", "a~", "~user"]);
+        let word = Word::literal("~/y");
+        assert_eq!(shell.expand_word(&word), "/home/me/y");
+    }
+
+    #[test]
+    fn test_functions() {
+        let mut shell = setup();
+        assert_eq!(run(&mut shell, "set_args() { first=$1; count=$#; all=\"$*\"; }"), 0);
+        assert!(shell.get_function("set_args").is_some());
+
+        run(&mut shell, "set_args a 'b c' d");
+        assert_eq!(shell.get_variable("first").as_deref(), Some("a"));
+        assert_eq!(shell.get_variable("count").as_deref(), Some("3"));
+        assert_eq!(shell.get_variable("all").as_deref(), Some("a b c d"));
+
+        // the caller's positional parameters are restored afterwards
+        shell.set_positional(vec!["outer".to_string()]);
+        run(&mut shell, "function f { inner=$1; }; f x; outer=$1");
+        assert_eq!(shell.get_variable("inner").as_deref(), Some("x"));
+        assert_eq!(shell.get_variable("outer").as_deref(), Some("outer"));
+    }
+
+    #[test]
+    fn test_return() {
+        let mut shell = setup();
+        run(&mut shell, "f() { reached=1; return 3; reached=2; }");
+
+        assert_eq!(run(&mut shell, "f"), 3);
+        assert_eq!(shell.get_variable("reached").as_deref(), Some("1"));
+        assert_eq!(run(&mut shell, "f; status=$?"), 0);
+        assert_eq!(shell.get_variable("status").as_deref(), Some("3"));
+
+        run(&mut shell, "g() { f; return; }");
+        assert_eq!(run(&mut shell, "g"), 3);
+        assert_eq!(run(&mut shell, "f || after=yes"), 0);
+        assert_eq!(shell.get_variable("after").as_deref(), Some("yes"));
+
+        assert_eq!(run(&mut shell, "return 4"), 1);
+        assert_eq!(run(&mut shell, "h() { return x; }; h"), 2);
+    }
+
+    #[test]
+    fn test_local_variables() {
+        let mut shell = setup();
+        run(&mut shell, "x=global; y=global");
+        run(&mut shell, "inner() { seen=$x; x=changed; y=changed; }");
+        run(&mut shell, "outer() { local x=local z; inner; after=$x; }");
+
+        assert_eq!(run(&mut shell, "outer"), 0);
+        assert_eq!(shell.get_variable("seen").as_deref(), Some("local"));
+        assert_eq!(shell.get_variable("after").as_deref(), Some("changed"));
+        assert_eq!(shell.get_variable("x").as_deref(), Some("global"));
+        assert_eq!(shell.get_variable("y").as_deref(), Some("changed"));
+        assert_eq!(shell.get_variable("z"), None);
+
+        assert_eq!(run(&mut shell, "local a=1"), 1);
+        assert_eq!(shell.get_variable("a"), None);
+
+        // assignments before a function call are local to it
+        run(&mut shell, "show() { shown=$v; }; v=global; v=prefixed show");
+        assert_eq!(shell.get_variable("shown").as_deref(), Some("prefixed"));
+        assert_eq!(shell.get_variable("v").as_deref(), Some("global"));
+    }
+
+    #[test]
+    fn test_recursion_limit() {
+        // give the test the same stack as the shell's main thread
+        let handle = std::thread::Builder::new()
+            .stack_size(8 * 1024 * 1024)
+            .spawn(|| {
+                let mut shell = setup();
+                run(&mut shell, "f() { f; }; f")
+            })
+            .unwrap();
+        assert_eq!(handle.join().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        let mut shell = setup();
+        assert_eq!(run(&mut shell, "f() { a"), 2);
+        assert!(shell.get_function("f").is_none());
+    }
+
+    #[test]
+    fn test_dispatch_order() {
+        let mut shell = setup();
+        let aliases = shell.get_aliases().clone();
+        // `unalias` is an intrinsic, so this function is never called
+        run(&mut shell, "unalias() { called=1; }; unalias not-an-alias");
+        assert_eq!(shell.get_variable("called"), None);
+
+        // aliases are expanded before functions are looked up
+        run(&mut shell, "greet() { greeted=$1; }");
+        aliases.set("hi", "greet hello");
+        run(&mut shell, "hi");
+        assert_eq!(shell.get_variable("greeted").as_deref(), Some("hello"));
+    }
+
+    #[test]
+    fn test_intrinsic_registry() {
+        let fs = FakeFileSystem::new();
+        fs.create_dir_all("/home/user").unwrap();
+        let intrinsics = IntrinsicRegistry::with_defaults(fs.clone());
+        let mut shell = Shell::new(VariableStore::new(), AliasTable::new(), intrinsics);
+
+        assert_eq!(run(&mut shell, "cd /nowhere"), 1);
+        assert_eq!(run(&mut shell, "cd /home/user"), 0);
+        assert_eq!(fs.current_dir().unwrap(), PathBuf::from("/home/user"));
+
+        // intrinsics can be removed while the shell runs, leaving functions of the same name
+        assert_eq!(run(&mut shell, "type cd"), 0);
+        assert!(shell.get_intrinsics().remove("cd"));
+        assert_eq!(run(&mut shell, "cd() { went=$1; }; cd /home"), 0);
+        assert_eq!(shell.get_variable("went").as_deref(), Some("/home"));
+        assert_eq!(fs.current_dir().unwrap(), PathBuf::from("/home/user"));
+    }
+
+    #[test]
+    fn test_builtin_and_command() {
+        let mut shell = setup();
+        run(&mut shell, "f() { return 3; }");
+
+        assert_eq!(run(&mut shell, "builtin alias ll='ls -l'"), 0);
+        assert_eq!(shell.get_aliases().get("ll").as_deref(), Some("ls -l"));
+        assert_eq!(run(&mut shell, "builtin f"), 1);
+        assert_eq!(run(&mut shell, "builtin"), 0);
+
+        // `command` skips functions
+        assert_eq!(run(&mut shell, "f"), 3);
+        assert_ne!(run(&mut shell, "command f"), 0);
+        assert_eq!(run(&mut shell, "command unalias ll"), 0);
+        assert_eq!(shell.get_aliases().get("ll"), None);
+
+        assert_eq!(run(&mut shell, "command -v f if cd"), 0);
+        assert_eq!(run(&mut shell, "command -v f not-a-command"), 1);
+        assert_eq!(run(&mut shell, "command -V builtin"), 0);
+    }
+
+    #[test]
+    fn test_find_command() {
+        let tool = write_file("find-command", "tool", "");
+        let not_executable = write_file("find-command", "data", "");
+        fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+        let dir = tool.parent().unwrap().to_path_buf();
+
+        let mut shell = setup();
+        shell.set_variable("PATH", &format!("/does/not/exist:{}", dir.display()));
+        shell.get_aliases().set("ll", "ls -l");
+        run(&mut shell, "f() { :; }");
+
+        assert_eq!(shell.find_command("ll"), Some(CommandKind::Alias("ls -l".to_string())));
+        assert_eq!(shell.find_command("while"), Some(CommandKind::Keyword));
+        assert_eq!(shell.find_command("cd"), Some(CommandKind::Builtin));
+        assert_eq!(shell.find_command("source"), Some(CommandKind::Builtin));
+        assert_eq!(shell.find_command("f"), Some(CommandKind::Function));
+        assert_eq!(shell.find_command("tool"), Some(CommandKind::External(tool.clone())));
+        assert_eq!(shell.find_command(&tool.to_string_lossy()), Some(CommandKind::External(tool)));
+        assert_eq!(shell.find_command("data"), None);
+        assert_eq!(shell.find_command(&not_executable.to_string_lossy()), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_external_status() {
+        let script = write_file("external-status", "script", "#!/bin/sh\nexit 5\n");
+        let not_executable = write_file("external-status", "data", "");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        let dir = script.parent().unwrap().to_path_buf();
+
+        let mut shell = setup();
+        shell.set_variable("PATH", "/bin:/usr/bin");
+        assert_eq!(run(&mut shell, &script.to_string_lossy()), 5);
+        assert_eq!(run(&mut shell, "no-such-command-here"), 127);
+        assert_eq!(run(&mut shell, &format!("{}/missing", dir.display())), 127);
+        assert_eq!(run(&mut shell, &not_executable.to_string_lossy()), 126);
+        assert_eq!(run(&mut shell, &dir.to_string_lossy()), 126);
+        // a command killed by a signal has the status 128 plus the signal's number
+        assert_eq!(run(&mut shell, "sh -c 'kill -TERM $$'"), 128 + libc::SIGTERM);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_stopped_jobs_and_hang_up() {
+        let mut child = std::process::Command::new("sleep").arg("10").spawn().unwrap();
+        let pid = child.id() as libc::pid_t;
+        unsafe { libc::kill(pid, libc::SIGSTOP) };
+        assert_eq!(wait_for_process(pid), None);
+
+        let mut jobs = JobTable::default();
+        assert_eq!(jobs.add_stopped(pid, "sleep 10".to_string()).describe_running(), "[1]  Stopped   sleep 10");
+        assert_eq!(jobs.reap(), vec![]);
+        // stopped jobs are continued to see the hang up, and die of it
+        jobs.hang_up();
+        assert_eq!(child.wait().unwrap().signal(), Some(libc::SIGHUP));
+    }
+
+    /// A shell with `yes` and `no` functions, which succeed and fail
+    fn setup_with_conditions() -> Shell {
+        let mut shell = setup();
+        run(&mut shell, "yes() { return 0; }; no() { return 1; }");
+        shell
+    }
+
+    #[test]
+    fn test_if() {
+        let mut shell = setup_with_conditions();
+        run(&mut shell, "if yes; then a=1; else a=2; fi");
+        assert_eq!(shell.get_variable("a").as_deref(), Some("1"));
+
+        run(&mut shell, "if no; then b=1; elif no; then b=2; elif yes; then b=3; else b=4; fi");
+        assert_eq!(shell.get_variable("b").as_deref(), Some("3"));
+
+        assert_eq!(run(&mut shell, "if no; then c=1; fi"), 0);
+        assert_eq!(shell.get_variable("c"), None);
+        assert_eq!(run(&mut shell, "if yes; then no; fi"), 1);
+        assert_eq!(run(&mut shell, "if ! no; then d=1; fi; ! yes"), 1);
+        assert_eq!(shell.get_variable("d").as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn test_while_and_until() {
+        let mut shell = setup_with_conditions();
+        run(&mut shell, "more() { case $n in xxx) return 1;; esac; }");
+        run(&mut shell, "n=; while more; do n=x$n; done");
+        assert_eq!(shell.get_variable("n").as_deref(), Some("xxx"));
+
+        run(&mut shell, "n=; until ! more; do n=${n}x; done");
+        assert_eq!(shell.get_variable("n").as_deref(), Some("xxx"));
+
+        assert_eq!(run(&mut shell, "while no; do yes; done"), 0);
+    }
+
+    #[test]
+    fn test_for() {
+        let mut shell = setup();
+        run(&mut shell, "list='b c'; out=; for x in a $list 'd e'; do out=\"$out<$x>\"; done");
+        assert_eq!(shell.get_variable("out").as_deref(), Some("<a><b><c><d e>"));
+
+        shell.set_positional(vec!["1".to_string(), "2".to_string()]);
+        run(&mut shell, "out=; for x; do out=$out$x; done");
+        assert_eq!(shell.get_variable("out").as_deref(), Some("12"));
+
+        run(&mut shell, "f() { for arg in \"$@\"; do last=$arg; done; }; f p q");
+        assert_eq!(shell.get_variable("last").as_deref(), Some("q"));
+    }
+
+    #[test]
+    fn test_break_and_continue() {
+        let mut shell = setup_with_conditions();
+        run(&mut shell, "out=; for x in 1 2 3 4; do case $x in 2) continue;; 4) break;; esac; out=$out$x; done");
+        assert_eq!(shell.get_variable("out").as_deref(), Some("13"));
+
+        run(
+            &mut shell,
+            "out=; for x in a b; do for y in 1 2 3; do out=$out$x$y; case $y in 2) continue 2;; esac; done; done",
+        );
+        assert_eq!(shell.get_variable("out").as_deref(), Some("a1a2b1b2"));
+
+        run(&mut shell, "out=; while yes; do while yes; do break 2; done; out=unreached; done");
+        assert_eq!(shell.get_variable("out").as_deref(), Some(""));
+
+        // a function can't break out of its caller's loop
+        run(&mut shell, "f() { break; }; out=; for x in 1 2; do f; out=$out$x; done");
+        assert_eq!(shell.get_variable("out").as_deref(), Some("12"));
+        assert_eq!(run(&mut shell, "break"), 0);
+    }
+
+    #[test]
+    fn test_case() {
+        let mut shell = setup();
+        let classify = "case $file in
+            *.rs|*.toml) kind=rust;;
+            \"*\") kind=star;;
+            [0-9]*) kind=number;;
+            *) kind=other;;
+        esac";
+        for (file, kind) in [("main.rs", "rust"), ("*", "star"), ("1x", "number"), ("x", "other")] {
+            shell.set_variable("file", file);
+            run(&mut shell, classify);
+            assert_eq!(shell.get_variable("kind").as_deref(), Some(kind), "{}", file);
+        }
+
+        shell.set_variable("pattern", "*.md");
+        run(&mut shell, "case README.md in $pattern) a=unquoted;; esac");
+        run(&mut shell, "case README.md in \"$pattern\") b=quoted;; esac");
+        assert_eq!(shell.get_variable("a").as_deref(), Some("unquoted"));
+        assert_eq!(shell.get_variable("b"), None);
+    }
+
+    #[test]
+    fn test_subshell() {
+        let mut shell = setup_with_conditions();
+        run(&mut shell, "a=outer; (a=inner; b=inner; f() { return 0; }); c=$a");
+        assert_eq!(shell.get_variable("c").as_deref(), Some("outer"));
+        assert_eq!(shell.get_variable("b"), None);
+        assert!(shell.get_function("f").is_none());
+
+        assert_eq!(run(&mut shell, "(no)"), 1);
+        assert_eq!(run(&mut shell, "(exit; a=unreached); a=after"), 0);
+        assert_eq!(shell.get_variable("a").as_deref(), Some("after"));
+    }
+
+    #[test]
+    fn test_redirections() {
+        let mut shell = setup();
+        shell.set_variable("PATH", "/bin:/usr/bin");
+        let path = write_file("redirections", "out", "");
+        shell.set_variable("out", path.to_str().unwrap());
+        let read = || fs::read_to_string(&path).unwrap();
+
+        // intrinsics and the shell's own builtins write where they are sent, as programs do
+        assert_eq!(run(&mut shell, "type type > $out; command -v type >>$out; type nope 2>>$out"), 1);
+        assert_eq!(read(), "type is a shell builtin\ntype\ntype: nope: not found\n");
+        assert_eq!(run(&mut shell, "{ echo a; sh -c 'echo b >&2'; } >$out 2>&1; echo c >>$out"), 0);
+        assert_eq!(read(), "a\nb\nc\n");
+        assert_eq!(run(&mut shell, "tr a-z A-Z <$out >$out.upper"), 0);
+        assert_eq!(fs::read_to_string(path.with_extension("upper")).unwrap(), "A\nB\nC\n");
+        // the redirection only lasts as long as the command
+        assert_eq!(run(&mut shell, "> $out; type cd >/dev/null; type cd >/dev/null 2>&1 </dev/null"), 0);
+        assert_eq!(read(), "");
+
+        assert_eq!(run(&mut shell, "echo a >/nonexistent/file"), 1);
+        assert_eq!(run(&mut shell, "cat </nonexistent/file || x=failed"), 0);
+        assert_eq!(shell.get_variable("x").as_deref(), Some("failed"));
+        assert_eq!(run(&mut shell, "echo a 3>$out"), 1);
+        assert_eq!(run(&mut shell, "echo a >&x"), 1);
+    }
+
+    #[test]
+    fn test_pipelines() {
+        let mut shell = setup();
+        shell.set_variable("PATH", "/bin:/usr/bin");
+        let path = write_file("pipelines", "out", "");
+        shell.set_variable("out", path.to_str().unwrap());
+        let read = || fs::read_to_string(&path).unwrap();
+
+        // an intrinsic's output goes down the pipe, to a program or to another part of the shell
+        run(&mut shell, "alias ll='ls -l' la='ls -a'");
+        assert_eq!(run(&mut shell, "alias | grep ll >$out"), 0);
+        assert_eq!(read(), "alias ll='ls -l'\n");
+        assert_eq!(run(&mut shell, "alias | { tr a-z A-Z; type cd; } | grep -v LL >$out"), 0);
+        assert_eq!(read(), "ALIAS LA='LS -A'\ncd is a shell builtin\n");
+
+        // the status is the last command's, and a program can stop reading early
+        assert_eq!(run(&mut shell, "seq 100000 | head -n 2 >$out"), 0);
+        assert_eq!(read(), "1\n2\n");
+        assert_eq!(run(&mut shell, "yes | { head -n 1 >$out; }"), 0);
+        assert_eq!(read(), "y\n");
+        assert_eq!(run(&mut shell, "true | false"), 1);
+        assert_eq!(run(&mut shell, "! true | false"), 0);
+        assert_eq!(run(&mut shell, "true | nonexistent-command"), 127);
+
+        // each command in a pipeline runs as a subshell would
+        assert_eq!(run(&mut shell, "a=1 | cat; f() { b=1; }; f | cat"), 0);
+        assert_eq!((shell.get_variable("a"), shell.get_variable("b")), (None, None));
+    }
+
+    #[test]
+    fn test_time() {
+        let mut shell = setup_with_conditions();
+        assert_eq!(run(&mut shell, "time no"), 1);
+        assert_eq!(run(&mut shell, "time -p ! no"), 0);
+        assert_eq!(run(&mut shell, "time"), 0);
+        // the command runs in the shell itself
+        run(&mut shell, "time a=timed");
+        assert_eq!(shell.get_variable("a").as_deref(), Some("timed"));
+
+        // the whole pipeline is timed, and its status is the last command's
+        shell.set_variable("PATH", "/bin:/usr/bin");
+        let path = write_file("time", "out", "");
+        shell.set_variable("out", path.to_str().unwrap());
+        assert_eq!(run(&mut shell, "{ time -p true | sleep 0.3 | no; } 2>$out"), 1);
+        let report = fs::read_to_string(&path).unwrap();
+        let real = report.lines().next().and_then(|line| line.strip_prefix("real ")).unwrap();
+        assert!(real.parse::<f64>().unwrap() >= 0.3, "{}", report);
+    }
+
+    #[test]
+    fn test_run_source() {
+        let mut shell = setup();
+        let script = "#!/usr/bin/env yeesh\nf() {\n  out=\"$out$1\"\n}\nfor x in a b\ndo\n  f $x\ndone\nf c";
+        assert_eq!(status_code(&shell.run_source(script.as_bytes(), "script").unwrap()), 0);
+        assert_eq!(shell.get_variable("out").as_deref(), Some("abc"));
+
+        // reading stops at the first syntax error
+        let script = "a=1\nif then\na=2";
+        assert_eq!(status_code(&shell.run_source(script.as_bytes(), "script").unwrap()), 2);
+        assert_eq!(shell.get_variable("a").as_deref(), Some("1"));
+
+        let script = "b=1\nif true; then";
+        assert_eq!(status_code(&shell.run_source(script.as_bytes(), "script").unwrap()), 2);
+        assert_eq!(shell.get_variable("b").as_deref(), Some("1"));
+
+        let script = "no() { return 3; }\nno";
+        assert_eq!(status_code(&shell.run_source(script.as_bytes(), "script").unwrap()), 3);
+    }
+
+    #[test]
+    fn test_run_source_exit() {
+        let mut shell = setup();
+        let script = "a=1\nexit\na=2";
+        assert!(matches!(shell.run_source(script.as_bytes(), "script"), Err(ControlFlow::Exit(_))));
+        assert_eq!(shell.get_variable("a").as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn test_exit() {
+        let mut shell = setup_with_conditions();
+        assert_eq!(shell.run("exit 3; a=unreached"), None);
+        assert_eq!(status_code(&shell.get_last_status()), 3);
+        assert_eq!(shell.get_variable("a"), None);
+
+        assert_eq!(run(&mut shell, "(no; exit)"), 1);
+        assert_eq!(run(&mut shell, "(exit 256)"), 0);
+        assert_eq!(run(&mut shell, "(exit -1)"), 255);
+        assert_eq!(run(&mut shell, "(exit nope)"), 2);
+        // with too many arguments, the shell doesn't exit
+        assert_eq!(run(&mut shell, "exit 1 2"), 1);
+    }
+
+    #[test]
+    fn test_exit_trap() {
+        let mut shell = setup();
+        let trap = |shell: &mut Shell, args: &[&str]| run_buffered(&Trap, shell, "", args);
+
+        assert_eq!(run(&mut shell, "trap 'a=$?' EXIT"), 0);
+        assert_eq!(trap(&mut shell, &[]), (0, "trap -- 'a=$?' EXIT\n".to_string(), "".to_string()));
+        assert_eq!(status_code(&shell.run_exit_trap(status_from_code(3))), 3);
+        assert_eq!(shell.get_variable("a").as_deref(), Some("3"));
+        // the trap only runs once
+        assert!(shell.get_traps().is_empty());
+
+        // the trap may exit with another status
+        run(&mut shell, "trap 'exit 4' 0");
+        assert_eq!(status_code(&shell.run_exit_trap(status_from_code(0))), 4);
+
+        run(&mut shell, "trap 'a=1' EXIT; trap - EXIT");
+        assert!(shell.get_traps().is_empty());
+        assert_eq!(trap(&mut shell, &["a=1", "NOPE"]).0, 1);
+        assert_eq!(trap(&mut shell, &["a=1"]).0, 2);
+
+        // subshells run their own traps as they end, and don't inherit the shell's traps
+        run(&mut shell, "trap 'a=outer' EXIT");
+        assert_eq!(run(&mut shell, "(trap 'exit 5' EXIT; a=inner)"), 5);
+        assert_eq!(run(&mut shell, "(exit 6)"), 6);
+        assert_eq!(shell.get_traps().get("EXIT").map(String::as_str), Some("a=outer"));
+    }
+
+    #[test]
+    fn test_condition_traps() {
+        let mut shell = setup_with_conditions();
+        let trap = |shell: &mut Shell, args: &[&str]| run_buffered(&Trap, shell, "", args);
+
+        // `ERR` runs for failures which aren't tested, and not in functions
+        run(&mut shell, "trap 'e=$e.$?' ERR");
+        run(&mut shell, "no; yes; f() { no; yes; }; f");
+        assert_eq!(shell.get_variable("e").as_deref(), Some(".1"));
+        run(&mut shell, "e=; if no; then yes; fi; while no; do yes; done; ! yes; no && yes; no || no; (no)");
+        assert_eq!(shell.get_variable("e").as_deref(), Some(".1.1"));
+        // the trap leaves `$?` as it was
+        assert_eq!(run(&mut shell, "no; s=$?"), 0);
+        assert_eq!(shell.get_variable("s").as_deref(), Some("1"));
+        run(&mut shell, "trap - ERR");
+
+        // `DEBUG` runs before each command outside functions, and `RETURN` as functions return
+        run(&mut shell, "trap 'd=$d.' DEBUG; f; x=1; trap - DEBUG");
+        assert_eq!(shell.get_variable("d").as_deref(), Some("..."));
+        run(&mut shell, "trap 'r=$r.$1' RETURN; g() { x=$1; }; g a; g b; trap - RETURN");
+        assert_eq!(shell.get_variable("r").as_deref(), Some(".a.b"));
+
+        // an `exit` in a trap exits
+        assert_eq!(shell.run("trap 'exit 3' ERR; no; a=unreached"), None);
+        assert_eq!(shell.get_variable("a"), None);
+        assert_eq!(status_code(&shell.get_last_status()), 3);
+        assert_eq!(trap(&mut shell, &["ERR"]).0, 0);
+        assert!(shell.get_traps().is_empty());
+    }
+
+    #[test]
+    fn test_signal_traps() {
+        let mut shell = setup();
+        let trap = |shell: &mut Shell, args: &[&str]| run_buffered(&Trap, shell, "", args);
+
+        // signals are given by name, with or without `SIG`, or by number
+        run(&mut shell, "trap 'echo usr' usr1; trap '' 12 QUIT; trap 'e=1' ERR");
+        let listing = [
+            "trap -- 'e=1' ERR\n",
+            "trap -- '' SIGQUIT\n",
+            "trap -- 'echo usr' SIGUSR1\n",
+            "trap -- '' SIGUSR2\n",
+        ];
+        assert_eq!(trap(&mut shell, &[]).1, listing.concat());
+        assert_eq!(trap(&mut shell, &["-p", "SIGUSR1", "ERR"]).1, format!("{}{}", listing[0], listing[2]));
+        assert_eq!(trap(&mut shell, &["-l"]).1.lines().next(), Some(" 1) SIGHUP"));
+        assert_eq!(trap(&mut shell, &["a=1", "KILL"]).0, 1);
+
+        // `-`, a lone condition, or conditions starting with a number reset traps
+        run(&mut shell, "trap - USR1; trap QUIT; trap 12 ERR");
+        assert!(shell.get_traps().is_empty());
+    }
+
+    #[test]
+    fn test_background_jobs() {
+        let mut shell = setup();
+        shell.set_executable(&shell_executable());
+        shell.set_variable("PATH", "/bin:/usr/bin");
+        assert_eq!(run(&mut shell, "sleep 0.1 && exit 3 &"), 0);
+        let job = shell.get_jobs().get_all()[0].clone();
+        assert_eq!((job.id, job.command.as_str()), (1, "sleep 0.1 && exit 3"));
+        assert_eq!(expand(&shell, "$!"), vec![job.pid.to_string()]);
+
+        let wait_for_jobs = |shell: &mut Shell, count: usize| {
+            let mut finished = vec![];
+            for _ in 0..100 {
+                finished.extend(shell.reap_jobs());
+                if finished.len() == count {
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(20));
+            }
+            finished
+        };
+        let finished = wait_for_jobs(&mut shell, 1);
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].0.describe_finished(&finished[0].1), "[1]  Exit 3    sleep 0.1 && exit 3");
+        assert!(shell.get_jobs().is_empty());
+
+        // the job is run by another shell, which is given the functions, the variables which
+        // aren't exported, including locals, and the positional parameters of this one
+        run(&mut shell, "f() { exit $x$y$1; }; y=2; g() { local x=1; f $1 & }; g 3");
+        let finished = wait_for_jobs(&mut shell, 1);
+        assert_eq!(finished.iter().map(|(_, status)| status_code(status)).collect::<Vec<_>>(), vec![123]);
+    }
+
+    #[test]
+    fn test_exit_with_jobs() {
+        let mut shell = setup();
+        shell.set_variable("PATH", "/bin:/usr/bin");
+        shell.set_executable(&shell_executable());
+        shell.set_interactive(true);
+        run(&mut shell, "sleep 10 &");
+        let pid = shell.get_jobs().get_all()[0].pid;
+
+        // an interactive shell warns once about running jobs, then exits
+        assert_eq!(run(&mut shell, "exit 3"), 1);
+        assert_eq!(shell.run("exit 3"), None);
+        assert_eq!(status_code(&shell.get_last_status()), 3);
+
+        unsafe { libc::kill(pid, libc::SIGKILL) };
+        unsafe { libc::waitpid(pid, std::ptr::null_mut(), 0) };
+    }
+
+    /// Write `contents` to a file called `name` in a directory of its own for `test`
+    fn write_file(test: &str, name: &str, contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("yeesh-{}-{}", test, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_source() {
+        let mut shell = setup();
+        let path = write_file("source", "lib.ysh", "sourced=\"$1$2\"\nreturn 4\nsourced=unreached");
+        let path = path.to_str().unwrap();
+
+        assert_eq!(run(&mut shell, &format!("source {} a b", path)), 4);
+        assert_eq!(shell.get_variable("sourced").as_deref(), Some("ab"));
+        assert!(shell.get_positional().is_empty());
+
+        assert_eq!(run(&mut shell, &format!(". {}; x=$?", path)), 0);
+        assert_eq!(shell.get_variable("x").as_deref(), Some("4"));
+        // `return` stops the sourced file, rather than the function sourcing it
+        assert_eq!(run(&mut shell, &format!("f() {{ . {}; y=$?; }}; f", path)), 0);
+        assert_eq!(shell.get_variable("y").as_deref(), Some("4"));
+
+        // without a `/`, the file is looked for in $PATH
+        let dir = Path::new(path).parent().unwrap();
+        shell.set_variable("PATH", &format!("/nonexistent:{}", dir.display()));
+        assert_eq!(run(&mut shell, "source lib.ysh"), 4);
+
+        assert_eq!(run(&mut shell, "source"), 2);
+        assert_eq!(run(&mut shell, "source /nonexistent/file"), 1);
+        assert_eq!(run(&mut shell, "return"), 1);
+    }
+
+    #[test]
+    fn test_startup_files() {
+        let files = get_startup_files(true, true, Some("/home/user"));
+        let expected = ["/etc/yeesh_profile", "/home/user/.yeesh_profile", "/etc/yeeshrc", "/home/user/.yeeshrc"];
+        assert_eq!(files, expected.iter().map(PathBuf::from).collect::<Vec<_>>());
+        assert_eq!(get_startup_files(false, true, None), vec![PathBuf::from("/etc/yeeshrc")]);
+        assert!(get_startup_files(false, false, Some("/home/user")).is_empty());
+
+        // a syntax error stops the file it is in, but not the files after it
+        let mut shell = setup();
+        let files = [
+            write_file("startup", "broken", "a=1\nif then\na=2"),
+            PathBuf::from("/nonexistent/rc"),
+            write_file("startup", "rc", "b=1"),
+        ];
+        assert_eq!(status_code(&shell.run_startup_files(&files).unwrap()), 0);
+        assert_eq!(shell.get_variable("a").as_deref(), Some("1"));
+        assert_eq!(shell.get_variable("b").as_deref(), Some("1"));
+
+        let files = [write_file("startup", "exit", "exit")];
+        assert!(matches!(shell.run_startup_files(&files), Err(ControlFlow::Exit(_))));
+    }
+
+    #[test]
+    fn test_error_locations() {
+        let mut shell = setup();
+        shell.set_variable("PATH", "/nonexistent");
+        let rc = write_file("locations", "rc", "a=1\nno-such-command\nlocal x\n\nif true; then\n  type nope\nfi");
+        let errors = rc.with_file_name("errors");
+        shell.set_variable("rc", rc.to_str().unwrap());
+        shell.set_variable("errors", errors.to_str().unwrap());
+
+        // errors in a file say where the command they come from is, whether the shell, a builtin or an
+        // intrinsic reported them
+        assert_eq!(run(&mut shell, "source $rc 2>$errors"), 1);
+        let rc = rc.display();
+        let expected = format!(
+            "yeesh: {0}:2: no-such-command: command not found\n\
+             yeesh: {0}:3: local: can only be used in a function\n\
+             yeesh: {0}:5: type: nope: not found\n",
+            rc
+        );
+        assert_eq!(fs::read_to_string(&errors).unwrap(), expected);
+
+        // and once it has been run, they no longer do
+        assert_eq!(run(&mut shell, "{ no-such-command; local x; } 2>$errors"), 1);
+        let expected = "yeesh: no-such-command: command not found\nlocal: can only be used in a function\n";
+        assert_eq!(fs::read_to_string(&errors).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_run_captured() {
+        let mut shell = setup();
+        shell.set_variable("PATH", "/bin:/usr/bin");
+        let output = shell.run_captured("sh -c 'echo out; echo err >&2; exit 3' && x=1").unwrap();
+        assert_eq!((output.stdout.as_str(), output.stderr.as_str()), ("out\n", "err\n"));
+        assert_eq!(status_code(&output.status), 3);
+
+        let output = shell.run_captured("if then").unwrap();
+        assert_eq!(status_code(&output.status), 2);
+
+        // intrinsics write to the command's streams, as programs do
+        let output = shell.run_captured("alias x=y; alias x; unalias nope").unwrap();
+        let expected = ("alias x='y'\n", "unalias: nope: not found\n");
+        assert_eq!((output.stdout.as_str(), output.stderr.as_str()), expected);
+        assert_eq!(status_code(&output.status), 1);
+        // the streams go back to the shell's own afterwards
+        let path = write_file("captured", "out", "");
+        shell.set_variable("out", path.to_str().unwrap());
+        let output = shell.run_captured("{ echo a; alias x; } >$out; echo b").unwrap();
+        assert_eq!(output.stdout, "b\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a\nalias x='y'\n");
+
+        // a job left running in the background doesn't hold up the command
+        shell.set_executable(&shell_executable());
+        let started = Instant::now();
+        let output = shell.run_captured("sleep 10 & echo started").unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(output.stdout, "started\n");
+        let pid = shell.get_jobs().get_all()[0].pid;
+        unsafe { libc::kill(pid, libc::SIGKILL) };
+        unsafe { libc::waitpid(pid, std::ptr::null_mut(), 0) };
+    }
+}
@@ -0,0 +1,30 @@
+#[cfg(test)]
+mod signals_tests {
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::shell::{describe_termination, get_signal_name, get_signal_number};
+
+    #[test]
+    fn test_signal_names() {
+        assert_eq!(get_signal_name(libc::SIGSEGV), Some("SIGSEGV"));
+        assert_eq!(get_signal_name(0), None);
+        for name in ["SIGTERM", "TERM", "term", "15"] {
+            assert_eq!(get_signal_number(name), Some(libc::SIGTERM));
+        }
+        assert_eq!(get_signal_number("SIGNOPE"), None);
+        assert_eq!(get_signal_number("0"), None);
+    }
+
+    #[test]
+    fn test_describe_termination() {
+        // the low 7 bits of a wait status are the signal, and 0x80 is set if a core was dumped
+        let describe = |status| describe_termination(&ExitStatus::from_raw(status));
+        assert_eq!(describe(libc::SIGSEGV | 0x80).as_deref(), Some("[SIGSEGV] core dumped"));
+        assert_eq!(describe(libc::SIGTERM).as_deref(), Some("[SIGTERM]"));
+        assert_eq!(describe(libc::SIGINT), None);
+        assert_eq!(describe(3 << 8), None);
+    }
+}
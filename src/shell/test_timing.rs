@@ -0,0 +1,27 @@
+#[cfg(test)]
+mod timing_tests {
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::shell::{format_duration, format_timing, CpuTimes};
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(4250)), "4.2s");
+        assert_eq!(format_duration(Duration::from_millis(59_999)), "59.9s");
+        assert_eq!(format_duration(Duration::from_secs(187)), "3m7s");
+        assert_eq!(format_duration(Duration::from_secs(3612)), "1h0m12s");
+    }
+
+    #[test]
+    fn test_format_timing() {
+        let cpu = CpuTimes {
+            user:   Duration::from_millis(1234),
+            system: Duration::from_micros(5600),
+        };
+        let real = Duration::from_millis(61_005);
+        assert_eq!(format_timing(real, cpu, false), "\nreal\t1m1.005s\nuser\t0m1.234s\nsys\t0m0.005s\n");
+        assert_eq!(format_timing(real, cpu, true), "real 61.00\nuser 1.23\nsys 0.00\n");
+    }
+}
@@ -0,0 +1,72 @@
+use std::ops::Sub;
+use std::time::Duration;
+
+/// The variable set to how long the last command line took to run, in milliseconds
+pub const CMD_DURATION: &str = "CMD_DURATION";
+
+/// CPU time spent in user mode and in the kernel
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CpuTimes {
+    pub user:   Duration,
+    pub system: Duration,
+}
+
+impl Sub for CpuTimes {
+    type Output = CpuTimes;
+
+    fn sub(self, earlier: CpuTimes) -> CpuTimes {
+        CpuTimes {
+            user:   self.user.saturating_sub(earlier.user),
+            system: self.system.saturating_sub(earlier.system),
+        }
+    }
+}
+
+fn get_usage(who: libc::c_int) -> CpuTimes {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(who, &mut usage) } != 0 {
+        return CpuTimes::default();
+    }
+    let to_duration = |time: libc::timeval| Duration::new(time.tv_sec as u64, time.tv_usec as u32 * 1000);
+    CpuTimes {
+        user:   to_duration(usage.ru_utime),
+        system: to_duration(usage.ru_stime),
+    }
+}
+
+/// Returns the CPU time used so far by the shell and the children it has waited for, so that
+/// timing a command covers both the processes it ran and any intrinsics
+pub fn get_cpu_times() -> CpuTimes {
+    let (shell, children) = (get_usage(libc::RUSAGE_SELF), get_usage(libc::RUSAGE_CHILDREN));
+    CpuTimes {
+        user:   shell.user + children.user,
+        system: shell.system + children.system,
+    }
+}
+
+/// Returns `duration` briefly for a prompt, e.g. `4.2s`, `3m7s` or `1h0m12s`
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        _ if seconds < 60 => format!("{}.{}s", seconds, duration.subsec_millis() / 100),
+        _ if seconds < 60 * 60 => format!("{}m{}s", seconds / 60, seconds % 60),
+        _ => format!("{}h{}m{}s", seconds / 3600, seconds / 60 % 60, seconds % 60),
+    }
+}
+
+/// Returns the report `time` prints: as bash does, in minutes and seconds after a blank line, or
+/// with `posix`, as `-p` asks, in seconds
+pub fn format_timing(real: Duration, cpu: CpuTimes, posix: bool) -> String {
+    let times = [("real", real), ("user", cpu.user), ("sys", cpu.system)];
+    let lines = times.iter().map(|(name, time)| {
+        let seconds = time.as_secs();
+        match posix {
+            true => format!("{} {}.{:02}\n", name, seconds, time.subsec_millis() / 10),
+            false => format!("{}\t{}m{}.{:03}s\n", name, seconds / 60, seconds % 60, time.subsec_millis()),
+        }
+    });
+    match posix {
+        true => lines.collect(),
+        false => std::iter::once("\n".to_string()).chain(lines).collect(),
+    }
+}
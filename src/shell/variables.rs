@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::sync::{Arc, RwLock};
+
+use log::debug;
+
+use crate::cmd_input::VariableSource;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Variable {
+    value:    String,
+    /// whether the variable is passed on to the environment of commands the shell runs
+    exported: bool,
+}
+
+/// The global variables of a shell, which can be shared with completion. Clones refer to the
+/// same set of variables.
+#[derive(Clone, Default, Debug)]
+pub struct VariableStore {
+    variables: Arc<RwLock<BTreeMap<String, Variable>>>,
+}
+
+impl VariableStore {
+    pub fn new() -> Self {
+        VariableStore::default()
+    }
+
+    /// Create a store holding the environment of the shell process, with every variable exported
+    pub fn from_env() -> Self {
+        let variables = env::vars()
+            .map(|(name, value)| (name, Variable { value, exported: true }))
+            .collect();
+        VariableStore {
+            variables: Arc::new(RwLock::new(variables)),
+        }
+    }
+
+    /// Returns a copy of the variables which doesn't share changes with this store, as a
+    /// subshell gets
+    pub fn copy(&self) -> Self {
+        let variables = self.variables.read().unwrap().clone();
+        VariableStore {
+            variables: Arc::new(RwLock::new(variables)),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.variables.read().unwrap().get(name).map(|v| v.value.clone())
+    }
+
+    /// Set the variable `name`, keeping whether it is exported if it already exists
+    pub fn set(&self, name: &str, value: &str) {
+        debug!("Setting variable '{}' to '{}'", name, value);
+        let mut variables = self.variables.write().unwrap();
+        match variables.get_mut(name) {
+            Some(variable) => variable.value = value.to_string(),
+            None => {
+                variables.insert(
+                    name.to_string(),
+                    Variable {
+                        value:    value.to_string(),
+                        exported: false,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Pass the variable `name` on to the environment of commands, returning false if there is
+    /// no such variable
+    pub fn export(&self, name: &str) -> bool {
+        match self.variables.write().unwrap().get_mut(name) {
+            Some(variable) => {
+                variable.exported = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove the variable `name`, returning false if there was no such variable
+    pub fn unset(&self, name: &str) -> bool {
+        self.variables.write().unwrap().remove(name).is_some()
+    }
+
+    /// Returns the exported variables, which make up the environment of commands the shell runs
+    pub fn get_exported(&self) -> Vec<(String, String)> {
+        self.get_where(|variable| variable.exported)
+    }
+
+    /// Returns the variables which aren't exported, which only the shell itself sees
+    pub fn get_unexported(&self) -> Vec<(String, String)> {
+        self.get_where(|variable| !variable.exported)
+    }
+
+    fn get_where<F: Fn(&Variable) -> bool>(&self, condition: F) -> Vec<(String, String)> {
+        self.variables
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, variable)| condition(variable))
+            .map(|(name, variable)| (name.clone(), variable.value.clone()))
+            .collect()
+    }
+}
+
+impl VariableSource for VariableStore {
+    fn get_variables(&self) -> Vec<(String, String)> {
+        self.variables
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, variable)| (name.clone(), variable.value.clone()))
+            .collect()
+    }
+}
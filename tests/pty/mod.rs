@@ -0,0 +1,276 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+#[allow(dead_code)]
+#[path = "../../src/fixture/raw_tty_emulator.rs"]
+mod raw_tty_emulator;
+
+pub use raw_tty_emulator::RawTTYEmulator;
+
+/// Sets the window title to something only the prompt writes, so that the harness can count the
+/// prompts the shell has shown without it taking up room on the screen
+const PROMPT_MARKER: &str = "\x1b]2;yeesh-pty-prompt\x07";
+/// The prompt the shell is started with, which shows as `$ `
+const PS1: &str = "\\e]2;yeesh-pty-prompt\\a\\C{reset}$ ";
+/// How many columns the prompt takes up
+const PS1_WIDTH: usize = 2;
+/// The keys the terminal sends for Backspace and Right
+const BACKSPACE: &str = "\x7f";
+const RIGHT: &str = "\x1b[C";
+/// How long the shell has to stay quiet after a key before it's taken to have finished with it
+const SETTLE_TIME: Duration = Duration::from_millis(100);
+/// How long to wait for the shell before failing the test
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Numbers the directories of the sessions a test run starts
+static SESSIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// The yeesh binary running in a pseudo-terminal, with what it writes drawn on a
+/// [RawTTYEmulator] of the same size, which answers its requests for the cursor position. Each
+/// session runs in a new directory, which is also its `$HOME`, with `$PS1` set to `$ `.
+pub struct PtySession {
+    master:  File,
+    child:   Child,
+    screen:  RawTTYEmulator,
+    /// everything the shell has written
+    output:  Vec<u8>,
+    /// how many prompts have been waited for
+    prompts: usize,
+    dir:     PathBuf,
+    /// set once the shell has closed the terminal
+    closed:  bool,
+}
+
+fn open_pty(width: usize, height: usize) -> io::Result<(File, File)> {
+    let size = libc::winsize {
+        ws_row:    height as u16,
+        ws_col:    width as u16,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    let (mut master, mut slave) = (0, 0);
+    let result = unsafe { libc::openpty(&mut master, &mut slave, std::ptr::null_mut(), std::ptr::null(), &size) };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) })
+}
+
+impl PtySession {
+    /// Start the shell with the arguments `args` and the variables `env` on an 80x24 terminal,
+    /// and wait for its first prompt
+    pub fn start(args: &[&str], env: &[(&str, &str)]) -> PtySession {
+        let screen = RawTTYEmulator::new();
+        let (width, height) = screen.get_size();
+
+        let number = SESSIONS.fetch_add(1, Ordering::SeqCst);
+        let dir = std::env::temp_dir().join(format!("yeesh-pty-{}-{}", std::process::id(), number));
+        fs::create_dir_all(&dir).expect("Unable to create the session's directory");
+
+        let (master, slave) = open_pty(width, height).expect("Unable to open a pseudo-terminal");
+        let mut command = Command::new(env!("CARGO_BIN_EXE_yeesh-shell"));
+        command
+            .args(args)
+            .current_dir(&dir)
+            .env_clear()
+            .env("PATH", std::env::var_os("PATH").unwrap_or_default())
+            .env("HOME", &dir)
+            .env("TERM", "xterm-256color")
+            .env("PS1", PS1)
+            .envs(env.iter().copied())
+            .stdin(Stdio::from(slave.try_clone().expect("Unable to share the terminal")))
+            .stdout(Stdio::from(slave.try_clone().expect("Unable to share the terminal")))
+            .stderr(Stdio::from(slave));
+        // the shell gets a session of its own, with the terminal as its controlling terminal, as
+        // a terminal emulator would start it
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() < 0 || libc::ioctl(0, libc::TIOCSCTTY, 0) < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let child = command.spawn().expect("Unable to start the shell");
+        // only the shell may keep the terminal open, so that reading it ends once the shell exits
+        drop(command);
+
+        let mut session = PtySession {
+            master,
+            child,
+            screen,
+            output: vec![],
+            prompts: 0,
+            dir,
+            closed: false,
+        };
+        session.wait_for_prompt();
+        // the shell starts by completing in its directory, as if Tab had been pressed, which is
+        // undone so that every test starts with an empty line
+        session.press(BACKSPACE);
+        session.clear_line();
+        session
+    }
+
+    pub fn get_screen(&self) -> &RawTTYEmulator {
+        &self.screen
+    }
+
+    /// Returns the directory the shell was started in
+    pub fn get_dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the text on the screen, with the attributes left out
+    pub fn get_text(&self) -> String {
+        let rows = self.screen.get_text();
+        let rows: Vec<String> =
+            rows.iter().map(|row| row.iter().collect::<String>().trim_end().to_string()).collect();
+        let used = rows.iter().rposition(|row| !row.is_empty()).map_or(0, |last| last + 1);
+        rows[..used].iter().map(|row| format!("{}\n", row)).collect()
+    }
+
+    fn fail(&self, message: &str) -> ! {
+        panic!("{}, with the screen showing:\n{}", message, self.get_text())
+    }
+
+    /// Read what the shell writes for up to `timeout`, returning whether anything was read
+    fn read(&mut self, timeout: Duration) -> bool {
+        if self.closed {
+            return false;
+        }
+        let mut fds = libc::pollfd {
+            fd:      self.master.as_raw_fd(),
+            events:  libc::POLLIN,
+            revents: 0,
+        };
+        if unsafe { libc::poll(&mut fds, 1, timeout.as_millis() as libc::c_int) } <= 0 {
+            return false;
+        }
+
+        let mut buf = [0; 4096];
+        let count = match self.master.read(&mut buf) {
+            // once the shell has exited, reading fails with EIO rather than returning nothing
+            Ok(0) | Err(_) => {
+                self.closed = true;
+                return false;
+            }
+            Ok(count) => count,
+        };
+        self.output.extend_from_slice(&buf[..count]);
+        if let Err(e) = self.screen.write(&buf[..count]) {
+            self.fail(&format!("The shell wrote something the emulator can't show: {}", e));
+        }
+        let responses = self.screen.take_responses();
+        if !responses.is_empty() {
+            self.master.write_all(&responses).expect("Unable to answer the shell");
+        }
+        true
+    }
+
+    /// Read until the shell has been quiet for a while
+    fn settle(&mut self) {
+        let start = Instant::now();
+        while self.read(SETTLE_TIME) {
+            if start.elapsed() > TIMEOUT {
+                self.fail("The shell didn't stop writing");
+            }
+        }
+    }
+
+    /// Read until `condition` holds, failing the test if it doesn't before the timeout
+    pub fn wait_until<F>(&mut self, description: &str, condition: F)
+    where
+        F: Fn(&PtySession) -> bool,
+    {
+        let start = Instant::now();
+        while !condition(self) {
+            if self.closed || start.elapsed() > TIMEOUT {
+                self.fail(&format!("Timed out waiting for {}", description));
+            }
+            self.read(SETTLE_TIME);
+        }
+    }
+
+    /// Wait until `text` is on the screen
+    pub fn wait_for_text(&mut self, text: &str) {
+        self.wait_until(&format!("{:?}", text), |session| session.get_text().contains(text));
+    }
+
+    /// Wait until the shell shows its next prompt and has finished drawing the line after it
+    pub fn wait_for_prompt(&mut self) {
+        let count_prompts = |session: &PtySession| {
+            let marker = PROMPT_MARKER.as_bytes();
+            session.output.windows(marker.len()).filter(|window| *window == marker).count()
+        };
+        self.wait_until("the prompt", |session| count_prompts(session) > session.prompts);
+        self.prompts = count_prompts(self);
+        self.settle();
+    }
+
+    /// Send `key`, e.g. `a`, `\x03` for Ctrl-C or `\x1b[D` for Left, and wait for the shell to
+    /// deal with it. Keys are sent one at a time, as a person would type them: the shell reads
+    /// the answer to its request for the cursor position from the terminal after each key, and
+    /// would take any keys sent before then as part of it.
+    pub fn press(&mut self, key: &str) {
+        self.master.write_all(key.as_bytes()).expect("Unable to send a key to the shell");
+        self.settle();
+    }
+
+    /// Delete everything typed on the line after the prompt
+    pub fn clear_line(&mut self) {
+        let mut pos = None;
+        while pos != Some(self.screen.get_cursor_pos()) {
+            pos = Some(self.screen.get_cursor_pos());
+            self.press(RIGHT);
+        }
+        while self.screen.get_cursor_pos().0 > PS1_WIDTH + 1 {
+            self.press(BACKSPACE);
+        }
+    }
+
+    /// Type each character of `text`
+    pub fn type_text(&mut self, text: &str) {
+        for c in text.chars() {
+            self.press(c.encode_utf8(&mut [0; 4]));
+        }
+    }
+
+    /// Type `line` and press Enter, then wait for the next prompt
+    pub fn run(&mut self, line: &str) {
+        self.type_text(line);
+        self.press("\r");
+        self.wait_for_prompt();
+    }
+
+    /// Wait for the shell to exit, returning its status
+    pub fn wait_for_exit(&mut self) -> ExitStatus {
+        let start = Instant::now();
+        loop {
+            self.read(SETTLE_TIME);
+            if let Some(status) = self.child.try_wait().expect("Unable to wait for the shell") {
+                self.settle();
+                return status;
+            }
+            if start.elapsed() > TIMEOUT {
+                self.fail("Timed out waiting for the shell to exit");
+            }
+        }
+    }
+}
+
+impl Drop for PtySession {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            self.child.kill().unwrap_or_default();
+            self.child.wait().map(drop).unwrap_or_default();
+        }
+        fs::remove_dir_all(&self.dir).unwrap_or_default();
+    }
+}
@@ -0,0 +1,163 @@
+mod pty;
+
+use std::fs;
+use std::os::unix::process::ExitStatusExt;
+
+use pretty_assertions::assert_eq;
+use pty::PtySession;
+
+#[test]
+fn test_run_command() {
+    let mut session = PtySession::start(&["--norc"], &[]);
+
+    session.run("echo hello");
+    assert_eq!(session.get_screen().snapshot(), "\n{fg=red}Hello, world!{}\n$ echo hello\nhello\n$\n");
+    assert_eq!(session.get_screen().get_cursor_pos(), (3, 5));
+}
+
+#[test]
+fn test_line_editing() {
+    let mut session = PtySession::start(&["--norc"], &[]);
+
+    session.type_text("eho hi");
+    for _ in 0..5 {
+        session.press("\x1b[D");
+    }
+    assert_eq!(session.get_screen().get_cursor_pos(), (4, 3));
+    session.type_text("c");
+    session.press("\x7f");
+    session.type_text("c");
+    assert_eq!(session.get_screen().get_line_str().trim_end(), "$ echo hi");
+    session.press("\r");
+    session.wait_for_prompt();
+    assert!(session.get_text().ends_with("$ echo hi\nhi\n$\n"));
+}
+
+#[test]
+fn test_failed_command() {
+    let mut session = PtySession::start(&["--norc"], &[]);
+
+    session.run("no-such-command");
+    session.run("echo $?");
+    assert!(session.get_text().ends_with("yeesh: no-such-command: command not found\n$ echo $?\n127\n$\n"));
+}
+
+#[test]
+fn test_tab_completion() {
+    let mut session = PtySession::start(&["--norc"], &[]);
+    fs::write(session.get_dir().join("notes.txt"), "some notes\n").expect("Unable to write the file");
+
+    session.type_text("cat no");
+    session.press("\t");
+    session.wait_for_text("$ cat notes.txt");
+    session.press("\r");
+    session.wait_for_prompt();
+    assert!(session.get_text().ends_with("$ cat notes.txt\nsome notes\n$\n"));
+}
+
+#[test]
+fn test_ctrl_c() {
+    let mut session = PtySession::start(&["--norc"], &[]);
+
+    // a running command is interrupted
+    session.type_text("sleep 10");
+    session.press("\r");
+    session.press("\x03");
+    session.wait_for_prompt();
+    session.run("echo $?");
+    assert!(session.get_text().ends_with("$ sleep 10\n^C\n$ echo $?\n130\n$\n"));
+
+    // and at the prompt, the line is abandoned
+    session.type_text("abc");
+    session.press("\x03");
+    session.wait_for_prompt();
+    session.run("echo $?");
+    assert!(session.get_text().ends_with("$ abc^C\n$ echo $?\n130\n$\n"));
+}
+
+#[test]
+fn test_ctrl_z_and_jobs() {
+    let mut session = PtySession::start(&["--norc"], &[]);
+
+    session.type_text("sleep 10");
+    session.press("\r");
+    session.press("\x1a");
+    session.wait_for_prompt();
+    session.run("jobs");
+    assert!(session.get_text().ends_with("[1]  Stopped   sleep 10\n$ jobs\n[1]  Stopped   sleep 10\n$\n"));
+
+    // the shell warns about the stopped job before exiting
+    session.press("\x04");
+    session.wait_for_prompt();
+    session.press("\x04");
+    assert_eq!(session.wait_for_exit().code(), Some(0));
+}
+
+#[test]
+fn test_pipeline() {
+    let mut session = PtySession::start(&["--norc"], &[]);
+
+    // the whole pipeline is in the foreground, so the first command reads from the terminal
+    session.type_text("cat | tr a-z A-Z");
+    session.press("\r");
+    session.type_text("hello");
+    session.press("\r");
+    session.wait_for_text("HELLO");
+    session.press("\x04");
+    session.wait_for_prompt();
+
+    // and Ctrl-C interrupts all of it
+    session.type_text("sleep 10 | sleep 10");
+    session.press("\r");
+    session.press("\x03");
+    session.wait_for_prompt();
+    session.run("echo $?");
+    assert!(session.get_text().ends_with("$ sleep 10 | sleep 10\n^C\n$ echo $?\n130\n$\n"));
+}
+
+#[test]
+fn test_exit() {
+    let mut session = PtySession::start(&["--norc"], &[]);
+
+    session.run("false");
+    session.press("\x04");
+    assert_eq!(session.wait_for_exit().code(), Some(1));
+    assert!(session.get_text().ends_with("$ false\n$\nexit\n"));
+
+    let mut session = PtySession::start(&["--norc"], &[]);
+    session.type_text("exit 3");
+    session.press("\r");
+    assert_eq!(session.wait_for_exit().code(), Some(3));
+}
+
+#[test]
+fn test_repair_terminal() {
+    let mut session = PtySession::start(&["--norc"], &[]);
+
+    // a full screen program which dies before putting the terminal back
+    session.run(r#"sh -c "printf '\033[?1049h\033[?25l\033[1mfull screen'; stty raw -echo""#);
+    let screen = session.get_screen();
+    assert!(!screen.is_alternate_screen() && screen.is_cursor_visible());
+    assert!(session.get_text().ends_with("stty raw -echo\"\n$\n"));
+
+    // the terminal's settings were put back, so commands get lines rather than keys again
+    session.run(r#"sh -c "stty -a | grep -o ' -*icanon'""#);
+    assert!(session.get_text().ends_with("\n icanon\n$\n"));
+
+    // a command which leaves mouse reporting on, but the settings as they were
+    session.run(r#"printf '\033[?1000h'"#);
+    assert!(!session.get_screen().is_mouse_reporting());
+}
+
+#[test]
+fn test_signal_exit() {
+    let mut session = PtySession::start(&["--norc"], &[]);
+
+    session.run("trap 'echo bye' EXIT");
+    session.run("sh -c 'kill -SEGV $$'");
+    assert!(session.get_text().contains("\n[SIGSEGV]"));
+    session.press("\x04");
+    let status = session.wait_for_exit();
+    assert_eq!((status.code(), status.signal()), (Some(139), None));
+    assert!(session.get_text().ends_with("exit\nbye\n"));
+}
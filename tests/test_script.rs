@@ -0,0 +1,22 @@
+use std::fs;
+use std::process::Command;
+
+use pretty_assertions::assert_eq;
+
+#[test]
+fn test_missing_script() {
+    let home = std::env::temp_dir().join(format!("yeesh-script-{}", std::process::id()));
+    fs::create_dir_all(&home).unwrap();
+    fs::write(home.join(".yeesh_profile"), "trap 'echo trapped' EXIT\n").unwrap();
+
+    // the shell still exits the usual way, running the EXIT trap
+    let output = Command::new(env!("CARGO_BIN_EXE_yeesh-shell"))
+        .args(["-l", "/nonexistent/script"])
+        .env("HOME", &home)
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&home).unwrap_or_default();
+    assert_eq!(output.status.code(), Some(127));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "trapped\n");
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("yeesh: /nonexistent/script: "));
+}